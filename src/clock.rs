@@ -0,0 +1,299 @@
+//! Clock of the run, with the timezone and the reference date of the report
+
+use chrono::{DateTime, Datelike, Days, FixedOffset, Local, Months, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::{fs, io::ErrorKind, sync::RwLock};
+
+/// Run level settings of the dates
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct Run {
+    /// Timezone of the report, like America/Sao_Paulo. The server one when omitted
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// Fixed reference date/time of the report, as date or RFC3339
+    #[serde(default)]
+    pub as_of: Option<String>,
+    /// File keeping the date/time of the last run, for the {{last_run}}
+    #[serde(default)]
+    pub state_file: Option<String>,
+    /// SQLite file keeping the values of the last run, for the `delta` of the querys
+    #[serde(default)]
+    pub state_db: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Clock {
+    tz: Option<Tz>,
+    as_of: Option<DateTime<Utc>>,
+    last_run: Option<DateTime<Utc>>,
+}
+
+/// Reference date of the deterministic runs without an `as_of`
+pub const DETERMINISTIC_AS_OF: &str = "1970-01-01T00:00:00Z";
+
+static CLOCK: RwLock<Clock> = RwLock::new(Clock {
+    tz: None,
+    as_of: None,
+    last_run: None,
+});
+
+impl Clock {
+    pub fn new(run: &Run) -> Result<Self, String> {
+        let tz = run
+            .timezone
+            .as_ref()
+            .map(|tz| {
+                tz.parse::<Tz>()
+                    .map_err(|e| format!("Invalid timezone {}: {}", tz, e))
+            })
+            .transpose()?;
+
+        let as_of = match &run.as_of {
+            Some(raw) => Some(parse_as_of(raw, tz)?),
+            None => None,
+        };
+
+        let last_run = match &run.state_file {
+            Some(path) => match fs::read_to_string(path) {
+                Ok(raw) => Some(
+                    DateTime::parse_from_rfc3339(raw.trim())
+                        .map_err(|e| format!("Invalid last run on {}: {}", path, e))?
+                        .to_utc(),
+                ),
+                Err(e) if e.kind() == ErrorKind::NotFound => None,
+                Err(e) => return Err(format!("State file {} not read: {}", path, e)),
+            },
+            None => None,
+        };
+
+        Ok(Self {
+            tz,
+            as_of,
+            last_run,
+        })
+    }
+
+    /// Current date/time of the report, on its timezone
+    pub fn now(&self) -> DateTime<FixedOffset> {
+        let now = match self.as_of {
+            Some(as_of) => as_of.fixed_offset(),
+            None if self.tz.is_none() => Local::now().fixed_offset(),
+            None => Utc::now().fixed_offset(),
+        };
+
+        self.to_local(&now)
+    }
+
+    /// Date/time converted to the timezone of the report, when informed
+    pub fn to_local(&self, dt: &DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+        match self.tz {
+            Some(tz) => dt.with_timezone(&tz).fixed_offset(),
+            None => *dt,
+        }
+    }
+
+    /// Replace the {{date}}, {{now}} and {{timezone}} placeholders, and the
+    /// ones of the dates, relative to today, and of the last run
+    pub fn placeholders(&self, raw: &str) -> String {
+        let now = self.now();
+        let tz = match self.tz {
+            Some(tz) => tz.name().to_string(),
+            None => now.offset().to_string(),
+        };
+
+        let today = now.date_naive();
+        let month = today.with_day(1).unwrap_or(today);
+        let date = |d: Option<NaiveDate>| d.unwrap_or(today).format("%Y-%m-%d").to_string();
+
+        // The start of yesterday on the first run
+        let last_run = match self.last_run {
+            Some(dt) => self.to_local(&dt.fixed_offset()).naive_local(),
+            None => today
+                .pred_opt()
+                .unwrap_or(today)
+                .and_time(Default::default()),
+        };
+
+        raw.replace("{{date}}", &now.format("%Y-%m-%d").to_string())
+            .replace("{{now}}", &now.format("%Y-%m-%d %H:%M").to_string())
+            .replace("{{timezone}}", &tz)
+            .replace("{{today}}", &date(Some(today)))
+            .replace("{{yesterday}}", &date(today.pred_opt()))
+            .replace(
+                "{{first_day_of_week}}",
+                &date(
+                    today
+                        .checked_sub_days(Days::new(today.weekday().num_days_from_monday().into())),
+                ),
+            )
+            .replace("{{first_day_of_month}}", &date(Some(month)))
+            .replace(
+                "{{last_day_of_month}}",
+                &date((month + Months::new(1)).pred_opt()),
+            )
+            .replace(
+                "{{first_day_of_previous_month}}",
+                &date(month.checked_sub_months(Months::new(1))),
+            )
+            .replace("{{last_day_of_previous_month}}", &date(month.pred_opt()))
+            .replace(
+                "{{last_run}}",
+                &last_run.format("%Y-%m-%d %H:%M:%S").to_string(),
+            )
+    }
+}
+
+fn parse_as_of(raw: &str, tz: Option<Tz>) -> Result<DateTime<Utc>, String> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(raw) {
+        return Ok(dt.to_utc());
+    }
+
+    let date = NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid as_of {}: {}", raw, e))?;
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+
+    let dt = match tz {
+        Some(tz) => tz
+            .from_local_datetime(&midnight)
+            .earliest()
+            .map(|d| d.to_utc()),
+        None => Some(midnight.and_utc()),
+    };
+
+    dt.ok_or(format!("Invalid as_of {} on the timezone", raw))
+}
+
+/// Setup the clock used by the run
+pub fn configure(run: &Run) -> Result<(), String> {
+    let clock = Clock::new(run)?;
+
+    *CLOCK
+        .write()
+        .map_err(|e| format!("Clock not configured: {}", e))? = clock;
+
+    Ok(())
+}
+
+/// Keep the date/time the run started as the last run, on the state
+/// file. Of the wall clock, not the one of the report pinned by the `as_of`
+pub fn record_run(run: &Run, started: DateTime<Utc>) -> Result<(), String> {
+    if let Some(path) = &run.state_file {
+        fs::write(path, started.to_rfc3339())
+            .map_err(|e| format!("State file {} not written: {}", path, e))?;
+    }
+
+    Ok(())
+}
+
+/// Clock of the run
+pub fn current() -> Clock {
+    CLOCK.read().map(|c| c.clone()).unwrap_or_default()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{Clock, Run};
+    use chrono::{DateTime, Utc};
+
+    #[test]
+    fn as_of_with_timezone() -> Result<(), String> {
+        let clock = Clock::new(&Run {
+            timezone: Some("America/Sao_Paulo".to_string()),
+            as_of: Some("2024-06-01".to_string()),
+            ..Default::default()
+        })?;
+
+        assert_eq!("2024-06-01T00:00:00-03:00", clock.now().to_rfc3339());
+        assert_eq!(
+            "Report for 2024-06-01 (America/Sao_Paulo)",
+            clock.placeholders("Report for {{date}} ({{timezone}})")
+        );
+
+        let dt = DateTime::parse_from_rfc3339("2024-06-01T12:00:00+00:00").unwrap();
+        assert_eq!(
+            "2024-06-01T09:00:00-03:00",
+            clock.to_local(&dt).to_rfc3339()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn as_of_utc() -> Result<(), String> {
+        let clock = Clock::new(&Run {
+            timezone: None,
+            as_of: Some("2024-06-01T10:30:00Z".to_string()),
+            ..Default::default()
+        })?;
+
+        assert_eq!("2024-06-01 10:30", clock.placeholders("{{now}}"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn dates() -> Result<(), String> {
+        let _ = std::fs::remove_file("/tmp/test-lmr-last-run");
+
+        let run = Run {
+            timezone: Some("America/Sao_Paulo".to_string()),
+            as_of: Some("2024-03-06T02:00:00Z".to_string()),
+            state_file: Some("/tmp/test-lmr-last-run".to_string()),
+            ..Default::default()
+        };
+        let clock = Clock::new(&run)?;
+
+        assert_eq!(
+            "2024-03-05 2024-03-04 2024-03-04 2024-03-01 2024-03-31 2024-02-01 2024-02-29",
+            clock.placeholders(
+                "{{today}} {{yesterday}} {{first_day_of_week}} {{first_day_of_month}} \
+                {{last_day_of_month}} {{first_day_of_previous_month}} {{last_day_of_previous_month}}"
+            )
+        );
+        assert_eq!(
+            "created > '2024-03-04 00:00:00'",
+            clock.placeholders("created > '{{last_run}}'")
+        );
+
+        std::fs::write("/tmp/test-lmr-last-run", "2024-03-05T12:30:00Z").unwrap();
+        assert_eq!(
+            "2024-03-05 09:30:00",
+            Clock::new(&run)?.placeholders("{{last_run}}")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn record_run() -> Result<(), String> {
+        let run = Run {
+            as_of: Some("2024-03-06T02:00:00Z".to_string()),
+            state_file: Some("/tmp/test-lmr-record-run".to_string()),
+            ..Default::default()
+        };
+
+        let started = Utc::now();
+        super::record_run(&run, started)?;
+
+        let raw = std::fs::read_to_string("/tmp/test-lmr-record-run").unwrap();
+        let recorded = DateTime::parse_from_rfc3339(&raw).unwrap();
+        assert_eq!(started, recorded);
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Clock::new(&Run {
+            timezone: Some("Mars/Olympus".to_string()),
+            ..Default::default()
+        })
+        .is_err());
+        assert!(Clock::new(&Run {
+            as_of: Some("yesterday".to_string()),
+            ..Default::default()
+        })
+        .is_err());
+    }
+}
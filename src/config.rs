@@ -2,30 +2,183 @@
 //! the template and send the result
 
 use crate::{
+    clock::{self, Run},
+    delta::Delta,
+    expect::{Expect, Severity},
     presentation::{
-        charts::ChartComponent, formats::OutputFormat, table::TableComponent, Component,
+        charts::{ChartComponent, ChartOutput},
+        diff::DiffComponent,
+        formats::OutputFormat,
+        locale::Language,
+        stats::StatsComponent,
+        table::TableComponent,
+        text::TextComponent,
+        Component,
     },
-    send::MailServer,
-    source::{Query, Source},
-    value::Field,
+    redact::RedactRule,
+    send::{Archive, MailServer, Zip},
+    sort::SortOrder,
+    source::{Join, Query, Source},
+    value::{Field, FieldOverride},
 };
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
+use std::{collections::BTreeMap, fs, path::Path};
 
 #[derive(Clone, Debug, PartialEq, Deserialize)]
 pub struct Config {
+    /// Source of the querys without their own
+    #[serde(default)]
     pub source: Source,
+    /// Named sources, referenced by the querys and joins
+    #[serde(default)]
+    pub sources: BTreeMap<String, Source>,
+    /// Parameters of all querys
+    #[serde(default, deserialize_with = "params_as_text")]
+    pub params: BTreeMap<String, String>,
     pub send: ConfigSend,
     pub title: String,
+    /// Text beneath the title of the report
+    #[serde(default)]
+    pub description: Option<String>,
     pub querys: Vec<ConfigQuery>,
+    #[serde(default)]
+    pub redact: Vec<RedactRule>,
+    #[serde(default)]
+    pub summary: bool,
+    /// Links to the querys at the top
+    #[serde(default)]
+    pub toc: bool,
+    /// Other config files combined as sections of this report
+    #[serde(default)]
+    pub digest: Vec<String>,
+    #[serde(default)]
+    pub run: Run,
+    /// Output of the charts without their own
+    #[serde(default)]
+    pub chart_output: Option<ChartOutput>,
+    /// HTML file in place of the default layout, relative to the config file
+    #[serde(default)]
+    pub template: Option<String>,
+    /// CSS added to the one of the HTML
+    #[serde(default)]
+    pub style: Option<String>,
+    /// File with the CSS, relative to the config file
+    #[serde(default)]
+    pub style_file: Option<String>,
+    /// Markdown in place of the default footer, empty for none
+    #[serde(default)]
+    pub footer: Option<String>,
+    /// Of the built-in texts, like the titles and the messages
+    #[serde(default)]
+    pub language: Language,
+    /// Results attached as a Excel workbook, a sheet for each query
+    #[serde(default)]
+    pub attach_xlsx: bool,
 }
 
 #[derive(Clone, Debug, PartialEq, Deserialize)]
 pub struct ConfigQuery {
     pub title: String,
+    /// Text beneath the heading of the query, about what it measures
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Optional for the file sources
+    #[serde(default)]
     pub sql: String,
+    /// File with the sql, relative to the config file
+    #[serde(default)]
+    pub sql_file: Option<String>,
+    /// List of fields or "*" for all columns of the result
+    #[serde(default, deserialize_with = "fields_or_wildcard")]
     pub fields: Vec<Field>,
     #[serde(default)]
+    pub overrides: Vec<FieldOverride>,
+    #[serde(default)]
     pub chart: Option<ChartComponent>,
+    /// Statistics of the numeric fields, in place of the rows
+    #[serde(default)]
+    pub stats: Option<StatsComponent>,
+    /// Rows added, removed or changed against a baseline, in place of the rows
+    #[serde(default)]
+    pub diff: Option<DiffComponent>,
+    #[serde(default)]
+    pub expect: Option<Expect>,
+    /// Of the failures, listed at the top of the report
+    #[serde(default)]
+    pub severity: Severity,
+    /// Result attached to the report as a CSV file
+    #[serde(default)]
+    pub attach_csv: bool,
+    /// Fields compared with the last run, on the `state_db`
+    #[serde(default)]
+    pub delta: Option<Delta>,
+    #[serde(default)]
+    pub row_link: Option<String>,
+    /// Field to group the rows of the table, under headers
+    #[serde(default)]
+    pub group_by: Option<String>,
+    /// Sums of the numeric columns after each group
+    #[serde(default)]
+    pub subtotals: bool,
+    /// Maximum of rows of each table, split in parts
+    #[serde(default)]
+    pub rows_per_table: Option<usize>,
+    /// Fields to sort the fetched rows
+    #[serde(default)]
+    pub sort_by: Vec<String>,
+    #[serde(default)]
+    pub order: SortOrder,
+    #[serde(default)]
+    pub batch_size: Option<usize>,
+    /// Maximum of rows, the fetch stops on it
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Values of the `:name` parameters of the sql
+    #[serde(default, deserialize_with = "params_as_text")]
+    pub params: BTreeMap<String, String>,
+    /// Source of the query, the global one when omitted
+    #[serde(default)]
+    pub source: Option<ConfigSource>,
+    /// Querys of other sources, as tables for the `sql` of this one
+    #[serde(default)]
+    pub join: Vec<ConfigJoin>,
+    /// Markdown text, in place of a query, with the placeholders of the
+    /// dates and of the params, like `{{customer}}`
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+/// Name of a source of the `sources` or the source itself
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum ConfigSource {
+    Named(String),
+    Inline(Box<Source>),
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct ConfigJoin {
+    pub table: String,
+    #[serde(default)]
+    pub sql: String,
+    #[serde(default)]
+    pub sql_file: Option<String>,
+    #[serde(default)]
+    pub source: Option<ConfigSource>,
+    #[serde(default, deserialize_with = "fields_or_wildcard")]
+    pub fields: Vec<Field>,
+}
+
+impl ConfigSource {
+    pub fn resolve(&self, sources: &BTreeMap<String, Source>) -> Result<Source, String> {
+        match self {
+            ConfigSource::Named(name) => sources
+                .get(name)
+                .cloned()
+                .ok_or(format!("Source {} not found on the sources", name)),
+            ConfigSource::Inline(source) => Ok(source.as_ref().clone()),
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Deserialize)]
@@ -34,38 +187,503 @@ pub struct ConfigSend {
     #[serde(default)]
     pub stdout: bool,
     #[serde(default)]
+    pub archive: Option<Archive>,
+    /// Single file with the report, the images embedded on it
+    #[serde(default)]
+    pub file: Option<String>,
+    #[serde(default)]
     pub format: OutputFormat,
+    /// Attachments of the mail and file bundled on a single zip
+    #[serde(default)]
+    pub zip: Option<Zip>,
 }
 
 impl ConfigQuery {
-    pub fn to_query(&self) -> Query {
-        Query {
+    pub fn to_query(&self, sources: &BTreeMap<String, Source>) -> Result<Query, String> {
+        let resolve = |s: &Option<ConfigSource>| s.as_ref().map(|s| s.resolve(sources)).transpose();
+
+        let mut joins = vec![];
+        for j in &self.join {
+            joins.push(Join {
+                table: j.table.clone(),
+                sql: j.sql.clone(),
+                source: resolve(&j.source)?,
+                fields: j.fields.clone(),
+            });
+        }
+
+        Ok(Query {
             sql: self.sql.clone(),
             title: self.title.clone(),
-            fields: self.fields.clone(),
+            description: self.description.clone(),
+            fields: self
+                .fields
+                .iter()
+                .cloned()
+                .map(|mut f| {
+                    if f.title.is_empty() {
+                        f.title = f.field.clone();
+                    }
+                    f
+                })
+                .collect(),
+            overrides: self.overrides.clone(),
+            batch_size: self.batch_size,
+            limit: self.limit,
+            params: self.params.clone(),
+            source: resolve(&self.source)?,
+            joins,
+            text: self.text.clone(),
+            severity: self.severity,
+            attach_csv: self.attach_csv,
+            csv: None,
+        })
+    }
+
+    pub fn component(&self) -> Box<dyn Component> {
+        if self.text.is_some() {
+            return Box::new(TextComponent {});
+        }
+        if let Some(stats) = &self.stats {
+            return Box::new(stats.clone());
+        }
+        if let Some(diff) = &self.diff {
+            return Box::new(diff.clone());
+        }
+
+        match self.chart.clone() {
+            Some(e) => Box::new(e),
+            _ => Box::new(TableComponent {
+                row_link: self.row_link.clone(),
+                group_by: self.group_by.clone(),
+                subtotals: self.subtotals,
+                rows_per_table: self.rows_per_table,
+            }),
+        }
+    }
+}
+
+/// The wildcard is the same of no fields, all of them will
+/// be inferred from the result
+fn fields_or_wildcard<'de, D>(deserializer: D) -> Result<Vec<Field>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Fields {
+        Wildcard(String),
+        List(Vec<Field>),
+    }
+
+    match Fields::deserialize(deserializer)? {
+        Fields::Wildcard(w) if w == "*" => Ok(vec![]),
+        Fields::Wildcard(w) => Err(serde::de::Error::custom(format!(
+            "Invalid fields {}, expected a list or \"*\"",
+            w
+        ))),
+        Fields::List(fields) => Ok(fields),
+    }
+}
+
+impl Config {
+    /// Set the parameters on the report and all querys, replacing their values
+    pub fn set_params(&mut self, params: &[(String, String)]) {
+        self.params.extend(params.iter().cloned());
+        for q in self.querys.iter_mut() {
+            q.params.extend(params.iter().cloned());
         }
     }
+
+    /// Set the `chart_output` on the charts without their own
+    pub fn set_chart_output(&mut self) {
+        let Some(output) = &self.chart_output else {
+            return;
+        };
+        for chart in self.querys.iter_mut().filter_map(|q| q.chart.as_mut()) {
+            chart.output.get_or_insert(output.clone());
+        }
+    }
+
+    /// Load the `sql_file` of the querys and joins, relative to the dir
+    pub fn load_sql_files(&mut self, dir: &Path) -> Result<(), String> {
+        for q in self.querys.iter_mut() {
+            load_sql(&mut q.sql, &q.sql_file, dir)
+                .map_err(|e| format!("Query {}: {}", q.title, e))?;
+            for j in q.join.iter_mut() {
+                load_sql(&mut j.sql, &j.sql_file, dir)
+                    .map_err(|e| format!("Query {}, join {}: {}", q.title, j.table, e))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Load the `style_file` into the `style`, relative to the dir
+    pub fn load_style_file(&mut self, dir: &Path) -> Result<(), String> {
+        let Some(file) = &self.style_file else {
+            return Ok(());
+        };
+
+        if self.style.is_some() {
+            return Err("Use only one of style and style_file".to_string());
+        }
+
+        let path = dir.join(file);
+        let style = fs::read_to_string(&path)
+            .map_err(|e| format!("Style file {} not loaded: {}", path.display(), e))?;
+        self.style = Some(style);
+
+        Ok(())
+    }
 }
 
-pub fn to_querys(querys: Vec<ConfigQuery>) -> Vec<(Query, Option<ChartComponent>)> {
-    querys
+fn load_sql(sql: &mut String, file: &Option<String>, dir: &Path) -> Result<(), String> {
+    let Some(file) = file else {
+        return Ok(());
+    };
+
+    if !sql.trim().is_empty() {
+        return Err("Use only one of sql and sql_file".to_string());
+    }
+
+    let path = dir.join(file);
+    *sql = fs::read_to_string(&path)
+        .map_err(|e| format!("SQL file {} not loaded: {}", path.display(), e))?;
+
+    Ok(())
+}
+
+/// Scalar values of the params as text
+pub(crate) fn params_as_text<'de, D>(deserializer: D) -> Result<BTreeMap<String, String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    BTreeMap::<String, serde_yaml::Value>::deserialize(deserializer)?
         .into_iter()
-        .map(|q| (q.to_query(), q.chart))
+        .map(|(k, v)| match v {
+            serde_yaml::Value::String(s) => Ok((k, s)),
+            serde_yaml::Value::Number(n) => Ok((k, n.to_string())),
+            serde_yaml::Value::Bool(b) => Ok((k, b.to_string())),
+            v => Err(serde::de::Error::custom(format!(
+                "Invalid param {}: {:?}, expected a scalar",
+                k, v
+            ))),
+        })
         .collect()
 }
 
-pub fn find_component(
-    querys: Vec<(Query, Option<ChartComponent>)>,
-    q: Query,
-) -> Box<dyn Component> {
-    let chart = querys
+/// Querys of the config, with the report parameters under
+/// the query ones and the date placeholders replaced
+pub fn to_querys(config: &Config) -> Result<Vec<Query>, String> {
+    config
+        .querys
         .iter()
-        .find(|(q2, _)| q2 == &q)
-        .map(|(_, c)| c.clone())
-        .flatten();
+        .map(|q| {
+            let mut query = q.to_query(&config.sources)?;
+
+            let mut params = config.params.clone();
+            params.extend(query.params);
+            query.params = params;
+
+            // Dates relative to the run
+            let clock = clock::current();
+            query.sql = clock.placeholders(&query.sql);
+            for j in query.joins.iter_mut() {
+                j.sql = clock.placeholders(&j.sql);
+            }
+            for v in query.params.values_mut() {
+                *v = clock.placeholders(v);
+            }
+            if let Some(description) = query.description.as_mut() {
+                *description = clock.placeholders(description);
+            }
+            if let Some(text) = query.text.as_mut() {
+                *text = clock.placeholders(text);
+                for (k, v) in &query.params {
+                    *text = text.replace(&format!("{{{{{}}}}}", k), v);
+                }
+            }
+
+            Ok(query)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{to_querys, Config, ConfigQuery};
+    use crate::presentation::charts::ChartOutput;
+    use crate::source::{Source, SourceType};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn fields_defaults() -> Result<(), String> {
+        let query = serde_yaml::from_str::<ConfigQuery>(
+            "
+            title: Test
+            sql: select * from test
+            fields:
+                - field: name
+                  kind: String
+                - field: qt
+                  title: Quantity
+                  kind: Integer
+            ",
+        )
+        .map_err(|e| e.to_string())?
+        .to_query(&BTreeMap::new())?;
+
+        assert_eq!("name", query.fields[0].title);
+        assert_eq!("Quantity", query.fields[1].title);
+
+        let query = serde_yaml::from_str::<ConfigQuery>(
+            "
+            title: Test
+            sql: select * from test
+            fields: \"*\"
+            ",
+        )
+        .map_err(|e| e.to_string())?
+        .to_query(&BTreeMap::new())?;
+
+        assert!(query.fields.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn named_sources() -> Result<(), String> {
+        let sources = BTreeMap::from([(
+            "erp".to_string(),
+            Source {
+                kind: SourceType::Postgres,
+                conn: "postgresql://erp".to_string(),
+                ..Default::default()
+            },
+        )]);
+
+        let query = serde_yaml::from_str::<ConfigQuery>(
+            "
+            title: Test
+            source: erp
+            join:
+                - table: orders
+                  source: erp
+                  sql: select * from orders
+                - table: users
+                  source:
+                      kind: Csv
+                      conn: /tmp/users.csv
+            sql: select * from orders join users using (id)
+            ",
+        )
+        .map_err(|e| e.to_string())?
+        .to_query(&sources)?;
+
+        assert_eq!(sources.get("erp"), query.source.as_ref());
+        assert_eq!(sources.get("erp"), query.joins[0].source.as_ref());
+        assert_eq!(
+            Some(SourceType::Csv),
+            query.joins[1].source.as_ref().map(|s| s.kind.clone())
+        );
+
+        let query = serde_yaml::from_str::<ConfigQuery>("title: Test\nsource: crm")
+            .map_err(|e| e.to_string())?;
+        assert_eq!(
+            Some("Source crm not found on the sources".to_string()),
+            query.to_query(&sources).err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn params() -> Result<(), String> {
+        let mut config = serde_yaml::from_str::<Config>(
+            "
+            title: Test
+            send:
+                stdout: true
+            params:
+                since: 2024-01-01
+                customer: 1
+            querys:
+                - title: Orders
+                  sql: select * from orders where customer = :customer
+                  params:
+                      customer: 42
+                      paid: true
+            ",
+        )
+        .map_err(|e| e.to_string())?;
+
+        let params = |config: &Config| -> Result<Vec<(String, String)>, String> {
+            Ok(to_querys(config)?[0].params.clone().into_iter().collect())
+        };
+
+        assert_eq!(
+            vec![
+                ("customer".to_string(), "42".to_string()),
+                ("paid".to_string(), "true".to_string()),
+                ("since".to_string(), "2024-01-01".to_string()),
+            ],
+            params(&config)?
+        );
+
+        config.set_params(&[("customer".to_string(), "7".to_string())]);
+        assert_eq!(
+            ("customer".to_string(), "7".to_string()),
+            params(&config)?[0]
+        );
+
+        assert!(
+            serde_yaml::from_str::<ConfigQuery>("title: Test\nparams:\n  ids: [1, 2]").is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn sql_files() -> Result<(), String> {
+        let dir = std::env::temp_dir().join("test-lmr-sql-files");
+        std::fs::create_dir_all(dir.join("sql")).map_err(|e| e.to_string())?;
+        std::fs::write(dir.join("sql/orders.sql"), "select * from orders\n")
+            .map_err(|e| e.to_string())?;
+
+        let yaml = |sql: &str| {
+            format!(
+                "
+                send:
+                    stdout: true
+                title: Files
+                querys:
+                    - title: Orders
+                      {}
+                      sql_file: sql/orders.sql
+                      join:
+                          - table: items
+                            sql_file: sql/orders.sql
+                ",
+                sql
+            )
+        };
+
+        let mut config = serde_yaml::from_str::<Config>(&yaml("")).map_err(|e| e.to_string())?;
+        config.load_sql_files(&dir)?;
+        assert_eq!("select * from orders\n", config.querys[0].sql);
+        assert_eq!("select * from orders\n", config.querys[0].join[0].sql);
+
+        let mut config =
+            serde_yaml::from_str::<Config>(&yaml("sql: select 1")).map_err(|e| e.to_string())?;
+        assert!(config.load_sql_files(&dir).is_err());
+
+        let mut config = serde_yaml::from_str::<Config>(&yaml("")).map_err(|e| e.to_string())?;
+        assert!(config.load_sql_files(&dir.join("missing")).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn style_file() -> Result<(), String> {
+        let dir = std::env::temp_dir().join("test-lmr-style-file");
+        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        std::fs::write(dir.join("mail.css"), ".lmr-h1 { color: #1b365d; }\n")
+            .map_err(|e| e.to_string())?;
+
+        let yaml = |style: &str| {
+            format!(
+                "
+                send:
+                    stdout: true
+                title: Styled
+                {}
+                style_file: mail.css
+                querys: []
+                ",
+                style
+            )
+        };
+
+        let mut config = serde_yaml::from_str::<Config>(&yaml("")).map_err(|e| e.to_string())?;
+        config.load_style_file(&dir)?;
+        assert_eq!(
+            Some(".lmr-h1 { color: #1b365d; }\n".to_string()),
+            config.style
+        );
+
+        let mut config = serde_yaml::from_str::<Config>(&yaml("style: \"p { margin: 0; }\""))
+            .map_err(|e| e.to_string())?;
+        assert_eq!(
+            Err("Use only one of style and style_file".to_string()),
+            config.load_style_file(&dir)
+        );
+
+        let mut config = serde_yaml::from_str::<Config>(&yaml("")).map_err(|e| e.to_string())?;
+        assert!(config.load_style_file(&dir.join("missing")).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn texts() -> Result<(), String> {
+        let config = serde_yaml::from_str::<Config>(
+            "
+            title: Test
+            send:
+                stdout: true
+            params:
+                customer: 42
+            querys:
+                - title: About
+                  text: Orders of the customer {{customer}}, {{unknown}}
+            ",
+        )
+        .map_err(|e| e.to_string())?;
+
+        assert_eq!(
+            Some("Orders of the customer 42, {{unknown}}".to_string()),
+            to_querys(&config)?[0].text
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn chart_output() -> Result<(), String> {
+        let mut config = serde_yaml::from_str::<Config>(
+            "
+            title: Report
+            send:
+                stdout: true
+            chart_output: Svg
+            querys:
+                - title: Default
+                  sql: select * from test
+                  chart:
+                    kind: Bar
+                - title: Own
+                  sql: select * from test
+                  chart:
+                    kind: Bar
+                    output: Png
+                - title: Table
+                  sql: select * from test
+            ",
+        )
+        .map_err(|e| e.to_string())?;
+        config.set_chart_output();
+
+        let outputs: Vec<_> = config
+            .querys
+            .iter()
+            .map(|q| q.chart.as_ref().and_then(|c| c.output.clone()))
+            .collect();
+        assert_eq!(
+            vec![Some(ChartOutput::Svg), Some(ChartOutput::Png), None],
+            outputs
+        );
 
-    match chart {
-        Some(e) => Box::new(e),
-        _ => Box::new(TableComponent {}),
+        Ok(())
     }
 }
@@ -0,0 +1,337 @@
+//! Change of the values against the last run, kept on a SQLite file
+//! with the results of the querys compared by the diffs
+
+use crate::{
+    presentation::diff::Snapshot,
+    source::Query,
+    value::{Field, FieldType, Trend, TypedValue, Value},
+};
+use rust_decimal::prelude::ToPrimitive;
+use serde::Deserialize;
+use sqlite::{Connection, State};
+use std::collections::BTreeMap;
+
+/// Fields of the query compared with the last run
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct Delta {
+    pub fields: Vec<String>,
+    /// Field identifying the rows between the runs, the position by default
+    #[serde(default)]
+    pub key: Option<String>,
+    /// Lower values are the better ones, like the errors
+    #[serde(default)]
+    pub lower_is_better: bool,
+}
+
+/// Values of the last run, by the key of the row and the field
+pub type Previous = BTreeMap<(String, String), f64>;
+
+pub struct DeltaStore {
+    conn: Connection,
+}
+
+impl DeltaStore {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let conn =
+            Connection::open(path).map_err(|e| format!("State db {} not opened: {}", path, e))?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS lmr_deltas (
+                query TEXT NOT NULL,
+                key TEXT NOT NULL,
+                field TEXT NOT NULL,
+                value REAL NOT NULL,
+                PRIMARY KEY (query, key, field)
+            )",
+        )
+        .map_err(|e| format!("State db {} not created: {}", path, e))?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS lmr_snapshots (
+                query TEXT NOT NULL PRIMARY KEY,
+                rows TEXT NOT NULL
+            )",
+        )
+        .map_err(|e| format!("State db {} not created: {}", path, e))?;
+
+        Ok(Self { conn })
+    }
+
+    /// Values of the query on the last run
+    pub fn previous(&self, query: &str) -> Result<Previous, String> {
+        let err = |e: sqlite::Error| format!("Last run of {} not read: {}", query, e);
+
+        let mut statement = self
+            .conn
+            .prepare("SELECT key, field, value FROM lmr_deltas WHERE query = ?1")
+            .map_err(err)?;
+        statement.bind((1, query)).map_err(err)?;
+
+        let mut previous = BTreeMap::new();
+        while let State::Row = statement.next().map_err(err)? {
+            let key = statement.read::<String, _>(0).map_err(err)?;
+            let field = statement.read::<String, _>(1).map_err(err)?;
+            let value = statement.read::<f64, _>(2).map_err(err)?;
+            previous.insert((key, field), value);
+        }
+
+        Ok(previous)
+    }
+
+    /// Keep the values of the rows, in place of the ones of the last run
+    pub fn record(&self, query: &str, delta: &Delta, rows: &[Vec<Value>]) -> Result<(), String> {
+        let err = |e: sqlite::Error| format!("Run of {} not recorded: {}", query, e);
+
+        self.conn.execute("BEGIN").map_err(err)?;
+
+        let mut statement = self
+            .conn
+            .prepare("DELETE FROM lmr_deltas WHERE query = ?1")
+            .map_err(err)?;
+        statement.bind((1, query)).map_err(err)?;
+        statement.next().map_err(err)?;
+
+        let mut statement = self
+            .conn
+            .prepare("INSERT OR REPLACE INTO lmr_deltas VALUES (?1, ?2, ?3, ?4)")
+            .map_err(err)?;
+        for (i, row) in rows.iter().enumerate() {
+            let key = row_key(delta, i, row)?;
+            for field in &delta.fields {
+                let Some(value) = number(row, field) else {
+                    continue;
+                };
+                statement.reset().map_err(err)?;
+                statement
+                    .bind::<&[(_, sqlite::Value)]>(&[
+                        (1, query.into()),
+                        (2, key.as_str().into()),
+                        (3, field.as_str().into()),
+                        (4, value.into()),
+                    ])
+                    .map_err(err)?;
+                statement.next().map_err(err)?;
+            }
+        }
+
+        self.conn.execute("COMMIT").map_err(err)
+    }
+
+    /// Result of the query on the last run
+    pub fn snapshot(&self, query: &str) -> Result<Option<Snapshot>, String> {
+        let err = |e: sqlite::Error| format!("Last run of {} not read: {}", query, e);
+
+        let mut statement = self
+            .conn
+            .prepare("SELECT rows FROM lmr_snapshots WHERE query = ?1")
+            .map_err(err)?;
+        statement.bind((1, query)).map_err(err)?;
+
+        if let State::Done = statement.next().map_err(err)? {
+            return Ok(None);
+        }
+        let rows = statement.read::<String, _>(0).map_err(err)?;
+
+        serde_json::from_str(&rows)
+            .map(Some)
+            .map_err(|e| format!("Last run of {} not parsed: {}", query, e))
+    }
+
+    /// Keep the result of the query, in place of the one of the last run
+    pub fn record_snapshot(&self, query: &str, rows: &Snapshot) -> Result<(), String> {
+        let err = |e: sqlite::Error| format!("Run of {} not recorded: {}", query, e);
+
+        let rows = serde_json::to_string(rows)
+            .map_err(|e| format!("Run of {} not serialized: {}", query, e))?;
+
+        let mut statement = self
+            .conn
+            .prepare("INSERT OR REPLACE INTO lmr_snapshots VALUES (?1, ?2)")
+            .map_err(err)?;
+        statement
+            .bind::<&[(_, &str)]>(&[(1, query), (2, rows.as_str())])
+            .map_err(err)?;
+        statement.next().map_err(err)?;
+
+        Ok(())
+    }
+}
+
+/// Query and rows with the change of each compared field, on the
+/// column after it. Without the last run, the changes are empty
+pub fn apply(
+    query: Query,
+    rows: Vec<Vec<Value>>,
+    delta: &Delta,
+    previous: &Previous,
+) -> Result<(Query, Vec<Vec<Value>>), String> {
+    if let Some(missing) = delta
+        .fields
+        .iter()
+        .find(|f| !query.fields.iter().any(|q| q.field == **f))
+    {
+        return Err(format!("Field {} not found", missing));
+    }
+
+    let trend = if delta.lower_is_better {
+        Trend::Lower
+    } else {
+        Trend::Higher
+    };
+
+    let mut fields = vec![];
+    for field in &query.fields {
+        fields.push(field.clone());
+        if delta.fields.contains(&field.field) {
+            fields.push(Field {
+                field: format!("{}_delta", field.field),
+                title: format!("{} Δ", field.title),
+                kind: FieldType::Float,
+                precision: field.precision,
+                format: field.format.clone(),
+                unit: field.unit.clone(),
+                align: field.align.clone(),
+                trend: Some(trend.clone()),
+                ..Default::default()
+            });
+        }
+    }
+
+    let mut drows = vec![];
+    for (i, row) in rows.into_iter().enumerate() {
+        let key = row_key(delta, i, &row)?;
+
+        let mut drow = vec![];
+        for value in row {
+            let name = value.field.field.clone();
+            let current = value.inner.as_ref().and_then(to_f64);
+            drow.push(value);
+
+            if let Some(field) = fields.iter().find(|f| f.field == format!("{}_delta", name)) {
+                let last = previous.get(&(key.clone(), name));
+                drow.push(Value {
+                    inner: current
+                        .zip(last)
+                        .map(|(current, last)| TypedValue::Float(current - last)),
+                    field: field.clone(),
+                });
+            }
+        }
+        drows.push(drow);
+    }
+
+    Ok((Query { fields, ..query }, drows))
+}
+
+/// Identification of the row between the runs
+fn row_key(delta: &Delta, i: usize, row: &[Value]) -> Result<String, String> {
+    match &delta.key {
+        Some(key) => row
+            .iter()
+            .find(|v| v.field.field == *key)
+            .map(|v| v.to_string())
+            .ok_or_else(|| format!("Field {} not found", key)),
+        None => Ok(i.to_string()),
+    }
+}
+
+fn number(row: &[Value], field: &str) -> Option<f64> {
+    let value = row.iter().find(|v| v.field.field == field)?;
+    to_f64(value.inner.as_ref()?)
+}
+
+fn to_f64(v: &TypedValue) -> Option<f64> {
+    match v {
+        TypedValue::Integer(v) => Some(*v as f64),
+        TypedValue::Float(v) => Some(*v),
+        TypedValue::Decimal(v) => v.to_f64(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{apply, Delta, DeltaStore};
+    use crate::{
+        source::Query,
+        value::{Field, FieldType, TypedValue, Value},
+    };
+    use std::fs;
+
+    fn rows(values: &[(&str, i64)]) -> (Query, Vec<Vec<Value>>) {
+        let fields = vec![
+            Field {
+                field: "region".to_string(),
+                title: "Region".to_string(),
+                ..Default::default()
+            },
+            Field {
+                field: "sales".to_string(),
+                title: "Sales".to_string(),
+                kind: FieldType::Integer,
+                ..Default::default()
+            },
+        ];
+        let rows = values
+            .iter()
+            .map(|(region, sales)| {
+                vec![
+                    Value {
+                        inner: Some(TypedValue::String(region.to_string())),
+                        field: fields[0].clone(),
+                    },
+                    Value {
+                        inner: Some(TypedValue::Integer(*sales)),
+                        field: fields[1].clone(),
+                    },
+                ]
+            })
+            .collect();
+
+        let query = Query {
+            title: "Sales".to_string(),
+            fields,
+            ..Default::default()
+        };
+        (query, rows)
+    }
+
+    #[test]
+    fn deltas() -> Result<(), String> {
+        let path = std::env::temp_dir().join("test-lmr-deltas.sqlite");
+        let _ = fs::remove_file(&path);
+        let store = DeltaStore::open(&path.to_string_lossy())?;
+
+        let delta = Delta {
+            fields: vec!["sales".to_string()],
+            key: Some("region".to_string()),
+            lower_is_better: false,
+        };
+
+        let (query, data) = rows(&[("north", 10), ("south", 5)]);
+        let (dquery, drows) = apply(query, data.clone(), &delta, &store.previous("Sales")?)?;
+        assert_eq!("Sales Δ", dquery.fields[2].title);
+        assert_eq!(None, drows[0][2].inner);
+        store.record("Sales", &delta, &data)?;
+
+        let (query, data) = rows(&[("south", 8), ("north", 7), ("east", 1)]);
+        let (_, drows) = apply(query, data, &delta, &store.previous("Sales")?)?;
+        assert_eq!(Some("▲ +3".to_string()), drows[0][2].trend());
+        assert_eq!(Some(true), drows[0][2].better());
+        assert_eq!(Some("▼ -3".to_string()), drows[1][2].trend());
+        assert_eq!(Some(false), drows[1][2].better());
+        assert_eq!(None, drows[2][2].trend());
+
+        assert!(store.previous("Other")?.is_empty());
+
+        let (query, data) = rows(&[]);
+        let delta = Delta {
+            fields: vec!["qt".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            Err("Field qt not found".to_string()),
+            apply(query, data, &delta, &Default::default()).map(|_| ())
+        );
+
+        Ok(())
+    }
+}
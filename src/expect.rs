@@ -0,0 +1,84 @@
+//! Row count expectations of the querys and the severity of their failures
+
+use serde::Deserialize;
+
+/// How much a failure of the query, or of its expectations, needs attention
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+pub enum Severity {
+    /// Only on the section of the query
+    Info,
+    #[default]
+    Warning,
+    Critical,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct Expect {
+    #[serde(default)]
+    pub min_rows: Option<usize>,
+    #[serde(default)]
+    pub max_rows: Option<usize>,
+    #[serde(default)]
+    pub exactly: Option<usize>,
+}
+
+impl Expect {
+    /// Validate the number of rows returned by the query
+    pub fn check(&self, rows: usize) -> Result<(), String> {
+        if let Some(exactly) = self.exactly {
+            if rows != exactly {
+                return Err(format!("Expected exactly {} rows, got {}", exactly, rows));
+            }
+        }
+
+        if let Some(min) = self.min_rows {
+            if rows < min {
+                return Err(format!("Expected at least {} rows, got {}", min, rows));
+            }
+        }
+
+        if let Some(max) = self.max_rows {
+            if rows > max {
+                return Err(format!("Expected at most {} rows, got {}", max, rows));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::Expect;
+
+    #[test]
+    fn check() {
+        let expect = Expect {
+            min_rows: Some(2),
+            max_rows: Some(4),
+            exactly: None,
+        };
+
+        assert_eq!(
+            Err("Expected at least 2 rows, got 1".to_string()),
+            expect.check(1)
+        );
+        assert_eq!(Ok(()), expect.check(2));
+        assert_eq!(Ok(()), expect.check(4));
+        assert_eq!(
+            Err("Expected at most 4 rows, got 5".to_string()),
+            expect.check(5)
+        );
+
+        let expect = Expect {
+            exactly: Some(0),
+            ..Default::default()
+        };
+
+        assert_eq!(Ok(()), expect.check(0));
+        assert_eq!(
+            Err("Expected exactly 0 rows, got 3".to_string()),
+            expect.check(3)
+        );
+    }
+}
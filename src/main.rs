@@ -1,17 +1,29 @@
 /// lmr - Lightweight email report tool
-use clap::{crate_authors, Parser};
+use chrono::{DateTime, Utc};
+use clap::{crate_authors, Parser, Subcommand};
 use clap_verbosity_flag::{InfoLevel, Verbosity};
 use log::*;
 use simplelog::*;
-use std::fs;
+use std::{collections::BTreeMap, fs, path::Path};
 
+mod clock;
 mod config;
+mod delta;
+mod expect;
 mod presentation;
+mod recipient;
+mod redact;
 mod send;
+mod serve;
+mod sort;
 mod source;
 mod value;
 
 use config::Config;
+use presentation::{diff::Snapshot, DataPresented, QueryData};
+use recipient::Recipient;
+use redact::Redactor;
+use source::QueryResult;
 
 #[derive(Parser, Debug)]
 #[command(help_template = "\
@@ -26,14 +38,44 @@ https://github.com/fernandobatels/lmr
 {all-args}{after-help}")]
 #[command(version)]
 #[command(author = crate_authors!())]
+#[command(subcommand_negates_reqs = true)]
 struct Args {
     /// Yaml config file
-    pub config: String,
+    #[arg(required = true)]
+    pub config: Option<String>,
+
+    /// Stable ids and mail headers on the rendered output, useful for golden tests
+    #[arg(long, env = "LMR_DETERMINISTIC", global = true)]
+    pub deterministic: bool,
+
+    /// Parameter of the querys, as key=value, replacing the config ones
+    #[arg(long = "param", value_parser = parse_param)]
+    pub params: Vec<(String, String)>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
 
     #[command(flatten)]
     verbose: Verbosity<InfoLevel>,
 }
 
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Render the report on demand over HTTP, instead of send it
+    Serve {
+        /// Yaml config file, reloaded on each request
+        config: String,
+
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        bind: String,
+
+        /// Basic auth credentials, as user:pass
+        #[arg(long, env = "LMR_SERVE_AUTH")]
+        auth: Option<String>,
+    },
+}
+
 #[tokio::main]
 async fn main() -> Result<(), String> {
     let args = Args::parse();
@@ -44,38 +86,368 @@ async fn main() -> Result<(), String> {
         TerminalMode::Mixed,
         ColorChoice::Auto,
     )
-    .map_err(|e| format!("Logger init failed: {}", e.to_string()))?;
+    .map_err(|e| format!("Logger init failed: {}", e))?;
+
+    presentation::set_deterministic(args.deterministic);
+
+    if let Some(Command::Serve { config, bind, auth }) = args.command {
+        return serve::serve(config, bind, auth).await;
+    }
+
+    let mut config = load_config(&args.config.unwrap_or_default())?;
+    config.set_params(&args.params);
+
+    let generated = report(&config).await?;
+
+    if config.send.stdout {
+        send::to_stdout(&generated.content).await?;
+    }
+
+    if let Some(path) = &config.send.file {
+        send::to_file(
+            &send::file_path(path)?,
+            &generated.content,
+            config.send.zip.as_ref(),
+        )
+        .await?;
+    }
+
+    if let Some(set) = config.send.archive.clone() {
+        send::to_archive(set, &generated.content, &generated.data()).await?;
+    }
 
-    debug!("Loading the config file: {}", args.config);
+    if let Some(set) = config.send.mail.clone() {
+        let mut mails = vec![];
 
-    let sconfig = fs::read_to_string(args.config)
-        .map_err(|e| format!("Config file not loaded: {}", e.to_string()))?;
+        if set.recipients.is_empty() {
+            mails.push((set.to.clone(), generated.content.clone()));
+        }
+
+        for recipient in &set.recipients {
+            let content = present(&config, &generated.fetched, Some(recipient))?;
+            mails.push((recipient.to.clone(), content));
+        }
+
+        let subject = clock::current().placeholders(&config.title);
+        send::to_mail(set, config.send.zip.as_ref(), subject, &mails).await?;
+    }
+
+    // Only the complete runs, the next one is compared with the last
+    // state of all the querys
+    if generated.complete() {
+        clock::record_run(&config.run, generated.started)?;
+        for f in &generated.fetched {
+            record_state(f)?;
+        }
+    } else if config.run.state_file.is_some() || config.run.state_db.is_some() {
+        warn!("State of the run not recorded, with failed querys");
+    }
+
+    if generated.unmet > 0 {
+        return Err(format!("{} query expectations not met", generated.unmet));
+    }
+
+    Ok(())
+}
+
+fn parse_param(param: &str) -> Result<(String, String), String> {
+    param
+        .split_once('=')
+        .map(|(k, v)| (k.trim().to_string(), v.to_string()))
+        .ok_or(format!("Invalid param {}, expected key=value", param))
+}
+
+/// Load and parse the config file
+pub fn load_config(path: &str) -> Result<Config, String> {
+    debug!("Loading the config file: {}", path);
+
+    let sconfig = fs::read_to_string(path).map_err(|e| format!("Config file not loaded: {}", e))?;
 
     debug!("Parsing the config file");
 
-    let config = serde_yaml::from_str::<Config>(&sconfig)
-        .map_err(|e| format!("Config file not parsed: {}", e.to_string()))?;
+    let mut config = serde_yaml::from_str::<Config>(&sconfig)
+        .map_err(|e| format!("Config file not parsed: {}", e))?;
+    config.set_chart_output();
 
-    let querys = config::to_querys(config.querys);
-    let lquerys = querys.iter().map(|q| q.0.clone()).collect::<Vec<_>>();
+    // The combined reports are relative to this one
+    if let Some(dir) = Path::new(path).parent() {
+        for p in config.digest.iter_mut() {
+            *p = dir.join(&p).to_string_lossy().to_string();
+        }
+        if let Some(p) = config.template.as_mut() {
+            *p = dir.join(&p).to_string_lossy().to_string();
+        }
 
-    let data = source::fetch(config.source, lquerys).await?;
+        config.load_sql_files(dir)?;
+        config.load_style_file(dir)?;
+    }
+
+    Ok(config)
+}
 
-    let mut ndata = vec![];
-    for (q, r) in data {
-        let chart = config::find_component(querys.clone(), q.clone());
-        ndata.push((q, chart, r));
+/// Querys fetched of a report, with the config to present them
+pub struct Fetched {
+    pub config: Config,
+    pub data: Vec<QueryResult>,
+    /// Results of the diffs against the last run, for the next one
+    pub snapshots: Vec<(String, Snapshot)>,
+}
+
+/// Presented report with the data used on it
+pub struct Generated {
+    pub content: DataPresented,
+    /// The report followed by the combined ones
+    pub fetched: Vec<Fetched>,
+    /// Number of the query expectations not met
+    pub unmet: usize,
+    /// When the fetch started, the last run of the next one
+    pub started: DateTime<Utc>,
+}
+
+impl Generated {
+    /// Querys of all reports
+    pub fn data(&self) -> Vec<QueryResult> {
+        self.fetched.iter().flat_map(|f| f.data.clone()).collect()
     }
 
-    let content = presentation::present_as(ndata, config.title.clone(), config.send.format)?;
+    /// If all the querys succeeded and met their expectations
+    pub fn complete(&self) -> bool {
+        self.unmet == 0
+            && self
+                .fetched
+                .iter()
+                .all(|f| f.data.iter().all(|(_, r)| r.is_ok()))
+    }
+}
 
-    if config.send.stdout {
-        send::to_stdout(&content).await?;
+/// Fetch and present the querys of the config, with
+/// the combined reports as sections
+pub async fn report(config: &Config) -> Result<Generated, String> {
+    let mut run = config.run.clone();
+    // Without the wall clock, the same data renders the same report
+    if presentation::is_deterministic() && run.as_of.is_none() {
+        run.as_of = Some(clock::DETERMINISTIC_AS_OF.to_string());
+    }
+    clock::configure(&run)?;
+    presentation::locale::configure(config.language);
+
+    // Before the fetch, the rows changed while fetching are of the next run
+    let started = Utc::now();
+    let (data, mut unmet, snapshots) = collect(config).await?;
+
+    let mut fetched = vec![Fetched {
+        config: config.clone(),
+        data,
+        snapshots,
+    }];
+
+    for path in &config.digest {
+        info!("Combining the {} report", path);
+
+        let sconfig = load_config(path)?;
+        let (sdata, sunmet, ssnapshots) = collect(&sconfig).await?;
+
+        fetched.push(Fetched {
+            config: sconfig,
+            data: sdata,
+            snapshots: ssnapshots,
+        });
+        unmet += sunmet;
     }
 
-    if let Some(set) = config.send.mail {
-        send::to_mail(set, config.title, &content).await?;
+    let content = present(config, &fetched, None)?;
+
+    Ok(Generated {
+        content,
+        fetched,
+        unmet,
+        started,
+    })
+}
+
+/// Present the fetched querys, only with the rows of the recipient when informed
+pub fn present(
+    config: &Config,
+    fetched: &[Fetched],
+    recipient: Option<&Recipient>,
+) -> Result<DataPresented, String> {
+    let mut reports = fetched.iter().map(|f| {
+        f.data
+            .iter()
+            .zip(f.config.querys.iter())
+            .map(|((q, r), cq)| {
+                let mut q = q.clone();
+                let r = match recipient {
+                    Some(recipient) => {
+                        // The CSV of the fetch has the rows of everyone
+                        q.csv = None;
+                        r.clone().and_then(|rows| recipient.filter(rows))
+                    }
+                    None => r.clone(),
+                };
+                (q, cq.component(), r)
+            })
+            .collect::<Vec<QueryData>>()
+    });
+
+    let data = reports.next().unwrap_or_default();
+    let sections = reports
+        .zip(fetched.iter().skip(1))
+        .map(|(data, f)| presentation::Section {
+            title: f.config.title.clone(),
+            description: f.config.description.clone(),
+            data,
+        })
+        .collect();
+
+    let report = presentation::Report {
+        title: clock::current().placeholders(&config.title),
+        description: config
+            .description
+            .as_ref()
+            .map(|d| clock::current().placeholders(d)),
+        format: config.send.format.clone(),
+        summary: config.summary,
+        toc: config.toc,
+        template: config
+            .template
+            .as_ref()
+            .map(|p| fs::read_to_string(p).map_err(|e| format!("Template file not loaded: {}", e)))
+            .transpose()?
+            .map(|t| clock::current().placeholders(&t)),
+        style: config.style.clone(),
+        footer: config
+            .footer
+            .as_ref()
+            .map(|f| clock::current().placeholders(f)),
+        attach_xlsx: config.attach_xlsx,
+    };
+
+    presentation::present_as(data, sections, report)
+}
+
+/// Keep the values of the querys with `delta` and the results of
+/// the diffs, for the next run
+fn record_state(fetched: &Fetched) -> Result<(), String> {
+    let Some(path) = &fetched.config.run.state_db else {
+        return Ok(());
+    };
+    let store = delta::DeltaStore::open(path)?;
+
+    for ((_, r), cq) in fetched.data.iter().zip(fetched.config.querys.iter()) {
+        if let (Some(delta), Ok(rows)) = (&cq.delta, r) {
+            store.record(&cq.title, delta, rows)?;
+        }
+    }
+    for (title, snapshot) in &fetched.snapshots {
+        store.record_snapshot(title, snapshot)?;
     }
 
     Ok(())
 }
+
+/// Fetch the querys of the config, applying the redaction and expectations
+async fn collect(
+    config: &Config,
+) -> Result<(Vec<QueryResult>, usize, Vec<(String, Snapshot)>), String> {
+    let redactor = Redactor::new(&config.redact)?;
+    let prepare = |rows| redactor.apply(value::apply_transforms(rows));
+    let store = config
+        .run
+        .state_db
+        .as_ref()
+        .map(|path| delta::DeltaStore::open(path))
+        .transpose()?;
+
+    let lquerys = config::to_querys(config)?;
+
+    // The baselines of the diffs, by the position of the query
+    let (indexes, bquerys): (Vec<_>, Vec<_>) = config
+        .querys
+        .iter()
+        .zip(&lquerys)
+        .enumerate()
+        .filter_map(|(i, (cq, q))| {
+            let sql = cq.diff.as_ref()?.sql.as_ref()?;
+            let bquery = source::Query {
+                sql: clock::current().placeholders(sql),
+                title: format!("{} baseline", q.title),
+                ..q.clone()
+            };
+            Some((i, bquery))
+        })
+        .unzip();
+    let mut baselines = BTreeMap::new();
+    if !bquerys.is_empty() {
+        let bdata = source::fetch_with(config.source.clone(), bquerys, &prepare).await?;
+        for (i, (_, r)) in indexes.into_iter().zip(bdata) {
+            baselines.insert(i, r);
+        }
+    }
+
+    // The transforms and redactions on the chunks, before of the CSV attachments
+    let data = source::fetch_with(config.source.clone(), lquerys, &prepare).await?;
+
+    let mut rdata = vec![];
+    let mut unmet = 0;
+    let mut snapshots = vec![];
+    for (i, ((mut q, r), cq)) in data.into_iter().zip(config.querys.iter()).enumerate() {
+        let mut r = r;
+
+        if !cq.sort_by.is_empty() {
+            r = r.and_then(|rows| sort::sort_rows(rows, &cq.sort_by, &cq.order));
+        }
+
+        // The change columns after the compared fields
+        if let (Some(delta), Some(store)) = (&cq.delta, &store) {
+            let previous = store.previous(&cq.title)?;
+            r = match r {
+                Ok(rows) => delta::apply(q.clone(), rows, delta, &previous).map(|(dq, rows)| {
+                    q = dq;
+                    rows
+                }),
+                Err(e) => Err(e),
+            };
+        }
+
+        if let (Some(expect), Ok(rows)) = (&cq.expect, &r) {
+            if let Err(e) = expect.check(rows.len()) {
+                warn!("Query '{}' expectation not met: {}", q.title, e);
+                unmet += 1;
+                r = Err(e);
+            }
+        }
+
+        // The differences against the baseline, in place of the rows
+        if let Some(diff) = &cq.diff {
+            let baseline = match baselines.remove(&i) {
+                Some(b) => b.map(|rows| Some(presentation::diff::snapshot(&rows))),
+                None => {
+                    let store = store
+                        .as_ref()
+                        .ok_or("Diff without the sql needs the run state_db")?;
+                    if let Ok(rows) = &r {
+                        snapshots.push((cq.title.clone(), presentation::diff::snapshot(rows)));
+                    }
+                    Ok(store.snapshot(&cq.title)?)
+                }
+            };
+            r = match (r, baseline) {
+                (Ok(rows), Ok(Some(baseline))) => {
+                    diff.compare(q.clone(), rows, &baseline).map(|(dq, rows)| {
+                        q = dq;
+                        rows
+                    })
+                }
+                // Nothing to compare on the first run
+                (Ok(_), Ok(None)) => Ok(vec![]),
+                (Ok(_), Err(e)) => Err(format!("Baseline failed: {}", e)),
+                (Err(e), _) => Err(e),
+            };
+        }
+
+        rdata.push((q, r));
+    }
+
+    Ok((rdata, unmet, snapshots))
+}
@@ -0,0 +1,236 @@
+//! Files with the results of the querys, attached to the report
+
+use crate::{source::Query, value::Value};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attachment {
+    pub name: String,
+    pub mime: String,
+    pub data: Vec<u8>,
+}
+
+/// CSV file of the rows, with the titles of the fields on the header
+/// and the raw values, without the formats of the fields. The one
+/// written on the fetch is kept, when the query has it
+pub fn csv(query: &Query, rows: &[Vec<Value>]) -> Result<Attachment, String> {
+    let data = match &query.csv {
+        Some(data) => data.to_vec(),
+        None => {
+            let mut writer = CsvWriter::new(query);
+            writer.write(rows)?;
+            writer.finish()?
+        }
+    };
+
+    Ok(Attachment {
+        name: format!("{}.csv", file_name(&query.title)),
+        mime: "text/csv".to_string(),
+        data,
+    })
+}
+
+/// CSV of the rows written chunk by chunk, as they are fetched
+pub struct CsvWriter {
+    title: String,
+    fields: Vec<String>,
+    writer: csv::Writer<Vec<u8>>,
+    header: bool,
+}
+
+impl CsvWriter {
+    /// Header by the fields of the query or, when inferred,
+    /// by the ones of the first row
+    pub fn new(query: &Query) -> Self {
+        Self {
+            title: query.title.clone(),
+            fields: query.fields.iter().map(|f| f.title.clone()).collect(),
+            writer: csv::Writer::from_writer(vec![]),
+            header: false,
+        }
+    }
+
+    pub fn write(&mut self, rows: &[Vec<Value>]) -> Result<(), String> {
+        if !self.header {
+            if self.fields.is_empty() {
+                if let Some(row) = rows.first() {
+                    self.fields = row.iter().map(|v| v.field.title.clone()).collect();
+                }
+            }
+            self.write_header()?;
+        }
+
+        for row in rows {
+            self.writer
+                .write_record(
+                    row.iter()
+                        .map(|v| v.inner.as_ref().map(|i| i.to_string()).unwrap_or_default()),
+                )
+                .map_err(|e| format!("CSV of {} not written: {}", self.title, e))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<Vec<u8>, String> {
+        if !self.header {
+            self.write_header()?;
+        }
+
+        self.writer
+            .into_inner()
+            .map_err(|e| format!("CSV of {} not written: {}", self.title, e))
+    }
+
+    fn write_header(&mut self) -> Result<(), String> {
+        self.header = true;
+        self.writer
+            .write_record(&self.fields)
+            .map_err(|e| format!("CSV of {} not written: {}", self.title, e))
+    }
+}
+
+/// Name of the file by the title, only with the letters, digits and dashes
+pub fn file_name(title: &str) -> String {
+    let name = title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|p| !p.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+
+    if name.is_empty() {
+        "result".to_string()
+    } else {
+        name
+    }
+}
+
+/// Attachments with a suffix on the repeated names, like `sales-2.csv`
+pub fn unique_names(attachments: &mut [Attachment]) {
+    let mut seen: Vec<String> = vec![];
+
+    for a in attachments.iter_mut() {
+        let (stem, ext) = match a.name.rsplit_once('.') {
+            Some((stem, ext)) => (stem.to_string(), format!(".{}", ext)),
+            None => (a.name.clone(), String::new()),
+        };
+
+        let mut n = 1;
+        while seen.contains(&a.name) {
+            n += 1;
+            a.name = format!("{}-{}{}", stem, n, ext);
+        }
+        seen.push(a.name.clone());
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{csv, file_name, unique_names, Attachment, CsvWriter};
+    use crate::{
+        source::Query,
+        value::{Field, FieldType, NumberFormat, TypedValue, Value},
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn csv_file() -> Result<(), String> {
+        let query = Query {
+            title: "Sales by region".to_string(),
+            fields: vec![
+                Field {
+                    title: "Region".to_string(),
+                    field: "region".to_string(),
+                    ..Default::default()
+                },
+                Field {
+                    title: "Total".to_string(),
+                    field: "total".to_string(),
+                    kind: FieldType::Float,
+                    format: Some(NumberFormat {
+                        currency: Some("$".to_string()),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let rows = vec![
+            vec![
+                Value {
+                    inner: Some(TypedValue::String("North, east".to_string())),
+                    field: query.fields[0].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::Float(1200.5)),
+                    field: query.fields[1].clone(),
+                },
+            ],
+            vec![
+                Value {
+                    inner: Some(TypedValue::String("South".to_string())),
+                    field: query.fields[0].clone(),
+                },
+                Value {
+                    inner: None,
+                    field: query.fields[1].clone(),
+                },
+            ],
+        ];
+
+        let attachment = csv(&query, &rows)?;
+        assert_eq!("sales-by-region.csv", attachment.name);
+        assert_eq!("text/csv", attachment.mime);
+        assert_eq!(
+            "Region,Total\n\"North, east\",1200.5\nSouth,\n",
+            String::from_utf8_lossy(&attachment.data)
+        );
+
+        // Chunk by chunk, with the header of the inferred fields
+        let mut writer = CsvWriter::new(&Query {
+            fields: vec![],
+            ..query.clone()
+        });
+        writer.write(&rows[..1])?;
+        writer.write(&[])?;
+        writer.write(&rows[1..])?;
+        let data = writer.finish()?;
+        assert_eq!(attachment.data, data);
+
+        let query = Query {
+            csv: Some(Arc::new(data)),
+            ..query
+        };
+        assert_eq!(attachment, csv(&query, &[])?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!("daily-sales-2024", file_name("Daily Sales (2024)"));
+        assert_eq!("vendas-são-paulo", file_name("Vendas: São Paulo"));
+        assert_eq!("result", file_name("%%"));
+
+        let attachment = |name: &str| Attachment {
+            name: name.to_string(),
+            mime: "text/csv".to_string(),
+            data: vec![],
+        };
+        let mut attachments = vec![
+            attachment("sales.csv"),
+            attachment("users.csv"),
+            attachment("sales.csv"),
+            attachment("sales.csv"),
+        ];
+        unique_names(&mut attachments);
+        assert_eq!(
+            vec!["sales.csv", "users.csv", "sales-2.csv", "sales-3.csv"],
+            attachments
+                .iter()
+                .map(|a| a.name.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+}
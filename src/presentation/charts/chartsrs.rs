@@ -0,0 +1,902 @@
+//! charts_rs backend implementation
+
+use super::{gauge_band, heat_color, ChartBackend, ChartComponent, ChartLegend, ChartType};
+use crate::{
+    presentation::{formats::escape, locale},
+    value::{Field, NumberFormat},
+};
+use charts_rs::{
+    self, Align, BarChart, Box, HorizontalBarChart, LineChart, PieChart, ScatterChart, Series,
+    SeriesCategory, DEFAULT_FONT_FAMILY, NIL_VALUE,
+};
+
+/// Size, margin and theme of the chart, on the fields that all the
+/// charts_rs charts have
+macro_rules! themed {
+    ($target:ident, $chart:expr, $margin:expr, $colors:expr, $format:expr) => {
+        let (width, height) = $chart.size();
+        let theme = &$chart.theme;
+        $target.width = width as f32;
+        $target.height = height as f32;
+        $target.margin = $margin;
+        if let Some(title) = &$chart.title {
+            // The legend below the title
+            $target.title_text = escape(title);
+            $target.legend_margin = Some(Box {
+                top: 35.0,
+                bottom: 10.0,
+                ..Default::default()
+            });
+        }
+        if !$colors.is_empty() {
+            $target.series_colors = $colors.iter().map(|c| (*c).into()).collect();
+        }
+        if let Some(family) = &theme.font_family {
+            $target.font_family = family.clone();
+        }
+        if let Some(size) = theme.font_size {
+            $target.x_axis_font_size = size;
+            $target.legend_font_size = size;
+            $target.series_label_font_size = size;
+            for axis in $target.y_axis_configs.iter_mut() {
+                axis.axis_font_size = size;
+            }
+        }
+        if let Some(format) = $format {
+            // The secondary axis keeps the raw values
+            let secondary = $target.series_list.iter().any(|s| s.y_axis_index != 0);
+            if secondary && $target.y_axis_configs.len() == 1 {
+                let raw = $target.y_axis_configs[0].clone();
+                $target.y_axis_configs.push(raw);
+            }
+            if let Some(axis) = $target.y_axis_configs.first_mut() {
+                axis.axis_formatter = Some(formatter(format));
+            }
+            // The labels support only the thousands, without the symbols
+            if format.thousands.is_some() {
+                $target.series_label_formatter = "{t}".to_string();
+            }
+        }
+        match theme.legend {
+            Some(ChartLegend::Left) => $target.legend_align = Align::Left,
+            Some(ChartLegend::Center) => $target.legend_align = Align::Center,
+            Some(ChartLegend::Right) => $target.legend_align = Align::Right,
+            Some(ChartLegend::Hidden) => $target.legend_show = Some(false),
+            None => {}
+        }
+        if theme.x_labels_rotation != 0.0 {
+            // Room for the rotated labels, below the axis
+            let longest = $target.x_axis_data.iter().map(|k| k.chars().count()).max();
+            let sin = theme.x_labels_rotation.to_radians().sin().abs();
+            $target.x_axis_height +=
+                longest.unwrap_or(0) as f32 * $target.x_axis_font_size * 0.6 * sin;
+            $target.x_axis_name_rotate = theme.x_labels_rotation.to_radians();
+        }
+        if !theme.grid {
+            $target.grid_stroke_width = 0.0;
+        }
+    };
+}
+
+pub struct ChartsRsBackend {}
+
+impl ChartBackend for ChartsRsBackend {
+    fn png(
+        &self,
+        chart: &ChartComponent,
+        keys: Vec<String>,
+        series: Vec<Series>,
+        field: Option<&Field>,
+    ) -> Result<Vec<u8>, String> {
+        let svg = self.svg(chart, keys, series, field)?;
+
+        // The PNG is of the size of the root element, the viewBox keeps the drawing
+        let (width, height) = chart.size();
+        let scale = chart.scale();
+        let svg = svg.replacen(
+            &format!("<svg width=\"{}\" height=\"{}\"", width, height),
+            &format!(
+                "<svg width=\"{}\" height=\"{}\"",
+                (width as f32 * scale).round(),
+                (height as f32 * scale).round()
+            ),
+            1,
+        );
+
+        charts_rs::svg_to_png(&svg).map_err(|e| format!("Error converting SVG to PNG: {}", e))
+    }
+
+    fn svg(
+        &self,
+        chart: &ChartComponent,
+        keys: Vec<String>,
+        series: Vec<Series>,
+        field: Option<&Field>,
+    ) -> Result<String, String> {
+        // The fonts of the charts_rs are loaded only once, by the first
+        // chart, and used for the texts of the gauge and heatmap SVGs too
+        let font = chart.theme.font_data()?;
+        charts_rs::get_or_try_init_fonts(font.map(|f| vec![f]))
+            .map_err(|e| format!("Error loading the chart fonts: {}", e))?;
+
+        let colors = chart.theme.colors()?;
+        // Room for the titles of the axes, drawn over the chart
+        let margin = Box {
+            top: 10.0,
+            bottom: if chart.x_title.is_some() { 30.0 } else { 10.0 },
+            left: if chart.y_title.is_some() { 30.0 } else { 10.0 },
+            right: 10.0,
+        };
+        // The formats of charts_rs only add the symbols to the values, so
+        // the percents go on the scale of 100 before
+        let format = field.and_then(|f| f.format.as_ref());
+        let percent = format.is_some_and(|f| f.percent)
+            && !matches!(
+                chart.kind,
+                ChartType::Gauge | ChartType::Heatmap | ChartType::Calendar
+            );
+        // The texts of the data go as they are into the SVG of the charts_rs,
+        // the gauge and heatmap ones escape them when drawn
+        let drawn = matches!(
+            chart.kind,
+            ChartType::Gauge | ChartType::Heatmap | ChartType::Calendar
+        );
+        let text = |t: &str| if drawn { t.to_string() } else { escape(t) };
+        let keys = keys.iter().map(|k| text(k)).collect::<Vec<_>>();
+        let series = series
+            .into_iter()
+            .map(|s| Series {
+                name: text(&s.name),
+                label_show: chart.labels.unwrap_or(false),
+                data: if percent && s.y_axis_index == 0 {
+                    // Only the y of the scatter points
+                    let y = |i: usize| chart.kind != ChartType::Scatter || i % 2 == 1;
+                    (s.data.iter().enumerate())
+                        .map(|(i, v)| if y(i) { v * 100.0 } else { *v })
+                        .collect()
+                } else {
+                    s.data.clone()
+                }
+                .into_iter()
+                // The gaps of the filled keys
+                .map(|v| if v.is_nan() { NIL_VALUE } else { v })
+                .collect(),
+                ..s
+            })
+            .collect::<Vec<_>>();
+
+        let svg = match chart.kind {
+            ChartType::Bar => {
+                let mut bar = BarChart::new(series, keys);
+                themed!(bar, chart, margin, colors, format);
+                bar.series_smooth = chart.smooth;
+                bar.series_fill = chart.fill;
+                bar.svg()
+            }
+            ChartType::HorizontalBar => {
+                let mut bar = HorizontalBarChart::new(series, keys);
+                themed!(bar, chart, margin, colors, format);
+                bar.svg()
+            }
+            // Only the bar chart mixes the kinds, the series without one as lines
+            ChartType::Line | ChartType::Area
+                if series
+                    .iter()
+                    .any(|s| s.category == Some(SeriesCategory::Bar)) =>
+            {
+                let series = series
+                    .into_iter()
+                    .map(|s| Series {
+                        category: s.category.or(Some(SeriesCategory::Line)),
+                        ..s
+                    })
+                    .collect();
+                let mut bar = BarChart::new(series, keys);
+                themed!(bar, chart, margin, colors, format);
+                bar.series_smooth = chart.smooth;
+                bar.series_fill = chart.filled();
+                bar.svg()
+            }
+            ChartType::Line | ChartType::Area => {
+                let mut line = LineChart::new(series, keys);
+                themed!(line, chart, margin, colors, format);
+                line.series_smooth = chart.smooth;
+                line.series_fill = chart.filled();
+                line.svg()
+            }
+            ChartType::Pizza => {
+                let mut pie = PieChart::new(series);
+                themed!(pie, chart, margin, colors, format);
+                if chart.percents {
+                    pie.series_label_formatter = "{a}: {d}".to_string();
+                }
+                pie.svg()
+            }
+            ChartType::Donut => {
+                let mut pie = PieChart::new(series);
+                themed!(pie, chart, margin, colors, format);
+                if chart.percents {
+                    pie.series_label_formatter = "{a}: {d}".to_string();
+                }
+                pie.rose_type = Some(false);
+                pie.inner_radius = 80.0;
+                pie.svg()
+            }
+            ChartType::Gauge => Ok(gauge_svg(chart, &series, field)?),
+            ChartType::Heatmap | ChartType::Calendar => {
+                Ok(heatmap_svg(chart, &keys, &series, field))
+            }
+            ChartType::Scatter => {
+                let mut scatter = ScatterChart::new(series);
+                themed!(scatter, chart, margin, colors, format);
+                scatter.svg()
+            }
+        }
+        .map_err(|e| format!("Error generating chart: {}", e))?;
+
+        Ok(match chart.kind {
+            ChartType::Pizza | ChartType::Donut | ChartType::Gauge => svg,
+            _ => with_axis_titles(chart, svg),
+        })
+    }
+}
+
+/// Template of the charts_rs axis values by the format. It supports only
+/// the thousands by comma and scales the big values by the SI prefixes
+fn formatter(format: &NumberFormat) -> String {
+    let mut value = if format.thousands.is_some() && !format.si {
+        "{t}".to_string()
+    } else {
+        "{c}".to_string()
+    };
+    if format.percent {
+        value.push('%');
+    }
+    match &format.currency {
+        Some(c) if format.currency_before => format!("{} {}", c, value),
+        Some(c) => format!("{} {}", value, c),
+        None => value,
+    }
+}
+
+/// Titles of the axes, at the bottom and at the left of the chart
+fn with_axis_titles(chart: &ChartComponent, mut svg: String) -> String {
+    let (width, height) = chart.size();
+    let family = chart
+        .theme
+        .font_family
+        .as_deref()
+        .unwrap_or(DEFAULT_FONT_FAMILY);
+    let text = |x: f32, y: f32, rotate: i32, content: &str| {
+        format!(
+            "<text x=\"{:.1}\" y=\"{:.1}\" transform=\"rotate({} {:.1} {:.1})\" font-size=\"14\" font-family=\"{}\" fill=\"#333\" text-anchor=\"middle\" dominant-baseline=\"middle\">{}</text>",
+            x, y, rotate, x, y, family, escape(content)
+        )
+    };
+
+    let mut titles = vec![];
+    if let Some(title) = &chart.x_title {
+        titles.push(text(width as f32 / 2.0, height as f32 - 12.0, 0, title));
+    }
+    if let Some(title) = &chart.y_title {
+        titles.push(text(14.0, height as f32 / 2.0, -90, title));
+    }
+
+    if let Some(end) = svg.rfind("</svg>") {
+        svg.insert_str(end, &titles.join("\n"));
+    }
+    svg
+}
+
+/// SVG of the heatmap, with the keys as the columns and the series as
+/// the rows, the first at the top. The charts_rs one only supports the
+/// matrices of the same count of columns and rows
+fn heatmap_svg(
+    chart: &ChartComponent,
+    keys: &[String],
+    series: &[Series],
+    field: Option<&Field>,
+) -> String {
+    let (full_width, full_height) = chart.size();
+    let values = series.iter().flat_map(|s| s.data.iter().copied());
+    let (min, max) = values.fold((f32::MAX, f32::MIN), |(a, b), v| (a.min(v), b.max(v)));
+
+    // Room for the titles and the names of the series and keys
+    let top = if chart.title.is_some() { 40.0 } else { 10.0 };
+    let bottom = if chart.x_title.is_some() { 60.0 } else { 40.0 };
+    let longest = series.iter().map(|s| s.name.chars().count()).max();
+    let left = (longest.unwrap_or(0) as f32 * 7.0 + 20.0).clamp(50.0, 300.0)
+        + if chart.y_title.is_some() { 20.0 } else { 0.0 };
+    let width = (full_width as f32 - 10.0 - left).max(0.0) / keys.len().max(1) as f32;
+    let height = (full_height as f32 - top - bottom).max(0.0) / series.len().max(1) as f32;
+
+    let label = |v: f32| match field {
+        Some(f) => f.format_number(v as f64),
+        None => format!("{}", v),
+    };
+    let family = chart
+        .theme
+        .font_family
+        .as_deref()
+        .unwrap_or(DEFAULT_FONT_FAMILY);
+    let text = |x: f32, y: f32, anchor: &str, content: &str| {
+        format!(
+            "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"11\" font-family=\"{}\" fill=\"#333\" text-anchor=\"{}\" dominant-baseline=\"middle\">{}</text>",
+            x, y, family, anchor, content
+        )
+    };
+
+    let mut svg = vec![
+        format!(
+            "<svg width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\" xmlns=\"http://www.w3.org/2000/svg\">",
+            full_width, full_height
+        ),
+        format!(
+            "<rect width=\"{}\" height=\"{}\" fill=\"#fff\"/>",
+            full_width, full_height
+        ),
+    ];
+
+    if let Some(title) = &chart.title {
+        svg.push(format!(
+            "<text x=\"{:.1}\" y=\"22\" font-size=\"18\" font-family=\"{}\" fill=\"#333\" text-anchor=\"middle\" dominant-baseline=\"middle\">{}</text>",
+            full_width as f32 / 2.0,
+            family,
+            escape(title)
+        ));
+    }
+    for (k, key) in keys.iter().enumerate() {
+        let y = full_height as f32 - bottom + 15.0;
+        svg.push(text(
+            left + width * (k as f32 + 0.5),
+            y,
+            "middle",
+            &escape(key),
+        ));
+    }
+    for (s, serie) in series.iter().enumerate() {
+        let y = top + height * s as f32;
+        svg.push(text(
+            left - 6.0,
+            y + height / 2.0,
+            "end",
+            &escape(&serie.name),
+        ));
+
+        for (k, v) in serie.data.iter().enumerate() {
+            let x = left + width * k as f32;
+            let (r, g, b) = heat_color(*v, min, max);
+            svg.push(format!(
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"rgb({},{},{})\" stroke=\"#fff\"/>",
+                x, y, width, height, r, g, b
+            ));
+            if chart.labels != Some(false) {
+                svg.push(text(
+                    x + width / 2.0,
+                    y + height / 2.0,
+                    "middle",
+                    &label(*v),
+                ));
+            }
+        }
+    }
+    svg.push("</svg>".to_string());
+
+    svg.join("\n")
+}
+
+/// SVG of the gauge, with the arc of the value over the full one and
+/// the mark of the target. Drawn on 600x400 and fitted on the size of the chart
+fn gauge_svg(
+    chart: &ChartComponent,
+    series: &[Series],
+    field: Option<&Field>,
+) -> Result<String, String> {
+    let gauge = chart.gauge.as_ref().ok_or("Gauge must be defined")?;
+    let data = series.first().map(|s| s.data.clone()).unwrap_or_default();
+    let value = *data.first().ok_or("Gauge without value")?;
+    let target = data.get(1).copied();
+
+    let label = |v: f32| match field {
+        Some(f) => f.format_number(v as f64),
+        None => format!("{}", v),
+    };
+    let polygon = |from: f32, to: f32, color: &str| {
+        let points = gauge_band(from, to, (300.0, 300.0), 200.0, 140.0)
+            .iter()
+            .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+            .collect::<Vec<_>>()
+            .join(" ");
+        format!("<polygon points=\"{}\" fill=\"{}\"/>", points, color)
+    };
+    let family = chart
+        .theme
+        .font_family
+        .as_deref()
+        .unwrap_or(DEFAULT_FONT_FAMILY);
+    let text = |x: f32, y: f32, size: u32, content: String| {
+        format!(
+            "<text x=\"{}\" y=\"{}\" font-size=\"{}\" font-family=\"{}\" fill=\"#333\" text-anchor=\"middle\">{}</text>",
+            x, y, size, family, content
+        )
+    };
+
+    let (width, height) = chart.size();
+    let fit = (width as f32 / 600.0).min(height as f32 / 400.0);
+    let (r, g, b) = gauge.color(value, target);
+    let mut svg = vec![
+        format!(
+            "<svg width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\" xmlns=\"http://www.w3.org/2000/svg\">",
+            width, height
+        ),
+        format!(
+            "<rect width=\"{}\" height=\"{}\" fill=\"#fff\"/>",
+            width, height
+        ),
+        format!(
+            "<g transform=\"translate({:.1},{:.1}) scale({})\">",
+            (width as f32 - 600.0 * fit) / 2.0,
+            (height as f32 - 400.0 * fit) / 2.0,
+            fit
+        ),
+        polygon(0.0, 1.0, "#e6e6e6"),
+        polygon(0.0, gauge.ratio(value), &format!("rgb({},{},{})", r, g, b)),
+        text(300.0, 60.0, 18, escape(chart.title.as_deref().unwrap_or_default())),
+        text(300.0, 280.0, 44, label(value)),
+        text(130.0, 330.0, 14, label(gauge.min)),
+        text(470.0, 330.0, 14, label(gauge.max)),
+        text(300.0, 330.0, 16, escape(&series[0].name)),
+    ];
+
+    if let Some(target) = target {
+        let mark = gauge_band(
+            gauge.ratio(target),
+            gauge.ratio(target),
+            (300.0, 300.0),
+            210.0,
+            130.0,
+        );
+        svg.push(format!(
+            "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"#333\" stroke-width=\"3\"/>",
+            mark[0].0, mark[0].1, mark[2].0, mark[2].1
+        ));
+        svg.push(text(
+            300.0,
+            360.0,
+            16,
+            locale::fill(locale::texts().target, &[&label(target)]),
+        ));
+    }
+    svg.push("</g>".to_string());
+    svg.push("</svg>".to_string());
+
+    Ok(svg.join("\n"))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{formatter, ChartsRsBackend};
+    use crate::presentation::charts::{
+        ChartBackend, ChartComponent, ChartGauge, ChartGaugeTarget, ChartLegend, ChartTheme,
+        ChartType,
+    };
+    use crate::value::{Field, NumberFormat};
+    use charts_rs::{Series, SeriesCategory};
+
+    #[test]
+    pub fn bar_png() {
+        let chart = ChartComponent {
+            kind: ChartType::Bar,
+            ..Default::default()
+        };
+
+        let png = ChartsRsBackend {}.png(
+            &chart,
+            vec!["a".to_string(), "b".to_string()],
+            vec![Series::new("Qt".to_string(), vec![1.0, 2.0])],
+            None,
+        );
+
+        assert!(png.is_ok());
+        assert!(png.unwrap().starts_with(&[0x89, b'P', b'N', b'G']));
+    }
+
+    #[test]
+    pub fn mixed_png() {
+        let mut margin = Series::new("Margin".to_string(), vec![0.2, 0.35]);
+        margin.category = Some(SeriesCategory::Line);
+        margin.y_axis_index = 1;
+
+        let chart = ChartComponent {
+            kind: ChartType::Bar,
+            ..Default::default()
+        };
+        let png = ChartsRsBackend {}.png(
+            &chart,
+            vec!["a".to_string(), "b".to_string()],
+            vec![
+                Series::new("Revenue".to_string(), vec![1500.0, 2300.0]),
+                margin.clone(),
+            ],
+            None,
+        );
+        assert!(png.is_ok());
+
+        let mut revenue = Series::new("Revenue".to_string(), vec![1500.0, 2300.0]);
+        revenue.category = Some(SeriesCategory::Bar);
+        margin.category = None;
+
+        let chart = ChartComponent {
+            kind: ChartType::Line,
+            ..Default::default()
+        };
+        let png = ChartsRsBackend {}.png(
+            &chart,
+            vec!["a".to_string(), "b".to_string()],
+            vec![revenue, margin],
+            None,
+        );
+        assert!(png.is_ok());
+    }
+
+    #[test]
+    pub fn sized_svg() {
+        let chart = ChartComponent {
+            kind: ChartType::Bar,
+            width: Some(300),
+            height: Some(200),
+            scale: Some(2.0),
+            ..Default::default()
+        };
+        let svg = ChartsRsBackend {}
+            .svg(
+                &chart,
+                vec!["a".to_string(), "b".to_string()],
+                vec![Series::new("Qt".to_string(), vec![1.0, 2.0])],
+                None,
+            )
+            .unwrap();
+        assert!(svg.starts_with("<svg width=\"300\" height=\"200\""));
+    }
+
+    #[test]
+    pub fn sized_png() {
+        // Width and height of the IHDR chunk
+        let size = |png: &[u8]| {
+            (
+                u32::from_be_bytes(png[16..20].try_into().unwrap()),
+                u32::from_be_bytes(png[20..24].try_into().unwrap()),
+            )
+        };
+
+        let chart = ChartComponent {
+            kind: ChartType::Bar,
+            width: Some(300),
+            height: Some(200),
+            scale: Some(2.0),
+            ..Default::default()
+        };
+        let png = ChartsRsBackend {}
+            .png(
+                &chart,
+                vec!["a".to_string(), "b".to_string()],
+                vec![Series::new("Qt".to_string(), vec![1.0, 2.0])],
+                None,
+            )
+            .unwrap();
+        assert_eq!((600, 400), size(&png));
+
+        let chart = ChartComponent {
+            kind: ChartType::Gauge,
+            gauge: Some(ChartGauge {
+                value: "qt".to_string(),
+                target: None,
+                min: 0.0,
+                max: 100.0,
+            }),
+            ..chart
+        };
+        let png = ChartsRsBackend {}
+            .png(
+                &chart,
+                vec![],
+                vec![Series::new("Qt".to_string(), vec![40.0])],
+                None,
+            )
+            .unwrap();
+        assert_eq!((600, 400), size(&png));
+    }
+
+    #[test]
+    pub fn themed_png() {
+        let chart = ChartComponent {
+            kind: ChartType::Bar,
+            theme: ChartTheme {
+                palette: vec!["#1b365d".to_string()],
+                font_size: Some(10.0),
+                legend: Some(ChartLegend::Right),
+                x_labels_rotation: 90.0,
+                grid: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let keys = vec!["a".to_string(), "b".to_string()];
+        let series = vec![
+            Series::new("Qt".to_string(), vec![1.0, 2.0]),
+            Series::new("Total".to_string(), vec![3.0, 4.0]),
+        ];
+        let png = ChartsRsBackend {}.png(&chart, keys.clone(), series.clone(), None);
+        assert!(png.is_ok());
+
+        let chart = ChartComponent {
+            kind: ChartType::Pizza,
+            theme: ChartTheme {
+                legend: Some(ChartLegend::Hidden),
+                ..chart.theme
+            },
+            ..chart
+        };
+        let png = ChartsRsBackend {}.png(&chart, keys.clone(), series.clone(), None);
+        assert!(png.is_ok());
+
+        let chart = ChartComponent {
+            theme: ChartTheme {
+                palette: vec!["dark".to_string()],
+                ..Default::default()
+            },
+            ..chart
+        };
+        let png = ChartsRsBackend {}.png(&chart, keys, series, None);
+        assert!(png.is_err());
+    }
+
+    #[test]
+    pub fn titled_png() {
+        let chart = ChartComponent {
+            kind: ChartType::Bar,
+            title: Some("Sales".to_string()),
+            x_title: Some("Month".to_string()),
+            y_title: Some("Units".to_string()),
+            labels: Some(true),
+            ..Default::default()
+        };
+
+        let keys = vec!["a".to_string(), "b".to_string()];
+        let series = vec![Series::new("Qt".to_string(), vec![1.0, 2.0])];
+        for kind in [ChartType::Bar, ChartType::Scatter, ChartType::Heatmap] {
+            let chart = ChartComponent {
+                kind,
+                ..chart.clone()
+            };
+            let png = ChartsRsBackend {}.png(&chart, keys.clone(), series.clone(), None);
+            assert!(png.is_ok());
+        }
+    }
+
+    #[test]
+    pub fn formatted_png() {
+        let format = NumberFormat {
+            thousands: Some(",".to_string()),
+            currency: Some("$".to_string()),
+            currency_before: true,
+            ..Default::default()
+        };
+        assert_eq!("$ {t}", formatter(&format));
+
+        let format = NumberFormat {
+            si: true,
+            percent: true,
+            currency: Some("pts".to_string()),
+            ..format
+        };
+        assert_eq!("pts {c}%", formatter(&format));
+
+        let field = Field {
+            format: Some(format),
+            ..Default::default()
+        };
+        for kind in [ChartType::Bar, ChartType::Scatter] {
+            let chart = ChartComponent {
+                kind,
+                ..Default::default()
+            };
+            let png = ChartsRsBackend {}.png(
+                &chart,
+                vec!["a".to_string(), "b".to_string()],
+                vec![Series::new("Qt".to_string(), vec![0.1, 0.2])],
+                Some(&field),
+            );
+            assert!(png.is_ok());
+        }
+    }
+
+    #[test]
+    pub fn area_png() {
+        let chart = ChartComponent {
+            kind: ChartType::Area,
+            smooth: true,
+            ..Default::default()
+        };
+
+        let png = ChartsRsBackend {}.png(
+            &chart,
+            vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            vec![Series::new("Qt".to_string(), vec![1.0, 3.0, 6.0])],
+            None,
+        );
+
+        assert!(png.is_ok());
+    }
+
+    #[test]
+    pub fn donut_and_gauge_png() {
+        let chart = ChartComponent {
+            kind: ChartType::Donut,
+            ..Default::default()
+        };
+
+        let png = ChartsRsBackend {}.png(
+            &chart,
+            vec![],
+            vec![
+                Series::new("A".to_string(), vec![1.0]),
+                Series::new("B".to_string(), vec![3.0]),
+            ],
+            None,
+        );
+        assert!(png.is_ok());
+
+        let chart = ChartComponent {
+            kind: ChartType::Gauge,
+            gauge: Some(ChartGauge {
+                value: "uptime".to_string(),
+                target: None,
+                min: 90.0,
+                max: 100.0,
+            }),
+            ..Default::default()
+        };
+
+        let png = ChartsRsBackend {}.png(
+            &chart,
+            vec![],
+            vec![Series::new("Uptime".to_string(), vec![98.7, 99.0])],
+            None,
+        );
+        assert!(png.is_ok());
+        assert!(png.unwrap().starts_with(&[0x89, b'P', b'N', b'G']));
+
+        let svg = ChartsRsBackend {}
+            .svg(
+                &chart,
+                vec![],
+                vec![Series::new("R&D <ops>".to_string(), vec![98.7])],
+                None,
+            )
+            .unwrap();
+        assert!(svg.contains(">R&amp;D &lt;ops&gt;</text>"));
+    }
+
+    #[test]
+    pub fn heatmap_png() {
+        let hours = (0..24).map(|h| h.to_string()).collect::<Vec<_>>();
+        let series = ["Mon", "Tue", "Wed"]
+            .iter()
+            .map(|d| Series::new(d.to_string(), (0..24).map(|h| h as f32).collect()))
+            .collect::<Vec<_>>();
+
+        let chart = ChartComponent {
+            kind: ChartType::Heatmap,
+            ..Default::default()
+        };
+        let png = ChartsRsBackend {}.png(&chart, hours, series, None);
+        assert!(png.is_ok());
+
+        let svg = super::heatmap_svg(
+            &ChartComponent::default(),
+            &["a".to_string(), "b".to_string()],
+            &[
+                Series::new("R&D".to_string(), vec![1.0, 2.0]),
+                Series::new("y".to_string(), vec![3.0, 4.0]),
+            ],
+            None,
+        );
+        assert_eq!(4, svg.matches("stroke=\"#fff\"").count());
+        assert!(svg.contains(">R&amp;D</text>"));
+        assert!(svg.contains("fill=\"rgb(240,217,156)\""));
+        assert!(svg.contains("fill=\"rgb(191,68,76)\""));
+    }
+
+    #[test]
+    pub fn texts_png() {
+        // Without the fonts the texts are dropped from the PNG, the same
+        // as the one of the SVG without them
+        let without_texts = |svg: &str| {
+            let mut r = String::new();
+            let mut rest = svg;
+            while let Some(start) = rest.find("<text") {
+                r.push_str(&rest[..start]);
+                let end = rest[start..].find("</text>").unwrap() + start + "</text>".len();
+                rest = &rest[end..];
+            }
+            r.push_str(rest);
+            r
+        };
+
+        let chart = ChartComponent {
+            kind: ChartType::Gauge,
+            gauge: Some(ChartGauge {
+                value: "uptime".to_string(),
+                target: Some(ChartGaugeTarget::Field("goal".to_string())),
+                min: 90.0,
+                max: 100.0,
+            }),
+            ..Default::default()
+        };
+        let series = vec![Series::new("Uptime".to_string(), vec![98.7, 99.0])];
+
+        let svg = ChartsRsBackend {}
+            .svg(&chart, vec![], series.clone(), None)
+            .unwrap();
+        assert!(svg.contains("<text"));
+
+        let png = ChartsRsBackend {}
+            .png(&chart, vec![], series, None)
+            .unwrap();
+        let blank = charts_rs::svg_to_png(&without_texts(&svg)).unwrap();
+        assert!(blank != png, "PNG without the texts");
+
+        let hours = (0..3).map(|h| h.to_string()).collect::<Vec<_>>();
+        let series = vec![Series::new("Mon".to_string(), vec![1.0, 2.0, 3.0])];
+        let chart = ChartComponent {
+            kind: ChartType::Heatmap,
+            ..Default::default()
+        };
+
+        let svg = ChartsRsBackend {}
+            .svg(&chart, hours.clone(), series.clone(), None)
+            .unwrap();
+        let png = ChartsRsBackend {}.png(&chart, hours, series, None).unwrap();
+        let blank = charts_rs::svg_to_png(&without_texts(&svg)).unwrap();
+        assert!(blank != png, "PNG without the texts");
+    }
+
+    #[test]
+    pub fn scatter_png() {
+        let chart = ChartComponent {
+            kind: ChartType::Scatter,
+            ..Default::default()
+        };
+
+        let png = ChartsRsBackend {}.png(
+            &chart,
+            vec![],
+            vec![Series::new(
+                "Latency".to_string(),
+                vec![10.0, 1.5, 20.0, 3.0, 30.0, 4.5],
+            )],
+            None,
+        );
+
+        assert!(png.is_ok());
+    }
+
+    #[test]
+    pub fn horizontal_bar_png() {
+        let chart = ChartComponent {
+            kind: ChartType::HorizontalBar,
+            ..Default::default()
+        };
+
+        let png = ChartsRsBackend {}.png(
+            &chart,
+            vec!["https://abc.com/orders".to_string(), "b".to_string()],
+            vec![Series::new("Qt".to_string(), vec![1.0, 2.0])],
+            None,
+        );
+
+        assert!(png.is_ok());
+    }
+}
@@ -0,0 +1,1959 @@
+//! Charts component
+
+use super::{formats::OutputFormat, locale, new_cid, Component, ImagePresented, RenderedContent};
+use crate::{
+    clock,
+    sort::SortOrder,
+    source::Query,
+    value::{Field, NumberFormat, TypedValue, Value},
+};
+use charts_rs::{Series, SeriesCategory};
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use log::*;
+use serde::Deserialize;
+use std::{collections::BTreeMap, sync::Mutex};
+
+pub mod chartsrs;
+#[cfg(feature = "plotters")]
+pub mod plotters;
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub enum ChartType {
+    #[default]
+    Bar,
+    /// Bars along the x axis, for the long keys
+    HorizontalBar,
+    Line,
+    /// Line filled down to the axis
+    Area,
+    Pizza,
+    /// Pizza with a hole in the center
+    Donut,
+    /// Points of two numeric fields, by the `points` option
+    Scatter,
+    /// Value against a target, by the `gauge` option
+    Gauge,
+    /// Matrix of the keys by the series, colored by the values
+    Heatmap,
+    /// Heatmap of the days, of the `keys_by` dates, by weekday and week
+    Calendar,
+}
+
+/// How the chart goes on the HTML
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub enum ChartOutput {
+    /// PNG attached to the mail, by the cid
+    #[default]
+    Png,
+    /// SVG inside of the HTML, the plotters backend with the PNG inside
+    Svg,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub enum ChartBackendType {
+    #[default]
+    ChartsRs,
+    Plotters,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct ChartComponent {
+    pub kind: ChartType,
+    #[serde(default)]
+    pub keys_by: Option<String>,
+    #[serde(default)]
+    pub series_by: Option<ChartSeriesBy>,
+    #[serde(default)]
+    pub series: Option<Vec<String>>,
+    /// Fields of the x and y of the scatter charts
+    #[serde(default)]
+    pub points: Option<ChartPoints>,
+    /// Value and target of the gauge charts
+    #[serde(default)]
+    pub gauge: Option<ChartGauge>,
+    /// Kind and axis of each series, by the field or, of the
+    /// `series_by`, by the value of it
+    #[serde(default)]
+    pub series_options: BTreeMap<String, ChartSeriesOptions>,
+    #[serde(default)]
+    pub backend: ChartBackendType,
+    /// The `chart_output` of the report when omitted
+    #[serde(default)]
+    pub output: Option<ChartOutput>,
+    /// Curved lines, on the line and area charts
+    #[serde(default)]
+    pub smooth: bool,
+    /// Area below the lines, always on the area charts
+    #[serde(default)]
+    pub fill: bool,
+    /// Missing dates and times between the keys, with zero
+    #[serde(default)]
+    pub fill_keys: Option<ChartFillKeys>,
+    /// Order of the keys, the one of the result by default
+    #[serde(default)]
+    pub sort_keys: Option<ChartSortKeys>,
+    /// Only the first keys, after the sort
+    #[serde(default)]
+    pub max_keys: Option<usize>,
+    /// Percent of the total on the labels of the pizza and donut slices
+    #[serde(default)]
+    pub percents: bool,
+    /// Only the biggest slices of the pizza and donut, the rest summed
+    /// as the `Others` one
+    #[serde(default)]
+    pub top: Option<usize>,
+    /// Size of the chart, 600x400 by default
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    /// Pixels of the PNG by each of the size, like 2 for the retina
+    /// displays
+    #[serde(default)]
+    pub scale: Option<f32>,
+    /// Title inside of the image, apart of the one of the query
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub x_title: Option<String>,
+    #[serde(default)]
+    pub y_title: Option<String>,
+    /// Values over the bars and points. By default, only by the plotters
+    /// backend and when the field has a format or unit
+    #[serde(default)]
+    pub labels: Option<bool>,
+    /// Presentation of the values on the axis and labels, in place of
+    /// the one of the field
+    #[serde(default)]
+    pub format: Option<NumberFormat>,
+    /// Colors, fonts, legend and axes, the ones of the backend by default
+    #[serde(default)]
+    pub theme: ChartTheme,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct ChartSortKeys {
+    pub by: ChartSortBy,
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub enum ChartSortBy {
+    Key,
+    /// Sum of the series on the key
+    Value,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct ChartFillKeys {
+    pub step: ChartKeyStep,
+    /// Gaps on the lines and bars in place of the zeros
+    #[serde(default)]
+    pub nulls: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub enum ChartKeyStep {
+    Hour,
+    Day,
+    Week,
+    Month,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct ChartSeriesBy {
+    pub key: String,
+    pub values: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct ChartSeriesOptions {
+    /// Bar or Line, the kind of the chart when omitted
+    #[serde(default)]
+    pub kind: Option<ChartSeriesKind>,
+    /// On the y axis of the right
+    #[serde(default)]
+    pub secondary: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub enum ChartSeriesKind {
+    Bar,
+    Line,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct ChartTheme {
+    /// Colors of the series, in order, like `#5470c6`
+    #[serde(default)]
+    pub palette: Vec<String>,
+    /// Family of the texts, the embedded Roboto by default
+    #[serde(default)]
+    pub font_family: Option<String>,
+    /// TTF or OTF file of the family, when it isn't the embedded one
+    #[serde(default)]
+    pub font_file: Option<String>,
+    #[serde(default)]
+    pub font_size: Option<f32>,
+    #[serde(default)]
+    pub legend: Option<ChartLegend>,
+    /// Degrees of the rotation of the labels of the x axis
+    #[serde(default)]
+    pub x_labels_rotation: f32,
+    /// Lines of the values across the chart
+    #[serde(default = "grid")]
+    pub grid: bool,
+}
+
+impl Default for ChartTheme {
+    fn default() -> Self {
+        Self {
+            palette: vec![],
+            font_family: None,
+            font_file: None,
+            font_size: None,
+            legend: None,
+            x_labels_rotation: 0.0,
+            grid: true,
+        }
+    }
+}
+
+fn grid() -> bool {
+    true
+}
+
+/// Alignment of the legend, at the top of the chart
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub enum ChartLegend {
+    Left,
+    Center,
+    Right,
+    Hidden,
+}
+
+/// Loaded font files, kept for the whole run since the backends
+/// need them static
+static FONTS: Mutex<BTreeMap<String, &'static [u8]>> = Mutex::new(BTreeMap::new());
+
+impl ChartTheme {
+    /// Colors of the palette, as RGB
+    pub fn colors(&self) -> Result<Vec<(u8, u8, u8)>, String> {
+        self.palette
+            .iter()
+            .map(|color| {
+                let hex = color.trim_start_matches('#');
+                let channel = |i: usize| {
+                    hex.get(i..i + 2)
+                        .and_then(|c| u8::from_str_radix(c, 16).ok())
+                        .ok_or(format!("Invalid color of the palette: {}", color))
+                };
+                if hex.len() != 6 {
+                    return Err(format!("Invalid color of the palette: {}", color));
+                }
+                Ok((channel(0)?, channel(2)?, channel(4)?))
+            })
+            .collect()
+    }
+
+    /// Data of the font file, if any
+    pub fn font_data(&self) -> Result<Option<&'static [u8]>, String> {
+        let Some(file) = &self.font_file else {
+            return Ok(None);
+        };
+
+        let mut fonts = FONTS
+            .lock()
+            .map_err(|e| format!("Error loading the font: {}", e))?;
+        if let Some(data) = fonts.get(file) {
+            return Ok(Some(data));
+        }
+
+        let data = std::fs::read(file)
+            .map_err(|e| format!("Error reading the font file {}: {}", file, e))?;
+        let data: &'static [u8] = Box::leak(data.into_boxed_slice());
+        fonts.insert(file.clone(), data);
+
+        Ok(Some(data))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct ChartPoints {
+    pub x: String,
+    pub y: String,
+    /// Field to split the points in series
+    #[serde(default)]
+    pub series: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct ChartGauge {
+    /// Field of the value, of the first row
+    pub value: String,
+    #[serde(default)]
+    pub target: Option<ChartGaugeTarget>,
+    #[serde(default)]
+    pub min: f32,
+    #[serde(default = "gauge_max")]
+    pub max: f32,
+}
+
+/// Fixed target or the field with it
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum ChartGaugeTarget {
+    Value(f32),
+    Field(String),
+}
+
+fn gauge_max() -> f32 {
+    100.0
+}
+
+impl ChartGauge {
+    /// Position of the value between the min and the max, from 0 to 1
+    pub fn ratio(&self, v: f32) -> f32 {
+        if self.max <= self.min {
+            return 0.0;
+        }
+        ((v - self.min) / (self.max - self.min)).clamp(0.0, 1.0)
+    }
+
+    /// Green when the value meets the target, red otherwise
+    pub fn color(&self, value: f32, target: Option<f32>) -> (u8, u8, u8) {
+        match target {
+            Some(t) if value >= t => (46, 158, 91),
+            Some(_) => (217, 83, 79),
+            None => (84, 112, 198),
+        }
+    }
+}
+
+/// Color of the heatmap cell, from the light to the dark of the min and
+/// max values
+pub fn heat_color(value: f32, min: f32, max: f32) -> (u8, u8, u8) {
+    let (low, high) = ((240.0, 217.0, 156.0), (191.0, 68.0, 76.0));
+    let t = if max > min {
+        ((value - min) / (max - min)).clamp(0.0, 1.0)
+    } else {
+        1.0
+    };
+    let mix = |a: f32, b: f32| (a + (b - a) * t).round() as u8;
+    (mix(low.0, high.0), mix(low.1, high.1), mix(low.2, high.2))
+}
+
+/// Polygon of the gauge arc between the ratios, of the half circle
+/// from the left to the right
+pub fn gauge_band(
+    from: f32,
+    to: f32,
+    center: (f32, f32),
+    outer: f32,
+    inner: f32,
+) -> Vec<(f32, f32)> {
+    let steps = ((to - from) * 60.0).ceil().max(1.0) as usize;
+    let point = |t: f32, r: f32| {
+        let angle = std::f32::consts::PI * (1.0 - t);
+        (center.0 + r * angle.cos(), center.1 - r * angle.sin())
+    };
+
+    let mut points = (0..=steps)
+        .map(|i| point(from + (to - from) * i as f32 / steps as f32, outer))
+        .collect::<Vec<_>>();
+    points.extend(
+        (0..=steps)
+            .rev()
+            .map(|i| point(from + (to - from) * i as f32 / steps as f32, inner)),
+    );
+    points
+}
+
+/// Chart rendering backend definitions
+pub trait ChartBackend {
+    // Draw the chart and encode it as PNG, with the value labels by
+    // the format of the field, when it has one. The data of the
+    // scatter series are the x and y of each point, in sequence, and
+    // of the gauge the value followed by the target, when it has one
+    fn png(
+        &self,
+        chart: &ChartComponent,
+        keys: Vec<String>,
+        series: Vec<Series>,
+        field: Option<&Field>,
+    ) -> Result<Vec<u8>, String>;
+
+    // Draw the chart as SVG, of the size of the chart without the scale
+    fn svg(
+        &self,
+        chart: &ChartComponent,
+        keys: Vec<String>,
+        series: Vec<Series>,
+        field: Option<&Field>,
+    ) -> Result<String, String>;
+}
+
+/// Setup the chart backend of specified kind
+#[allow(unreachable_patterns)]
+fn get_backend(kind: ChartBackendType) -> Result<Box<dyn ChartBackend>, String> {
+    debug!("Preparing the chart backend {:?}", kind);
+
+    match kind {
+        ChartBackendType::ChartsRs => Ok(Box::new(chartsrs::ChartsRsBackend {})),
+        #[cfg(feature = "plotters")]
+        ChartBackendType::Plotters => Ok(Box::new(plotters::PlottersBackend {})),
+        _ => Err("Not supported chart backend".to_string()),
+    }
+}
+
+impl ChartComponent {
+    pub fn prepare_series(
+        &self,
+        query: &Query,
+        keys: &[String],
+        data: &[Vec<Value>],
+    ) -> Result<Vec<Series>, String> {
+        if let Some(points) = &self.points {
+            return prepare_points(points, data);
+        }
+        if let Some(gauge) = &self.gauge {
+            return prepare_gauge(gauge, data);
+        }
+        if self.kind == ChartType::Gauge {
+            return Err("Gauge must be defined".to_string());
+        }
+        if self.series.is_none() && self.series_by.is_none() {
+            return Err("Series must be defined".to_string());
+        }
+
+        let mut series = vec![];
+
+        if let Some(dseries) = &self.series {
+            for serie in dseries {
+                let col = query
+                    .fields
+                    .iter()
+                    .find(|f| f.field == *serie)
+                    .ok_or_else(|| format!("Field {} not found", serie))?;
+
+                let mut values = vec![];
+                for row in data {
+                    let value = get_value_by(col.field.clone(), row)?;
+                    values.push(value);
+                }
+                series.push(self.with_options(&col.field, Series::new(col.title.clone(), values)));
+            }
+        }
+
+        if let Some(series_by) = self.series_by.clone() {
+            if self.keys_by.is_none() {
+                return Err("Keys must be defined".to_string());
+            }
+            let keys_by = self.keys_by.clone().unwrap();
+
+            let mut dseries = vec![];
+            for row in data {
+                let serie = get_key_by(series_by.key.clone(), row)?;
+
+                if !dseries.contains(&serie) {
+                    dseries.push(serie);
+                }
+            }
+
+            for serie in dseries {
+                let mut values: Vec<(String, f32)> =
+                    keys.iter().map(|k| (k.clone(), 0.0)).collect();
+                for row in data {
+                    let serie_key = get_key_by(series_by.key.clone(), row)?;
+                    if serie_key == serie {
+                        let value = get_value_by(series_by.values.clone(), row)?;
+                        let key = get_key_by(keys_by.clone(), row)?;
+
+                        if let Some(v) = values.iter_mut().find(|(k, _)| k == &key) {
+                            *v = (key, value);
+                        }
+                    }
+                }
+
+                let values = values.iter().map(|(_, v)| *v).collect();
+                series.push(self.with_options(&serie, Series::new(serie.clone(), values)));
+            }
+        }
+
+        Ok(series)
+    }
+
+    pub fn prepare_keys(&self, _query: &Query, data: &[Vec<Value>]) -> Result<Vec<String>, String> {
+        let keyless = matches!(
+            self.kind,
+            ChartType::Pizza | ChartType::Donut | ChartType::Scatter | ChartType::Gauge
+        );
+        if self.keys_by.is_none() && !keyless {
+            return Err("Keys must be defined".to_string());
+        }
+
+        let mut keys = vec![];
+
+        if let Some(by) = self.keys_by.clone() {
+            for row in data {
+                let value = get_key_by(by.clone(), row)?;
+
+                if !keys.contains(&value) {
+                    keys.push(value);
+                }
+            }
+        }
+
+        Ok(keys)
+    }
+
+    /// Field of the values of the series, with a number format or unit
+    pub fn values_field(&self, data: &[Vec<Value>]) -> Option<Field> {
+        let mut names = self.series.clone().unwrap_or_default();
+        names.extend(self.series_by.iter().map(|s| s.values.clone()));
+        names.extend(self.points.iter().map(|p| p.y.clone()));
+        names.extend(self.gauge.iter().map(|g| g.value.clone()));
+
+        let row = data.first()?;
+        names
+            .iter()
+            .filter_map(|n| row.iter().find(|v| v.field.field == *n))
+            .map(|v| v.field.clone())
+            .find(|f| f.format.is_some() || f.unit.is_some())
+    }
+
+    /// Keys with the missing dates or times between the first and the
+    /// last one, by the step of the `fill_keys`
+    pub fn fill_keys(
+        &self,
+        data: &[Vec<Value>],
+        keys: Vec<String>,
+        series: Vec<Series>,
+    ) -> Result<(Vec<String>, Vec<Series>), String> {
+        let (Some(fill), Some(by)) = (&self.fill_keys, &self.keys_by) else {
+            return Ok((keys, series));
+        };
+        if self.points.is_some() || self.gauge.is_some() {
+            return Ok((keys, series));
+        }
+
+        let mut values = vec![];
+        for row in data {
+            let col = row
+                .iter()
+                .find(|v| v.field.field == *by)
+                .ok_or_else(|| format!("Field {} not found", by))?;
+            match &col.inner {
+                Some(TypedValue::Date(_) | TypedValue::DateTime(_)) => values.push(col),
+                Some(v) => return Err(format!("Invalid date {} to fill the keys", v)),
+                None => continue,
+            }
+        }
+        let first = values.iter().min_by_key(|v| key_time(v));
+        let last = values.iter().max_by_key(|v| key_time(v));
+        let (Some(first), Some(last)) = (first, last) else {
+            return Ok((keys, series));
+        };
+
+        let next = |v: &TypedValue| match (v, &fill.step) {
+            (TypedValue::Date(d), ChartKeyStep::Month) => {
+                d.checked_add_months(Months::new(1)).map(TypedValue::Date)
+            }
+            (TypedValue::Date(d), ChartKeyStep::Week) => {
+                Some(TypedValue::Date(*d + Duration::weeks(1)))
+            }
+            (TypedValue::Date(d), _) => Some(TypedValue::Date(*d + Duration::days(1))),
+            (TypedValue::DateTime(dt), ChartKeyStep::Month) => dt
+                .checked_add_months(Months::new(1))
+                .map(TypedValue::DateTime),
+            (TypedValue::DateTime(dt), step) => Some(TypedValue::DateTime(
+                *dt + match step {
+                    ChartKeyStep::Hour => Duration::hours(1),
+                    ChartKeyStep::Week => Duration::weeks(1),
+                    _ => Duration::days(1),
+                },
+            )),
+            _ => None,
+        };
+
+        let mut filled = vec![];
+        let mut key = (*first).clone();
+        while key_time(&key) <= key_time(last) {
+            if filled.len() > MAX_FILLED_KEYS {
+                return Err(format!("More than {} keys to fill", MAX_FILLED_KEYS));
+            }
+            filled.push(key.to_string());
+            let Some(inner) = key.inner.as_ref().and_then(next) else {
+                break;
+            };
+            key.inner = Some(inner);
+        }
+
+        // The nulls are only gaps on the lines and bars
+        let empty = match self.kind {
+            ChartType::Bar | ChartType::Line | ChartType::Area if fill.nulls => f32::NAN,
+            _ => 0.0,
+        };
+        let series = series
+            .into_iter()
+            .map(|s| Series {
+                data: filled
+                    .iter()
+                    .map(|f| match keys.iter().position(|k| k == f) {
+                        Some(i) => s.data.get(i).copied().unwrap_or(empty),
+                        None => empty,
+                    })
+                    .collect(),
+                ..s
+            })
+            .collect();
+
+        Ok((filled, series))
+    }
+
+    /// Keys, with the values of the series, by the `sort_keys` and
+    /// limited by the `max_keys`
+    pub fn sort_keys(&self, keys: Vec<String>, series: Vec<Series>) -> (Vec<String>, Vec<Series>) {
+        // The scatter and gauge series are not by the keys
+        if keys.is_empty() || self.points.is_some() || self.gauge.is_some() {
+            return (keys, series);
+        }
+
+        let mut indexes: Vec<usize> = (0..keys.len()).collect();
+        if let Some(sort) = &self.sort_keys {
+            let total = |i: usize| -> f32 {
+                let values = series.iter().filter_map(|s| s.data.get(i));
+                values.filter(|v| !v.is_nan()).sum()
+            };
+            indexes.sort_by(|a, b| {
+                let ord = match sort.by {
+                    ChartSortBy::Key => match (keys[*a].parse::<f64>(), keys[*b].parse::<f64>()) {
+                        (Ok(x), Ok(y)) => x.total_cmp(&y),
+                        _ => keys[*a].cmp(&keys[*b]),
+                    },
+                    ChartSortBy::Value => total(*a).total_cmp(&total(*b)),
+                };
+                match sort.order {
+                    SortOrder::Asc => ord,
+                    SortOrder::Desc => ord.reverse(),
+                }
+            });
+        }
+        if let Some(max) = self.max_keys {
+            indexes.truncate(max);
+        }
+
+        let keys = indexes.iter().map(|i| keys[*i].clone()).collect();
+        let series = series
+            .into_iter()
+            .map(|s| Series {
+                data: indexes
+                    .iter()
+                    .filter_map(|i| s.data.get(*i))
+                    .copied()
+                    .collect(),
+                ..s
+            })
+            .collect();
+        (keys, series)
+    }
+
+    /// Weekdays as the keys and a series for each week, from the Monday
+    /// of the first date, with the sum of the values of each day
+    pub fn prepare_calendar(
+        &self,
+        data: &[Vec<Value>],
+    ) -> Result<(Vec<String>, Vec<Series>), String> {
+        let by = self.keys_by.clone().ok_or("Keys must be defined")?;
+        let values = self
+            .series
+            .as_ref()
+            .and_then(|s| s.first())
+            .ok_or("Series must be defined")?;
+
+        let mut days = BTreeMap::new();
+        for row in data {
+            let col = row
+                .iter()
+                .find(|v| v.field.field == by)
+                .ok_or_else(|| format!("Field {} not found", by))?;
+            let day = match &col.inner {
+                Some(TypedValue::Date(d)) => *d,
+                Some(TypedValue::DateTime(dt)) => clock::current().to_local(dt).date_naive(),
+                Some(v) => NaiveDate::parse_from_str(&v.to_string(), "%Y-%m-%d")
+                    .map_err(|e| format!("Invalid date {}: {}", v, e))?,
+                None => continue,
+            };
+            *days.entry(day).or_insert(0.0) += get_value_by(values.clone(), row)?;
+        }
+
+        let keys = locale::texts().weekdays.map(|d| d.to_string()).to_vec();
+        let (Some(first), Some(last)) = (days.keys().next(), days.keys().last()) else {
+            return Ok((keys, vec![]));
+        };
+
+        let mut series = vec![];
+        let mut week = *first - Duration::days(first.weekday().num_days_from_monday() as i64);
+        while week <= *last {
+            let data = (0..7)
+                .map(|d| {
+                    days.get(&(week + Duration::days(d)))
+                        .copied()
+                        .unwrap_or(0.0)
+                })
+                .collect();
+            series.push(Series::new(week.format("%Y-%m-%d").to_string(), data));
+            week += Duration::days(7);
+        }
+
+        Ok((keys, series))
+    }
+
+    /// Series with the kind and the axis of the options of the name
+    fn with_options(&self, name: &str, serie: Series) -> Series {
+        let Some(options) = self.series_options.get(name) else {
+            return serie;
+        };
+
+        Series {
+            category: options.kind.as_ref().map(|k| match k {
+                ChartSeriesKind::Bar => SeriesCategory::Bar,
+                ChartSeriesKind::Line => SeriesCategory::Line,
+            }),
+            y_axis_index: options.secondary as usize,
+            ..serie
+        }
+    }
+
+    /// Width and height of the chart, before the scale
+    pub fn size(&self) -> (u32, u32) {
+        (self.width.unwrap_or(600), self.height.unwrap_or(400))
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale.filter(|s| *s > 0.0).unwrap_or(1.0)
+    }
+
+    /// Lines of the chart with the area below them
+    pub fn filled(&self) -> bool {
+        self.fill || self.kind == ChartType::Area
+    }
+
+    /// Draw by the backend of the chart. With the plotters feature, what
+    /// charts_rs fails to draw, like without the fonts, is drawn by plotters
+    fn draw<T>(&self, draw: impl Fn(&dyn ChartBackend) -> Result<T, String>) -> Result<T, String> {
+        let backend = get_backend(self.backend.clone())?;
+
+        match draw(backend.as_ref()) {
+            #[cfg(feature = "plotters")]
+            Err(e) if self.backend == ChartBackendType::ChartsRs => {
+                warn!(
+                    "Chart not drawn by charts_rs, falling back to plotters: {}",
+                    e
+                );
+                draw(&plotters::PlottersBackend {})
+            }
+            drawn => drawn,
+        }
+    }
+}
+
+impl Component for ChartComponent {
+    fn render(
+        &self,
+        query: Query,
+        data: Vec<Vec<Value>>,
+        format: OutputFormat,
+    ) -> Result<RenderedContent, String> {
+        if matches!(format, OutputFormat::Plain | OutputFormat::SlackMrkdwn) {
+            return Err("Output format without chart support".to_string());
+        }
+
+        let (keys, series) = if self.kind == ChartType::Calendar {
+            self.prepare_calendar(&data)?
+        } else {
+            let keys = self.prepare_keys(&query, &data)?;
+            let series = self.prepare_series(&query, &keys, &data)?;
+            let (keys, series) = self.fill_keys(&data, keys, series)?;
+            self.sort_keys(keys, series)
+        };
+        let field = match &self.format {
+            Some(format) => Some(Field {
+                format: Some(format.clone()),
+                ..self.values_field(&data).unwrap_or_default()
+            }),
+            None => self.values_field(&data),
+        };
+        let series = match &field {
+            // The points and gauges keep the values, of the x and of the
+            // min, max and target
+            Some(f) if self.points.is_none() && self.gauge.is_none() => scale_series(series, f),
+            _ => series,
+        };
+        let series = match self.top {
+            Some(top) if matches!(self.kind, ChartType::Pizza | ChartType::Donut) => {
+                top_slices(series, top)
+            }
+            _ => series,
+        };
+
+        if self.output == Some(ChartOutput::Svg) && format == OutputFormat::Html {
+            let svg = self.draw(|b| b.svg(self, keys.clone(), series.clone(), field.as_ref()))?;
+            return Ok(RenderedContent {
+                content: format!(
+                    "<div class=\"lmr-chart\" title=\"{}\">{}</div>",
+                    format.escape(&query.title),
+                    svg
+                ),
+                images: vec![],
+            });
+        }
+
+        let png = self.draw(|b| b.png(self, keys.clone(), series.clone(), field.as_ref()))?;
+
+        let cid = new_cid();
+
+        // The archive saves the image alongside of the report and
+        // replaces the cid by the file
+        let img_tag = match format {
+            OutputFormat::Markdown => format!("![{}](cid:{})", query.title, cid),
+            OutputFormat::Jira => format!("!cid:{}!", cid),
+            _ => format!(
+                "<img class=\"lmr-img\" title=\"{}\" src=\"cid:{}\">",
+                format.escape(&query.title),
+                cid
+            ),
+        };
+
+        Ok(RenderedContent {
+            content: img_tag,
+            images: vec![ImagePresented {
+                mime: "image/png".to_string(),
+                data: png,
+                cid,
+            }],
+        })
+    }
+}
+
+/// Values of the series on the scale of the unit of the field, like the
+/// bytes as MB, with the scale on the names. The series of the secondary
+/// axis keep their values
+fn scale_series(series: Vec<Series>, field: &Field) -> Vec<Series> {
+    let Some(unit) = &field.unit else {
+        return series;
+    };
+
+    let max = series
+        .iter()
+        .filter(|s| s.y_axis_index == 0)
+        .flat_map(|s| s.data.iter())
+        .fold(0.0f32, |a, b| a.max(b.abs()));
+    let (divisor, name) = unit.scale(max as f64);
+
+    series
+        .into_iter()
+        .map(|s| {
+            if s.y_axis_index != 0 {
+                return s;
+            }
+            Series {
+                data: s.data.iter().map(|v| v / divisor as f32).collect(),
+                name: format!("{} ({})", s.name, name),
+                ..s
+            }
+        })
+        .collect()
+}
+
+/// The biggest slices, by the sum of the values, followed by the sum
+/// of the others
+fn top_slices(series: Vec<Series>, top: usize) -> Vec<Series> {
+    if series.len() <= top {
+        return series;
+    }
+
+    let mut slices: Vec<(f32, Series)> = series
+        .into_iter()
+        .map(|s| (s.data.iter().sum(), s))
+        .collect();
+    slices.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let others = slices[top..].iter().map(|(v, _)| v).sum();
+    slices.truncate(top);
+
+    let mut series: Vec<Series> = slices.into_iter().map(|(_, s)| s).collect();
+    series.push(Series::new(
+        locale::texts().others.to_string(),
+        vec![others],
+    ));
+    series
+}
+
+/// Limit of the keys generated by the `fill_keys`
+const MAX_FILLED_KEYS: usize = 10000;
+
+/// Moment of the date or datetime key, to compare them
+fn key_time(value: &Value) -> Option<chrono::NaiveDateTime> {
+    match &value.inner {
+        Some(TypedValue::Date(d)) => d.and_hms_opt(0, 0, 0),
+        Some(TypedValue::DateTime(dt)) => Some(dt.naive_utc()),
+        _ => None,
+    }
+}
+
+/// Series of the points, with the x and y of each one in sequence
+fn prepare_points(points: &ChartPoints, data: &[Vec<Value>]) -> Result<Vec<Series>, String> {
+    let mut series: Vec<(String, Vec<f32>)> = vec![];
+
+    for row in data {
+        let name = match &points.series {
+            Some(by) => get_key_by(by.clone(), row)?,
+            None => {
+                let col = row
+                    .iter()
+                    .find(|v| v.field.field == points.y)
+                    .ok_or_else(|| format!("Field {} not found", points.y))?;
+                col.field.title.clone()
+            }
+        };
+        let x = get_value_by(points.x.clone(), row)?;
+        let y = get_value_by(points.y.clone(), row)?;
+
+        match series.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, values)) => values.extend([x, y]),
+            None => series.push((name, vec![x, y])),
+        }
+    }
+
+    Ok(series
+        .into_iter()
+        .map(|(name, values)| Series::new(name, values))
+        .collect())
+}
+
+/// Series of the value and target of the gauge, by the first row
+fn prepare_gauge(gauge: &ChartGauge, data: &[Vec<Value>]) -> Result<Vec<Series>, String> {
+    let row = data.first().ok_or("Gauge without rows".to_string())?;
+
+    let col = row
+        .iter()
+        .find(|v| v.field.field == gauge.value)
+        .ok_or_else(|| format!("Field {} not found", gauge.value))?;
+    let mut values = vec![col.to_float()?];
+
+    match &gauge.target {
+        Some(ChartGaugeTarget::Value(t)) => values.push(*t),
+        Some(ChartGaugeTarget::Field(f)) => values.push(get_value_by(f.clone(), row)?),
+        None => {}
+    }
+
+    Ok(vec![Series::new(col.field.title.clone(), values)])
+}
+
+fn get_key_by(by: String, row: &[Value]) -> Result<String, String> {
+    let col = row
+        .iter()
+        .find(|v| v.field.field == by)
+        .ok_or_else(|| format!("Field {} not found", by))?;
+    Ok(col.to_string())
+}
+
+fn get_value_by(by: String, row: &[Value]) -> Result<f32, String> {
+    let col = row
+        .iter()
+        .find(|v| v.field.field == by)
+        .ok_or_else(|| format!("Field {} not found", by))?;
+    col.to_float()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{scale_series, ChartComponent, ChartType};
+    use crate::{
+        presentation::{
+            charts::{
+                gauge_band, heat_color, ChartFillKeys, ChartGauge, ChartGaugeTarget, ChartKeyStep,
+                ChartOutput, ChartPoints, ChartSeriesBy, ChartSeriesKind, ChartSeriesOptions,
+                ChartSortBy, ChartSortKeys, ChartTheme,
+            },
+            formats::OutputFormat,
+            Component,
+        },
+        sort::SortOrder,
+        source::Query,
+        value::{Field, FieldType, TypedValue, Unit, Value},
+    };
+    use charts_rs::{Series, SeriesCategory};
+    use chrono::NaiveDate;
+
+    #[test]
+    pub fn non_html_format() {
+        let query = Query {
+            sql: "SELECT * FROM table".to_string(),
+            title: "Test".to_string(),
+            fields: vec![],
+            ..Default::default()
+        };
+
+        let data = vec![];
+
+        let chart = ChartComponent {
+            kind: ChartType::Bar,
+            keys_by: Some("key".to_string()),
+            series: Some(vec!["field".to_string()]),
+            series_by: None,
+            ..Default::default()
+        };
+
+        let result = chart.render(query, data, OutputFormat::Plain);
+        assert_eq!(
+            Err("Output format without chart support".to_string()),
+            result
+        );
+    }
+
+    #[test]
+    pub fn html_format() {
+        let query = Query {
+            title: "Title test".to_string(),
+            sql: "select * from users".to_string(),
+            fields: vec![
+                Field {
+                    title: "User name".to_string(),
+                    field: "name".to_string(),
+                    kind: FieldType::String,
+                    ..Default::default()
+                },
+                Field {
+                    title: "Age".to_string(),
+                    field: "age".to_string(),
+                    kind: FieldType::Integer,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let data = vec![
+            vec![
+                Value {
+                    inner: Some(TypedValue::String("john.abc".to_string())),
+                    field: query.fields[0].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::Integer(30)),
+                    field: query.fields[1].clone(),
+                },
+            ],
+            vec![
+                Value {
+                    inner: Some(TypedValue::String("jane.abc".to_string())),
+                    field: query.fields[0].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::Integer(25)),
+                    field: query.fields[1].clone(),
+                },
+            ],
+        ];
+
+        let chart = ChartComponent {
+            kind: ChartType::Bar,
+            series_by: None,
+            keys_by: Some("name".to_string()),
+            series: Some(vec!["age".to_string()]),
+            ..Default::default()
+        };
+
+        let result = chart.render(query.clone(), data.clone(), OutputFormat::Html);
+        assert!(result.is_ok());
+        assert!(result
+            .unwrap()
+            .content
+            .starts_with("<img class=\"lmr-img\" title=\"Title test\" src=\"cid:"));
+
+        let chart = ChartComponent {
+            output: Some(ChartOutput::Svg),
+            ..chart
+        };
+        let result = chart.render(query.clone(), data.clone(), OutputFormat::Html);
+        let result = result.unwrap();
+        assert!(result
+            .content
+            .starts_with("<div class=\"lmr-chart\" title=\"Title test\"><svg "));
+        assert!(result.images.is_empty());
+
+        // Only the HTML has the SVG inside
+        let result = chart.render(query.clone(), data.clone(), OutputFormat::Markdown);
+        let result = result.unwrap();
+        assert_eq!(
+            format!("![Title test](cid:{})", result.images[0].cid),
+            result.content
+        );
+        assert_eq!("image/png", result.images[0].mime);
+
+        // The title and the keys escaped on the HTML
+        let mut query = query;
+        query.title = "Sales \"A&B\"".to_string();
+        let mut data = data;
+        data[0][0].inner = Some(TypedValue::String("<b>John</b>".to_string()));
+        let result = chart.render(query, data, OutputFormat::Html).unwrap();
+        assert!(result
+            .content
+            .starts_with("<div class=\"lmr-chart\" title=\"Sales &quot;A&amp;B&quot;\"><svg "));
+        assert!(result.content.contains("&lt;b&gt;John&lt;/b&gt;"));
+        assert!(!result.content.contains("<b>John"));
+    }
+
+    #[test]
+    pub fn prepare_keys() {
+        let query = Query {
+            title: "Title test".to_string(),
+            sql: "select * from users".to_string(),
+            fields: vec![
+                Field {
+                    title: "User name".to_string(),
+                    field: "name".to_string(),
+                    kind: FieldType::String,
+                    ..Default::default()
+                },
+                Field {
+                    title: "Age".to_string(),
+                    field: "age".to_string(),
+                    kind: FieldType::Integer,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let data = vec![
+            vec![
+                Value {
+                    inner: Some(TypedValue::String("john.abc".to_string())),
+                    field: query.fields[0].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::Integer(30)),
+                    field: query.fields[1].clone(),
+                },
+            ],
+            vec![
+                Value {
+                    inner: Some(TypedValue::String("jane.abc".to_string())),
+                    field: query.fields[0].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::Integer(25)),
+                    field: query.fields[1].clone(),
+                },
+            ],
+        ];
+
+        let chart = ChartComponent {
+            kind: ChartType::Bar,
+            series_by: None,
+            keys_by: Some("name".to_string()),
+            series: Some(vec!["age".to_string()]),
+            ..Default::default()
+        };
+
+        let result = chart.prepare_keys(&query, &data);
+        assert_eq!(
+            Ok(vec!["john.abc".to_string(), "jane.abc".to_string()]),
+            result
+        );
+    }
+
+    #[test]
+    pub fn prepare_keys_duplicate() {
+        let query = Query {
+            title: "Title test".to_string(),
+            sql: "select * from users".to_string(),
+            fields: vec![
+                Field {
+                    title: "User name".to_string(),
+                    field: "name".to_string(),
+                    kind: FieldType::String,
+                    ..Default::default()
+                },
+                Field {
+                    title: "Age".to_string(),
+                    field: "age".to_string(),
+                    kind: FieldType::Integer,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let data = vec![
+            vec![
+                Value {
+                    inner: Some(TypedValue::String("john.abc".to_string())),
+                    field: query.fields[0].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::Integer(30)),
+                    field: query.fields[1].clone(),
+                },
+            ],
+            vec![
+                Value {
+                    inner: Some(TypedValue::String("jane.abc".to_string())),
+                    field: query.fields[0].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::Integer(25)),
+                    field: query.fields[1].clone(),
+                },
+            ],
+            vec![
+                Value {
+                    inner: Some(TypedValue::String("jane.abc".to_string())),
+                    field: query.fields[0].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::Integer(28)),
+                    field: query.fields[1].clone(),
+                },
+            ],
+        ];
+
+        let chart = ChartComponent {
+            kind: ChartType::Bar,
+            series_by: None,
+            keys_by: Some("name".to_string()),
+            series: Some(vec!["age".to_string()]),
+            ..Default::default()
+        };
+
+        let result = chart.prepare_keys(&query, &data);
+        assert_eq!(
+            Ok(vec!["john.abc".to_string(), "jane.abc".to_string()]),
+            result
+        );
+    }
+
+    #[test]
+    pub fn prepare_keys_not_found() {
+        let query = Query {
+            title: "Title test".to_string(),
+            sql: "select * from users".to_string(),
+            fields: vec![
+                Field {
+                    title: "User name".to_string(),
+                    field: "name".to_string(),
+                    kind: FieldType::String,
+                    ..Default::default()
+                },
+                Field {
+                    title: "Age".to_string(),
+                    field: "age".to_string(),
+                    kind: FieldType::Integer,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let data = vec![
+            vec![
+                Value {
+                    inner: Some(TypedValue::String("john.abc".to_string())),
+                    field: query.fields[0].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::Integer(30)),
+                    field: query.fields[1].clone(),
+                },
+            ],
+            vec![
+                Value {
+                    inner: Some(TypedValue::String("jane.abc".to_string())),
+                    field: query.fields[0].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::Integer(25)),
+                    field: query.fields[1].clone(),
+                },
+            ],
+        ];
+
+        let chart = ChartComponent {
+            kind: ChartType::Bar,
+            series_by: None,
+            keys_by: Some("name2".to_string()),
+            series: Some(vec!["age".to_string()]),
+            ..Default::default()
+        };
+
+        let result = chart.prepare_keys(&query, &data);
+        assert_eq!(Err("Field name2 not found".to_string()), result);
+    }
+
+    #[test]
+    pub fn prepare_series_with_series_option() {
+        let query = Query {
+            title: "Title test".to_string(),
+            sql: "select * from users".to_string(),
+            fields: vec![
+                Field {
+                    title: "User name".to_string(),
+                    field: "name".to_string(),
+                    kind: FieldType::String,
+                    ..Default::default()
+                },
+                Field {
+                    title: "Age".to_string(),
+                    field: "age".to_string(),
+                    kind: FieldType::Integer,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let data = vec![
+            vec![
+                Value {
+                    inner: Some(TypedValue::String("john.abc".to_string())),
+                    field: query.fields[0].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::Integer(30)),
+                    field: query.fields[1].clone(),
+                },
+            ],
+            vec![
+                Value {
+                    inner: Some(TypedValue::String("jane.abc".to_string())),
+                    field: query.fields[0].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::Integer(25)),
+                    field: query.fields[1].clone(),
+                },
+            ],
+        ];
+
+        let chart = ChartComponent {
+            kind: ChartType::Bar,
+            series_by: None,
+            keys_by: Some("name".to_string()),
+            series: Some(vec!["age".to_string()]),
+            ..Default::default()
+        };
+
+        let result = chart.prepare_series(
+            &query,
+            &["john.abc".to_string(), "jane.abc".to_string()],
+            &data,
+        );
+        assert!(result.is_ok());
+        let series = result.unwrap();
+
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].name, "Age".to_string());
+        assert_eq!(series[0].data, vec![30.0, 25.0]);
+        assert_eq!(None, series[0].category);
+
+        let chart = ChartComponent {
+            series_options: [(
+                "age".to_string(),
+                ChartSeriesOptions {
+                    kind: Some(ChartSeriesKind::Line),
+                    secondary: true,
+                },
+            )]
+            .into(),
+            ..chart
+        };
+        let series = chart
+            .prepare_series(
+                &query,
+                &["john.abc".to_string(), "jane.abc".to_string()],
+                &data,
+            )
+            .unwrap();
+        assert_eq!(Some(SeriesCategory::Line), series[0].category);
+        assert_eq!(1, series[0].y_axis_index);
+    }
+
+    #[test]
+    pub fn prepare_series_with_series_by_option() {
+        let query = Query {
+            title: "Title test".to_string(),
+            sql: "select * from users".to_string(),
+            fields: vec![
+                Field {
+                    title: "User name".to_string(),
+                    field: "name".to_string(),
+                    kind: FieldType::String,
+                    ..Default::default()
+                },
+                Field {
+                    title: "Age".to_string(),
+                    field: "age".to_string(),
+                    kind: FieldType::Integer,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let data = vec![
+            vec![
+                Value {
+                    inner: Some(TypedValue::String("john.abc".to_string())),
+                    field: query.fields[0].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::Integer(30)),
+                    field: query.fields[1].clone(),
+                },
+            ],
+            vec![
+                Value {
+                    inner: Some(TypedValue::String("jane.abc".to_string())),
+                    field: query.fields[0].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::Integer(25)),
+                    field: query.fields[1].clone(),
+                },
+            ],
+        ];
+
+        let chart = ChartComponent {
+            kind: ChartType::Bar,
+            series_by: Some(ChartSeriesBy {
+                key: "name".to_string(),
+                values: "age".to_string(),
+            }),
+            keys_by: Some("name".to_string()),
+            series: None,
+            ..Default::default()
+        };
+
+        let result = chart.prepare_series(
+            &query,
+            &["john.abc".to_string(), "jane.abc".to_string()],
+            &data,
+        );
+        assert!(result.is_ok());
+        let series = result.unwrap();
+
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[0].name, "john.abc");
+        assert_eq!(series[0].data, vec![30.0, 0.0]);
+        assert_eq!(series[1].name, "jane.abc");
+        assert_eq!(series[1].data, vec![0.0, 25.0]);
+    }
+
+    #[test]
+    pub fn prepare_series_without_serie_setup() {
+        let query = Query {
+            title: "Title test".to_string(),
+            sql: "select * from users".to_string(),
+            fields: vec![
+                Field {
+                    title: "User name".to_string(),
+                    field: "name".to_string(),
+                    kind: FieldType::String,
+                    ..Default::default()
+                },
+                Field {
+                    title: "Age".to_string(),
+                    field: "age".to_string(),
+                    kind: FieldType::Integer,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let data = vec![
+            vec![
+                Value {
+                    inner: Some(TypedValue::String("john.abc".to_string())),
+                    field: query.fields[0].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::Integer(30)),
+                    field: query.fields[1].clone(),
+                },
+            ],
+            vec![
+                Value {
+                    inner: Some(TypedValue::String("jane.abc".to_string())),
+                    field: query.fields[0].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::Integer(25)),
+                    field: query.fields[1].clone(),
+                },
+            ],
+        ];
+
+        let chart = ChartComponent {
+            kind: ChartType::Bar,
+            series_by: None,
+            keys_by: Some("name".to_string()),
+            series: None,
+            ..Default::default()
+        };
+
+        let result = chart.prepare_series(
+            &query,
+            &["john.abc".to_string(), "jane.abc".to_string()],
+            &data,
+        );
+        assert_eq!(Err("Series must be defined".to_string()), result);
+    }
+
+    #[test]
+    pub fn prepare_keys_without_keys_setup() {
+        let query = Query {
+            title: "Title test".to_string(),
+            sql: "select * from users".to_string(),
+            fields: vec![
+                Field {
+                    title: "User name".to_string(),
+                    field: "name".to_string(),
+                    kind: FieldType::String,
+                    ..Default::default()
+                },
+                Field {
+                    title: "Age".to_string(),
+                    field: "age".to_string(),
+                    kind: FieldType::Integer,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let data = vec![
+            vec![
+                Value {
+                    inner: Some(TypedValue::String("john.abc".to_string())),
+                    field: query.fields[0].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::Integer(30)),
+                    field: query.fields[1].clone(),
+                },
+            ],
+            vec![
+                Value {
+                    inner: Some(TypedValue::String("jane.abc".to_string())),
+                    field: query.fields[0].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::Integer(25)),
+                    field: query.fields[1].clone(),
+                },
+            ],
+        ];
+
+        let chart = ChartComponent {
+            kind: ChartType::Bar,
+            series_by: None,
+            keys_by: None,
+            series: Some(vec!["age".to_string()]),
+            ..Default::default()
+        };
+
+        let result = chart.prepare_keys(&query, &data);
+        assert_eq!(Err("Keys must be defined".to_string()), result);
+
+        let chart = ChartComponent {
+            kind: ChartType::Line,
+            series_by: None,
+            keys_by: None,
+            series: Some(vec!["age".to_string()]),
+            ..Default::default()
+        };
+
+        let result = chart.prepare_keys(&query, &data);
+        assert_eq!(Err("Keys must be defined".to_string()), result);
+
+        let chart = ChartComponent {
+            kind: ChartType::Pizza,
+            series_by: None,
+            keys_by: None,
+            series: Some(vec!["age".to_string()]),
+            ..Default::default()
+        };
+
+        let result = chart.prepare_keys(&query, &data);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    pub fn fill_keys() {
+        let fields = ["day", "sales"].map(|f| Field {
+            field: f.to_string(),
+            title: f.to_string(),
+            ..Default::default()
+        });
+        let row = |day: u32, qt: i64| {
+            vec![
+                Value {
+                    inner: Some(TypedValue::Date(
+                        NaiveDate::from_ymd_opt(2024, 6, day).unwrap(),
+                    )),
+                    field: fields[0].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::Integer(qt)),
+                    field: fields[1].clone(),
+                },
+            ]
+        };
+        let data = vec![row(3, 2), row(6, 4), row(4, 1)];
+        let query = Query {
+            fields: fields.to_vec(),
+            ..Default::default()
+        };
+
+        let mut chart = ChartComponent {
+            kind: ChartType::Line,
+            keys_by: Some("day".to_string()),
+            series: Some(vec!["sales".to_string()]),
+            fill_keys: Some(ChartFillKeys {
+                step: ChartKeyStep::Day,
+                nulls: false,
+            }),
+            ..Default::default()
+        };
+        let keys = chart.prepare_keys(&query, &data).unwrap();
+        let series = chart.prepare_series(&query, &keys, &data).unwrap();
+
+        let (fkeys, fseries) = chart
+            .fill_keys(&data, keys.clone(), series.clone())
+            .unwrap();
+        assert_eq!(
+            vec!["2024-06-03", "2024-06-04", "2024-06-05", "2024-06-06"],
+            fkeys
+        );
+        assert_eq!(vec![2.0, 1.0, 0.0, 4.0], fseries[0].data);
+
+        chart.fill_keys = Some(ChartFillKeys {
+            step: ChartKeyStep::Week,
+            nulls: true,
+        });
+        let (fkeys, fseries) = chart
+            .fill_keys(&data, keys.clone(), series.clone())
+            .unwrap();
+        assert_eq!(vec!["2024-06-03"], fkeys);
+        assert_eq!(vec![2.0], fseries[0].data);
+
+        chart.fill_keys = Some(ChartFillKeys {
+            step: ChartKeyStep::Day,
+            nulls: true,
+        });
+        let (_, fseries) = chart.fill_keys(&data, keys, series).unwrap();
+        assert!(fseries[0].data[2].is_nan());
+
+        let data = vec![vec![Value {
+            inner: Some(TypedValue::String("june".to_string())),
+            field: fields[0].clone(),
+        }]];
+        assert!(chart.fill_keys(&data, vec![], vec![]).is_err());
+    }
+
+    #[test]
+    pub fn sort_keys() {
+        let keys = ["b", "c", "a"].map(|k| k.to_string()).to_vec();
+        let series = vec![
+            Series::new("Qt".to_string(), vec![1.0, 5.0, 3.0]),
+            Series::new("Returns".to_string(), vec![4.0, 0.0, 1.0]),
+        ];
+
+        let chart = ChartComponent {
+            sort_keys: Some(ChartSortKeys {
+                by: ChartSortBy::Value,
+                order: SortOrder::Desc,
+            }),
+            max_keys: Some(2),
+            ..Default::default()
+        };
+        let (skeys, sseries) = chart.sort_keys(keys.clone(), series.clone());
+        assert_eq!(vec!["b", "c"], skeys);
+        assert_eq!(vec![1.0, 5.0], sseries[0].data);
+        assert_eq!(vec![4.0, 0.0], sseries[1].data);
+
+        let chart = ChartComponent {
+            sort_keys: Some(ChartSortKeys {
+                by: ChartSortBy::Key,
+                order: SortOrder::Asc,
+            }),
+            ..Default::default()
+        };
+        let (skeys, sseries) = chart.sort_keys(keys.clone(), series.clone());
+        assert_eq!(vec!["a", "b", "c"], skeys);
+        assert_eq!(vec![3.0, 1.0, 5.0], sseries[0].data);
+
+        let chart = ChartComponent::default();
+        assert_eq!(
+            (keys.clone(), series.clone()),
+            chart.sort_keys(keys, series)
+        );
+    }
+
+    #[test]
+    pub fn top_slices() {
+        let series = ["a", "b", "c", "d"]
+            .iter()
+            .zip([1.0, 5.0, 2.0, 7.0])
+            .map(|(n, v)| Series::new(n.to_string(), vec![v, 1.0]))
+            .collect::<Vec<_>>();
+
+        let slices = super::top_slices(series.clone(), 2);
+        let slices: Vec<_> = slices
+            .iter()
+            .map(|s| (s.name.as_str(), s.data.iter().sum::<f32>()))
+            .collect();
+        assert_eq!(vec![("d", 8.0), ("b", 6.0), ("Others", 5.0)], slices);
+
+        assert_eq!(series, super::top_slices(series.clone(), 4));
+    }
+
+    #[test]
+    pub fn scale_series_bytes() {
+        let field = Field {
+            unit: Some(Unit::Bytes),
+            ..Default::default()
+        };
+
+        let series = scale_series(
+            vec![Series::new("Size".to_string(), vec![1048576.0, 3145728.0])],
+            &field,
+        );
+        assert_eq!("Size (MB)", series[0].name);
+        assert_eq!(vec![1.0, 3.0], series[0].data);
+
+        let series = scale_series(
+            vec![Series::new("Size".to_string(), vec![1.0])],
+            &Field::default(),
+        );
+        assert_eq!("Size", series[0].name);
+
+        let mut secondary = Series::new("Files".to_string(), vec![3.0]);
+        secondary.y_axis_index = 1;
+        let series = scale_series(
+            vec![Series::new("Size".to_string(), vec![2048.0]), secondary],
+            &field,
+        );
+        assert_eq!("Size (KB)", series[0].name);
+        assert_eq!(vec![2.0], series[0].data);
+        assert_eq!("Files", series[1].name);
+        assert_eq!(vec![3.0], series[1].data);
+    }
+
+    #[test]
+    pub fn prepare_series_with_points() {
+        let fields = ["host", "size", "ms"].map(|f| Field {
+            field: f.to_string(),
+            title: f.to_uppercase(),
+            ..Default::default()
+        });
+        let row = |host: &str, size: i64, ms: f64| {
+            vec![
+                Value {
+                    inner: Some(TypedValue::String(host.to_string())),
+                    field: fields[0].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::Integer(size)),
+                    field: fields[1].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::Float(ms)),
+                    field: fields[2].clone(),
+                },
+            ]
+        };
+        let data = vec![row("a", 10, 1.5), row("b", 20, 3.0), row("a", 30, 4.5)];
+
+        let mut chart = ChartComponent {
+            kind: ChartType::Scatter,
+            points: Some(ChartPoints {
+                x: "size".to_string(),
+                y: "ms".to_string(),
+                series: None,
+            }),
+            ..Default::default()
+        };
+
+        let query = Query::default();
+        assert_eq!(Ok(vec![]), chart.prepare_keys(&query, &data));
+
+        let series = chart.prepare_series(&query, &[], &data).unwrap();
+        assert_eq!(1, series.len());
+        assert_eq!("MS", series[0].name);
+        assert_eq!(vec![10.0, 1.5, 20.0, 3.0, 30.0, 4.5], series[0].data);
+
+        chart.points.as_mut().unwrap().series = Some("host".to_string());
+        let series = chart.prepare_series(&query, &[], &data).unwrap();
+        assert_eq!(2, series.len());
+        assert_eq!("a", series[0].name);
+        assert_eq!(vec![10.0, 1.5, 30.0, 4.5], series[0].data);
+        assert_eq!(vec![20.0, 3.0], series[1].data);
+
+        chart.points.as_mut().unwrap().x = "weight".to_string();
+        assert!(chart.prepare_series(&query, &[], &data).is_err());
+    }
+
+    #[test]
+    pub fn prepare_series_with_gauge() {
+        let fields = ["uptime", "sla"].map(|f| Field {
+            field: f.to_string(),
+            title: f.to_uppercase(),
+            ..Default::default()
+        });
+        let data = vec![vec![
+            Value {
+                inner: Some(TypedValue::Float(98.5)),
+                field: fields[0].clone(),
+            },
+            Value {
+                inner: Some(TypedValue::Integer(99)),
+                field: fields[1].clone(),
+            },
+        ]];
+
+        let mut gauge = ChartGauge {
+            value: "uptime".to_string(),
+            target: Some(ChartGaugeTarget::Field("sla".to_string())),
+            min: 90.0,
+            max: 100.0,
+        };
+        let mut chart = ChartComponent {
+            kind: ChartType::Gauge,
+            gauge: Some(gauge.clone()),
+            ..Default::default()
+        };
+
+        let query = Query::default();
+        assert_eq!(Ok(vec![]), chart.prepare_keys(&query, &data));
+
+        let series = chart.prepare_series(&query, &[], &data).unwrap();
+        assert_eq!("UPTIME", series[0].name);
+        assert_eq!(vec![98.5, 99.0], series[0].data);
+
+        assert_eq!(0.85, gauge.ratio(98.5));
+        assert_eq!(1.0, gauge.ratio(120.0));
+        assert_eq!((217, 83, 79), gauge.color(98.5, Some(99.0)));
+        assert_eq!((46, 158, 91), gauge.color(99.0, Some(99.0)));
+
+        gauge.target = None;
+        chart.gauge = Some(gauge);
+        let series = chart.prepare_series(&query, &[], &data).unwrap();
+        assert_eq!(vec![98.5], series[0].data);
+
+        assert_eq!(
+            Err("Gauge without rows".to_string()),
+            chart.prepare_series(&query, &[], &[])
+        );
+
+        let band = gauge_band(0.0, 1.0, (100.0, 100.0), 50.0, 30.0)
+            .into_iter()
+            .map(|(x, y)| (x.round(), y.round()))
+            .collect::<Vec<_>>();
+        assert_eq!((50.0, 100.0), band[0]);
+        assert_eq!((100.0, 50.0), band[band.len() / 4]);
+        assert_eq!((150.0, 100.0), band[band.len() / 2 - 1]);
+        assert_eq!((70.0, 100.0), band[band.len() - 1]);
+    }
+
+    #[test]
+    pub fn prepare_calendar() {
+        let fields = ["day", "incidents"].map(|f| Field {
+            field: f.to_string(),
+            title: f.to_string(),
+            ..Default::default()
+        });
+        let row = |day: (i32, u32, u32), qt: i64| {
+            vec![
+                Value {
+                    inner: Some(TypedValue::Date(
+                        NaiveDate::from_ymd_opt(day.0, day.1, day.2).unwrap(),
+                    )),
+                    field: fields[0].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::Integer(qt)),
+                    field: fields[1].clone(),
+                },
+            ]
+        };
+        // Wednesday to the Monday after the next one
+        let data = vec![
+            row((2024, 6, 5), 2),
+            row((2024, 6, 5), 1),
+            row((2024, 6, 9), 4),
+            row((2024, 6, 17), 1),
+        ];
+
+        let chart = ChartComponent {
+            kind: ChartType::Calendar,
+            keys_by: Some("day".to_string()),
+            series: Some(vec!["incidents".to_string()]),
+            ..Default::default()
+        };
+
+        let (keys, series) = chart.prepare_calendar(&data).unwrap();
+        assert_eq!(7, keys.len());
+        assert_eq!("Mon", keys[0]);
+        assert_eq!(
+            vec!["2024-06-03", "2024-06-10", "2024-06-17"],
+            series.iter().map(|s| s.name.clone()).collect::<Vec<_>>()
+        );
+        assert_eq!(vec![0.0, 0.0, 3.0, 0.0, 0.0, 0.0, 4.0], series[0].data);
+        assert_eq!(vec![0.0; 7], series[1].data);
+        assert_eq!(1.0, series[2].data[0]);
+
+        let (_, series) = chart.prepare_calendar(&[]).unwrap();
+        assert!(series.is_empty());
+
+        let chart = ChartComponent {
+            series: None,
+            ..chart
+        };
+        assert!(chart.prepare_calendar(&data).is_err());
+
+        assert_eq!((240, 217, 156), heat_color(0.0, 0.0, 4.0));
+        assert_eq!((191, 68, 76), heat_color(4.0, 0.0, 4.0));
+        assert_eq!((216, 143, 116), heat_color(2.0, 0.0, 4.0));
+    }
+
+    #[test]
+    pub fn theme() {
+        let theme = ChartTheme {
+            palette: vec!["#5470c6".to_string(), "91CC75".to_string()],
+            ..Default::default()
+        };
+        assert!(theme.grid);
+        assert_eq!(Ok(vec![(84, 112, 198), (145, 204, 117)]), theme.colors());
+        assert_eq!(Ok(None), theme.font_data());
+
+        let theme = ChartTheme {
+            palette: vec!["#54g0c6".to_string()],
+            font_file: Some("not_found.ttf".to_string()),
+            ..theme
+        };
+        assert!(theme.colors().is_err());
+        assert!(theme.font_data().is_err());
+
+        let theme = ChartTheme {
+            palette: vec!["blue".to_string()],
+            ..theme
+        };
+        assert!(theme.colors().is_err());
+    }
+}
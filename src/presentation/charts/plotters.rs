@@ -0,0 +1,1083 @@
+//! plotters backend implementation
+//!
+//! Uses the font embedded on charts_rs, so it doesn't depend on the
+//! fonts installed on the host
+
+use super::{
+    gauge_band, heat_color, ChartBackend, ChartComponent, ChartLegend, ChartTheme, ChartType,
+};
+use crate::{presentation::locale, value::Field};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use charts_rs::{Series, SeriesCategory, DEFAULT_FONT_DATA};
+use image::{ImageFormat, RgbImage};
+use plotters::prelude::*;
+use plotters::style::text_anchor::{HPos, Pos, VPos};
+use plotters::style::{register_font, FontStyle};
+use std::io::Cursor;
+use std::sync::Once;
+
+static FONT: Once = Once::new();
+
+pub struct PlottersBackend {}
+
+impl ChartBackend for PlottersBackend {
+    fn png(
+        &self,
+        chart: &ChartComponent,
+        keys: Vec<String>,
+        series: Vec<Series>,
+        field: Option<&Field>,
+    ) -> Result<Vec<u8>, String> {
+        FONT.call_once(|| {
+            if register_font("sans-serif", FontStyle::Normal, DEFAULT_FONT_DATA).is_err() {
+                log::error!("Plotters font not registered");
+            }
+        });
+
+        // The font of the theme, registered by the family
+        let theme = &chart.theme;
+        let family = match theme.font_data()? {
+            Some(data) => {
+                let family = theme.font_family.as_deref().unwrap_or("theme");
+                register_font(family, FontStyle::Normal, data)
+                    .map_err(|_| "Error loading the chart font".to_string())?;
+                family
+            }
+            None => "sans-serif",
+        };
+        let palette = theme.colors()?;
+        let pick = |i: usize| {
+            if palette.is_empty() {
+                return Palette99::pick(i).to_rgba();
+            }
+            let (r, g, b) = palette[i % palette.len()];
+            RGBColor(r, g, b).to_rgba()
+        };
+
+        // The sizes of the texts and areas, of the chart by the scale
+        let scale = chart.scale();
+        let px = |v: u32| (v as f32 * scale).round() as u32;
+        let (width, height) = chart.size();
+        let (width, height) = (px(width), px(height));
+        let font = |size: u32| (family, px(size));
+        let label_size = theme.font_size.map(|s| s.round() as u32).unwrap_or(12);
+        let turn = rotation(theme.x_labels_rotation);
+        let x_labels = match turn {
+            FontTransform::Rotate90 => Pos::new(HPos::Left, VPos::Center),
+            FontTransform::Rotate270 => Pos::new(HPos::Right, VPos::Center),
+            _ => Pos::new(HPos::Center, VPos::Top),
+        };
+        let x_labels = font(label_size)
+            .into_font()
+            .transform(turn.clone())
+            .color(&BLACK)
+            .pos(x_labels);
+        let turned = !matches!(turn, FontTransform::None);
+        let labeled = chart.labels.unwrap_or(field.is_some());
+        // Room for the titles of the axes, at the outer side of the labels
+        let x_desc = chart.x_title.clone().unwrap_or_default();
+        let y_desc = chart.y_title.clone().unwrap_or_default();
+        let x_room = |area: u32| area + if chart.x_title.is_some() { px(20) } else { 0 };
+        let y_room = |area: u32| area + if chart.y_title.is_some() { px(20) } else { 0 };
+        // Room for the keys below the x axis, longer when turned
+        let x_area = |labels: &[String]| {
+            if !turned {
+                return px(30);
+            }
+            let longest = labels.iter().map(|l| l.chars().count()).max();
+            px(20 + longest.unwrap_or(0) as u32 * label_size * 6 / 10)
+        };
+
+        let mut buffer = vec![0u8; (width * height * 3) as usize];
+
+        {
+            let canvas =
+                BitMapBackend::with_buffer(&mut buffer, (width, height)).into_drawing_area();
+            canvas.fill(&WHITE).map_err(draw_err)?;
+            let root = canvas.margin(px(10), px(10), px(10), px(10));
+            let root = match &chart.title {
+                Some(title) => root.titled(title, font(18)).map_err(draw_err)?,
+                None => root,
+            };
+
+            // The mesh centers the labels on the ticks, so the turned ones
+            // are drawn below the axis, from the pixel of each key
+            let turned_labels = |at: &dyn Fn(usize) -> (i32, i32)| {
+                for (k, key) in keys.iter().enumerate() {
+                    let (x, y) = at(k);
+                    let pos = (x, y + px(5) as i32);
+                    canvas
+                        .draw(&Text::new(key.clone(), pos, x_labels.clone()))
+                        .map_err(draw_err)?;
+                }
+                Ok::<(), String>(())
+            };
+
+            match chart.kind {
+                ChartType::Bar | ChartType::Line | ChartType::Area => {
+                    let top = |axis: usize| {
+                        let values = series.iter().filter(|s| s.y_axis_index == axis);
+                        let max = values
+                            .flat_map(|s| s.data.iter())
+                            .fold(0.0f32, |a, b| a.max(*b));
+                        (max * 1.1).max(1.0)
+                    };
+                    let secondary = series.iter().any(|s| s.y_axis_index != 0);
+                    let (top, top2) = (top(0), top(1));
+                    let xrange = -0.5f32..(keys.len() as f32 - 0.5);
+
+                    let mut ctx = ChartBuilder::on(&root)
+                        .x_label_area_size(x_room(x_area(&keys)))
+                        .y_label_area_size(y_room(px(if field.is_some() { 80 } else { 50 })))
+                        .right_y_label_area_size(px(if secondary { 50 } else { 0 }))
+                        .build_cartesian_2d(xrange.clone(), 0.0f32..top)
+                        .map_err(draw_err)?
+                        .set_secondary_coord(xrange, 0.0f32..top2);
+
+                    let label = |v: f32| match field {
+                        Some(f) => f.format_number(v as f64),
+                        None => format!("{}", v),
+                    };
+
+                    let mut mesh = ctx.configure_mesh();
+                    if !theme.grid {
+                        mesh.disable_mesh();
+                    }
+                    mesh.label_style(font(label_size))
+                        .x_desc(&x_desc)
+                        .y_desc(&y_desc)
+                        .disable_x_mesh()
+                        .y_label_formatter(&|y| label(*y))
+                        .x_labels(keys.len())
+                        .x_label_formatter(&|x| {
+                            if !turned && (x - x.round()).abs() < 0.001 && *x >= 0.0 {
+                                keys.get(x.round() as usize).cloned().unwrap_or_default()
+                            } else {
+                                String::new()
+                            }
+                        })
+                        .draw()
+                        .map_err(draw_err)?;
+                    if turned {
+                        turned_labels(&|k| ctx.backend_coord(&(k as f32, 0.0)))?;
+                    }
+                    if secondary {
+                        ctx.configure_secondary_axes()
+                            .label_style(font(label_size))
+                            .y_label_formatter(&|y| format!("{}", y))
+                            .draw()
+                            .map_err(draw_err)?;
+                    }
+
+                    // The kind of the series, of the chart when it has none
+                    let is_bar = |s: &Series| match &s.category {
+                        Some(c) => *c == SeriesCategory::Bar,
+                        None => chart.kind == ChartType::Bar,
+                    };
+                    let bars = series.iter().filter(|s| is_bar(s)).count();
+                    let width = 0.8 / bars.max(1) as f32;
+                    let mut bar = 0;
+
+                    for (i, serie) in series.iter().enumerate() {
+                        let color = pick(i);
+                        // The values of the secondary axis on the scale of the primary
+                        let scale = if serie.y_axis_index != 0 {
+                            top / top2
+                        } else {
+                            1.0
+                        };
+                        let values = serie.data.iter().map(|v| v * scale).collect::<Vec<_>>();
+
+                        let (drawn, offset) = if is_bar(serie) {
+                            bar += 1;
+                            let x = width * (bar - 1) as f32 - 0.4;
+                            let drawn = ctx
+                                .draw_series(
+                                    values.iter().enumerate().filter(|(_, v)| !v.is_nan()).map(
+                                        |(k, v)| {
+                                            let x0 = k as f32 + x;
+                                            Rectangle::new(
+                                                [(x0, 0.0), (x0 + width, *v)],
+                                                color.filled(),
+                                            )
+                                        },
+                                    ),
+                                )
+                                .map_err(draw_err)?;
+                            (drawn, x + width / 2.0)
+                        } else {
+                            let points = values
+                                .iter()
+                                .enumerate()
+                                .map(|(k, v)| (k as f32, *v))
+                                .collect::<Vec<_>>();
+
+                            // The gaps of the filled keys split the line
+                            for points in points.split(|p| p.1.is_nan()).filter(|p| !p.is_empty()) {
+                                let points = if chart.smooth {
+                                    smooth(points)
+                                } else {
+                                    points.to_vec()
+                                };
+                                if points.len() == 1 {
+                                    ctx.draw_series([Circle::new(
+                                        points[0],
+                                        px(3),
+                                        color.filled(),
+                                    )])
+                                    .map_err(draw_err)?;
+                                } else if chart.filled() {
+                                    ctx.draw_series(
+                                        AreaSeries::new(points, 0.0, color.mix(0.2))
+                                            .border_style(color.stroke_width(px(2))),
+                                    )
+                                    .map_err(draw_err)?;
+                                } else {
+                                    ctx.draw_series(LineSeries::new(
+                                        points,
+                                        color.stroke_width(px(2)),
+                                    ))
+                                    .map_err(draw_err)?;
+                                }
+                            }
+
+                            // Only for the legend, of all the parts
+                            let drawn = ctx
+                                .draw_series(LineSeries::new(vec![], color.stroke_width(px(2))))
+                                .map_err(draw_err)?;
+                            (drawn, 0.0)
+                        };
+
+                        drawn.label(serie.name.clone()).legend(move |(x, y)| {
+                            let (w, h) = (px(10) as i32, px(5) as i32);
+                            Rectangle::new([(x, y - h), (x + w, y + h)], color.filled())
+                        });
+
+                        if labeled {
+                            let style = font(label_size)
+                                .into_font()
+                                .color(&BLACK)
+                                .pos(Pos::new(HPos::Center, VPos::Bottom));
+
+                            let values = serie.data.iter().zip(&values).enumerate();
+                            ctx.draw_series(values.filter(|(_, (v, _))| !v.is_nan()).map(
+                                |(k, (v, y))| {
+                                    let text = if serie.y_axis_index != 0 {
+                                        format!("{}", v)
+                                    } else {
+                                        label(*v)
+                                    };
+                                    Text::new(text, (k as f32 + offset, *y), style.clone())
+                                },
+                            ))
+                            .map_err(draw_err)?;
+                        }
+                    }
+
+                    if let Some(position) = legend(theme, SeriesLabelPosition::UpperRight) {
+                        ctx.configure_series_labels()
+                            .label_font(font(label_size))
+                            .position(position)
+                            .background_style(WHITE.mix(0.8))
+                            .border_style(BLACK)
+                            .draw()
+                            .map_err(draw_err)?;
+                    }
+                }
+                ChartType::HorizontalBar => {
+                    let max = series
+                        .iter()
+                        .flat_map(|s| s.data.iter())
+                        .fold(0.0f32, |a, b| a.max(*b));
+                    let yrange = -0.5f32..(keys.len() as f32 - 0.5);
+                    // Room for the longest key, up to the half of the chart
+                    let longest = keys.iter().map(|k| k.chars().count()).max().unwrap_or(0);
+                    let key_area = (longest as u32 * 7 + 10).clamp(50, chart.size().0 / 2);
+
+                    let mut ctx = ChartBuilder::on(&root)
+                        .x_label_area_size(x_room(px(30)))
+                        .y_label_area_size(y_room(px(key_area)))
+                        .build_cartesian_2d(0.0f32..(max * 1.1).max(1.0), yrange)
+                        .map_err(draw_err)?;
+
+                    let label = |v: f32| match field {
+                        Some(f) => f.format_number(v as f64),
+                        None => format!("{}", v),
+                    };
+
+                    // The first key at the top
+                    let key = |y: f32| keys.len() as f32 - 1.0 - y;
+
+                    let mut mesh = ctx.configure_mesh();
+                    if !theme.grid {
+                        mesh.disable_mesh();
+                    }
+                    mesh.label_style(font(label_size))
+                        .x_desc(&x_desc)
+                        .y_desc(&y_desc)
+                        .disable_y_mesh()
+                        .x_label_formatter(&|x| label(*x))
+                        .y_labels(keys.len())
+                        .y_label_formatter(&|y| {
+                            let y = key(*y);
+                            if (y - y.round()).abs() < 0.001 && y >= 0.0 {
+                                keys.get(y.round() as usize).cloned().unwrap_or_default()
+                            } else {
+                                String::new()
+                            }
+                        })
+                        .draw()
+                        .map_err(draw_err)?;
+
+                    let height = 0.8 / series.len().max(1) as f32;
+
+                    for (i, serie) in series.iter().enumerate() {
+                        let color = pick(i);
+
+                        ctx.draw_series(serie.data.iter().enumerate().map(|(k, v)| {
+                            let y0 = key(k as f32) + 0.4 - height * (i + 1) as f32;
+                            Rectangle::new([(0.0, y0), (*v, y0 + height)], color.filled())
+                        }))
+                        .map_err(draw_err)?
+                        .label(serie.name.clone())
+                        .legend(move |(x, y)| {
+                            let (w, h) = (px(10) as i32, px(5) as i32);
+                            Rectangle::new([(x, y - h), (x + w, y + h)], color.filled())
+                        });
+
+                        if labeled {
+                            let style = font(label_size)
+                                .into_font()
+                                .color(&BLACK)
+                                .pos(Pos::new(HPos::Left, VPos::Center));
+
+                            ctx.draw_series(serie.data.iter().enumerate().map(|(k, v)| {
+                                let y = key(k as f32) + 0.4 - height * (i as f32 + 0.5);
+                                Text::new(label(*v), (*v, y), style.clone())
+                            }))
+                            .map_err(draw_err)?;
+                        }
+                    }
+
+                    if let Some(position) = legend(theme, SeriesLabelPosition::LowerRight) {
+                        ctx.configure_series_labels()
+                            .label_font(font(label_size))
+                            .position(position)
+                            .background_style(WHITE.mix(0.8))
+                            .border_style(BLACK)
+                            .draw()
+                            .map_err(draw_err)?;
+                    }
+                }
+                ChartType::Scatter => {
+                    let points = series
+                        .iter()
+                        .map(|s| {
+                            s.data
+                                .chunks_exact(2)
+                                .map(|p| (p[0], p[1]))
+                                .collect::<Vec<_>>()
+                        })
+                        .collect::<Vec<_>>();
+                    let range = |axis: fn(&(f32, f32)) -> f32| {
+                        let (min, max) = points
+                            .iter()
+                            .flatten()
+                            .map(axis)
+                            .fold((f32::MAX, f32::MIN), |(a, b), v| (a.min(v), b.max(v)));
+                        if min > max {
+                            return 0.0f32..1.0;
+                        }
+                        let pad = ((max - min) * 0.05).max(0.5);
+                        (min - pad)..(max + pad)
+                    };
+
+                    let mut ctx = ChartBuilder::on(&root)
+                        .x_label_area_size(px(30))
+                        .y_label_area_size(y_room(px(if field.is_some() { 80 } else { 50 })))
+                        .build_cartesian_2d(range(|p| p.0), range(|p| p.1))
+                        .map_err(draw_err)?;
+
+                    let mut mesh = ctx.configure_mesh();
+                    if !theme.grid {
+                        mesh.disable_mesh();
+                    }
+                    mesh.label_style(font(label_size))
+                        .x_desc(&x_desc)
+                        .y_desc(&y_desc)
+                        .y_label_formatter(&|y| match field {
+                            Some(f) => f.format_number(*y as f64),
+                            None => format!("{}", y),
+                        })
+                        .draw()
+                        .map_err(draw_err)?;
+
+                    for (i, (serie, points)) in series.iter().zip(points).enumerate() {
+                        let color = pick(i);
+
+                        ctx.draw_series(
+                            points
+                                .iter()
+                                .map(|p| Circle::new(*p, px(4), color.filled())),
+                        )
+                        .map_err(draw_err)?
+                        .label(serie.name.clone())
+                        .legend(move |(x, y)| {
+                            Circle::new((x + px(5) as i32, y), px(4), color.filled())
+                        });
+
+                        if labeled {
+                            let style = font(label_size)
+                                .into_font()
+                                .color(&BLACK)
+                                .pos(Pos::new(HPos::Left, VPos::Bottom));
+
+                            ctx.draw_series(points.iter().map(|p| {
+                                let text = match field {
+                                    Some(f) => f.format_number(p.1 as f64),
+                                    None => format!("{}", p.1),
+                                };
+                                Text::new(text, *p, style.clone())
+                            }))
+                            .map_err(draw_err)?;
+                        }
+                    }
+
+                    if let Some(position) = legend(theme, SeriesLabelPosition::UpperLeft) {
+                        ctx.configure_series_labels()
+                            .label_font(font(label_size))
+                            .position(position)
+                            .background_style(WHITE.mix(0.8))
+                            .border_style(BLACK)
+                            .draw()
+                            .map_err(draw_err)?;
+                    }
+                }
+                ChartType::Heatmap | ChartType::Calendar => {
+                    let rows = series.len();
+                    let values = series.iter().flat_map(|s| s.data.iter().copied());
+                    let (min, max) =
+                        values.fold((f32::MAX, f32::MIN), |(a, b), v| (a.min(v), b.max(v)));
+                    let longest = series.iter().map(|s| s.name.chars().count()).max();
+                    let name_area =
+                        (longest.unwrap_or(0) as u32 * 7 + 10).clamp(50, chart.size().0 / 2);
+
+                    let mut ctx = ChartBuilder::on(&root)
+                        .x_label_area_size(x_room(x_area(&keys)))
+                        .y_label_area_size(y_room(px(name_area)))
+                        .build_cartesian_2d(
+                            -0.5f32..(keys.len() as f32 - 0.5),
+                            -0.5f32..(rows as f32 - 0.5),
+                        )
+                        .map_err(draw_err)?;
+
+                    // The first series at the top
+                    let row = |y: f32| rows as f32 - 1.0 - y;
+                    let at = |labels: &[String], v: f32| {
+                        if (v - v.round()).abs() < 0.001 && v >= 0.0 {
+                            labels.get(v.round() as usize).cloned().unwrap_or_default()
+                        } else {
+                            String::new()
+                        }
+                    };
+                    let names = series.iter().map(|s| s.name.clone()).collect::<Vec<_>>();
+
+                    ctx.configure_mesh()
+                        .label_style(font(label_size))
+                        .x_desc(&x_desc)
+                        .y_desc(&y_desc)
+                        .disable_mesh()
+                        .x_labels(keys.len())
+                        .x_label_formatter(&|x| {
+                            if turned {
+                                return String::new();
+                            }
+                            at(&keys, *x)
+                        })
+                        .y_labels(rows)
+                        .y_label_formatter(&|y| at(&names, row(*y)))
+                        .draw()
+                        .map_err(draw_err)?;
+                    if turned {
+                        turned_labels(&|k| ctx.backend_coord(&(k as f32, -0.5)))?;
+                    }
+
+                    let label = |v: f32| match field {
+                        Some(f) => f.format_number(v as f64),
+                        None => format!("{}", v),
+                    };
+                    let style = font(11)
+                        .into_font()
+                        .color(&BLACK)
+                        .pos(Pos::new(HPos::Center, VPos::Center));
+
+                    for (s, serie) in series.iter().enumerate() {
+                        let y = row(s as f32);
+                        for (k, v) in serie.data.iter().enumerate() {
+                            let (r, g, b) = heat_color(*v, min, max);
+                            let x = k as f32;
+                            ctx.draw_series([Rectangle::new(
+                                [(x - 0.5, y - 0.5), (x + 0.5, y + 0.5)],
+                                RGBColor(r, g, b).filled(),
+                            )])
+                            .map_err(draw_err)?;
+                            if chart.labels != Some(false) {
+                                ctx.draw_series([Text::new(label(*v), (x, y), style.clone())])
+                                    .map_err(draw_err)?;
+                            }
+                        }
+                    }
+                }
+                ChartType::Pizza | ChartType::Donut => {
+                    let (w, h) = root.dim_in_pixel();
+                    let center = (w as i32 / 2, h as i32 / 2);
+                    let radius = (w.min(h) as f64 / 2.0) * 0.8;
+                    let sizes: Vec<f64> = series
+                        .iter()
+                        .map(|s| s.data.iter().map(|v| *v as f64).sum())
+                        .collect();
+                    let colors: Vec<RGBColor> = (0..series.len())
+                        .map(|i| {
+                            let c = pick(i);
+                            RGBColor(c.0, c.1, c.2)
+                        })
+                        .collect();
+                    let total: f64 = sizes.iter().sum();
+                    let labels: Vec<String> = series
+                        .iter()
+                        .zip(&sizes)
+                        .map(|(s, size)| match (labeled, field) {
+                            _ if chart.percents && total != 0.0 => {
+                                format!("{}: {:.1}%", s.name, size / total * 100.0)
+                            }
+                            (true, Some(f)) => format!("{}: {}", s.name, f.format_number(*size)),
+                            (true, None) => format!("{}: {}", s.name, size),
+                            (false, _) => s.name.clone(),
+                        })
+                        .collect();
+
+                    let mut pie = Pie::new(&center, &radius, &sizes, &colors, &labels);
+                    pie.label_style(font(label_size));
+                    if chart.kind == ChartType::Donut {
+                        pie.donut_hole(radius * 0.55);
+                    }
+                    root.draw(&pie).map_err(draw_err)?;
+                }
+                ChartType::Gauge => {
+                    let gauge = chart.gauge.as_ref().ok_or("Gauge must be defined")?;
+                    let data = series.first().map(|s| s.data.clone()).unwrap_or_default();
+                    let value = *data.first().ok_or("Gauge without value")?;
+                    let target = data.get(1).copied();
+
+                    let label = |v: f32| match field {
+                        Some(f) => f.format_number(v as f64),
+                        None => format!("{}", v),
+                    };
+                    let (w, h) = root.dim_in_pixel();
+                    let center = (w as f32 / 2.0, h as f32 * 0.75);
+                    let outer = (w as f32 / 3.0).min(h as f32 * 0.65);
+                    let inner = outer * 0.7;
+                    let pixels = |points: Vec<(f32, f32)>| {
+                        points
+                            .into_iter()
+                            .map(|(x, y)| (x as i32, y as i32))
+                            .collect::<Vec<_>>()
+                    };
+
+                    let (r, g, b) = gauge.color(value, target);
+                    root.draw(&Polygon::new(
+                        pixels(gauge_band(0.0, 1.0, center, outer, inner)),
+                        RGBColor(230, 230, 230).filled(),
+                    ))
+                    .map_err(draw_err)?;
+                    root.draw(&Polygon::new(
+                        pixels(gauge_band(0.0, gauge.ratio(value), center, outer, inner)),
+                        RGBColor(r, g, b).filled(),
+                    ))
+                    .map_err(draw_err)?;
+
+                    let text = |size: u32| {
+                        font(size)
+                            .into_font()
+                            .color(&BLACK)
+                            .pos(Pos::new(HPos::Center, VPos::Bottom))
+                    };
+                    let (cx, cy) = (center.0 as i32, center.1 as i32);
+                    let below = cy + px(30) as i32;
+                    let texts = vec![
+                        (label(value), (cx, cy - px(10) as i32), text(40)),
+                        (series[0].name.clone(), (cx, below), text(16)),
+                        (
+                            label(gauge.min),
+                            ((center.0 - (outer + inner) / 2.0) as i32, below),
+                            text(14),
+                        ),
+                        (
+                            label(gauge.max),
+                            ((center.0 + (outer + inner) / 2.0) as i32, below),
+                            text(14),
+                        ),
+                    ];
+                    for (content, pos, style) in texts {
+                        root.draw(&Text::new(content, pos, style))
+                            .map_err(draw_err)?;
+                    }
+
+                    if let Some(target) = target {
+                        let t = gauge.ratio(target);
+                        let mark = pixels(gauge_band(
+                            t,
+                            t,
+                            center,
+                            outer + 10.0 * scale,
+                            inner - 10.0 * scale,
+                        ));
+                        root.draw(&PathElement::new(
+                            vec![mark[0], mark[2]],
+                            BLACK.stroke_width(px(3)),
+                        ))
+                        .map_err(draw_err)?;
+                        root.draw(&Text::new(
+                            locale::fill(locale::texts().target, &[&label(target)]),
+                            (cx, below + px(25) as i32),
+                            text(16),
+                        ))
+                        .map_err(draw_err)?;
+                    }
+                }
+            }
+
+            root.present().map_err(draw_err)?;
+        }
+
+        let img =
+            RgbImage::from_raw(width, height, buffer).ok_or("Error on chart buffer".to_string())?;
+
+        let mut png = Cursor::new(vec![]);
+        img.write_to(&mut png, ImageFormat::Png)
+            .map_err(|e| format!("Error encoding PNG: {}", e))?;
+
+        Ok(png.into_inner())
+    }
+
+    /// The PNG inside of the SVG, plotters draws only bitmaps here
+    fn svg(
+        &self,
+        chart: &ChartComponent,
+        keys: Vec<String>,
+        series: Vec<Series>,
+        field: Option<&Field>,
+    ) -> Result<String, String> {
+        let png = self.png(chart, keys, series, field)?;
+        let (width, height) = chart.size();
+
+        Ok(format!(
+            "<svg width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\" xmlns=\"http://www.w3.org/2000/svg\"><image width=\"{0}\" height=\"{1}\" href=\"data:image/png;base64,{2}\"/></svg>",
+            width,
+            height,
+            STANDARD.encode(png)
+        ))
+    }
+}
+
+/// Points of a Catmull-Rom curve through the points, clamped to not
+/// overshoot the values around it
+fn smooth(points: &[(f32, f32)]) -> Vec<(f32, f32)> {
+    const STEPS: usize = 8;
+
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    let mut curve = vec![points[0]];
+    for i in 0..points.len() - 1 {
+        let p0 = points[i.saturating_sub(1)];
+        let (p1, p2) = (points[i], points[i + 1]);
+        let p3 = points[(i + 2).min(points.len() - 1)];
+        let (low, high) = (p1.1.min(p2.1), p1.1.max(p2.1));
+
+        for s in 1..=STEPS {
+            let t = s as f32 / STEPS as f32;
+            let (t2, t3) = (t * t, t * t * t);
+            let y = 0.5
+                * (2.0 * p1.1
+                    + (p2.1 - p0.1) * t
+                    + (2.0 * p0.1 - 5.0 * p1.1 + 4.0 * p2.1 - p3.1) * t2
+                    + (3.0 * p1.1 - p0.1 - 3.0 * p2.1 + p3.1) * t3);
+            curve.push((p1.0 + (p2.0 - p1.0) * t, y.clamp(low, high)));
+        }
+    }
+
+    curve
+}
+
+/// Position of the legend by the theme, none when hidden
+fn legend(theme: &ChartTheme, default: SeriesLabelPosition) -> Option<SeriesLabelPosition> {
+    match theme.legend {
+        Some(ChartLegend::Left) => Some(SeriesLabelPosition::UpperLeft),
+        Some(ChartLegend::Center) => Some(SeriesLabelPosition::UpperMiddle),
+        Some(ChartLegend::Right) => Some(SeriesLabelPosition::UpperRight),
+        Some(ChartLegend::Hidden) => None,
+        None => Some(default),
+    }
+}
+
+/// Rotation of the labels, plotters only turns them by the quarters
+fn rotation(degrees: f32) -> FontTransform {
+    match degrees.rem_euclid(360.0) {
+        d if (45.0..135.0).contains(&d) => FontTransform::Rotate90,
+        d if (135.0..225.0).contains(&d) => FontTransform::Rotate180,
+        d if (225.0..315.0).contains(&d) => FontTransform::Rotate270,
+        _ => FontTransform::None,
+    }
+}
+
+fn draw_err<E: std::fmt::Display>(e: E) -> String {
+    format!("Error generating chart: {}", e)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{smooth, PlottersBackend};
+    use crate::presentation::charts::{
+        ChartBackend, ChartComponent, ChartGauge, ChartLegend, ChartTheme, ChartType,
+    };
+    use crate::value::{Field, NumberFormat};
+    use charts_rs::{Series, SeriesCategory};
+
+    #[test]
+    pub fn bar_png() {
+        let chart = ChartComponent {
+            kind: ChartType::Bar,
+            ..Default::default()
+        };
+
+        let png = PlottersBackend {}.png(
+            &chart,
+            vec!["a".to_string(), "b".to_string()],
+            vec![Series::new("Qt".to_string(), vec![1.0, 2.0])],
+            None,
+        );
+
+        assert!(png.is_ok());
+        assert!(png.unwrap().starts_with(&[0x89, b'P', b'N', b'G']));
+    }
+
+    #[test]
+    pub fn horizontal_bar_png() {
+        let chart = ChartComponent {
+            kind: ChartType::HorizontalBar,
+            ..Default::default()
+        };
+
+        let png = PlottersBackend {}.png(
+            &chart,
+            vec!["https://abc.com/orders".to_string(), "b".to_string()],
+            vec![
+                Series::new("Qt".to_string(), vec![1.0, 2.0]),
+                Series::new("Total".to_string(), vec![3.0, 0.5]),
+            ],
+            Some(&Field::default()),
+        );
+
+        assert!(png.is_ok());
+    }
+
+    #[test]
+    pub fn mixed_png() {
+        let mut margin = Series::new("Margin".to_string(), vec![0.2, 0.35]);
+        margin.category = Some(SeriesCategory::Line);
+        margin.y_axis_index = 1;
+
+        let chart = ChartComponent {
+            kind: ChartType::Bar,
+            ..Default::default()
+        };
+        let png = PlottersBackend {}.png(
+            &chart,
+            vec!["a".to_string(), "b".to_string()],
+            vec![
+                Series::new("Revenue".to_string(), vec![1500.0, 2300.0]),
+                Series::new("Cost".to_string(), vec![900.0, 1200.0]),
+                margin,
+            ],
+            Some(&Field::default()),
+        );
+        assert!(png.is_ok());
+    }
+
+    #[test]
+    pub fn sized_svg() {
+        let chart = ChartComponent {
+            kind: ChartType::Bar,
+            width: Some(300),
+            height: Some(200),
+            scale: Some(2.0),
+            ..Default::default()
+        };
+        let svg = PlottersBackend {}
+            .svg(
+                &chart,
+                vec!["a".to_string(), "b".to_string()],
+                vec![Series::new("Qt".to_string(), vec![1.0, 2.0])],
+                None,
+            )
+            .unwrap();
+        assert!(svg.starts_with("<svg width=\"300\" height=\"200\""));
+    }
+
+    #[test]
+    pub fn sized_png() {
+        // Width and height of the IHDR chunk
+        let size = |png: &[u8]| {
+            (
+                u32::from_be_bytes(png[16..20].try_into().unwrap()),
+                u32::from_be_bytes(png[20..24].try_into().unwrap()),
+            )
+        };
+
+        let chart = ChartComponent {
+            kind: ChartType::Bar,
+            width: Some(300),
+            height: Some(200),
+            scale: Some(2.0),
+            ..Default::default()
+        };
+        let png = PlottersBackend {}
+            .png(
+                &chart,
+                vec!["a".to_string(), "b".to_string()],
+                vec![Series::new("Qt".to_string(), vec![1.0, 2.0])],
+                None,
+            )
+            .unwrap();
+        assert_eq!((600, 400), size(&png));
+    }
+
+    #[test]
+    pub fn themed_png() {
+        let chart = ChartComponent {
+            kind: ChartType::Bar,
+            theme: ChartTheme {
+                palette: vec!["#1b365d".to_string()],
+                font_size: Some(10.0),
+                legend: Some(ChartLegend::Right),
+                x_labels_rotation: 90.0,
+                grid: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let keys = vec!["a".to_string(), "b".to_string()];
+        let series = vec![
+            Series::new("Qt".to_string(), vec![1.0, 2.0]),
+            Series::new("Total".to_string(), vec![3.0, 4.0]),
+        ];
+        let png = PlottersBackend {}.png(&chart, keys.clone(), series.clone(), None);
+        assert!(png.is_ok());
+
+        let chart = ChartComponent {
+            kind: ChartType::Pizza,
+            theme: ChartTheme {
+                legend: Some(ChartLegend::Hidden),
+                ..chart.theme
+            },
+            ..chart
+        };
+        let png = PlottersBackend {}.png(&chart, keys.clone(), series.clone(), None);
+        assert!(png.is_ok());
+
+        let chart = ChartComponent {
+            theme: ChartTheme {
+                palette: vec!["dark".to_string()],
+                ..Default::default()
+            },
+            ..chart
+        };
+        let png = PlottersBackend {}.png(&chart, keys, series, None);
+        assert!(png.is_err());
+    }
+
+    #[test]
+    pub fn titled_png() {
+        let chart = ChartComponent {
+            kind: ChartType::Bar,
+            title: Some("Sales".to_string()),
+            x_title: Some("Month".to_string()),
+            y_title: Some("Units".to_string()),
+            labels: Some(true),
+            ..Default::default()
+        };
+
+        let keys = vec!["a".to_string(), "b".to_string()];
+        let series = vec![Series::new("Qt".to_string(), vec![1.0, 2.0])];
+        for kind in [ChartType::Bar, ChartType::Scatter, ChartType::Heatmap] {
+            let chart = ChartComponent {
+                kind,
+                ..chart.clone()
+            };
+            let png = PlottersBackend {}.png(&chart, keys.clone(), series.clone(), None);
+            assert!(png.is_ok());
+        }
+    }
+
+    #[test]
+    pub fn area_png() {
+        let chart = ChartComponent {
+            kind: ChartType::Area,
+            smooth: true,
+            ..Default::default()
+        };
+
+        let png = PlottersBackend {}.png(
+            &chart,
+            vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            vec![Series::new("Qt".to_string(), vec![1.0, 3.0, 6.0])],
+            None,
+        );
+
+        assert!(png.is_ok());
+    }
+
+    #[test]
+    pub fn smooth_points() {
+        let points = vec![(0.0, 1.0), (1.0, 3.0), (2.0, 6.0)];
+        let curve = smooth(&points);
+
+        assert_eq!(17, curve.len());
+        assert_eq!((0.0, 1.0), curve[0]);
+        assert_eq!((1.0, 3.0), curve[8]);
+        assert_eq!((2.0, 6.0), curve[16]);
+        assert!(curve.windows(2).all(|w| w[0].1 <= w[1].1));
+
+        assert_eq!(vec![(0.0, 1.0)], smooth(&[(0.0, 1.0)]));
+    }
+
+    #[test]
+    pub fn donut_and_gauge_png() {
+        let chart = ChartComponent {
+            kind: ChartType::Donut,
+            ..Default::default()
+        };
+
+        let png = PlottersBackend {}.png(
+            &chart,
+            vec![],
+            vec![
+                Series::new("A".to_string(), vec![1.0]),
+                Series::new("B".to_string(), vec![3.0]),
+            ],
+            None,
+        );
+        assert!(png.is_ok());
+
+        let chart = ChartComponent {
+            kind: ChartType::Gauge,
+            gauge: Some(ChartGauge {
+                value: "uptime".to_string(),
+                target: None,
+                min: 90.0,
+                max: 100.0,
+            }),
+            ..Default::default()
+        };
+
+        let png = PlottersBackend {}.png(
+            &chart,
+            vec![],
+            vec![Series::new("Uptime".to_string(), vec![98.7, 99.0])],
+            None,
+        );
+        assert!(png.is_ok());
+    }
+
+    #[test]
+    pub fn heatmap_png() {
+        let chart = ChartComponent {
+            kind: ChartType::Calendar,
+            ..Default::default()
+        };
+
+        let png = PlottersBackend {}.png(
+            &chart,
+            ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"]
+                .map(|d| d.to_string())
+                .to_vec(),
+            vec![
+                Series::new(
+                    "2024-06-03".to_string(),
+                    vec![0.0, 1.0, 3.0, 0.0, 2.0, 0.0, 4.0],
+                ),
+                Series::new("2024-06-10".to_string(), vec![1.0; 7]),
+            ],
+            None,
+        );
+        assert!(png.is_ok());
+    }
+
+    #[test]
+    pub fn scatter_png() {
+        let chart = ChartComponent {
+            kind: ChartType::Scatter,
+            ..Default::default()
+        };
+
+        let png = PlottersBackend {}.png(
+            &chart,
+            vec![],
+            vec![
+                Series::new("a".to_string(), vec![10.0, 1.5, 30.0, 4.5]),
+                Series::new("b".to_string(), vec![20.0, 3.0]),
+            ],
+            None,
+        );
+
+        assert!(png.is_ok());
+    }
+
+    #[test]
+    pub fn pizza_png() {
+        let chart = ChartComponent {
+            kind: ChartType::Pizza,
+            ..Default::default()
+        };
+
+        let png = PlottersBackend {}.png(
+            &chart,
+            vec![],
+            vec![
+                Series::new("A".to_string(), vec![1.0]),
+                Series::new("B".to_string(), vec![3.0]),
+            ],
+            None,
+        );
+
+        assert!(png.is_ok());
+    }
+
+    #[test]
+    pub fn labels_png() {
+        let chart = ChartComponent {
+            kind: ChartType::Bar,
+            ..Default::default()
+        };
+        let field = Field {
+            format: Some(NumberFormat {
+                thousands: Some(".".to_string()),
+                currency: Some("€".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let png = PlottersBackend {}.png(
+            &chart,
+            vec!["a".to_string(), "b".to_string()],
+            vec![Series::new("Total".to_string(), vec![1500.0, 2300.0])],
+            Some(&field),
+        );
+
+        assert!(png.is_ok());
+    }
+}
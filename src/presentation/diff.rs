@@ -0,0 +1,221 @@
+//! Diff component, the rows added, removed or changed against a baseline
+
+use super::{formats::OutputFormat, locale, table::TableComponent, Component, RenderedContent};
+use crate::{
+    source::Query,
+    value::{Field, TypedValue, Value},
+};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct DiffComponent {
+    /// Field identifying the rows between the results
+    pub key: String,
+    /// Query of the baseline, on the same source. The result of the last
+    /// run, kept on the `state_db`, when omitted
+    #[serde(default)]
+    pub sql: Option<String>,
+}
+
+/// Rows of a result by the fields, with the values as presented
+pub type Snapshot = Vec<BTreeMap<String, String>>;
+
+impl Component for DiffComponent {
+    fn render(
+        &self,
+        query: Query,
+        data: Vec<Vec<Value>>,
+        format: OutputFormat,
+    ) -> Result<RenderedContent, String> {
+        TableComponent::default().render(query, data, format)
+    }
+}
+
+impl DiffComponent {
+    /// Query and rows of the differences, with the `Change` of each row
+    /// on the first column. The changed values are like `old → new`
+    pub fn compare(
+        &self,
+        query: Query,
+        rows: Vec<Vec<Value>>,
+        baseline: &Snapshot,
+    ) -> Result<(Query, Vec<Vec<Value>>), String> {
+        if !query.fields.iter().any(|f| f.field == self.key) {
+            return Err(format!("Field {} not found", self.key));
+        }
+
+        let change = Field {
+            field: "change".to_string(),
+            title: locale::texts().change.to_string(),
+            ..Default::default()
+        };
+        let row = |kind: &str, values: Vec<Value>| {
+            let mut row = vec![Value {
+                inner: Some(TypedValue::String(kind.to_string())),
+                field: change.clone(),
+            }];
+            row.extend(values);
+            row
+        };
+
+        let current = snapshot(&rows);
+        let keys = current
+            .iter()
+            .map(|r| r.get(&self.key).cloned().unwrap_or_default())
+            .collect::<Vec<_>>();
+        let find = |key: &str| {
+            baseline
+                .iter()
+                .find(|b| b.get(&self.key).map(|k| k.as_str()) == Some(key))
+        };
+
+        let (mut added, mut changed) = (vec![], vec![]);
+        for ((values, presented), key) in rows.into_iter().zip(&current).zip(&keys) {
+            let Some(before) = find(key) else {
+                added.push(row(locale::texts().added, values));
+                continue;
+            };
+
+            let mut differ = false;
+            let values = values
+                .into_iter()
+                .map(|v| {
+                    let now = presented.get(&v.field.field).cloned().unwrap_or_default();
+                    match before.get(&v.field.field) {
+                        Some(old) if *old != now => {
+                            differ = true;
+                            Value {
+                                inner: Some(TypedValue::String(format!("{} → {}", old, now))),
+                                field: v.field,
+                            }
+                        }
+                        _ => v,
+                    }
+                })
+                .collect();
+            if differ {
+                changed.push(row(locale::texts().changed, values));
+            }
+        }
+
+        let removed = baseline
+            .iter()
+            .filter(|b| !keys.iter().any(|k| b.get(&self.key) == Some(k)))
+            .map(|b| {
+                let values = query
+                    .fields
+                    .iter()
+                    .map(|f| Value {
+                        inner: b.get(&f.field).cloned().map(TypedValue::String),
+                        field: f.clone(),
+                    })
+                    .collect();
+                row(locale::texts().removed, values)
+            })
+            .collect::<Vec<_>>();
+
+        let mut fields = vec![change];
+        fields.extend(query.fields.iter().cloned());
+
+        let mut rows = added;
+        rows.extend(removed);
+        rows.extend(changed);
+
+        Ok((Query { fields, ..query }, rows))
+    }
+}
+
+/// Values of the rows as presented, to compare them with the next run
+pub fn snapshot(rows: &[Vec<Value>]) -> Snapshot {
+    rows.iter()
+        .map(|r| {
+            r.iter()
+                .map(|v| (v.field.field.clone(), v.to_string()))
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{snapshot, DiffComponent};
+    use crate::{
+        presentation::{formats::OutputFormat, Component},
+        source::Query,
+        value::{Field, FieldType, TypedValue, Value},
+    };
+
+    fn users(rows: &[(i64, &str)]) -> (Query, Vec<Vec<Value>>) {
+        let fields = vec![
+            Field {
+                field: "id".to_string(),
+                title: "Id".to_string(),
+                kind: FieldType::Integer,
+                ..Default::default()
+            },
+            Field {
+                field: "name".to_string(),
+                title: "Name".to_string(),
+                ..Default::default()
+            },
+        ];
+        let rows = rows
+            .iter()
+            .map(|(id, name)| {
+                vec![
+                    Value {
+                        inner: Some(TypedValue::Integer(*id)),
+                        field: fields[0].clone(),
+                    },
+                    Value {
+                        inner: Some(TypedValue::String(name.to_string())),
+                        field: fields[1].clone(),
+                    },
+                ]
+            })
+            .collect();
+
+        let query = Query {
+            title: "Users".to_string(),
+            fields,
+            ..Default::default()
+        };
+        (query, rows)
+    }
+
+    #[test]
+    fn compare() -> Result<(), String> {
+        let (_, before) = users(&[(1, "ana"), (2, "bob"), (3, "carl")]);
+        let (query, rows) = users(&[(1, "ana"), (3, "carla"), (4, "dan")]);
+
+        let diff = DiffComponent {
+            key: "id".to_string(),
+            sql: None,
+        };
+        let (query, rows) = diff.compare(query, rows, &snapshot(&before))?;
+
+        let result = diff.render(query, rows, OutputFormat::Markdown)?;
+        assert_eq!(
+            r#"| Change  | Id | Name         |
+|---------|----|--------------|
+| Added   | 4  | dan          |
+| Removed | 2  | bob          |
+| Changed | 3  | carl → carla |"#
+                .to_string(),
+            result.content
+        );
+
+        let (query, rows) = users(&[(1, "ana")]);
+        let diff = DiffComponent {
+            key: "email".to_string(),
+            sql: None,
+        };
+        assert_eq!(
+            Err("Field email not found".to_string()),
+            diff.compare(query, rows, &vec![]).map(|_| ())
+        );
+
+        Ok(())
+    }
+}
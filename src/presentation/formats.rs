@@ -2,56 +2,196 @@
 
 use serde::Deserialize;
 
-#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
 pub enum OutputFormat {
+    #[default]
     Plain,
     Html,
     Markdown,
+    /// Wiki markup of the Jira issues and of the Confluence pages
+    Jira,
+    /// Markdown dialect of the Slack messages, the tables on code blocks
+    SlackMrkdwn,
 }
 
 impl OutputFormat {
     pub fn title1(&self, title: &str) -> String {
         match self {
             OutputFormat::Plain => format!("\n{}\n\n", title),
-            OutputFormat::Html => format!("<h1 class=\"lmr-h1\">{}</h1>\n", title),
+            OutputFormat::Html => format!("<h1 class=\"lmr-h1\">{}</h1>\n", escape(title)),
             OutputFormat::Markdown => format!("\n# {}\n\n", title),
+            OutputFormat::Jira => format!("\nh1. {}\n\n", escape_jira(title)),
+            OutputFormat::SlackMrkdwn => format!("\n*{}*\n\n", escape_slack(title)),
         }
     }
 
     pub fn title2(&self, title: &str) -> String {
         match self {
             OutputFormat::Plain => format!("{}\n\n", title),
-            OutputFormat::Html => format!("<h3 class=\"lmr-h3\">{}</h3>\n", title),
+            OutputFormat::Html => format!("<h3 class=\"lmr-h3\">{}</h3>\n", escape(title)),
             OutputFormat::Markdown => format!("## {}\n\n", title),
+            OutputFormat::Jira => format!("h2. {}\n\n", escape_jira(title)),
+            OutputFormat::SlackMrkdwn => format!("*{}*\n\n", escape_slack(title)),
+        }
+    }
+
+    pub fn title3(&self, title: &str) -> String {
+        match self {
+            OutputFormat::Plain => format!("{}\n\n", title),
+            OutputFormat::Html => format!("<h4 class=\"lmr-h4\">{}</h4>\n", escape(title)),
+            OutputFormat::Markdown => format!("### {}\n\n", title),
+            OutputFormat::Jira => format!("h3. {}\n\n", escape_jira(title)),
+            OutputFormat::SlackMrkdwn => format!("_{}_\n\n", escape_slack(title)),
+        }
+    }
+
+    /// Text about the report or the query, beneath the title of it
+    pub fn description(&self, text: &str) -> String {
+        match self {
+            OutputFormat::Html => {
+                format!("<p class=\"lmr-description\">{}</p>\n", escape(text.trim()))
+            }
+            _ => format!("{}\n\n", text.trim()),
         }
     }
 
     pub fn simple(&self, content: &str) -> String {
+        format!("{}\n", content)
+    }
+
+    /// Plain text, like the messages, escaped on the HTML
+    pub fn text(&self, text: &str) -> String {
+        self.simple(&self.escape(text))
+    }
+
+    /// Text safe to be placed on the content or on an attribute of the format
+    pub fn escape(&self, text: &str) -> String {
+        match self {
+            OutputFormat::Html => escape(text),
+            OutputFormat::Jira => escape_jira(text),
+            OutputFormat::SlackMrkdwn => escape_slack(text),
+            _ => text.to_string(),
+        }
+    }
+
+    pub fn list(&self, items: &[String]) -> String {
         match self {
-            _ => format!("{}\n", content),
+            OutputFormat::Html => format!(
+                "<ul class=\"lmr-list\">\n{}</ul>\n",
+                items
+                    .iter()
+                    .map(|i| format!("<li>{}</li>\n", escape(i)))
+                    .collect::<String>()
+            ),
+            OutputFormat::Jira => items
+                .iter()
+                .map(|i| format!("* {}\n", escape_jira(i)))
+                .collect(),
+            OutputFormat::SlackMrkdwn => items
+                .iter()
+                .map(|i| format!("• {}\n", escape_slack(i)))
+                .collect(),
+            _ => items.iter().map(|i| format!("- {}\n", i)).collect(),
+        }
+    }
+
+    /// Target of the links to the content after it, only on the HTML
+    pub fn anchor(&self, id: &str) -> String {
+        match self {
+            OutputFormat::Html => format!("<a name=\"{0}\" id=\"{0}\"></a>\n", escape(id)),
+            OutputFormat::Jira => format!("{{anchor:{}}}\n", escape_jira(id)),
+            _ => String::new(),
+        }
+    }
+
+    /// List of the links to the anchors, of the texts on the others formats
+    pub fn links(&self, items: &[(String, String)]) -> String {
+        match self {
+            OutputFormat::Html => format!(
+                "<ul class=\"lmr-list lmr-toc\">\n{}</ul>\n",
+                items
+                    .iter()
+                    .map(|(id, text)| {
+                        format!(
+                            "<li><a href=\"#{}\">{}</a></li>\n",
+                            escape(id),
+                            escape(text)
+                        )
+                    })
+                    .collect::<String>()
+            ),
+            OutputFormat::Jira => items
+                .iter()
+                .map(|(id, text)| format!("* [{}|#{}]\n", escape_jira(text), escape_jira(id)))
+                .collect(),
+            _ => self.list(&items.iter().map(|(_, t)| t.clone()).collect::<Vec<_>>()),
         }
     }
 
     pub fn break_line(&self) -> String {
         match self {
-            OutputFormat::Plain => format!("\n"),
-            OutputFormat::Html => format!("<br>\n"),
-            OutputFormat::Markdown => format!("\n"),
+            OutputFormat::Plain => "\n".to_string(),
+            OutputFormat::Html => "<br>\n".to_string(),
+            OutputFormat::Markdown => "\n".to_string(),
+            OutputFormat::Jira => "\n".to_string(),
+            OutputFormat::SlackMrkdwn => "\n".to_string(),
         }
     }
 
-    pub fn body(&self, content: &str) -> String {
+    /// Document of the content, on the HTML with the CSS after the default one
+    pub fn body(&self, content: &str, style: Option<&str>) -> String {
         match self {
-            OutputFormat::Html => include_str!("template.html").replace("{{ content }}", content),
-            _ => format!("{}", content),
+            OutputFormat::Html => include_str!("template.html")
+                .replace("{{ style }}", &self.style(style))
+                .replace("{{ content }}", content),
+            _ => content.to_string(),
         }
     }
+
+    /// Tag of the CSS, on the HTML
+    pub fn style(&self, style: Option<&str>) -> String {
+        match (self, style) {
+            (OutputFormat::Html, Some(style)) => format!("<style>\n{}\n</style>", style),
+            _ => String::new(),
+        }
+    }
+}
+
+/// Text with the HTML special characters replaced by their entities
+pub fn escape(text: &str) -> String {
+    let mut r = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => r.push_str("&amp;"),
+            '<' => r.push_str("&lt;"),
+            '>' => r.push_str("&gt;"),
+            '"' => r.push_str("&quot;"),
+            '\'' => r.push_str("&#39;"),
+            c => r.push(c),
+        }
+    }
+    r
 }
 
-impl Default for OutputFormat {
-    fn default() -> Self {
-        OutputFormat::Plain
+/// Text with the characters of the links, tables and macros of the Jira
+/// wiki markup escaped by a backslash
+pub fn escape_jira(text: &str) -> String {
+    let mut r = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '|' | '[' | ']' | '{' | '}') {
+            r.push('\\');
+        }
+        r.push(c);
     }
+    r
+}
+
+/// Text with the control characters of the Slack messages replaced
+/// by their entities
+pub fn escape_slack(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
 }
 
 #[cfg(test)]
@@ -87,6 +227,35 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn title3() {
+        assert_eq!("Title\n\n".to_string(), OutputFormat::Plain.title3("Title"));
+        assert_eq!(
+            "<h4 class=\"lmr-h4\">Title</h4>\n".to_string(),
+            OutputFormat::Html.title3("Title")
+        );
+        assert_eq!(
+            "### Title\n\n".to_string(),
+            OutputFormat::Markdown.title3("Title")
+        );
+    }
+
+    #[test]
+    fn description() {
+        assert_eq!(
+            "Daily sales\n\n".to_string(),
+            OutputFormat::Plain.description("Daily sales\n")
+        );
+        assert_eq!(
+            "<p class=\"lmr-description\">Daily sales</p>\n".to_string(),
+            OutputFormat::Html.description("Daily sales")
+        );
+        assert_eq!(
+            "Daily sales\n\n".to_string(),
+            OutputFormat::Markdown.description("Daily sales")
+        );
+    }
+
     #[test]
     fn simple() {
         assert_eq!(
@@ -103,6 +272,149 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn text() {
+        assert_eq!(
+            "a < b & c\n".to_string(),
+            OutputFormat::Plain.text("a < b & c")
+        );
+        assert_eq!(
+            "a &lt; b &amp; c\n".to_string(),
+            OutputFormat::Html.text("a < b & c")
+        );
+        assert_eq!(
+            "a < b & c\n".to_string(),
+            OutputFormat::Markdown.text("a < b & c")
+        );
+    }
+
+    #[test]
+    fn escape() {
+        assert_eq!(
+            "&lt;b&gt;&quot;Tom&#39;s&quot; &amp; co&lt;/b&gt;".to_string(),
+            super::escape("<b>\"Tom's\" & co</b>")
+        );
+        assert_eq!("Sales".to_string(), super::escape("Sales"));
+        assert_eq!(
+            "<h3 class=\"lmr-h3\">Query: &lt;script&gt;</h3>\n".to_string(),
+            OutputFormat::Html.title2("Query: <script>")
+        );
+        assert_eq!(
+            "<ul class=\"lmr-list\">\n<li>A &amp; B: 2 rows</li>\n</ul>\n".to_string(),
+            OutputFormat::Html.list(&["A & B: 2 rows".to_string()])
+        );
+        assert_eq!(
+            "Query: <script>\n\n".to_string(),
+            OutputFormat::Plain.title2("Query: <script>")
+        );
+    }
+
+    #[test]
+    fn links() {
+        let items = vec![
+            ("lmr-query-1".to_string(), "Sales".to_string()),
+            ("lmr-query-2".to_string(), "R&D".to_string()),
+        ];
+
+        assert_eq!(
+            "- Sales\n- R&D\n".to_string(),
+            OutputFormat::Plain.links(&items)
+        );
+        assert_eq!(
+            "<ul class=\"lmr-list lmr-toc\">\n<li><a href=\"#lmr-query-1\">Sales</a></li>\n<li><a href=\"#lmr-query-2\">R&amp;D</a></li>\n</ul>\n".to_string(),
+            OutputFormat::Html.links(&items)
+        );
+        assert_eq!(
+            "- Sales\n- R&D\n".to_string(),
+            OutputFormat::Markdown.links(&items)
+        );
+
+        assert_eq!(
+            "<a name=\"lmr-query-1\" id=\"lmr-query-1\"></a>\n".to_string(),
+            OutputFormat::Html.anchor("lmr-query-1")
+        );
+        assert_eq!("".to_string(), OutputFormat::Markdown.anchor("lmr-query-1"));
+    }
+
+    #[test]
+    fn jira() {
+        assert_eq!(
+            "\nh1. Sales \\[2024\\]\n\n".to_string(),
+            OutputFormat::Jira.title1("Sales [2024]")
+        );
+        assert_eq!(
+            "h2. Title\n\n".to_string(),
+            OutputFormat::Jira.title2("Title")
+        );
+        assert_eq!(
+            "h3. Title\n\n".to_string(),
+            OutputFormat::Jira.title3("Title")
+        );
+        assert_eq!(
+            "* A\n* B \\| C\n".to_string(),
+            OutputFormat::Jira.list(&["A".to_string(), "B | C".to_string()])
+        );
+        assert_eq!(
+            "{anchor:lmr-query-1}\n".to_string(),
+            OutputFormat::Jira.anchor("lmr-query-1")
+        );
+        assert_eq!(
+            "* [Sales|#lmr-query-1]\n".to_string(),
+            OutputFormat::Jira.links(&[("lmr-query-1".to_string(), "Sales".to_string())])
+        );
+        assert_eq!(
+            "a \\{b\\} c\n".to_string(),
+            OutputFormat::Jira.text("a {b} c")
+        );
+        assert_eq!(
+            "Content".to_string(),
+            OutputFormat::Jira.body("Content", None)
+        );
+    }
+
+    #[test]
+    fn slack() {
+        assert_eq!(
+            "\n*Sales &amp; costs*\n\n".to_string(),
+            OutputFormat::SlackMrkdwn.title1("Sales & costs")
+        );
+        assert_eq!(
+            "*Title*\n\n".to_string(),
+            OutputFormat::SlackMrkdwn.title2("Title")
+        );
+        assert_eq!(
+            "_Title_\n\n".to_string(),
+            OutputFormat::SlackMrkdwn.title3("Title")
+        );
+        assert_eq!(
+            "• A\n• &lt;B&gt;\n".to_string(),
+            OutputFormat::SlackMrkdwn.list(&["A".to_string(), "<B>".to_string()])
+        );
+        assert_eq!(
+            "• Sales\n".to_string(),
+            OutputFormat::SlackMrkdwn.links(&[("lmr-query-1".to_string(), "Sales".to_string())])
+        );
+        assert_eq!(
+            "".to_string(),
+            OutputFormat::SlackMrkdwn.anchor("lmr-query-1")
+        );
+    }
+
+    #[test]
+    fn list() {
+        let items = vec!["A".to_string(), "B".to_string()];
+
+        assert_eq!("- A\n- B\n".to_string(), OutputFormat::Plain.list(&items));
+        assert_eq!(
+            "<ul class=\"lmr-list\">\n<li>A</li>\n<li>B</li>\n</ul>\n".to_string(),
+            OutputFormat::Html.list(&items)
+        );
+        assert_eq!(
+            "- A\n- B\n".to_string(),
+            OutputFormat::Markdown.list(&items)
+        );
+    }
+
     #[test]
     fn break_line() {
         assert_eq!("\n".to_string(), OutputFormat::Plain.break_line());
@@ -112,14 +424,23 @@ pub mod tests {
 
     #[test]
     pub fn body() {
-        assert_eq!("Content".to_string(), OutputFormat::Plain.body("Content"));
         assert_eq!(
             "Content".to_string(),
-            OutputFormat::Markdown.body("Content")
+            OutputFormat::Plain.body("Content", None)
         );
         assert_eq!(
-            include_str!("template.html").replace("{{ content }}", "Content"),
-            OutputFormat::Html.body("Content")
+            "Content".to_string(),
+            OutputFormat::Markdown.body("Content", Some("p { color: red; }"))
+        );
+        assert_eq!(
+            include_str!("template.html")
+                .replace("{{ style }}", "")
+                .replace("{{ content }}", "Content"),
+            OutputFormat::Html.body("Content", None)
         );
+
+        let body = OutputFormat::Html.body("Content", Some("p { color: red; }"));
+        assert!(body.contains("</style>\n    <style>\np { color: red; }\n</style>\n  </head>"));
+        assert!(!body.contains("{{ style }}"));
     }
 }
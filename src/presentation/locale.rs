@@ -0,0 +1,187 @@
+//! Built-in texts of the reports, on the language of the config
+
+use serde::Deserialize;
+use std::sync::RwLock;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+pub enum Language {
+    #[default]
+    #[serde(rename = "en")]
+    En,
+    #[serde(rename = "pt-BR")]
+    PtBr,
+    #[serde(rename = "es")]
+    Es,
+}
+
+/// Texts of a language, with the `{}` placeholders filled in order
+pub struct Texts {
+    pub results: &'static str,
+    pub query: &'static str,
+    pub empty: &'static str,
+    pub query_failed: &'static str,
+    pub render_failed: &'static str,
+    pub summary: &'static str,
+    pub attention: &'static str,
+    /// Labels of the info, warning and critical severities
+    pub severities: [&'static str; 3],
+    pub contents: &'static str,
+    pub rows: &'static str,
+    pub failed: &'static str,
+    pub support: &'static str,
+    pub part: &'static str,
+    pub open: &'static str,
+    pub subtotal: &'static str,
+    pub change: &'static str,
+    pub added: &'static str,
+    pub removed: &'static str,
+    pub changed: &'static str,
+    /// Titles of the field, count, min, max, mean and median statistics
+    pub stats: [&'static str; 6],
+    /// Slice of the smaller ones, after the top of the pizza and donut charts
+    pub others: &'static str,
+    /// Of the gauge charts
+    pub target: &'static str,
+    /// Columns of the calendar charts, from the monday
+    pub weekdays: [&'static str; 7],
+}
+
+const EN: Texts = Texts {
+    results: "The {} results are here!",
+    query: "Query: {}",
+    empty: "Empty result",
+    query_failed: "Query failed: {}",
+    render_failed: "Error on rendering: {}",
+    summary: "Summary",
+    attention: "Attention",
+    severities: ["Info", "Warning", "Critical"],
+    contents: "Contents",
+    rows: "{} rows",
+    failed: "failed, {}",
+    support: "Consider support the project at {}",
+    part: "Part {} of {}",
+    open: "Open",
+    subtotal: "Subtotal",
+    change: "Change",
+    added: "Added",
+    removed: "Removed",
+    changed: "Changed",
+    stats: ["Field", "Count", "Min", "Max", "Mean", "Median"],
+    others: "Others",
+    target: "Target: {}",
+    weekdays: ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+};
+
+const PT_BR: Texts = Texts {
+    results: "Os resultados de {} chegaram!",
+    query: "Consulta: {}",
+    empty: "Resultado vazio",
+    query_failed: "Falha na consulta: {}",
+    render_failed: "Erro ao renderizar: {}",
+    summary: "Resumo",
+    attention: "Atenção",
+    severities: ["Info", "Aviso", "Crítico"],
+    contents: "Sumário",
+    rows: "{} linhas",
+    failed: "falhou, {}",
+    support: "Considere apoiar o projeto em {}",
+    part: "Parte {} de {}",
+    open: "Abrir",
+    subtotal: "Subtotal",
+    change: "Alteração",
+    added: "Adicionado",
+    removed: "Removido",
+    changed: "Alterado",
+    stats: ["Campo", "Quantidade", "Mín", "Máx", "Média", "Mediana"],
+    others: "Outros",
+    target: "Meta: {}",
+    weekdays: ["Seg", "Ter", "Qua", "Qui", "Sex", "Sáb", "Dom"],
+};
+
+const ES: Texts = Texts {
+    results: "¡Los resultados de {} están aquí!",
+    query: "Consulta: {}",
+    empty: "Resultado vacío",
+    query_failed: "Consulta fallida: {}",
+    render_failed: "Error al renderizar: {}",
+    summary: "Resumen",
+    attention: "Atención",
+    severities: ["Info", "Advertencia", "Crítico"],
+    contents: "Contenido",
+    rows: "{} filas",
+    failed: "falló, {}",
+    support: "Considere apoyar el proyecto en {}",
+    part: "Parte {} de {}",
+    open: "Abrir",
+    subtotal: "Subtotal",
+    change: "Cambio",
+    added: "Agregado",
+    removed: "Eliminado",
+    changed: "Modificado",
+    stats: ["Campo", "Cantidad", "Mín", "Máx", "Media", "Mediana"],
+    others: "Otros",
+    target: "Objetivo: {}",
+    weekdays: ["Lun", "Mar", "Mié", "Jue", "Vie", "Sáb", "Dom"],
+};
+
+static LANGUAGE: RwLock<Language> = RwLock::new(Language::En);
+
+/// Language of the next reports
+pub fn configure(language: Language) {
+    if let Ok(mut current) = LANGUAGE.write() {
+        *current = language;
+    }
+}
+
+/// Texts of the configured language
+pub fn texts() -> &'static Texts {
+    match LANGUAGE.read().map(|l| *l).unwrap_or_default() {
+        Language::En => &EN,
+        Language::PtBr => &PT_BR,
+        Language::Es => &ES,
+    }
+}
+
+/// Text with the `{}` placeholders replaced by the args, in order
+pub fn fill(text: &str, args: &[&str]) -> String {
+    let mut r = String::new();
+    let mut args = args.iter();
+    let mut parts = text.split("{}");
+
+    if let Some(first) = parts.next() {
+        r.push_str(first);
+    }
+    for part in parts {
+        r.push_str(args.next().copied().unwrap_or_default());
+        r.push_str(part);
+    }
+
+    r
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{fill, Language, EN, ES, PT_BR};
+
+    #[test]
+    fn fill_placeholders() {
+        assert_eq!("Part 1 of 3", fill(EN.part, &["1", "3"]));
+        assert_eq!(
+            "Os resultados de Vendas chegaram!",
+            fill(PT_BR.results, &["Vendas"])
+        );
+        assert_eq!("Resultado vacío", fill(ES.empty, &[]));
+        assert_eq!("Part  of ", fill(EN.part, &[]));
+        assert_eq!("Meta: 99%", fill(PT_BR.target, &["99%"]));
+    }
+
+    #[test]
+    fn languages() {
+        let language = |raw: &str| serde_yaml::from_str::<Language>(raw).map_err(|e| e.to_string());
+
+        assert_eq!(Ok(Language::En), language("en"));
+        assert_eq!(Ok(Language::PtBr), language("pt-BR"));
+        assert_eq!(Ok(Language::Es), language("es"));
+        assert!(language("fr").is_err());
+    }
+}
@@ -1,18 +1,53 @@
 //! Export/Presentation api
 
-use crate::{source::Query, value::Value};
+use crate::{expect::Severity, source::Query, value::Value};
+use attachment::Attachment;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
 use formats::OutputFormat;
 use log::*;
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+use uuid::Uuid;
 
+pub mod attachment;
 pub mod charts;
+pub mod diff;
 pub mod formats;
+pub mod locale;
+pub mod stats;
 pub mod table;
+pub mod text;
+#[cfg(feature = "xlsx")]
+pub mod workbook;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct DataPresented {
     pub is_html: bool,
     pub content: String,
     pub images: Vec<ImagePresented>,
+    /// Files with the results, attached to the report
+    pub attachments: Vec<Attachment>,
+}
+
+impl DataPresented {
+    /// Content with the images embedded as data uris, for
+    /// outputs without support to the cid references
+    pub fn inline_content(&self) -> String {
+        let mut content = self.content.clone();
+
+        for img in &self.images {
+            let img64 = STANDARD.encode(&img.data);
+            content = content.replace(
+                &format!("cid:{}", img.cid),
+                &format!("data:{};base64,{}", img.mime, img64),
+            );
+        }
+
+        content
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -28,6 +63,78 @@ pub struct RenderedContent {
     pub images: Vec<ImagePresented>,
 }
 
+static DETERMINISTIC: AtomicBool = AtomicBool::new(false);
+static CID_SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+
+/// Enable the deterministic rendering, useful to golden-test the
+/// rendered reports since the generated ids will be stable between runs.
+/// The dates are pinned too, when the run has no `as_of`
+pub fn set_deterministic(enabled: bool) {
+    DETERMINISTIC.store(enabled, Ordering::SeqCst);
+}
+
+pub fn is_deterministic() -> bool {
+    DETERMINISTIC.load(Ordering::SeqCst)
+}
+
+/// Content id for the inline images
+pub fn new_cid() -> String {
+    if is_deterministic() {
+        sequence_cid(&CID_SEQUENCE)
+    } else {
+        Uuid::new_v4().to_string()
+    }
+}
+
+/// Next content id of the deterministic rendering
+fn sequence_cid(sequence: &AtomicUsize) -> String {
+    let seq = sequence.fetch_add(1, Ordering::SeqCst) + 1;
+    format!("lmr-img-{}", seq)
+}
+
+/// Source and inline attachment of an image value. The value can be
+/// an url, a base64 string or the raw image bytes
+pub fn inline_image(raw: &[u8]) -> (String, Option<ImagePresented>) {
+    if let Ok(s) = std::str::from_utf8(raw) {
+        let s = s.trim();
+        if s.starts_with("http://") || s.starts_with("https://") {
+            return (s.to_string(), None);
+        }
+
+        if let Ok(decoded) = STANDARD.decode(s) {
+            if image_mime(&decoded).is_some() {
+                return inline_image(&decoded);
+            }
+        }
+    }
+
+    let cid = new_cid();
+    let img = ImagePresented {
+        cid: cid.clone(),
+        mime: image_mime(raw).unwrap_or("image/png").to_string(),
+        data: raw.to_vec(),
+    };
+
+    (format!("cid:{}", cid), Some(img))
+}
+
+/// Mime type by the magic number of the image
+fn image_mime(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(&[0x89, b'P', b'N', b'G']) {
+        Some("image/png")
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if data.starts_with(b"GIF8") {
+        Some("image/gif")
+    } else if data.len() > 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if data.starts_with(b"<svg") || data.starts_with(b"<?xml") {
+        Some("image/svg+xml")
+    } else {
+        None
+    }
+}
+
 pub trait Component {
     fn render(
         &self,
@@ -37,21 +144,227 @@ pub trait Component {
     ) -> Result<RenderedContent, String>;
 }
 
-/// Export the querys results into specified format
+/// Query with the component and the fetch result to be presented
+pub type QueryData = (Query, Box<dyn Component>, Result<Vec<Vec<Value>>, String>);
+
+/// Report level settings of the presentation
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report {
+    pub title: String,
+    pub description: Option<String>,
+    pub format: OutputFormat,
+    pub summary: bool,
+    /// Links to the querys, after the summary
+    pub toc: bool,
+    /// HTML in place of the default one, with the `{{ name }}` placeholders
+    /// of the parts of the report
+    pub template: Option<String>,
+    /// CSS added to the HTML
+    pub style: Option<String>,
+    /// Markdown in place of the default footer, empty for none
+    pub footer: Option<String>,
+    /// Results attached as a Excel workbook, a sheet for each query
+    pub attach_xlsx: bool,
+}
+
+/// Workbook with the results of the querys, without the texts and the
+/// failed ones. None when all of them failed
+#[allow(unused_variables)]
+fn present_workbook(
+    data: &[QueryData],
+    sections: &[Section],
+    report: &Report,
+) -> Result<Option<Attachment>, String> {
+    #[cfg(feature = "xlsx")]
+    {
+        let sheets = data
+            .iter()
+            .chain(sections.iter().flat_map(|s| s.data.iter()))
+            .filter(|(query, _, _)| query.text.is_none())
+            .filter_map(|(query, _, result)| match result {
+                Ok(rows) => Some((query, rows.as_slice())),
+                Err(_) => None,
+            })
+            .collect::<Vec<_>>();
+
+        if sheets.is_empty() {
+            warn!("Excel workbook without results, not attached");
+            return Ok(None);
+        }
+
+        workbook::xlsx(&attachment::file_name(&report.title), &sheets).map(Some)
+    }
+
+    #[cfg(not(feature = "xlsx"))]
+    Err("Not supported Excel workbook, the xlsx feature is disabled".to_string())
+}
+
+/// Another report combined into a digest
+pub struct Section {
+    pub title: String,
+    pub description: Option<String>,
+    pub data: Vec<QueryData>,
+}
+
+/// Export the querys results, followed by a section for each one
+/// of the combined reports, into specified format
 pub fn present_as(
-    data: Vec<(Query, Box<dyn Component>, Result<Vec<Vec<Value>>, String>)>,
-    title: String,
-    format: OutputFormat,
+    data: Vec<QueryData>,
+    sections: Vec<Section>,
+    report: Report,
 ) -> Result<DataPresented, String> {
     info!("Generating the presentation");
 
-    let mut r = String::new();
+    // Each report numbers its images from the start
+    CID_SEQUENCE.store(0, Ordering::SeqCst);
+
+    let format = report.format.clone();
     let mut images = vec![];
 
-    r.push_str(&format.title1(&format!("The {} results are here!", title)));
+    let texts = locale::texts();
+    let mut header = format.title1(&locale::fill(texts.results, &[&report.title]));
+    if let Some(description) = &report.description {
+        header.push_str(&format.description(description));
+    }
+
+    let mut attachments = vec![];
+    for (query, _, result) in data
+        .iter()
+        .chain(sections.iter().flat_map(|s| s.data.iter()))
+    {
+        if let (true, Ok(rows)) = (query.attach_csv, result) {
+            attachments.push(attachment::csv(query, rows)?);
+        }
+    }
+    if report.attach_xlsx {
+        attachments.extend(present_workbook(&data, &sections, &report)?);
+    }
+    attachment::unique_names(&mut attachments);
+
+    let attention = present_attention(
+        data.iter()
+            .chain(sections.iter().flat_map(|s| s.data.iter())),
+        &format,
+    );
+
+    let mut summary = String::new();
+    if report.summary {
+        let all = data
+            .iter()
+            .chain(sections.iter().flat_map(|s| s.data.iter()));
+        summary.push_str(&present_summary(all, &format));
+        summary.push_str(&format.break_line());
+    }
+
+    let mut toc = String::new();
+    if report.toc {
+        let all = data
+            .iter()
+            .chain(sections.iter().flat_map(|s| s.data.iter()));
+        toc.push_str(&present_toc(all, &format));
+        toc.push_str(&format.break_line());
+    }
+    // The querys are numbered across the sections, like on the toc
+    let mut anchors = Some(0).filter(|_| report.toc);
+
+    let mut querys = String::new();
+    present_querys_as(data, &format, &mut querys, &mut images, &mut anchors)?;
+
+    let mut rsections = String::new();
+    for section in sections {
+        rsections.push_str(&format.title1(&section.title));
+        if let Some(description) = &section.description {
+            rsections.push_str(&format.description(description));
+        }
+        present_querys_as(
+            section.data,
+            &format,
+            &mut rsections,
+            &mut images,
+            &mut anchors,
+        )?;
+    }
+
+    let footer = match &report.footer {
+        Some(footer) if footer.trim().is_empty() => String::new(),
+        Some(footer) => text::markdown(footer, &format),
+        None => format.text(&locale::fill(
+            texts.support,
+            &["https://github.com/fernandobatels/lmr"],
+        )),
+    };
+
+    let content = [
+        header.as_str(),
+        &attention,
+        &summary,
+        &toc,
+        &querys,
+        &rsections,
+        &footer,
+    ]
+    .concat();
 
+    let r = match (&report.template, &format) {
+        (Some(template), OutputFormat::Html) => render_template(
+            template,
+            &[
+                ("title", &format.escape(&report.title)),
+                (
+                    "description",
+                    &format.escape(report.description.as_deref().unwrap_or_default()),
+                ),
+                ("header", &header),
+                ("attention", &attention),
+                ("summary", &summary),
+                ("toc", &toc),
+                ("querys", &querys),
+                ("sections", &rsections),
+                ("footer", &footer),
+                ("content", &content),
+                ("style", &format.style(report.style.as_deref())),
+            ],
+        )?,
+        _ => format.body(&content, report.style.as_deref()),
+    };
+
+    Ok(DataPresented {
+        is_html: format == OutputFormat::Html,
+        content: r,
+        images,
+        attachments,
+    })
+}
+
+/// Jinja template rendered with the parts of the report as the variables,
+/// inserted as they are and never rendered again
+fn render_template(template: &str, parts: &[(&str, &str)]) -> Result<String, String> {
+    let mut env = minijinja::Environment::new();
+    env.set_keep_trailing_newline(true);
+
+    let template = env
+        .template_from_str(template)
+        .map_err(|e| format!("Invalid template: {}", e))?;
+    let parts = parts.iter().copied().collect::<BTreeMap<_, _>>();
+
+    template
+        .render(parts)
+        .map_err(|e| format!("Template not rendered: {}", e))
+}
+
+fn present_querys_as(
+    data: Vec<QueryData>,
+    format: &OutputFormat,
+    r: &mut String,
+    images: &mut Vec<ImagePresented>,
+    anchors: &mut Option<usize>,
+) -> Result<(), String> {
     for (query, comp, result) in data {
         r.push_str(&format.break_line());
+        if let Some(n) = anchors.as_mut().filter(|_| query.text.is_none()) {
+            *n += 1;
+            r.push_str(&format.anchor(&anchor(*n)));
+        }
 
         let rquery = present_query_as(query, comp, result, format.clone())?;
         r.push_str(&rquery.content);
@@ -60,17 +373,90 @@ pub fn present_as(
         images.extend(rquery.images);
     }
 
-    r.push_str(
-        &format.simple("Consider support the project at https://github.com/fernandobatels/lmr"),
-    );
+    Ok(())
+}
 
-    let r = format.body(&r);
+/// Summary with the headline of each query
+fn present_summary<'a>(
+    data: impl IntoIterator<Item = &'a QueryData>,
+    format: &OutputFormat,
+) -> String {
+    let texts = locale::texts();
+    let mut items = vec![];
 
-    Ok(DataPresented {
-        is_html: format == OutputFormat::Html,
-        content: r,
-        images,
-    })
+    for (query, _, result) in data.into_iter().filter(|(q, _, _)| q.text.is_none()) {
+        let headline = match result {
+            Ok(rows) if rows.len() == 1 && rows[0].len() == 1 => rows[0][0].to_string(),
+            // The single value with the change against the last run
+            Ok(rows)
+                if rows.len() == 1 && rows[0].len() == 2 && rows[0][1].field.trend.is_some() =>
+            {
+                match rows[0][1].trend() {
+                    Some(trend) => format!("{} ({})", rows[0][0], trend),
+                    None => rows[0][0].to_string(),
+                }
+            }
+            Ok(rows) => locale::fill(texts.rows, &[&rows.len().to_string()]),
+            Err(e) => locale::fill(texts.failed, &[e]),
+        };
+
+        items.push(format!("{}: {}", query.title, headline));
+    }
+
+    let mut r = format.title2(texts.summary);
+    r.push_str(&format.list(&items));
+    r
+}
+
+/// Failed querys, like the ones with the expectations not met, by the
+/// severity. Without the info ones
+fn present_attention<'a>(
+    data: impl IntoIterator<Item = &'a QueryData>,
+    format: &OutputFormat,
+) -> String {
+    let texts = locale::texts();
+
+    let mut failed = data
+        .into_iter()
+        .filter(|(q, _, _)| q.severity > Severity::Info)
+        .filter_map(|(q, _, r)| r.as_ref().err().map(|e| (q, e)))
+        .collect::<Vec<_>>();
+    if failed.is_empty() {
+        return String::new();
+    }
+    failed.sort_by_key(|(q, _)| Reverse(q.severity));
+
+    let items = failed
+        .iter()
+        .map(|(q, e)| {
+            let severity = texts.severities[q.severity as usize];
+            format!("[{}] {}: {}", severity, q.title, e)
+        })
+        .collect::<Vec<_>>();
+
+    let mut r = format.title2(texts.attention);
+    r.push_str(&format.list(&items));
+    r.push_str(&format.break_line());
+    r
+}
+
+/// Links to the querys, without the texts
+fn present_toc<'a>(data: impl IntoIterator<Item = &'a QueryData>, format: &OutputFormat) -> String {
+    let items = data
+        .into_iter()
+        .filter(|(q, _, _)| q.text.is_none())
+        .enumerate()
+        .map(|(i, (q, _, _))| (anchor(i + 1), q.title.clone()))
+        .collect::<Vec<_>>();
+
+    let mut r = format.title2(locale::texts().contents);
+    r.push_str(&format.links(&items));
+    r
+}
+
+/// Id of the nth query of the report
+fn anchor(n: usize) -> String {
+    format!("lmr-query-{}", n)
 }
 
 /// Export the query result
@@ -81,6 +467,11 @@ fn present_query_as(
     format: OutputFormat,
 ) -> Result<RenderedContent, String> {
     debug!("Generating for '{}' query", query.title);
+    let texts = locale::texts();
+
+    if query.text.is_some() {
+        return component.render(query, vec![], format);
+    }
 
     let mut r = RenderedContent {
         content: String::new(),
@@ -88,10 +479,13 @@ fn present_query_as(
     };
 
     r.content
-        .push_str(&format.title2(&format!("Query: {}", query.title)));
+        .push_str(&format.title2(&locale::fill(texts.query, &[&query.title])));
+    if let Some(description) = &query.description {
+        r.content.push_str(&format.description(description));
+    }
 
     if let Ok(rows) = data {
-        if rows.len() > 0 {
+        if !rows.is_empty() {
             let table = component.render(query, rows, format.clone());
 
             if let Ok(table) = table {
@@ -99,15 +493,15 @@ fn present_query_as(
                 r.images.extend(table.images);
             } else {
                 r.content.push_str(
-                    &format.simple(&format!("Error on rendering: {}", table.err().unwrap())),
+                    &format.text(&locale::fill(texts.render_failed, &[&table.err().unwrap()])),
                 );
             }
         } else {
-            r.content.push_str(&format.simple("Empty result"));
+            r.content.push_str(&format.text(texts.empty));
         }
     } else {
         r.content
-            .push_str(&format.simple(&format!("Query falied: {}", data.err().unwrap())));
+            .push_str(&format.text(&locale::fill(texts.query_failed, &[&data.err().unwrap()])));
     }
 
     Ok(r)
@@ -116,12 +510,49 @@ fn present_query_as(
 #[cfg(test)]
 pub mod tests {
     use crate::{
-        presentation::{charts::ChartComponent, charts::*, table::TableComponent, Component},
+        expect::Severity,
+        presentation::{
+            charts::ChartComponent, charts::*, table::TableComponent, text::TextComponent,
+            Component,
+        },
         source::Query,
-        value::{Field, FieldType, TypedValue, Value},
+        value::{Field, FieldType, Trend, TypedValue, Value},
     };
 
-    use super::{DataPresented, OutputFormat};
+    use super::{DataPresented, OutputFormat, Report, Section};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn inline_image() {
+        let (src, img) = super::inline_image(b"https://abc.com/a.png");
+        assert_eq!("https://abc.com/a.png".to_string(), src);
+        assert_eq!(None, img);
+
+        let (src, img) = super::inline_image(b"iVBORw0KGgo=");
+        let img = img.unwrap();
+        assert_eq!(format!("cid:{}", img.cid), src);
+        assert_eq!("image/png".to_string(), img.mime);
+        assert_eq!(
+            vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A],
+            img.data
+        );
+
+        let (src, img) = super::inline_image(&[0xFF, 0xD8, 0xFF, 0xE0]);
+        let img = img.unwrap();
+        assert_eq!(format!("cid:{}", img.cid), src);
+        assert_eq!("image/jpeg".to_string(), img.mime);
+    }
+
+    #[test]
+    fn deterministic_cid() {
+        // Without the global flag, the other tests run in parallel
+        let sequence = AtomicUsize::new(0);
+        assert_eq!("lmr-img-1", super::sequence_cid(&sequence));
+        assert_eq!("lmr-img-2", super::sequence_cid(&sequence));
+
+        sequence.store(0, Ordering::SeqCst);
+        assert_eq!("lmr-img-1", super::sequence_cid(&sequence));
+    }
 
     #[test]
     fn present_as_txt() -> Result<(), String> {
@@ -133,18 +564,21 @@ pub mod tests {
                     title: "User name".to_string(),
                     field: "name".to_string(),
                     kind: FieldType::String,
+                    ..Default::default()
                 },
                 Field {
                     title: "Age".to_string(),
                     field: "age".to_string(),
                     kind: FieldType::Integer,
+                    ..Default::default()
                 },
             ],
+            ..Default::default()
         };
 
         let data = vec![(
             query.clone(),
-            Box::new(TableComponent {}) as Box<dyn Component>,
+            Box::new(TableComponent::default()) as Box<dyn Component>,
             Ok(vec![
                 vec![
                     Value {
@@ -179,12 +613,21 @@ pub mod tests {
             ]),
         )];
 
-        let exported = super::present_as(data, "Project Name".to_string(), OutputFormat::Plain)?;
+        let exported = super::present_as(
+            data,
+            vec![],
+            Report {
+                title: "Project Name".to_string(),
+                format: OutputFormat::Plain,
+                ..Default::default()
+            },
+        )?;
 
         assert_eq!(
             DataPresented {
                 is_html: false,
                 images: vec![],
+                attachments: vec![],
                 content: r#"
 The Project Name results are here!
 
@@ -222,34 +665,50 @@ Consider support the project at https://github.com/fernandobatels/lmr
                     title: "User name".to_string(),
                     field: "name".to_string(),
                     kind: FieldType::String,
+                    ..Default::default()
                 },
                 Field {
                     title: "Age".to_string(),
                     field: "age".to_string(),
                     kind: FieldType::Integer,
+                    ..Default::default()
                 },
             ],
+            ..Default::default()
         };
 
         let data = vec![(
             query.clone(),
-            Box::new(TableComponent {}) as Box<dyn Component>,
+            Box::new(TableComponent::default()) as Box<dyn Component>,
             Err("Table 'users' not found".to_string()),
         )];
 
-        let exported = super::present_as(data, "Project Name".to_string(), OutputFormat::Plain)?;
+        let exported = super::present_as(
+            data,
+            vec![],
+            Report {
+                title: "Project Name".to_string(),
+                format: OutputFormat::Plain,
+                ..Default::default()
+            },
+        )?;
 
         assert_eq!(
             DataPresented {
                 is_html: false,
                 images: vec![],
+                attachments: vec![],
                 content: r#"
 The Project Name results are here!
 
+Attention
+
+- [Warning] Title test: Table 'users' not found
+
 
 Query: Title test
 
-Query falied: Table 'users' not found
+Query failed: Table 'users' not found
 
 
 Consider support the project at https://github.com/fernandobatels/lmr
@@ -272,27 +731,39 @@ Consider support the project at https://github.com/fernandobatels/lmr
                     title: "User name".to_string(),
                     field: "name".to_string(),
                     kind: FieldType::String,
+                    ..Default::default()
                 },
                 Field {
                     title: "Age".to_string(),
                     field: "age".to_string(),
                     kind: FieldType::Integer,
+                    ..Default::default()
                 },
             ],
+            ..Default::default()
         };
 
         let data = vec![(
             query.clone(),
-            Box::new(TableComponent {}) as Box<dyn Component>,
+            Box::new(TableComponent::default()) as Box<dyn Component>,
             Ok(vec![]),
         )];
 
-        let exported = super::present_as(data, "Project Name".to_string(), OutputFormat::Plain)?;
+        let exported = super::present_as(
+            data,
+            vec![],
+            Report {
+                title: "Project Name".to_string(),
+                format: OutputFormat::Plain,
+                ..Default::default()
+            },
+        )?;
 
         assert_eq!(
             DataPresented {
                 is_html: false,
                 images: vec![],
+                attachments: vec![],
                 content: r#"
 The Project Name results are here!
 
@@ -322,13 +793,16 @@ Consider support the project at https://github.com/fernandobatels/lmr
                     title: "User name".to_string(),
                     field: "name".to_string(),
                     kind: FieldType::String,
+                    ..Default::default()
                 },
                 Field {
                     title: "Age".to_string(),
                     field: "age".to_string(),
                     kind: FieldType::Integer,
+                    ..Default::default()
                 },
             ],
+            ..Default::default()
         };
 
         let data = vec![(
@@ -338,6 +812,7 @@ Consider support the project at https://github.com/fernandobatels/lmr
                 keys_by: Some("name".to_string()),
                 series_by: None,
                 series: Some(vec![]),
+                ..Default::default()
             }) as Box<dyn Component>,
             Ok(vec![
                 vec![
@@ -363,12 +838,21 @@ Consider support the project at https://github.com/fernandobatels/lmr
             ]),
         )];
 
-        let exported = super::present_as(data, "Project Name".to_string(), OutputFormat::Plain)?;
+        let exported = super::present_as(
+            data,
+            vec![],
+            Report {
+                title: "Project Name".to_string(),
+                format: OutputFormat::Plain,
+                ..Default::default()
+            },
+        )?;
 
         assert_eq!(
             DataPresented {
                 is_html: false,
                 images: vec![],
+                attachments: vec![],
                 content: r#"
 The Project Name results are here!
 
@@ -387,4 +871,428 @@ Consider support the project at https://github.com/fernandobatels/lmr
 
         Ok(())
     }
+
+    #[test]
+    fn present_as_txt_with_summary() -> Result<(), String> {
+        let query = Query {
+            title: "Users".to_string(),
+            sql: "select count(1) as qt from users".to_string(),
+            fields: vec![Field {
+                title: "Quantity".to_string(),
+                field: "qt".to_string(),
+                kind: FieldType::Integer,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let data = vec![
+            (
+                query.clone(),
+                Box::new(TableComponent::default()) as Box<dyn Component>,
+                Ok(vec![vec![Value {
+                    inner: Some(TypedValue::Integer(30)),
+                    field: query.fields[0].clone(),
+                }]]),
+            ),
+            (
+                query.clone(),
+                Box::new(TableComponent::default()) as Box<dyn Component>,
+                Ok(vec![]),
+            ),
+            (
+                query.clone(),
+                Box::new(TableComponent::default()) as Box<dyn Component>,
+                Err("Expected at least 1 rows, got 0".to_string()),
+            ),
+            (
+                query.clone(),
+                Box::new(TableComponent::default()) as Box<dyn Component>,
+                Ok(vec![vec![
+                    Value {
+                        inner: Some(TypedValue::Integer(30)),
+                        field: query.fields[0].clone(),
+                    },
+                    Value {
+                        inner: Some(TypedValue::Float(-2.0)),
+                        field: Field {
+                            trend: Some(Trend::Higher),
+                            ..Default::default()
+                        },
+                    },
+                ]]),
+            ),
+        ];
+
+        let exported = super::present_summary(&data, &OutputFormat::Plain);
+
+        assert_eq!(
+            r#"Summary
+
+- Users: 30
+- Users: 0 rows
+- Users: failed, Expected at least 1 rows, got 0
+- Users: 30 (▼ -2)
+"#
+            .to_string(),
+            exported
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn present_as_txt_with_sections() -> Result<(), String> {
+        let query = Query {
+            title: "Users".to_string(),
+            description: Some("Active accounts".to_string()),
+            sql: "select count(1) as qt from users".to_string(),
+            fields: vec![Field {
+                title: "Quantity".to_string(),
+                field: "qt".to_string(),
+                kind: FieldType::Integer,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let item = |v| {
+            (
+                query.clone(),
+                Box::new(TableComponent::default()) as Box<dyn Component>,
+                Ok(vec![vec![Value {
+                    inner: Some(TypedValue::Integer(v)),
+                    field: query.fields[0].clone(),
+                }]]),
+            )
+        };
+
+        let exported = super::present_as(
+            vec![item(10)],
+            vec![Section {
+                title: "Sales".to_string(),
+                description: Some("Orders of the stores".to_string()),
+                data: vec![item(20)],
+            }],
+            Report {
+                title: "Digest".to_string(),
+                description: Some("Weekly numbers".to_string()),
+                format: OutputFormat::Plain,
+                summary: true,
+                ..Default::default()
+            },
+        )?;
+
+        assert_eq!(
+            r#"
+The Digest results are here!
+
+Weekly numbers
+
+Summary
+
+- Users: 10
+- Users: 20
+
+
+Query: Users
+
+Active accounts
+
++----------+
+| Quantity |
++----------+
+| 10       |
++----------+
+
+
+
+Sales
+
+Orders of the stores
+
+
+Query: Users
+
+Active accounts
+
++----------+
+| Quantity |
++----------+
+| 20       |
++----------+
+
+
+Consider support the project at https://github.com/fernandobatels/lmr
+"#
+            .to_string(),
+            exported.content
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn present_as_html_with_template() -> Result<(), String> {
+        let query = Query {
+            title: "Users".to_string(),
+            fields: vec![Field {
+                title: "Quantity".to_string(),
+                field: "qt".to_string(),
+                kind: FieldType::Integer,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let data = vec![(
+            query.clone(),
+            Box::new(TableComponent::default()) as Box<dyn Component>,
+            Ok(vec![]),
+        )];
+
+        let exported = super::present_as(
+            data,
+            vec![],
+            Report {
+                title: "Sales & Co".to_string(),
+                format: OutputFormat::Html,
+                template: Some(
+                    "<title>{{ title }}</title><main>{{ querys }}</main><footer>{{ footer }}</footer>"
+                        .to_string(),
+                ),
+                ..Default::default()
+            },
+        )?;
+
+        assert_eq!(
+            "<title>Sales &amp; Co</title><main><br>\n<h3 class=\"lmr-h3\">Query: Users</h3>\nEmpty result\n<br>\n<br>\n</main><footer>Consider support the project at https://github.com/fernandobatels/lmr\n</footer>"
+                .to_string(),
+            exported.content
+        );
+        assert!(exported.is_html);
+
+        // The values are not rendered as templates
+        let data = vec![(
+            Query {
+                title: "{{ footer }}".to_string(),
+                ..query.clone()
+            },
+            Box::new(TableComponent::default()) as Box<dyn Component>,
+            Ok(vec![]),
+        )];
+        let exported = super::present_as(
+            data,
+            vec![],
+            Report {
+                title: "{{ querys }}".to_string(),
+                format: OutputFormat::Html,
+                footer: Some(String::new()),
+                template: Some(
+                    "<title>{{ title }}</title>{% if attention %}<b>x</b>{% endif %}<main>{{ querys }}</main>\n"
+                        .to_string(),
+                ),
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(
+            "<title>{{ querys }}</title><main><br>\n<h3 class=\"lmr-h3\">Query: {{ footer }}</h3>\nEmpty result\n<br>\n<br>\n</main>\n"
+                .to_string(),
+            exported.content
+        );
+
+        // Only the HTML has a template
+        let data = vec![(
+            query.clone(),
+            Box::new(TableComponent::default()) as Box<dyn Component>,
+            Ok(vec![]),
+        )];
+        let exported = super::present_as(
+            data,
+            vec![],
+            Report {
+                title: "Sales".to_string(),
+                format: OutputFormat::Markdown,
+                template: Some("{{ querys }}".to_string()),
+                ..Default::default()
+            },
+        )?;
+        assert!(exported
+            .content
+            .starts_with("\n# The Sales results are here!"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn present_as_with_footer() -> Result<(), String> {
+        let present = |format: OutputFormat, footer: Option<&str>| {
+            super::present_as(
+                vec![],
+                vec![],
+                Report {
+                    title: "Sales".to_string(),
+                    format,
+                    footer: footer.map(|f| f.to_string()),
+                    ..Default::default()
+                },
+            )
+            .map(|p| p.content)
+        };
+
+        let footer = "Confidential, see the [policy](https://abc.com/policy).";
+        assert_eq!(
+            "\n# The Sales results are here!\n\nConfidential, see the [policy](https://abc.com/policy).\n"
+                .to_string(),
+            present(OutputFormat::Markdown, Some(footer))?
+        );
+        assert!(present(OutputFormat::Html, Some(footer))?.contains(
+            "<p class=\"lmr-text\">Confidential, see the <a href=\"https://abc.com/policy\">policy</a>.</p>"
+        ));
+
+        // Without any footer
+        assert_eq!(
+            "\nThe Sales results are here!\n\n".to_string(),
+            present(OutputFormat::Plain, Some(""))?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn present_as_html_with_toc() -> Result<(), String> {
+        let query = |title: &str| Query {
+            title: title.to_string(),
+            ..Default::default()
+        };
+        let item = |title: &str| {
+            (
+                query(title),
+                Box::new(TableComponent::default()) as Box<dyn Component>,
+                Ok(vec![]),
+            )
+        };
+        let text = Query {
+            text: Some("Values in USD".to_string()),
+            ..Default::default()
+        };
+
+        let exported = super::present_as(
+            vec![
+                item("Users"),
+                (
+                    text,
+                    Box::new(TextComponent {}) as Box<dyn Component>,
+                    Ok(vec![]),
+                ),
+            ],
+            vec![Section {
+                title: "Sales".to_string(),
+                description: None,
+                data: vec![item("Orders")],
+            }],
+            Report {
+                title: "Digest".to_string(),
+                format: OutputFormat::Html,
+                toc: true,
+                template: Some("{{ toc }}{{ querys }}{{ sections }}".to_string()),
+                ..Default::default()
+            },
+        )?;
+
+        assert_eq!(
+            r##"<h3 class="lmr-h3">Contents</h3>
+<ul class="lmr-list lmr-toc">
+<li><a href="#lmr-query-1">Users</a></li>
+<li><a href="#lmr-query-2">Orders</a></li>
+</ul>
+<br>
+<br>
+<a name="lmr-query-1" id="lmr-query-1"></a>
+<h3 class="lmr-h3">Query: Users</h3>
+Empty result
+<br>
+<br>
+<br>
+<p class="lmr-text">Values in USD</p>
+<br>
+<br>
+<h1 class="lmr-h1">Sales</h1>
+<br>
+<a name="lmr-query-2" id="lmr-query-2"></a>
+<h3 class="lmr-h3">Query: Orders</h3>
+Empty result
+<br>
+<br>
+"##
+            .to_string(),
+            exported.content
+        );
+
+        let exported = super::present_as(
+            vec![item("Users"), item("Orders")],
+            vec![],
+            Report {
+                title: "Digest".to_string(),
+                format: OutputFormat::Markdown,
+                toc: true,
+                ..Default::default()
+            },
+        )?;
+        assert!(exported
+            .content
+            .contains("## Contents\n\n- Users\n- Orders\n\n\n## Query: Users"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn present_attention() {
+        let item = |title: &str, severity: Severity, result: Result<Vec<Vec<Value>>, String>| {
+            (
+                Query {
+                    title: title.to_string(),
+                    severity,
+                    ..Default::default()
+                },
+                Box::new(TableComponent::default()) as Box<dyn Component>,
+                result,
+            )
+        };
+
+        let data = vec![
+            item("Users", Severity::Warning, Ok(vec![])),
+            item(
+                "Logins",
+                Severity::Warning,
+                Err("Expected at least 1 rows, got 0".to_string()),
+            ),
+            item("Visits", Severity::Info, Err("Timeout".to_string())),
+            item(
+                "Payments",
+                Severity::Critical,
+                Err("Table 'payments' not found".to_string()),
+            ),
+        ];
+
+        assert_eq!(
+            r#"Attention
+
+- [Critical] Payments: Table 'payments' not found
+- [Warning] Logins: Expected at least 1 rows, got 0
+
+"#
+            .to_string(),
+            super::present_attention(&data, &OutputFormat::Plain)
+        );
+
+        assert_eq!(
+            "".to_string(),
+            super::present_attention(&data[..1], &OutputFormat::Plain)
+        );
+        assert_eq!(
+            "".to_string(),
+            super::present_attention(&data[2..3], &OutputFormat::Html)
+        );
+    }
 }
@@ -0,0 +1,224 @@
+//! Summary statistics component
+
+use super::{formats::OutputFormat, locale, table::TableComponent, Component, RenderedContent};
+use crate::{
+    source::Query,
+    value::{Field, FieldType, TypedValue, Value},
+};
+use serde::Deserialize;
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct StatsComponent {
+    /// Numeric fields of the statistics, all of them when empty
+    #[serde(default)]
+    pub fields: Vec<String>,
+    #[serde(default = "percentiles")]
+    pub percentiles: Vec<f64>,
+}
+
+fn percentiles() -> Vec<f64> {
+    vec![90.0, 95.0, 99.0]
+}
+
+impl Default for StatsComponent {
+    fn default() -> Self {
+        Self {
+            fields: vec![],
+            percentiles: percentiles(),
+        }
+    }
+}
+
+impl Component for StatsComponent {
+    fn render(
+        &self,
+        query: Query,
+        data: Vec<Vec<Value>>,
+        format: OutputFormat,
+    ) -> Result<RenderedContent, String> {
+        let mut columns = vec![];
+        if self.fields.is_empty() {
+            columns.extend(query.fields.iter().enumerate().filter(|(_, f)| {
+                matches!(
+                    f.kind,
+                    FieldType::Integer | FieldType::Float | FieldType::Decimal
+                )
+            }));
+        }
+        for name in &self.fields {
+            let column = query
+                .fields
+                .iter()
+                .enumerate()
+                .find(|(_, f)| f.field == *name)
+                .ok_or_else(|| format!("Field {} not found", name))?;
+            columns.push(column);
+        }
+
+        // The names of the fields are the same on all the languages
+        let mut titles = ["field", "count", "min", "max", "mean", "median"]
+            .into_iter()
+            .zip(locale::texts().stats)
+            .map(|(f, t)| (f.to_string(), t.to_string()))
+            .collect::<Vec<_>>();
+        titles.extend(
+            self.percentiles
+                .iter()
+                .map(|p| (format!("p{}", p), format!("P{}", p))),
+        );
+
+        let fields = titles
+            .into_iter()
+            .map(|(field, title)| Field {
+                field,
+                title,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+
+        let mut rows = vec![];
+        for (i, field) in columns {
+            let mut values = data
+                .iter()
+                .filter_map(|r| r.get(i)?.inner.as_ref()?.to_float().ok())
+                .map(f64::from)
+                .collect::<Vec<_>>();
+            values.sort_by(|a, b| a.total_cmp(b));
+
+            let mut stats = vec![
+                values.first().copied(),
+                values.last().copied(),
+                Some(values.iter().sum::<f64>() / values.len() as f64)
+                    .filter(|_| !values.is_empty()),
+                percentile(&values, 50.0),
+            ];
+            stats.extend(self.percentiles.iter().map(|p| percentile(&values, *p)));
+
+            // The stats on the format of the source field
+            let source = Field {
+                precision: field.precision.or(Some(2)),
+                format: field.format.clone(),
+                unit: field.unit.clone(),
+                ..Default::default()
+            };
+
+            let mut row = vec![
+                Value {
+                    inner: Some(TypedValue::String(field.title.clone())),
+                    field: fields[0].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::Integer(values.len() as i64)),
+                    field: fields[1].clone(),
+                },
+            ];
+            for (stat, title) in stats.into_iter().zip(&fields[2..]) {
+                row.push(Value {
+                    inner: stat.map(TypedValue::Float),
+                    field: Field {
+                        field: title.field.clone(),
+                        title: title.title.clone(),
+                        ..source.clone()
+                    },
+                });
+            }
+            rows.push(row);
+        }
+
+        let query = Query { fields, ..query };
+
+        TableComponent::default().render(query, rows, format)
+    }
+}
+
+/// Percentile of the sorted values, linear between the closest ranks
+fn percentile(sorted: &[f64], p: f64) -> Option<f64> {
+    let last = sorted.len().checked_sub(1)?;
+    let rank = (p / 100.0).clamp(0.0, 1.0) * last as f64;
+    let (low, high) = (rank.floor() as usize, rank.ceil() as usize);
+
+    Some(sorted[low] + (sorted[high] - sorted[low]) * (rank - low as f64))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{percentile, StatsComponent};
+    use crate::{
+        presentation::{formats::OutputFormat, Component},
+        source::Query,
+        value::{Field, FieldType, TypedValue, Value},
+    };
+
+    #[test]
+    fn percentiles() {
+        let values = vec![1.0, 2.0, 3.0, 4.0];
+        assert_eq!(Some(2.5), percentile(&values, 50.0));
+        assert_eq!(Some(1.0), percentile(&values, 0.0));
+        assert_eq!(Some(4.0), percentile(&values, 100.0));
+        assert_eq!(
+            Some(3.7),
+            percentile(&values, 90.0).map(|v| (v * 10.0).round() / 10.0)
+        );
+        assert_eq!(None, percentile(&[], 50.0));
+    }
+
+    #[test]
+    fn render() {
+        let query = Query {
+            title: "Latency".to_string(),
+            fields: vec![
+                Field {
+                    field: "host".to_string(),
+                    title: "Host".to_string(),
+                    kind: FieldType::String,
+                    ..Default::default()
+                },
+                Field {
+                    field: "ms".to_string(),
+                    title: "Latency".to_string(),
+                    kind: FieldType::Integer,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let data = [Some(10), Some(20), None, Some(40)]
+            .iter()
+            .map(|ms| {
+                vec![
+                    Value {
+                        inner: Some(TypedValue::String("a".to_string())),
+                        field: query.fields[0].clone(),
+                    },
+                    Value {
+                        inner: ms.map(TypedValue::Integer),
+                        field: query.fields[1].clone(),
+                    },
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let stats = StatsComponent {
+            percentiles: vec![90.0],
+            ..Default::default()
+        };
+
+        let result = stats
+            .render(query.clone(), data.clone(), OutputFormat::Markdown)
+            .unwrap();
+        assert_eq!(
+            r#"| Field   | Count | Min   | Max   | Mean  | Median | P90   |
+|---------|-------|-------|-------|-------|--------|-------|
+| Latency | 3     | 10.00 | 40.00 | 23.33 | 20.00  | 36.00 |"#
+                .to_string(),
+            result.content
+        );
+
+        let stats = StatsComponent {
+            fields: vec!["size".to_string()],
+            ..Default::default()
+        };
+        assert!(stats.render(query, data, OutputFormat::Plain).is_err());
+    }
+}
@@ -1,18 +1,45 @@
 //! Table component
 
-use super::Component;
-use super::{formats::OutputFormat, RenderedContent};
+use super::{
+    formats::{escape, escape_jira, escape_slack, OutputFormat},
+    inline_image, locale, Component, ImagePresented, RenderedContent,
+};
 use crate::{
     source::Query,
-    value::{TypedValue, Value},
+    value::{Align, FieldType, TypedValue, Value},
 };
+use log::*;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use regex::{Captures, Regex};
 use table_to_html::{
     html::{Attribute, HtmlElement, HtmlVisitorMut},
     HtmlTable,
 };
-use tabled::{builder::Builder, settings::Style};
+use tabled::{
+    builder::Builder,
+    settings::{object::Columns, Alignment, Modify, Style},
+    Table,
+};
+
+/// Characters encoded on the values of the urls, all but the unreserved ones
+const URL_VALUE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
 
-pub struct TableComponent {}
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TableComponent {
+    /// Url template, with {{field}} placeholders, for the extra column with links to each row
+    pub row_link: Option<String>,
+    /// Field to group the rows, with a header before each group
+    pub group_by: Option<String>,
+    /// Sums of the numeric columns after each group
+    pub subtotals: bool,
+    /// Maximum of rows of each table, the longer results are split
+    /// in parts, with a subtitle before each one
+    pub rows_per_table: Option<usize>,
+}
 
 impl Component for TableComponent {
     fn render(
@@ -21,56 +48,486 @@ impl Component for TableComponent {
         rows: Vec<Vec<Value>>,
         format: OutputFormat,
     ) -> Result<RenderedContent, String> {
+        let mut images = vec![];
+
+        let bars = if format == OutputFormat::Html {
+            data_bars_max(&rows)
+        } else {
+            vec![]
+        };
+
+        let content = match self.rows_per_table {
+            Some(max) if max > 0 && rows.len() > max => {
+                let parts = rows.chunks(max).collect::<Vec<_>>();
+                let mut content = vec![];
+                for (i, part) in parts.iter().enumerate() {
+                    let title = format.title3(&locale::fill(
+                        locale::texts().part,
+                        &[&(i + 1).to_string(), &parts.len().to_string()],
+                    ));
+                    let table = self.table(&query, part.to_vec(), &bars, &format, &mut images)?;
+                    content.push(format!("{}{}", title, table));
+                }
+                content.join("\n\n")
+            }
+            _ => self.table(&query, rows, &bars, &format, &mut images)?,
+        };
+
+        Ok(RenderedContent { content, images })
+    }
+}
+
+impl TableComponent {
+    /// Table of the rows, on the format
+    fn table(
+        &self,
+        query: &Query,
+        rows: Vec<Vec<Value>>,
+        bars: &[Option<f32>],
+        format: &OutputFormat,
+        images: &mut Vec<ImagePresented>,
+    ) -> Result<String, String> {
         let mut btable = Builder::default();
 
-        btable.push_record(
-            query
-                .fields
-                .iter()
-                .map(|e| e.title.clone())
-                .collect::<Vec<String>>(),
-        );
+        let row_link = self
+            .row_link
+            .as_ref()
+            .filter(|_| *format == OutputFormat::Html);
 
-        for row in rows {
-            btable.push_record(
-                row.iter()
-                    .map(|e| {
-                        e.inner
-                            .clone()
-                            .unwrap_or(TypedValue::String(String::new()))
-                            .to_string()
-                    })
-                    .collect::<Vec<String>>(),
-            );
+        let mut header = query
+            .fields
+            .iter()
+            .map(|e| cell_escape(&e.title, format))
+            .collect::<Vec<String>>();
+        if row_link.is_some() {
+            header.push(locale::texts().open.to_string());
         }
+        let columns = header.len();
+        btable.push_record(header);
+
+        let groups = match &self.group_by {
+            Some(by) => group_rows(by, query, rows)?,
+            None => vec![(None, rows)],
+        };
+
+        for (group, rows) in groups {
+            if let Some(group) = &group {
+                let mut record = vec![String::new(); columns];
+                record[0] = match format {
+                    OutputFormat::Html => format!("<b>{}</b>", escape(group)),
+                    OutputFormat::Markdown => format!("**{}**", group),
+                    OutputFormat::Jira => format!("*{}*", escape_jira(group)),
+                    OutputFormat::SlackMrkdwn => group.clone(),
+                    OutputFormat::Plain => group.clone(),
+                };
+                btable.push_record(record);
+            }
+
+            for row in &rows {
+                let mut record = vec![];
+                for (i, value) in row.iter().enumerate() {
+                    let content = cell(value, row, format, images);
+                    match bars.get(i) {
+                        Some(Some(max)) => record.push(data_bar(content, value, *max)),
+                        _ => record.push(content),
+                    }
+                }
+                if let Some(template) = row_link {
+                    record.push(format!(
+                        "<a href=\"{}\">{}</a>",
+                        escape(&row_template(template, row)),
+                        locale::texts().open
+                    ));
+                }
+                btable.push_record(record);
+            }
+
+            if group.is_some() && self.subtotals {
+                let mut record = subtotals(query, &rows);
+                record.resize(columns, String::new());
+                btable.push_record(record);
+            }
+        }
+
+        let aligns = query
+            .fields
+            .iter()
+            .map(|f| f.align.clone())
+            .collect::<Vec<_>>();
 
         let table = match format {
-            OutputFormat::Plain => btable.build().with(Style::ascii()).to_string(),
+            OutputFormat::Plain => align(btable.build().with(Style::ascii()), &aligns).to_string(),
             OutputFormat::Html => {
                 let rows: Vec<Vec<String>> = btable.into();
                 let mut table = HtmlTable::with_header(rows);
-                table.visit_mut(HtmlTableClasses {});
+                table.visit_mut(HtmlTableClasses { aligns, column: 0 });
 
                 format!("{}", table)
             }
-            OutputFormat::Markdown => btable.build().with(Style::markdown()).to_string(),
+            OutputFormat::Markdown => {
+                align(btable.build().with(Style::markdown()), &aligns).to_string()
+            }
+            OutputFormat::Jira => {
+                let rows: Vec<Vec<String>> = btable.into();
+                jira_table(&rows)
+            }
+            // Code block, the Slack has no tables. Escaped after the
+            // layout, the entities would misalign the columns
+            OutputFormat::SlackMrkdwn => format!(
+                "```\n{}\n```",
+                escape_slack(&align(btable.build().with(Style::psql()), &aligns).to_string())
+            ),
         };
 
-        Ok(RenderedContent {
-            content: table,
-            images: vec![],
+        Ok(table)
+    }
+}
+
+/// Text of the cell escaped on the format, the Slack one escapes
+/// the whole table
+fn cell_escape(text: &str, format: &OutputFormat) -> String {
+    match format {
+        OutputFormat::SlackMrkdwn => text.to_string(),
+        _ => format.escape(text),
+    }
+}
+
+/// Content of the table cell
+fn cell(
+    value: &Value,
+    row: &[Value],
+    format: &OutputFormat,
+    images: &mut Vec<ImagePresented>,
+) -> String {
+    let content = match (&value.inner, format) {
+        (Some(TypedValue::Image(raw)), OutputFormat::Html) => {
+            let (src, img) = inline_image(raw);
+            images.extend(img);
+            return format!("<img class=\"lmr-cell-img\" src=\"{}\">", src);
+        }
+        (Some(TypedValue::Array(items)), OutputFormat::Html) if value.field.sparkline => {
+            let points = items
+                .iter()
+                .filter_map(|i| i.to_float().ok())
+                .collect::<Vec<_>>();
+            match sparkline(&points) {
+                Some(png) => {
+                    let (src, img) = inline_image(&png);
+                    images.extend(img);
+                    return format!("<img class=\"lmr-sparkline\" src=\"{}\">", src);
+                }
+                None => value.to_string(),
+            }
+        }
+        (Some(_), _) if value.field.trend.is_some() => {
+            let trend = cell_escape(&value.trend().unwrap_or_else(|| value.to_string()), format);
+            return match (value.better(), format) {
+                (Some(true), OutputFormat::Html) => {
+                    format!("<span class=\"lmr-better\">{}</span>", trend)
+                }
+                (Some(false), OutputFormat::Html) => {
+                    format!("<span class=\"lmr-worse\">{}</span>", trend)
+                }
+                _ => trend,
+            };
+        }
+        (Some(_), _) => value.to_string(),
+        (None, _) => return String::new(),
+    };
+    let content = match value.field.icons.get(&content) {
+        Some(icon) => icon.clone(),
+        None => content,
+    };
+
+    let url = if let Some(template) = &value.field.link_template {
+        Some(row_template(template, row))
+    } else if value.field.link && is_link_safe(&content) {
+        Some(content.clone())
+    } else {
+        None
+    };
+
+    let content = match value.field.max_width {
+        Some(max) if content.chars().count() > max => {
+            let mut truncated = content
+                .chars()
+                .take(max.saturating_sub(1))
+                .collect::<String>();
+            truncated.push('…');
+            truncated
+        }
+        _ => content,
+    };
+
+    match (url, format) {
+        (Some(url), OutputFormat::Html) => {
+            format!("<a href=\"{}\">{}</a>", escape(&url), escape(&content))
+        }
+        (None, OutputFormat::Html) => escape(&content),
+        (Some(url), OutputFormat::Markdown) => format!("[{}]({})", content, url),
+        (Some(url), OutputFormat::Jira) => {
+            format!("[{}|{}]", escape_jira(&content), escape_jira(&url))
+        }
+        (None, OutputFormat::Jira) => escape_jira(&content),
+        // Without the links, not formatted inside of the code blocks
+        (_, OutputFormat::SlackMrkdwn) => content,
+        _ => content,
+    }
+}
+
+/// Only the web and mail urls of the values become links, the
+/// others like `javascript:` are kept as text
+fn is_link_safe(url: &str) -> bool {
+    match url.trim_start().split_once(':') {
+        Some((scheme, _)) => ["http", "https", "mailto"]
+            .iter()
+            .any(|s| scheme.eq_ignore_ascii_case(s)),
+        None => false,
+    }
+}
+
+/// Table of the Jira wiki markup, the first row as the header. The empty
+/// cells keep a space, the `||` is the separator of the header ones
+fn jira_table(rows: &[Vec<String>]) -> String {
+    rows.iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let sep = if i == 0 { "||" } else { "|" };
+            let cells = row
+                .iter()
+                .map(|c| match c.trim() {
+                    "" => " ".to_string(),
+                    c => c.replace('\n', " "),
+                })
+                .collect::<Vec<_>>();
+            format!("{}{}{}", sep, cells.join(sep), sep)
         })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Header, when grouped, and the rows of a group
+type RowsGroup = (Option<String>, Vec<Vec<Value>>);
+
+/// Rows grouped by the value of the field, in the order of the first
+/// row of each group, with the header of the group
+fn group_rows(by: &str, query: &Query, rows: Vec<Vec<Value>>) -> Result<Vec<RowsGroup>, String> {
+    let title = query
+        .fields
+        .iter()
+        .find(|f| f.field == by)
+        .map(|f| f.title.clone())
+        .ok_or_else(|| format!("Field {} not found", by))?;
+
+    let mut groups: Vec<RowsGroup> = vec![];
+    for row in rows {
+        let key = row
+            .iter()
+            .find(|v| v.field.field == by)
+            .map(|v| v.to_string())
+            .unwrap_or_default();
+        let header = Some(format!("{}: {}", title, key));
+
+        match groups.iter_mut().find(|(h, _)| *h == header) {
+            Some((_, group)) => group.push(row),
+            None => groups.push((header, vec![row])),
+        }
+    }
+
+    Ok(groups)
+}
+
+/// Sums of the numeric columns of the rows, on the format of the
+/// fields, and the `Subtotal` label on the first column when not numeric
+fn subtotals(query: &Query, rows: &[Vec<Value>]) -> Vec<String> {
+    query
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let values = rows.iter().filter_map(|r| r.get(i)?.inner.clone());
+            let inner = match field.kind {
+                FieldType::Integer => Some(TypedValue::Integer(
+                    values
+                        .filter_map(|v| match v {
+                            TypedValue::Integer(v) => Some(v),
+                            _ => None,
+                        })
+                        .sum(),
+                )),
+                FieldType::Float => Some(TypedValue::Float(
+                    values
+                        .filter_map(|v| match v {
+                            TypedValue::Float(v) => Some(v),
+                            TypedValue::Integer(v) => Some(v as f64),
+                            _ => None,
+                        })
+                        .sum(),
+                )),
+                FieldType::Decimal => Some(TypedValue::Decimal(
+                    values
+                        .filter_map(|v| match v {
+                            TypedValue::Decimal(v) => Some(v),
+                            _ => None,
+                        })
+                        .sum(),
+                )),
+                _ if i == 0 => return locale::texts().subtotal.to_string(),
+                _ => return String::new(),
+            };
+
+            Value {
+                inner,
+                field: field.clone(),
+            }
+            .to_string()
+        })
+        .collect()
+}
+
+/// Alignment of the columns of the text table
+fn align<'a>(table: &'a mut Table, aligns: &[Option<Align>]) -> &'a mut Table {
+    for (i, a) in aligns.iter().enumerate() {
+        let alignment = match a {
+            Some(Align::Left) | None => continue,
+            Some(Align::Right) => Alignment::right(),
+            Some(Align::Center) => Alignment::center(),
+        };
+        table.with(Modify::new(Columns::single(i)).with(alignment));
     }
+
+    table
+}
+
+/// PNG of the trend line of the points, at least two of them
+fn sparkline(points: &[f32]) -> Option<Vec<u8>> {
+    if points.len() < 2 {
+        return None;
+    }
+
+    let (width, height) = (100.0, 24.0);
+    let min = points.iter().fold(f32::MAX, |a, b| a.min(*b));
+    let max = points.iter().fold(f32::MIN, |a, b| a.max(*b));
+    let step = (width - 2.0) / (points.len() - 1) as f32;
+
+    let line = points
+        .iter()
+        .enumerate()
+        .map(|(i, v)| {
+            let y = if max > min {
+                height - 2.0 - (v - min) / (max - min) * (height - 4.0)
+            } else {
+                height / 2.0
+            };
+            format!("{:.1},{:.1}", 1.0 + i as f32 * step, y)
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\
+        <rect width=\"{0}\" height=\"{1}\" fill=\"white\"/>\
+        <polyline points=\"{2}\" fill=\"none\" stroke=\"#3182bd\" stroke-width=\"1.5\"/></svg>",
+        width, height, line
+    );
+
+    charts_rs::svg_to_png(&svg)
+        .map_err(|e| warn!("Sparkline not rendered: {}", e))
+        .ok()
 }
 
-struct HtmlTableClasses {}
+/// Greatest absolute value of each column with data bars
+fn data_bars_max(rows: &[Vec<Value>]) -> Vec<Option<f32>> {
+    let Some(first) = rows.first() else {
+        return vec![];
+    };
+
+    first
+        .iter()
+        .enumerate()
+        .map(|(i, v)| {
+            if !v.field.data_bar {
+                return None;
+            }
+
+            let max = rows
+                .iter()
+                .filter_map(|row| row.get(i).and_then(|v| v.to_float().ok()))
+                .fold(0.0f32, |max, v| max.max(v.abs()));
+
+            Some(max).filter(|max| *max > 0.0)
+        })
+        .collect()
+}
+
+/// Cell content over a bar proportional to the greatest value of the column
+fn data_bar(content: String, value: &Value, max: f32) -> String {
+    let width = value
+        .to_float()
+        .map(|v| (v.abs() / max * 100.0).round())
+        .unwrap_or(0.0);
+
+    format!(
+        "<div class=\"lmr-data-bar\" style=\"background: linear-gradient(90deg, #c6dbef {0}%, transparent {0}%);\">{1}</div>",
+        width, content
+    )
+}
+
+/// Replace the {{field}} placeholders by the values of the row, percent
+/// encoded
+pub fn row_template(template: &str, row: &[Value]) -> String {
+    let Ok(placeholder) = Regex::new(r"\{\{([^{}]+)\}\}") else {
+        return template.to_string();
+    };
+
+    // In a single pass, the values are never replaced again
+    placeholder
+        .replace_all(template, |caps: &Captures| {
+            match row.iter().find(|v| v.field.field == caps[1]) {
+                Some(value) => {
+                    let raw = value
+                        .inner
+                        .as_ref()
+                        .map(|v| v.to_string())
+                        .unwrap_or_default();
+                    utf8_percent_encode(&raw, URL_VALUE).to_string()
+                }
+                None => caps[0].to_string(),
+            }
+        })
+        .to_string()
+}
+
+/// Class of the table and the inline alignment of the cells of each column
+struct HtmlTableClasses {
+    aligns: Vec<Option<Align>>,
+    column: usize,
+}
 
 impl HtmlVisitorMut for HtmlTableClasses {
     fn visit_element_mut(&mut self, e: &mut HtmlElement) -> bool {
-        if e.tag() == "table" {
-            let mut attrs = e.attrs().to_vec();
-            attrs.push(Attribute::new("class", "lmr-table"));
-            *e = HtmlElement::new("table", attrs, e.value().cloned());
+        match e.tag() {
+            "table" => {
+                let mut attrs = e.attrs().to_vec();
+                attrs.push(Attribute::new("class", "lmr-table"));
+                *e = HtmlElement::new("table", attrs, e.value().cloned());
+            }
+            "tr" => self.column = 0,
+            tag @ ("td" | "th") => {
+                let align = match self.aligns.get(self.column) {
+                    Some(Some(Align::Left)) => Some("left"),
+                    Some(Some(Align::Right)) => Some("right"),
+                    Some(Some(Align::Center)) => Some("center"),
+                    _ => None,
+                };
+                if let Some(align) = align {
+                    let mut attrs = e.attrs().to_vec();
+                    attrs.push(Attribute::new("style", format!("text-align: {};", align)));
+                    *e = HtmlElement::new(tag, attrs, e.value().cloned());
+                }
+                self.column += 1;
+            }
+            _ => {}
         }
 
         true
@@ -79,11 +536,11 @@ impl HtmlVisitorMut for HtmlTableClasses {
 
 #[cfg(test)]
 pub mod tests {
-    use super::TableComponent;
+    use super::{row_template, TableComponent};
     use crate::presentation::formats::OutputFormat;
     use crate::presentation::{Component, RenderedContent};
     use crate::source::Query;
-    use crate::value::{Field, FieldType, TypedValue, Value};
+    use crate::value::{Align, Field, FieldType, Trend, TypedValue, Value};
 
     #[test]
     pub fn txt_table() {
@@ -95,13 +552,16 @@ pub mod tests {
                     title: "User name".to_string(),
                     field: "name".to_string(),
                     kind: FieldType::String,
+                    ..Default::default()
                 },
                 Field {
                     title: "Age".to_string(),
                     field: "age".to_string(),
                     kind: FieldType::Integer,
+                    ..Default::default()
                 },
             ],
+            ..Default::default()
         };
 
         let data = vec![
@@ -127,7 +587,7 @@ pub mod tests {
             ],
         ];
 
-        let table = TableComponent {};
+        let table = TableComponent::default();
         let result = table.render(query, data, OutputFormat::Plain);
 
         assert_eq!(
@@ -156,13 +616,16 @@ pub mod tests {
                     title: "User name".to_string(),
                     field: "name".to_string(),
                     kind: FieldType::String,
+                    ..Default::default()
                 },
                 Field {
                     title: "Age".to_string(),
                     field: "age".to_string(),
                     kind: FieldType::Integer,
+                    ..Default::default()
                 },
             ],
+            ..Default::default()
         };
 
         let data = vec![
@@ -188,7 +651,7 @@ pub mod tests {
             ],
         ];
 
-        let table = TableComponent {};
+        let table = TableComponent::default();
         let result = table.render(query, data, OutputFormat::Markdown);
 
         assert_eq!(
@@ -204,6 +667,131 @@ pub mod tests {
         );
     }
 
+    #[test]
+    pub fn jira_table() {
+        let query = Query {
+            title: "Title test".to_string(),
+            sql: "select * from users".to_string(),
+            fields: vec![
+                Field {
+                    title: "User name".to_string(),
+                    field: "name".to_string(),
+                    kind: FieldType::String,
+                    ..Default::default()
+                },
+                Field {
+                    title: "Age".to_string(),
+                    field: "age".to_string(),
+                    kind: FieldType::Integer,
+                    ..Default::default()
+                },
+                Field {
+                    title: "Site".to_string(),
+                    field: "site".to_string(),
+                    kind: FieldType::String,
+                    link: true,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let data = vec![
+            vec![
+                Value {
+                    inner: Some(TypedValue::String("john|abc".to_string())),
+                    field: query.fields[0].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::Integer(30)),
+                    field: query.fields[1].clone(),
+                },
+                Value {
+                    inner: Some(TypedValue::String("https://abc.com".to_string())),
+                    field: query.fields[2].clone(),
+                },
+            ],
+            vec![
+                Value {
+                    inner: Some(TypedValue::String("jane.abc".to_string())),
+                    field: query.fields[0].clone(),
+                },
+                Value {
+                    inner: None,
+                    field: query.fields[1].clone(),
+                },
+                Value {
+                    inner: None,
+                    field: query.fields[2].clone(),
+                },
+            ],
+        ];
+
+        let table = TableComponent::default();
+        let result = table.render(query, data, OutputFormat::Jira);
+
+        assert_eq!(
+            Ok(RenderedContent {
+                images: vec![],
+                content: r#"||User name||Age||Site||
+|john\|abc|30|[https://abc.com|https://abc.com]|
+|jane.abc| | |"#
+                    .to_string()
+            }),
+            result
+        );
+    }
+
+    #[test]
+    pub fn slack_table() {
+        let query = Query {
+            title: "Title test".to_string(),
+            sql: "select * from users".to_string(),
+            fields: vec![
+                Field {
+                    title: "User name".to_string(),
+                    field: "name".to_string(),
+                    kind: FieldType::String,
+                    ..Default::default()
+                },
+                Field {
+                    title: "Age".to_string(),
+                    field: "age".to_string(),
+                    kind: FieldType::Integer,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let data = vec![vec![
+            Value {
+                inner: Some(TypedValue::String("<john>".to_string())),
+                field: query.fields[0].clone(),
+            },
+            Value {
+                inner: Some(TypedValue::Integer(30)),
+                field: query.fields[1].clone(),
+            },
+        ]];
+
+        let table = TableComponent::default();
+        let result = table.render(query, data, OutputFormat::SlackMrkdwn);
+
+        assert_eq!(
+            Ok(RenderedContent {
+                images: vec![],
+                content: r#"```
+ User name | Age 
+-----------+-----
+ &lt;john&gt;    | 30  
+```"#
+                    .to_string()
+            }),
+            result
+        );
+    }
+
     #[test]
     pub fn html_table() {
         let query = Query {
@@ -214,13 +802,16 @@ pub mod tests {
                     title: "User name".to_string(),
                     field: "name".to_string(),
                     kind: FieldType::String,
+                    ..Default::default()
                 },
                 Field {
                     title: "Age".to_string(),
                     field: "age".to_string(),
                     kind: FieldType::Integer,
+                    ..Default::default()
                 },
             ],
+            ..Default::default()
         };
 
         let data = vec![
@@ -246,7 +837,7 @@ pub mod tests {
             ],
         ];
 
-        let table = TableComponent {};
+        let table = TableComponent::default();
         let result = table.render(query, data, OutputFormat::Html);
 
         assert_eq!(
@@ -311,4 +902,612 @@ pub mod tests {
             result
         );
     }
+
+    #[test]
+    pub fn html_table_with_image() {
+        let query = Query {
+            title: "Title test".to_string(),
+            sql: "select * from products".to_string(),
+            fields: vec![Field {
+                title: "Thumb".to_string(),
+                field: "thumb".to_string(),
+                kind: FieldType::Image,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let data = vec![
+            vec![Value {
+                inner: Some(TypedValue::Image(b"https://abc.com/a.png".to_vec())),
+                field: query.fields[0].clone(),
+            }],
+            vec![Value {
+                inner: Some(TypedValue::Image(vec![0x89, b'P', b'N', b'G'])),
+                field: query.fields[0].clone(),
+            }],
+        ];
+
+        let table = TableComponent::default();
+        let result = table.render(query.clone(), data.clone(), OutputFormat::Html);
+
+        let result = result.unwrap();
+        assert_eq!(1, result.images.len());
+        assert!(result
+            .content
+            .contains("<img class=\"lmr-cell-img\" src=\"https://abc.com/a.png\">"));
+        assert!(result.content.contains(&format!(
+            "<img class=\"lmr-cell-img\" src=\"cid:{}\">",
+            result.images[0].cid
+        )));
+
+        let result = table.render(query, data, OutputFormat::Markdown).unwrap();
+        assert_eq!(0, result.images.len());
+        assert!(result.content.contains("https://abc.com/a.png"));
+        assert!(result.content.contains("[image]"));
+    }
+
+    #[test]
+    pub fn links() {
+        let query = Query {
+            title: "Title test".to_string(),
+            sql: "select * from customers".to_string(),
+            fields: vec![
+                Field {
+                    title: "Id".to_string(),
+                    field: "id".to_string(),
+                    kind: FieldType::Integer,
+                    ..Default::default()
+                },
+                Field {
+                    title: "Name".to_string(),
+                    field: "name".to_string(),
+                    kind: FieldType::String,
+                    link_template: Some("https://crm/customer/{{id}}".to_string()),
+                    ..Default::default()
+                },
+                Field {
+                    title: "Site".to_string(),
+                    field: "site".to_string(),
+                    kind: FieldType::String,
+                    link: true,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let data = vec![vec![
+            Value {
+                inner: Some(TypedValue::Integer(30)),
+                field: query.fields[0].clone(),
+            },
+            Value {
+                inner: Some(TypedValue::String("John".to_string())),
+                field: query.fields[1].clone(),
+            },
+            Value {
+                inner: Some(TypedValue::String("https://john.com".to_string())),
+                field: query.fields[2].clone(),
+            },
+        ]];
+
+        let table = TableComponent::default();
+
+        let result = table
+            .render(query.clone(), data.clone(), OutputFormat::Markdown)
+            .unwrap();
+        assert_eq!(
+            r#"| Id | Name                            | Site                                 |
+|----|---------------------------------|--------------------------------------|
+| 30 | [John](https://crm/customer/30) | [https://john.com](https://john.com) |"#
+                .to_string(),
+            result.content
+        );
+
+        let result = table
+            .render(query.clone(), data.clone(), OutputFormat::Html)
+            .unwrap();
+        assert!(result
+            .content
+            .contains("<a href=\"https://crm/customer/30\">John</a>"));
+        assert!(result
+            .content
+            .contains("<a href=\"https://john.com\">https://john.com</a>"));
+
+        let result = table
+            .render(query.clone(), data.clone(), OutputFormat::Plain)
+            .unwrap();
+        assert!(result.content.contains("| John "));
+
+        let mut data = data;
+        for site in ["javascript:alert(1)", "data:text/html,x", "john.com"] {
+            data[0][2].inner = Some(TypedValue::String(site.to_string()));
+            let result = table
+                .render(query.clone(), data.clone(), OutputFormat::Html)
+                .unwrap();
+            assert!(!result.content.contains(&format!("href=\"{}\"", site)));
+            assert!(result.content.contains(site));
+        }
+
+        data[0][2].inner = Some(TypedValue::String("MAILTO:john@john.com".to_string()));
+        let result = table.render(query, data, OutputFormat::Html).unwrap();
+        assert!(result
+            .content
+            .contains("<a href=\"MAILTO:john@john.com\">MAILTO:john@john.com</a>"));
+    }
+
+    #[test]
+    pub fn row_link() {
+        let query = Query {
+            title: "Title test".to_string(),
+            sql: "select * from customers".to_string(),
+            fields: vec![Field {
+                title: "Id".to_string(),
+                field: "id".to_string(),
+                kind: FieldType::Integer,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let data = vec![vec![Value {
+            inner: Some(TypedValue::Integer(30)),
+            field: query.fields[0].clone(),
+        }]];
+
+        let table = TableComponent {
+            row_link: Some("https://dash/customer?id={{id}}".to_string()),
+            ..Default::default()
+        };
+
+        let result = table
+            .render(query.clone(), data.clone(), OutputFormat::Html)
+            .unwrap();
+        assert!(result.content.contains("Open"));
+        assert!(result
+            .content
+            .contains("<a href=\"https://dash/customer?id=30\">Open</a>"));
+
+        let result = table.render(query, data, OutputFormat::Plain).unwrap();
+        assert!(!result.content.contains("Open"));
+    }
+
+    #[test]
+    pub fn data_bars() {
+        let query = Query {
+            title: "Title test".to_string(),
+            sql: "select * from sales".to_string(),
+            fields: vec![Field {
+                title: "Total".to_string(),
+                field: "total".to_string(),
+                kind: FieldType::Integer,
+                data_bar: true,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let data = vec![
+            vec![Value {
+                inner: Some(TypedValue::Integer(200)),
+                field: query.fields[0].clone(),
+            }],
+            vec![Value {
+                inner: Some(TypedValue::Integer(50)),
+                field: query.fields[0].clone(),
+            }],
+        ];
+
+        let table = TableComponent::default();
+
+        let result = table
+            .render(query.clone(), data.clone(), OutputFormat::Html)
+            .unwrap();
+        assert!(result
+            .content
+            .contains("#c6dbef 100%, transparent 100%);\">200</div>"));
+        assert!(result
+            .content
+            .contains("#c6dbef 25%, transparent 25%);\">50</div>"));
+
+        let result = table.render(query, data, OutputFormat::Plain).unwrap();
+        assert!(!result.content.contains("lmr-data-bar"));
+    }
+
+    #[test]
+    pub fn icons() {
+        let query = Query {
+            title: "Title test".to_string(),
+            sql: "select * from checks".to_string(),
+            fields: vec![Field {
+                title: "Status".to_string(),
+                field: "status".to_string(),
+                kind: FieldType::String,
+                icons: [("ok", "✅"), ("fail", "❌")]
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let data = ["ok", "fail", "unknown"]
+            .iter()
+            .map(|s| {
+                vec![Value {
+                    inner: Some(TypedValue::String(s.to_string())),
+                    field: query.fields[0].clone(),
+                }]
+            })
+            .collect::<Vec<_>>();
+
+        let table = TableComponent::default();
+
+        let result = table.render(query, data, OutputFormat::Markdown).unwrap();
+        assert_eq!(
+            r#"| Status  |
+|---------|
+| ✅      |
+| ❌      |
+| unknown |"#
+                .to_string(),
+            result.content
+        );
+    }
+
+    #[test]
+    pub fn groups() {
+        let query = Query {
+            title: "Title test".to_string(),
+            sql: "select * from employees".to_string(),
+            fields: vec![
+                Field {
+                    title: "Department".to_string(),
+                    field: "department".to_string(),
+                    kind: FieldType::String,
+                    ..Default::default()
+                },
+                Field {
+                    title: "Salary".to_string(),
+                    field: "salary".to_string(),
+                    kind: FieldType::Float,
+                    precision: Some(2),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let data = [("Sales", 10.0), ("IT", 20.0), ("Sales", 5.5)]
+            .iter()
+            .map(|(d, s)| {
+                vec![
+                    Value {
+                        inner: Some(TypedValue::String(d.to_string())),
+                        field: query.fields[0].clone(),
+                    },
+                    Value {
+                        inner: Some(TypedValue::Float(*s)),
+                        field: query.fields[1].clone(),
+                    },
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let table = TableComponent {
+            group_by: Some("department".to_string()),
+            subtotals: true,
+            ..Default::default()
+        };
+
+        let result = table
+            .render(query.clone(), data.clone(), OutputFormat::Markdown)
+            .unwrap();
+        assert_eq!(
+            r#"| Department            | Salary |
+|-----------------------|--------|
+| **Department: Sales** |        |
+| Sales                 | 10.00  |
+| Sales                 | 5.50   |
+| Subtotal              | 15.50  |
+| **Department: IT**    |        |
+| IT                    | 20.00  |
+| Subtotal              | 20.00  |"#
+                .to_string(),
+            result.content
+        );
+
+        let table = TableComponent {
+            group_by: Some("name".to_string()),
+            ..Default::default()
+        };
+        assert!(table.render(query, data, OutputFormat::Plain).is_err());
+    }
+
+    #[test]
+    pub fn align_and_width() {
+        let query = Query {
+            title: "Title test".to_string(),
+            sql: "select * from pages".to_string(),
+            fields: vec![
+                Field {
+                    title: "Url".to_string(),
+                    field: "url".to_string(),
+                    kind: FieldType::String,
+                    max_width: Some(12),
+                    link: true,
+                    ..Default::default()
+                },
+                Field {
+                    title: "Hits".to_string(),
+                    field: "hits".to_string(),
+                    kind: FieldType::Integer,
+                    align: Some(Align::Right),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let data = vec![vec![
+            Value {
+                inner: Some(TypedValue::String(
+                    "https://site.com/a/very/long/path".to_string(),
+                )),
+                field: query.fields[0].clone(),
+            },
+            Value {
+                inner: Some(TypedValue::Integer(7)),
+                field: query.fields[1].clone(),
+            },
+        ]];
+
+        let table = TableComponent::default();
+
+        let result = table
+            .render(query.clone(), data.clone(), OutputFormat::Plain)
+            .unwrap();
+        assert_eq!(
+            r#"+--------------+------+
+| Url          | Hits |
++--------------+------+
+| https://sit… |    7 |
++--------------+------+"#
+                .to_string(),
+            result.content
+        );
+
+        let result = table.render(query, data, OutputFormat::Html).unwrap();
+        assert!(result
+            .content
+            .contains("<a href=\"https://site.com/a/very/long/path\">https://sit…</a>"));
+        assert!(result.content.contains("<td style=\"text-align: right;\">"));
+    }
+
+    #[test]
+    pub fn parts() {
+        let query = Query {
+            title: "Title test".to_string(),
+            sql: "select * from sales".to_string(),
+            fields: vec![Field {
+                title: "Total".to_string(),
+                field: "total".to_string(),
+                kind: FieldType::Integer,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let data = (1..=5)
+            .map(|i| {
+                vec![Value {
+                    inner: Some(TypedValue::Integer(i)),
+                    field: query.fields[0].clone(),
+                }]
+            })
+            .collect::<Vec<_>>();
+
+        let table = TableComponent {
+            rows_per_table: Some(2),
+            ..Default::default()
+        };
+
+        let result = table
+            .render(query.clone(), data.clone(), OutputFormat::Markdown)
+            .unwrap();
+        assert_eq!(
+            r#"### Part 1 of 3
+
+| Total |
+|-------|
+| 1     |
+| 2     |
+
+### Part 2 of 3
+
+| Total |
+|-------|
+| 3     |
+| 4     |
+
+### Part 3 of 3
+
+| Total |
+|-------|
+| 5     |"#
+                .to_string(),
+            result.content
+        );
+
+        let table = TableComponent {
+            rows_per_table: Some(5),
+            ..Default::default()
+        };
+        let result = table.render(query, data, OutputFormat::Markdown).unwrap();
+        assert!(!result.content.contains("Part"));
+    }
+
+    #[test]
+    pub fn sparklines() {
+        let query = Query {
+            title: "Title test".to_string(),
+            sql: "select * from services".to_string(),
+            fields: vec![Field {
+                title: "Last hours".to_string(),
+                field: "last_hours".to_string(),
+                kind: FieldType::Array,
+                sparkline: true,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let data = [vec![3, 5, 2, 8], vec![1]]
+            .iter()
+            .map(|points| {
+                vec![Value {
+                    inner: Some(TypedValue::Array(
+                        points.iter().map(|p| TypedValue::Integer(*p)).collect(),
+                    )),
+                    field: query.fields[0].clone(),
+                }]
+            })
+            .collect::<Vec<_>>();
+
+        let table = TableComponent::default();
+
+        let result = table
+            .render(query.clone(), data.clone(), OutputFormat::Html)
+            .unwrap();
+        assert_eq!(1, result.images.len());
+        assert!(result.images[0].data.starts_with(&[0x89, b'P', b'N', b'G']));
+        assert!(result.content.contains(&format!(
+            "<img class=\"lmr-sparkline\" src=\"cid:{}\">",
+            result.images[0].cid
+        )));
+
+        let result = table.render(query, data, OutputFormat::Plain).unwrap();
+        assert!(result.content.contains("3, 5, 2, 8"));
+    }
+
+    #[test]
+    pub fn trends() {
+        let query = Query {
+            title: "Title test".to_string(),
+            sql: "select * from errors".to_string(),
+            fields: vec![Field {
+                title: "Errors Δ".to_string(),
+                field: "errors_delta".to_string(),
+                kind: FieldType::Float,
+                trend: Some(Trend::Lower),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let data = [Some(3.0), Some(-2.0), Some(0.0), None]
+            .iter()
+            .map(|v| {
+                vec![Value {
+                    inner: v.map(TypedValue::Float),
+                    field: query.fields[0].clone(),
+                }]
+            })
+            .collect::<Vec<_>>();
+
+        let table = TableComponent::default();
+
+        let result = table
+            .render(query.clone(), data.clone(), OutputFormat::Html)
+            .unwrap();
+        assert!(result
+            .content
+            .contains("<span class=\"lmr-worse\">▲ +3</span>"));
+        assert!(result
+            .content
+            .contains("<span class=\"lmr-better\">▼ -2</span>"));
+
+        let result = table.render(query, data, OutputFormat::Markdown).unwrap();
+        assert_eq!(
+            r#"| Errors Δ |
+|----------|
+| ▲ +3     |
+| ▼ -2     |
+| = 0      |
+|          |"#
+                .to_string(),
+            result.content
+        );
+    }
+
+    #[test]
+    pub fn escaped() {
+        let query = Query {
+            title: "Title test".to_string(),
+            sql: "select * from customers".to_string(),
+            fields: vec![
+                Field {
+                    title: "Name <full>".to_string(),
+                    field: "name".to_string(),
+                    kind: FieldType::String,
+                    link_template: Some("https://crm/?q={{name}}&x=1".to_string()),
+                    ..Default::default()
+                },
+                Field {
+                    title: "Note".to_string(),
+                    field: "note".to_string(),
+                    kind: FieldType::String,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let data = vec![vec![
+            Value {
+                inner: Some(TypedValue::String("Tom & \"Jerry\"".to_string())),
+                field: query.fields[0].clone(),
+            },
+            Value {
+                inner: Some(TypedValue::String("<script>alert(1)</script>".to_string())),
+                field: query.fields[1].clone(),
+            },
+        ]];
+
+        let table = TableComponent::default();
+
+        let result = table
+            .render(query.clone(), data.clone(), OutputFormat::Html)
+            .unwrap();
+        assert!(result.content.contains("Name &lt;full&gt;"));
+        assert!(result.content.contains(
+            "<a href=\"https://crm/?q=Tom%20%26%20%22Jerry%22&amp;x=1\">Tom &amp; &quot;Jerry&quot;</a>"
+        ));
+        assert!(result
+            .content
+            .contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(!result.content.contains("<script>"));
+
+        let result = table.render(query, data, OutputFormat::Markdown).unwrap();
+        assert!(result.content.contains("<script>alert(1)</script>"));
+    }
+
+    #[test]
+    pub fn templates() {
+        let value = |field: &str, inner: &str| Value {
+            inner: Some(TypedValue::String(inner.to_string())),
+            field: Field {
+                field: field.to_string(),
+                ..Default::default()
+            },
+        };
+        let row = vec![value("name", "a b&c#d"), value("other", "{{name}}")];
+
+        assert_eq!(
+            "https://crm/?q=a%20b%26c%23d&o=%7B%7Bname%7D%7D&u={{unknown}}",
+            row_template("https://crm/?q={{name}}&o={{other}}&u={{unknown}}", &row)
+        );
+    }
 }
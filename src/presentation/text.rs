@@ -0,0 +1,151 @@
+//! Free text component, static or with placeholders, written as Markdown
+
+use super::{formats::OutputFormat, Component, RenderedContent};
+use crate::{source::Query, value::Value};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextComponent {}
+
+impl Component for TextComponent {
+    fn render(
+        &self,
+        query: Query,
+        _data: Vec<Vec<Value>>,
+        format: OutputFormat,
+    ) -> Result<RenderedContent, String> {
+        let mut content = String::new();
+
+        if !query.title.is_empty() {
+            content.push_str(&format.title2(&query.title));
+        }
+
+        content.push_str(&markdown(&query.text.unwrap_or_default(), &format));
+
+        Ok(RenderedContent {
+            content,
+            images: vec![],
+        })
+    }
+}
+
+/// Markdown text on the format
+pub fn markdown(text: &str, format: &OutputFormat) -> String {
+    match format {
+        OutputFormat::Html => to_html(text),
+        _ => format.simple(text.trim_end()),
+    }
+}
+
+/// HTML of the Markdown subset: paragraphs, `#` headings, `-` lists,
+/// `**bold**`, `*italic*` and `[links](url)`
+fn to_html(text: &str) -> String {
+    let mut r = String::new();
+
+    for block in text
+        .split("\n\n")
+        .map(|b| b.trim())
+        .filter(|b| !b.is_empty())
+    {
+        let lines = block.lines().map(|l| l.trim()).collect::<Vec<_>>();
+
+        if lines.iter().all(|l| l.starts_with("- ")) {
+            r.push_str("<ul class=\"lmr-list\">\n");
+            for l in lines {
+                r.push_str(&format!("<li>{}</li>\n", inline(&l[2..])));
+            }
+            r.push_str("</ul>\n");
+        } else if let Some(title) = block.strip_prefix('#') {
+            let title = title.trim_start_matches('#').trim();
+            r.push_str(&format!("<h4 class=\"lmr-h4\">{}</h4>\n", inline(title)));
+        } else {
+            r.push_str(&format!(
+                "<p class=\"lmr-text\">{}</p>\n",
+                inline(&lines.join("<br>\n"))
+            ));
+        }
+    }
+
+    r
+}
+
+/// Bold, italic and links of the line
+fn inline(line: &str) -> String {
+    let mut r = String::new();
+    let mut rest = line;
+
+    while let Some(i) = rest.find(['*', '[']) {
+        r.push_str(&rest[..i]);
+        let tail = &rest[i..];
+
+        let replaced = if let Some(t) = tail.strip_prefix("**") {
+            t.split_once("**")
+                .map(|(b, after)| (format!("<b>{}</b>", b), after))
+        } else if let Some(t) = tail.strip_prefix('*') {
+            t.split_once('*')
+                .map(|(b, after)| (format!("<i>{}</i>", b), after))
+        } else {
+            tail[1..].split_once("](").and_then(|(label, t)| {
+                t.split_once(')')
+                    .map(|(url, after)| (format!("<a href=\"{}\">{}</a>", url, label), after))
+            })
+        };
+
+        match replaced {
+            Some((html, after)) => {
+                r.push_str(&html);
+                rest = after;
+            }
+            None => {
+                r.push_str(&tail[..1]);
+                rest = &tail[1..];
+            }
+        }
+    }
+    r.push_str(rest);
+
+    r
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{inline, TextComponent};
+    use crate::{
+        presentation::{formats::OutputFormat, Component},
+        source::Query,
+    };
+
+    #[test]
+    fn markdown() {
+        assert_eq!(
+            "The <b>daily</b> <i>sales</i>, see the <a href=\"https://crm\">CRM</a> * 2",
+            inline("The **daily** *sales*, see the [CRM](https://crm) * 2")
+        );
+
+        let query = Query {
+            title: "About".to_string(),
+            text: Some(
+                "# Notes\n\nValues in **USD**.\nUpdated hourly.\n\n- one\n- two\n".to_string(),
+            ),
+            ..Default::default()
+        };
+
+        let result = TextComponent {}
+            .render(query.clone(), vec![], OutputFormat::Html)
+            .unwrap();
+        assert_eq!(
+            "<h3 class=\"lmr-h3\">About</h3>\n\
+            <h4 class=\"lmr-h4\">Notes</h4>\n\
+            <p class=\"lmr-text\">Values in <b>USD</b>.<br>\nUpdated hourly.</p>\n\
+            <ul class=\"lmr-list\">\n<li>one</li>\n<li>two</li>\n</ul>\n",
+            result.content
+        );
+
+        let result = TextComponent {}
+            .render(query, vec![], OutputFormat::Markdown)
+            .unwrap();
+        assert_eq!(
+            "## About\n\n# Notes\n\nValues in **USD**.\nUpdated hourly.\n\n- one\n- two\n",
+            result.content
+        );
+    }
+}
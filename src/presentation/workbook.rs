@@ -0,0 +1,275 @@
+//! Excel workbook with the results of the querys, one sheet for each
+
+use super::attachment::Attachment;
+use crate::{
+    clock,
+    source::Query,
+    value::{TypedValue, Value},
+};
+use chrono::{NaiveDate, NaiveDateTime, Timelike};
+use rust_decimal::prelude::ToPrimitive;
+use rust_xlsxwriter::{Format, Workbook, Worksheet, XlsxError};
+
+/// Longest text of a cell on the Excel
+const MAX_TEXT: usize = 32767;
+
+/// Workbook with a sheet for each query, with the titles of the fields
+/// on the first row and the values on the cells of their types
+pub fn xlsx(name: &str, sheets: &[(&Query, &[Vec<Value>])]) -> Result<Attachment, String> {
+    let err = |e: XlsxError| format!("Workbook not written: {}", e);
+
+    // The Excel refuses the workbooks without sheets
+    if sheets.is_empty() {
+        return Err("Workbook without results".to_string());
+    }
+
+    let names = sheet_names(sheets.iter().map(|(q, _)| q.title.as_str()));
+
+    let mut workbook = Workbook::new();
+    for ((query, rows), name) in sheets.iter().zip(names) {
+        let sheet = workbook.add_worksheet();
+        sheet.set_name(name).map_err(err)?;
+        write_sheet(sheet, query, rows).map_err(err)?;
+    }
+
+    Ok(Attachment {
+        name: format!("{}.xlsx", name),
+        mime: "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet".to_string(),
+        data: workbook.save_to_buffer().map_err(err)?,
+    })
+}
+
+fn write_sheet(sheet: &mut Worksheet, query: &Query, rows: &[Vec<Value>]) -> Result<(), XlsxError> {
+    let header = Format::new().set_bold();
+    for (c, field) in query.fields.iter().enumerate() {
+        sheet.write_string_with_format(0, c as u16, text(&field.title), &header)?;
+    }
+
+    for (r, row) in rows.iter().enumerate() {
+        for (c, value) in row.iter().enumerate() {
+            let Some(inner) = &value.inner else {
+                continue;
+            };
+            write_cell(sheet, r as u32 + 1, c as u16, inner)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Cell of the value, the numbers and dates as numbers
+fn write_cell(
+    sheet: &mut Worksheet,
+    row: u32,
+    col: u16,
+    value: &TypedValue,
+) -> Result<(), XlsxError> {
+    let date = |format: &str| Format::new().set_num_format(format);
+
+    match value {
+        TypedValue::Integer(v) => sheet.write_number(row, col, *v as f64)?,
+        TypedValue::Float(v) if v.is_finite() => sheet.write_number(row, col, *v)?,
+        TypedValue::Decimal(v) => match v.to_f64() {
+            Some(n) => sheet.write_number(row, col, n)?,
+            None => sheet.write_string(row, col, v.to_string())?,
+        },
+        TypedValue::Date(v) => sheet.write_number_with_format(
+            row,
+            col,
+            serial(&v.and_time(Default::default())),
+            &date("yyyy-mm-dd"),
+        )?,
+        TypedValue::DateTime(v) => {
+            let local = clock::current().to_local(v).naive_local();
+            sheet.write_number_with_format(
+                row,
+                col,
+                serial(&local),
+                &date("yyyy-mm-dd hh:mm:ss"),
+            )?
+        }
+        TypedValue::Time(v) => sheet.write_number_with_format(
+            row,
+            col,
+            v.num_seconds_from_midnight() as f64 / 86400.0,
+            &date("hh:mm:ss"),
+        )?,
+        v => sheet.write_string(row, col, text(&v.to_string()))?,
+    };
+
+    Ok(())
+}
+
+/// Text up to the limit of the cells
+fn text(text: &str) -> String {
+    text.chars().take(MAX_TEXT).collect()
+}
+
+/// Days since the start of the Excel calendar, with the time as the fraction
+fn serial(dt: &NaiveDateTime) -> f64 {
+    let epoch = NaiveDate::from_ymd_opt(1899, 12, 30)
+        .unwrap_or_default()
+        .and_time(Default::default());
+    (*dt - epoch).num_seconds() as f64 / 86400.0
+}
+
+/// Names of the sheets by the titles, without the invalid characters,
+/// up to 31 characters and unique
+fn sheet_names<'a>(titles: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+
+    for (i, title) in titles.enumerate() {
+        let base = title
+            .chars()
+            .filter(|c| !matches!(c, '[' | ']' | ':' | '*' | '?' | '/' | '\\') && !c.is_control())
+            .collect::<String>()
+            .trim()
+            .trim_matches('\'')
+            .to_string();
+        let base = match base.is_empty() {
+            true => format!("Sheet{}", i + 1),
+            false => base,
+        };
+
+        let mut name = base.chars().take(31).collect::<String>();
+        let mut n = 1;
+        while names
+            .iter()
+            .any(|e| e.to_lowercase() == name.to_lowercase())
+        {
+            n += 1;
+            let suffix = format!(" ({})", n);
+            name = base
+                .chars()
+                .take(31 - suffix.chars().count())
+                .collect::<String>()
+                + &suffix;
+        }
+        names.push(name);
+    }
+
+    names
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{serial, sheet_names, xlsx};
+    use crate::{
+        source::Query,
+        value::{Field, FieldType, TypedValue, Value},
+    };
+    use calamine::{Data, Reader, Xlsx};
+    use chrono::NaiveDate;
+    use std::io::Cursor;
+
+    #[test]
+    fn serials() {
+        let date = |y, m, d| {
+            NaiveDate::from_ymd_opt(y, m, d)
+                .unwrap()
+                .and_time(Default::default())
+        };
+
+        assert_eq!(45292.0, serial(&date(2024, 1, 1)));
+        assert_eq!(
+            45292.5,
+            serial(
+                &NaiveDate::from_ymd_opt(2024, 1, 1)
+                    .unwrap()
+                    .and_hms_opt(12, 0, 0)
+                    .unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(
+            vec![
+                "Sales 2024",
+                "sales 2024 (2)",
+                "Sheet3",
+                "A very long title of a query th"
+            ],
+            sheet_names(
+                [
+                    "Sales: 2024",
+                    "sales 2024",
+                    "[?]",
+                    "A very long title of a query that goes on"
+                ]
+                .into_iter()
+            )
+        );
+    }
+
+    #[test]
+    fn workbook() -> Result<(), String> {
+        let query = Query {
+            title: "Users".to_string(),
+            fields: vec![
+                Field {
+                    title: "Name".to_string(),
+                    field: "name".to_string(),
+                    ..Default::default()
+                },
+                Field {
+                    title: "Age".to_string(),
+                    field: "age".to_string(),
+                    kind: FieldType::Integer,
+                    ..Default::default()
+                },
+                Field {
+                    title: "Since".to_string(),
+                    field: "since".to_string(),
+                    kind: FieldType::Date,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let rows = vec![vec![
+            Value {
+                inner: Some(TypedValue::String("ana\u{1}<b>".to_string())),
+                field: query.fields[0].clone(),
+            },
+            Value {
+                inner: Some(TypedValue::Integer(30)),
+                field: query.fields[1].clone(),
+            },
+            Value {
+                inner: Some(TypedValue::Date(
+                    NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+                )),
+                field: query.fields[2].clone(),
+            },
+        ]];
+
+        let attachment = xlsx("report", &[(&query, &rows), (&query, &[])])?;
+        assert_eq!("report.xlsx", attachment.name);
+
+        let mut excel: Xlsx<_> =
+            Xlsx::new(Cursor::new(attachment.data)).map_err(|e| e.to_string())?;
+        assert_eq!(vec!["Users", "Users (2)"], excel.sheet_names());
+
+        let range = excel.worksheet_range("Users").map_err(|e| e.to_string())?;
+        assert_eq!(Some(&Data::String("Age".to_string())), range.get((0, 1)));
+        // The control characters encoded like on the Excel
+        assert_eq!(
+            Some(&Data::String("ana_x0001_<b>".to_string())),
+            range.get((1, 0))
+        );
+        assert_eq!(Some(&Data::Float(30.0)), range.get((1, 1)));
+        assert!(matches!(range.get((1, 2)), Some(Data::DateTime(d)) if d.as_f64() == 45292.0));
+
+        let range = excel
+            .worksheet_range("Users (2)")
+            .map_err(|e| e.to_string())?;
+        assert_eq!(Some(&Data::String("Name".to_string())), range.get((0, 0)));
+        assert_eq!(1, range.height());
+
+        assert!(xlsx("report", &[]).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+//! Recipient scoped filtering of the rows
+
+use crate::value::Value;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// Recipient of a personalized message, with only the rows where
+/// the fields have the informed values. The querys without these
+/// fields are sent as failed, never complete
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct Recipient {
+    pub to: String,
+    #[serde(default)]
+    pub filter: BTreeMap<String, serde_yaml::Value>,
+}
+
+impl Recipient {
+    pub fn filter(&self, rows: Vec<Vec<Value>>) -> Result<Vec<Vec<Value>>, String> {
+        if self.filter.is_empty() {
+            return Ok(rows);
+        }
+
+        let expected = self
+            .filter
+            .iter()
+            .map(|(k, v)| (k.as_str(), scalar_to_string(v)))
+            .collect::<Vec<_>>();
+
+        let mut filtered = vec![];
+        for row in rows {
+            let mut matches = true;
+            for (field, expected) in &expected {
+                let value = row
+                    .iter()
+                    .find(|v| v.field.field == *field)
+                    .ok_or(format!("Field {} of the recipient filter not found", field))?;
+                matches &= value.inner.as_ref().map(|i| i.to_string()) == *expected;
+            }
+
+            if matches {
+                filtered.push(row);
+            }
+        }
+
+        Ok(filtered)
+    }
+}
+
+fn scalar_to_string(value: &serde_yaml::Value) -> Option<String> {
+    match value {
+        serde_yaml::Value::Null => None,
+        serde_yaml::Value::String(s) => Some(s.clone()),
+        serde_yaml::Value::Number(n) => Some(n.to_string()),
+        serde_yaml::Value::Bool(b) => Some(b.to_string()),
+        v => Some(format!("{:?}", v)),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::Recipient;
+    use crate::value::{Field, FieldType, TypedValue, Value};
+
+    #[test]
+    fn filter() -> Result<(), String> {
+        let recipient = serde_yaml::from_str::<Recipient>(
+            "
+            to: ana@abc.com
+            filter:
+                owner: 12
+            ",
+        )
+        .map_err(|e| e.to_string())?;
+
+        let owner = Field {
+            field: "owner".to_string(),
+            kind: FieldType::Integer,
+            ..Default::default()
+        };
+        let name = Field {
+            field: "name".to_string(),
+            kind: FieldType::String,
+            ..Default::default()
+        };
+
+        let rows = [(12, "Acme"), (13, "Globex"), (12, "Initech")]
+            .iter()
+            .map(|(o, n)| {
+                vec![
+                    Value {
+                        inner: Some(TypedValue::Integer(*o)),
+                        field: owner.clone(),
+                    },
+                    Value {
+                        inner: Some(TypedValue::String(n.to_string())),
+                        field: name.clone(),
+                    },
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let filtered = recipient.filter(rows.clone())?;
+        assert_eq!(2, filtered.len());
+        assert_eq!(
+            Some(TypedValue::String("Acme".to_string())),
+            filtered[0][1].inner
+        );
+        assert_eq!(
+            Some(TypedValue::String("Initech".to_string())),
+            filtered[1][1].inner
+        );
+
+        let names = rows
+            .iter()
+            .map(|row| vec![row[1].clone()])
+            .collect::<Vec<_>>();
+        assert_eq!(
+            Err("Field owner of the recipient filter not found".to_string()),
+            recipient.filter(names)
+        );
+        assert_eq!(Ok(vec![]), recipient.filter(vec![]));
+        assert_eq!(3, Recipient::default().filter(rows)?.len());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+//! Redaction of sensitive values before the presentation
+
+use crate::value::{TypedValue, Value};
+use regex::Regex;
+use serde::Deserialize;
+
+/// Rule to replace the matches of the pattern on string values
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct RedactRule {
+    pub pattern: String,
+    #[serde(default = "default_replacement")]
+    pub replacement: String,
+}
+
+fn default_replacement() -> String {
+    "***".to_string()
+}
+
+pub struct Redactor {
+    rules: Vec<(Regex, String)>,
+}
+
+impl Redactor {
+    pub fn new(rules: &[RedactRule]) -> Result<Self, String> {
+        let mut compiled = vec![];
+
+        for rule in rules {
+            let re = Regex::new(&rule.pattern)
+                .map_err(|e| format!("Invalid redact pattern {}: {}", rule.pattern, e))?;
+            compiled.push((re, rule.replacement.clone()));
+        }
+
+        Ok(Self { rules: compiled })
+    }
+
+    /// Apply the rules on all string values of the rows
+    pub fn apply(&self, rows: Vec<Vec<Value>>) -> Vec<Vec<Value>> {
+        if self.rules.is_empty() {
+            return rows;
+        }
+
+        rows.into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|mut value| {
+                        if let Some(TypedValue::String(s)) = &value.inner {
+                            value.inner = Some(TypedValue::String(self.apply_str(s)));
+                        }
+                        value
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn apply_str(&self, raw: &str) -> String {
+        let mut s = raw.to_string();
+
+        for (re, replacement) in &self.rules {
+            s = re.replace_all(&s, replacement.as_str()).to_string();
+        }
+
+        s
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{RedactRule, Redactor};
+    use crate::value::{Field, FieldType, TypedValue, Value};
+
+    #[test]
+    fn apply() -> Result<(), String> {
+        let redactor = Redactor::new(&[
+            RedactRule {
+                pattern: r"[\w.]+@[\w.]+".to_string(),
+                replacement: "<email>".to_string(),
+            },
+            RedactRule {
+                pattern: r"token=\w+".to_string(),
+                replacement: "token=***".to_string(),
+            },
+        ])?;
+
+        let field = Field {
+            title: "Log".to_string(),
+            field: "log".to_string(),
+            kind: FieldType::String,
+            ..Default::default()
+        };
+
+        let rows = vec![vec![
+            Value {
+                inner: Some(TypedValue::String(
+                    "john@abc.com called with token=abc123".to_string(),
+                )),
+                field: field.clone(),
+            },
+            Value {
+                inner: Some(TypedValue::Integer(10)),
+                field: field.clone(),
+            },
+            Value {
+                inner: None,
+                field: field.clone(),
+            },
+        ]];
+
+        let rows = redactor.apply(rows);
+
+        assert_eq!(
+            Some(TypedValue::String(
+                "<email> called with token=***".to_string()
+            )),
+            rows[0][0].inner
+        );
+        assert_eq!(Some(TypedValue::Integer(10)), rows[0][1].inner);
+        assert_eq!(None, rows[0][2].inner);
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        let redactor = Redactor::new(&[RedactRule {
+            pattern: r"(abc".to_string(),
+            replacement: "".to_string(),
+        }]);
+
+        assert!(redactor.is_err());
+    }
+}
@@ -1,12 +1,36 @@
 //! Send/Output api
 
 use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::{Duration, NaiveDateTime};
 use log::*;
-use mail_builder::MessageBuilder;
-use mail_send::SmtpClientBuilder;
+use mail_builder::{headers::content_type::ContentType, mime::MimePart, MessageBuilder};
+use mail_send::{
+    smtp::message::{Address, IntoMessage, Message},
+    SmtpClientBuilder,
+};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use regex::Regex;
 use serde::Deserialize;
+use serde_json::json;
+use smtp_proto::EXT_DSN;
+use std::{
+    borrow::Cow,
+    fs,
+    io::{Cursor, Write},
+    path::Path,
+};
+use uuid::Uuid;
+use zip::{result::ZipError, write::SimpleFileOptions, AesMode, CompressionMethod, ZipWriter};
 
-use crate::presentation::DataPresented;
+use crate::{
+    clock,
+    presentation::{self, attachment::Attachment, formats::escape, DataPresented},
+    recipient::Recipient,
+    source::QueryResult,
+    value::{TypedValue, Value},
+};
+
+const ARCHIVE_DIR_FORMAT: &str = "%Y-%m-%dT%H%M%S";
 
 #[derive(Clone, Debug, PartialEq, Deserialize)]
 pub struct MailServer {
@@ -16,54 +40,1000 @@ pub struct MailServer {
     pub port: u16,
     pub user: String,
     pub pass: String,
+    /// Max size, in bytes, of the message with the images
+    #[serde(default)]
+    pub max_size: Option<usize>,
+    /// Where the images go when the message exceeds the max size
+    #[serde(default)]
+    pub upload: Option<Upload>,
+    /// One personalized message for each recipient, in place of `to`
+    #[serde(default)]
+    pub recipients: Vec<Recipient>,
+    /// Mailbox with a silent copy of every message
+    #[serde(default)]
+    pub audit_bcc: Option<String>,
+    /// Envelope sender, where the bounces go, when different of `from`
+    #[serde(default)]
+    pub envelope_from: Option<String>,
+    /// Delivery status notifications requested to the server
+    #[serde(default)]
+    pub dsn: Vec<DsnNotify>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub enum DsnNotify {
+    Success,
+    Failure,
+    Delay,
+}
+
+/// WebDAV/HTTP target that accepts the files by PUT, like a
+/// WebDAV share or a presigned bucket url
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct Upload {
+    pub url: String,
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub pass: Option<String>,
+    /// Url used on the links, when different of the upload one
+    #[serde(default)]
+    pub public_url: Option<String>,
+}
+
+/// Attachments of the mail and file bundled on a single zip file
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct Zip {
+    /// Name of the file, with the `{{date}}` like placeholders.
+    /// The `attachments.zip` by default
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Password of the AES encryption of the files
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Total size, in bytes, of the attachments to bundle them.
+    /// Always bundled by default
+    #[serde(default)]
+    pub min_size: Option<usize>,
+}
+
+/// Directory to keep a copy of every rendered report
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Archive {
+    pub dir: String,
+    /// Remove the reports older than this number of days
+    #[serde(default)]
+    pub retention_days: Option<u32>,
 }
 
 /// Send the exported data to STDOUT
 pub async fn to_stdout(dt: &DataPresented) -> Result<(), String> {
-    let mut content = dt.content.clone();
+    println!("{}", dt.inline_content());
 
-    for img in &dt.images {
-        let img64 = STANDARD.encode(&img.data);
-        content = content.replace(
-            &format!("cid:{}", img.cid),
-            &format!("data:{};base64,{}", img.mime, img64),
-        );
+    Ok(())
+}
+
+/// Write the exported data to a single file, with the images
+/// embedded as data uris, like on the STDOUT, and the attachments
+/// beside of it, named after the file. On a directory, the path
+/// ending with a `/` or an existing one, the report is written with
+/// the images and the attachments beside of it, like on the archive
+pub async fn to_file(path: &str, dt: &DataPresented, zip: Option<&Zip>) -> Result<(), String> {
+    info!("Writing the report on {}", path);
+
+    let dt = &*bundle(zip, dt)?;
+
+    let target = Path::new(path);
+    if path.ends_with(['/', std::path::MAIN_SEPARATOR]) || target.is_dir() {
+        return write_report(target, dt);
+    }
+
+    if let Some(parent) = target.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Report dir {} not created: {}", parent.display(), e))?;
+    }
+
+    fs::write(path, dt.inline_content())
+        .map_err(|e| format!("Report file {} not written: {}", path, e))?;
+
+    let stem = target
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    for a in &dt.attachments {
+        let file = target.with_file_name(format!("{}-{}", stem, a.name));
+        info!("Writing the attachment on {}", file.display());
+        fs::write(&file, &a.data)
+            .map_err(|e| format!("Report attachment {} not written: {}", file.display(), e))?;
+    }
+
+    Ok(())
+}
+
+/// Path of the report file with the `{{date}}` like placeholders
+/// replaced. The `{date}` ones, of a single brace, are refused
+pub fn file_path(raw: &str) -> Result<String, String> {
+    let path = clock::current().placeholders(raw);
+
+    let single = Regex::new(r"\{\w+\}").map_err(|e| e.to_string())?;
+    if let Some(found) = single.find(&path) {
+        return Err(format!(
+            "Unknown placeholder {} on the file, use like {{{{date}}}}",
+            found.as_str()
+        ));
+    }
+
+    Ok(path)
+}
+
+/// Send the exported data to email, a message for each recipient
+/// over the same SMTP connection
+pub async fn to_mail(
+    config: MailServer,
+    zip: Option<&Zip>,
+    title: String,
+    mails: &[(String, DataPresented)],
+) -> Result<(), String> {
+    let mut messages = vec![];
+    for (to, dt) in mails {
+        messages.push(message(&config, zip, &title, to, dt).await?);
+    }
+
+    let builder = SmtpClientBuilder::new(config.host.clone(), config.port)
+        .implicit_tls(false)
+        .credentials((config.user.clone(), config.pass.clone()));
+    let mut conn = builder
+        .connect()
+        .await
+        .map_err(|e| format!("SMTP connect failed: {}", e))?;
+
+    let mut dsn = false;
+    if !config.dsn.is_empty() {
+        let ehlo = conn
+            .capabilities(&builder.local_host, false)
+            .await
+            .map_err(|e| format!("SMTP EHLO failed: {}", e))?;
+        dsn = ehlo.has_capability(EXT_DSN);
+        if !dsn {
+            warn!("SMTP server without DSN support, sending without the notifications");
+        }
     }
 
-    println!("{}", content);
+    for ((to, _), mut message) in mails.iter().zip(messages) {
+        info!("Sending as email to {}", to);
+
+        if dsn {
+            add_dsn(&config, &mut message);
+        }
+
+        conn.send(message)
+            .await
+            .map_err(|e| format!("SMTP send to {} failed: {}", to, e))?;
+    }
+
+    conn.quit()
+        .await
+        .map_err(|e| format!("SMTP quit failed: {}", e))?;
 
     Ok(())
 }
 
-/// Send the exported data to email
-pub async fn to_mail(config: MailServer, title: String, dt: &DataPresented) -> Result<(), String> {
-    info!("Sending as email to {}", config.to);
+async fn message(
+    config: &MailServer,
+    zip: Option<&Zip>,
+    title: &str,
+    to: &str,
+    dt: &DataPresented,
+) -> Result<Message<'static>, String> {
+    let dt = &*bundle(zip, dt)?;
+
+    let uploaded;
+    let dt = match config.max_size {
+        Some(max) if message_size(dt) > max => {
+            let upload = config.upload.as_ref().ok_or(format!(
+                "Message with {} bytes exceeds the limit of {} bytes",
+                message_size(dt),
+                max
+            ))?;
+            uploaded = upload_files(upload, dt, max).await?;
+
+            if message_size(&uploaded) > max {
+                return Err(format!(
+                    "Message with {} bytes exceeds the limit of {} bytes, even after the upload",
+                    message_size(&uploaded),
+                    max
+                ));
+            }
+            &uploaded
+        }
+        _ => dt,
+    };
 
     let mut mb = MessageBuilder::new()
-        .from(("lmr".to_string(), config.from))
-        .to(config.to)
-        .subject(title);
+        .from(("lmr".to_string(), config.from.clone()))
+        .to(to.to_string())
+        .subject(title.to_string());
+
+    if presentation::is_deterministic() {
+        mb = mb.date(0i64).message_id("lmr@localhost");
+    }
+
+    let mb = mb.body(mime_body(dt, presentation::is_deterministic()));
+
+    let mut message = mb
+        .into_message()
+        .map_err(|e| format!("Message not built: {}", e))?;
+
+    // Only on the envelope, the recipients don't see the copy
+    if let Some(audit) = &config.audit_bcc {
+        message.rcpt_to.push(Address::from(audit.clone()));
+    }
+
+    if let Some(envelope) = &config.envelope_from {
+        message.mail_from = Address::from(envelope.clone());
+    }
+
+    Ok(message)
+}
+
+/// Body of the message, with the inline images and attachments. The
+/// boundary is fixed on the deterministic rendering, random by default
+fn mime_body(dt: &DataPresented, deterministic: bool) -> MimePart<'static> {
+    let content = match dt.is_html {
+        true => MimePart::new("text/html", dt.content.clone()),
+        false => MimePart::new("text/plain", dt.content.clone()),
+    };
+    if dt.images.is_empty() && dt.attachments.is_empty() {
+        return content;
+    }
+
+    let mut parts = vec![content];
+    for img in &dt.images {
+        parts.push(
+            MimePart::new(img.mime.clone(), img.data.clone())
+                .inline()
+                .cid(img.cid.clone()),
+        );
+    }
+    for a in &dt.attachments {
+        parts.push(MimePart::new(a.mime.clone(), a.data.clone()).attachment(a.name.clone()));
+    }
+
+    let mut mixed = ContentType::new("multipart/mixed");
+    if deterministic {
+        mixed = mixed.attribute("boundary", "lmr-boundary");
+    }
+
+    MimePart::new(mixed, parts)
+}
+
+/// Delivery status notifications on the envelope, only for the servers
+/// that advertise the DSN extension
+fn add_dsn(config: &MailServer, message: &mut Message) {
+    if !config.dsn.is_empty() {
+        let notify = config
+            .dsn
+            .iter()
+            .map(|n| match n {
+                DsnNotify::Success => "SUCCESS",
+                DsnNotify::Failure => "FAILURE",
+                DsnNotify::Delay => "DELAY",
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+
+        message
+            .mail_from
+            .parameters
+            .add(("RET".to_string(), "HDRS".to_string()));
+        for rcpt in message.rcpt_to.iter_mut() {
+            rcpt.parameters.add(("NOTIFY".to_string(), notify.clone()));
+        }
+    }
+}
+
+/// Attachments bundled on a single zip, when their size reaches the minimum
+fn bundle<'a>(zip: Option<&Zip>, dt: &'a DataPresented) -> Result<Cow<'a, DataPresented>, String> {
+    let Some(zip) = zip else {
+        return Ok(Cow::Borrowed(dt));
+    };
+
+    let size = dt.attachments.iter().map(|a| a.data.len()).sum::<usize>();
+    if dt.attachments.is_empty() || size < zip.min_size.unwrap_or_default() {
+        return Ok(Cow::Borrowed(dt));
+    }
+
+    let err = |e: ZipError| format!("Zip of the attachments not written: {}", e);
+
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let options = match &zip.password {
+        Some(password) => options.with_aes_encryption(AesMode::Aes256, password),
+        None => options,
+    };
+
+    let mut writer = ZipWriter::new(Cursor::new(vec![]));
+    for a in &dt.attachments {
+        writer.start_file(a.name.as_str(), options).map_err(err)?;
+        writer
+            .write_all(&a.data)
+            .map_err(|e| format!("Zip of the attachments not written: {}", e))?;
+    }
+    let data = writer.finish().map_err(err)?.into_inner();
+
+    debug!(
+        "Bundled {} attachments of {} bytes on a zip of {} bytes",
+        dt.attachments.len(),
+        size,
+        data.len()
+    );
+
+    Ok(Cow::Owned(DataPresented {
+        is_html: dt.is_html,
+        content: dt.content.clone(),
+        images: dt.images.clone(),
+        attachments: vec![Attachment {
+            name: clock::current().placeholders(zip.name.as_deref().unwrap_or("attachments.zip")),
+            mime: "application/zip".to_string(),
+            data,
+        }],
+    }))
+}
+
+/// Approximated size of the message, with the images as base64
+fn message_size(dt: &DataPresented) -> usize {
+    dt.content.len()
+        + dt.images
+            .iter()
+            .map(|img| img.data.len().div_ceil(3) * 4)
+            .sum::<usize>()
+        + dt.attachments
+            .iter()
+            .map(|a| a.data.len().div_ceil(3) * 4)
+            .sum::<usize>()
+}
+
+/// Upload the images, replacing the cid references by links to them, and
+/// the biggest attachments, in place of links on the end of the report,
+/// until the message fits on the limit
+async fn upload_files(
+    config: &Upload,
+    dt: &DataPresented,
+    max: usize,
+) -> Result<DataPresented, String> {
+    let client = reqwest::Client::new();
+    let base = config.url.trim_end_matches('/');
+    let public = config
+        .public_url
+        .as_deref()
+        .unwrap_or(base)
+        .trim_end_matches('/');
+
+    let put = |name: String, mime: String, data: Vec<u8>| {
+        let mut req = client
+            .put(format!("{}/{}", base, name))
+            .header("Content-Type", mime)
+            .body(data);
+        if let Some(user) = &config.user {
+            req = req.basic_auth(user, config.pass.as_ref());
+        }
+
+        async move {
+            info!("Uploading the file {} to {}", name, base);
+
+            let resp = req
+                .send()
+                .await
+                .map_err(|e| format!("Upload of {} failed: {}", name, e))?;
+            if !resp.status().is_success() {
+                return Err(format!("Upload of {} failed: {}", name, resp.status()));
+            }
+
+            Ok(format!("{}/{}", public, name))
+        }
+    };
+
+    // The names of the previous runs are kept, the files aren't replaced
+    let run = match presentation::is_deterministic() {
+        true => clock::current().now().format("%Y%m%d%H%M%S").to_string(),
+        false => Uuid::new_v4().simple().to_string(),
+    };
+
+    let mut uploaded = DataPresented {
+        is_html: dt.is_html,
+        content: dt.content.clone(),
+        images: vec![],
+        attachments: dt.attachments.clone(),
+    };
 
     for img in &dt.images {
-        mb = mb.inline(img.mime.clone(), img.cid.clone(), img.data.clone());
+        let name = format!(
+            "{}-{}.{}",
+            run,
+            img.cid,
+            img.mime.trim_start_matches("image/")
+        );
+        let url = put(name, img.mime.clone(), img.data.clone()).await?;
+
+        uploaded.content = uploaded.content.replace(&format!("cid:{}", img.cid), &url);
+    }
+
+    uploaded
+        .attachments
+        .sort_by_key(|a| std::cmp::Reverse(a.data.len()));
+
+    while message_size(&uploaded) > max && !uploaded.attachments.is_empty() {
+        let a = uploaded.attachments.remove(0);
+        let name = format!("{}-{}", run, utf8_percent_encode(&a.name, NON_ALPHANUMERIC));
+        let url = put(name, a.mime.clone(), a.data).await?;
+
+        let link = match dt.is_html {
+            true => format!(
+                "<p><a href=\"{}\">{}</a></p>",
+                escape(&url),
+                escape(&a.name)
+            ),
+            false => format!("\n{}: {}", a.name, url),
+        };
+        let at = match dt.is_html {
+            true => uploaded.content.rfind("</body>"),
+            false => None,
+        }
+        .unwrap_or(uploaded.content.len());
+        uploaded.content.insert_str(at, &link);
     }
 
-    let message = if dt.is_html {
-        mb.html_body(dt.content.clone())
+    // Keep the order of the attachments that stay on the message
+    uploaded.attachments = dt
+        .attachments
+        .iter()
+        .filter(|a| uploaded.attachments.contains(a))
+        .cloned()
+        .collect();
+
+    Ok(uploaded)
+}
+
+/// Store the report, images and data under a dated directory of the archive
+pub async fn to_archive(
+    config: Archive,
+    dt: &DataPresented,
+    data: &[QueryResult],
+) -> Result<(), String> {
+    let now = clock::current().now().naive_local();
+
+    let dir = Path::new(&config.dir).join(now.format(ARCHIVE_DIR_FORMAT).to_string());
+
+    info!("Archiving the report on {}", dir.display());
+
+    write_report(&dir, dt)?;
+
+    let json = serde_json::to_string_pretty(&data_to_json(data))
+        .map_err(|e| format!("Archive data not serialized: {}", e))?;
+    fs::write(dir.join("data.json"), json)
+        .map_err(|e| format!("Archive data not written: {}", e))?;
+
+    if let Some(days) = config.retention_days {
+        prune_archive(Path::new(&config.dir), now - Duration::days(days as i64))?;
+    }
+
+    Ok(())
+}
+
+/// Write the report on the directory, with the images under `images/`
+/// in place of the cid references, and the attachments
+fn write_report(dir: &Path, dt: &DataPresented) -> Result<(), String> {
+    fs::create_dir_all(dir.join("images"))
+        .map_err(|e| format!("Report dir {} not created: {}", dir.display(), e))?;
+
+    let mut content = dt.content.clone();
+    for img in &dt.images {
+        let ext = img.mime.trim_start_matches("image/");
+        let name = format!("images/{}.{}", img.cid, ext);
+        fs::write(dir.join(&name), &img.data)
+            .map_err(|e| format!("Report image not written: {}", e))?;
+        content = content.replace(&format!("cid:{}", img.cid), &name);
+    }
+
+    let name = if dt.is_html {
+        "report.html"
     } else {
-        mb.text_body(dt.content.clone())
+        "report.txt"
     };
+    fs::write(dir.join(name), content).map_err(|e| format!("Report not written: {}", e))?;
 
-    let mut conn = SmtpClientBuilder::new(config.host, config.port)
-        .implicit_tls(false)
-        .credentials((config.user, config.pass))
-        .connect()
-        .await
-        .map_err(|e| format!("SMTP connect failed: {}", e.to_string()))?;
+    for a in &dt.attachments {
+        fs::write(dir.join(&a.name), &a.data)
+            .map_err(|e| format!("Report attachment not written: {}", e))?;
+    }
 
-    conn.send(message)
-        .await
-        .map_err(|e| format!("SMTP send failed: {}", e.to_string()))?;
+    Ok(())
+}
+
+/// Remove the archived reports older than the limit. Only the
+/// directories named by the archive are touched
+fn prune_archive(dir: &Path, limit: NaiveDateTime) -> Result<(), String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("Archive dir not read: {}", e))?;
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Ok(dt) = NaiveDateTime::parse_from_str(&name, ARCHIVE_DIR_FORMAT) else {
+            continue;
+        };
+
+        if dt < limit && entry.path().is_dir() {
+            debug!("Removing the archived report {}", name);
+            fs::remove_dir_all(entry.path())
+                .map_err(|e| format!("Archived report {} not removed: {}", name, e))?;
+        }
+    }
 
     Ok(())
 }
+
+fn data_to_json(data: &[QueryResult]) -> serde_json::Value {
+    let querys = data
+        .iter()
+        .map(|(query, result)| match result {
+            Ok(rows) => json!({
+                "title": query.title,
+                "rows": rows
+                    .iter()
+                    .map(|row| {
+                        row.iter()
+                            .map(|v| (v.field.field.clone(), value_to_json(v)))
+                            .collect::<serde_json::Map<_, _>>()
+                    })
+                    .collect::<Vec<_>>(),
+            }),
+            Err(e) => json!({
+                "title": query.title,
+                "error": e,
+            }),
+        })
+        .collect::<Vec<_>>();
+
+    serde_json::Value::Array(querys)
+}
+
+fn value_to_json(value: &Value) -> serde_json::Value {
+    match &value.inner {
+        None => serde_json::Value::Null,
+        Some(TypedValue::Integer(v)) => json!(v),
+        Some(TypedValue::Float(v)) => json!(v),
+        Some(TypedValue::Image(v)) => json!(STANDARD.encode(v)),
+        Some(v) => v.to_json(),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{
+        add_dsn, bundle, file_path, message, message_size, mime_body, to_archive, to_file,
+        upload_files, Archive, DsnNotify, MailServer, Upload, Zip,
+    };
+    use crate::{
+        presentation::{attachment::Attachment, DataPresented, ImagePresented},
+        source::Query,
+        value::{Field, FieldType, TypedValue, Value},
+    };
+    use std::{
+        fs,
+        io::{Cursor, Read},
+        sync::{Arc, Mutex},
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+    use zip::ZipArchive;
+
+    #[test]
+    fn file_paths() {
+        let path = file_path("reports/{{date}}-sales.html").unwrap();
+        assert!(path.starts_with("reports/") && !path.contains('{'));
+        assert!(path.ends_with("-sales.html"));
+
+        assert_eq!(
+            Err("Unknown placeholder {date} on the file, use like {{date}}".to_string()),
+            file_path("reports/{date}-sales.html")
+        );
+    }
+
+    #[tokio::test]
+    async fn file() -> Result<(), String> {
+        let dir = std::env::temp_dir().join("test-lmr-file");
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("reports/report.html");
+
+        let content = DataPresented {
+            is_html: true,
+            content: "<img src=\"cid:abc\">".to_string(),
+            images: vec![ImagePresented {
+                cid: "abc".to_string(),
+                mime: "image/png".to_string(),
+                data: vec![1, 2, 3],
+            }],
+            attachments: vec![Attachment {
+                name: "users.csv".to_string(),
+                mime: "text/csv".to_string(),
+                data: b"Quantity\n10\n".to_vec(),
+            }],
+        };
+
+        to_file(&path.to_string_lossy(), &content, None).await?;
+
+        assert_eq!(
+            "<img src=\"data:image/png;base64,AQID\">",
+            fs::read_to_string(&path).unwrap()
+        );
+        assert_eq!(
+            "Quantity\n10\n",
+            fs::read_to_string(dir.join("reports/report-users.csv")).unwrap()
+        );
+
+        let out = dir.join("out");
+        to_file(&format!("{}/", out.to_string_lossy()), &content, None).await?;
+
+        assert_eq!(
+            "<img src=\"images/abc.png\">",
+            fs::read_to_string(out.join("report.html")).unwrap()
+        );
+        assert_eq!(vec![1, 2, 3], fs::read(out.join("images/abc.png")).unwrap());
+        assert_eq!(
+            "Quantity\n10\n",
+            fs::read_to_string(out.join("users.csv")).unwrap()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn zip() -> Result<(), String> {
+        let attachment = |name: &str, data: &[u8]| Attachment {
+            name: name.to_string(),
+            mime: "text/csv".to_string(),
+            data: data.to_vec(),
+        };
+        let content = DataPresented {
+            is_html: false,
+            content: "Report".to_string(),
+            images: vec![],
+            attachments: vec![
+                attachment("users.csv", b"Quantity\n10\n"),
+                attachment("orders.csv", b"Total\n5\n"),
+            ],
+        };
+
+        // Under the threshold, attached as they are
+        let config = Zip {
+            min_size: Some(100),
+            ..Default::default()
+        };
+        assert_eq!(content, *bundle(Some(&config), &content)?);
+        assert_eq!(content, *bundle(None, &content)?);
+
+        let config = Zip {
+            name: Some("sales.zip".to_string()),
+            password: Some("123".to_string()),
+            min_size: Some(20),
+        };
+        let bundled = bundle(Some(&config), &content)?;
+        assert_eq!("Report", bundled.content);
+        assert_eq!(1, bundled.attachments.len());
+        assert_eq!("sales.zip", bundled.attachments[0].name);
+        assert_eq!("application/zip", bundled.attachments[0].mime);
+
+        let mut archive = ZipArchive::new(Cursor::new(bundled.attachments[0].data.clone()))
+            .map_err(|e| e.to_string())?;
+        assert_eq!(2, archive.len());
+        assert!(archive.by_index_decrypt(0, b"456").is_err());
+
+        let mut file = archive
+            .by_index_decrypt(1, b"123")
+            .map_err(|e| e.to_string())?;
+        assert_eq!("orders.csv", file.name());
+        let mut data = String::new();
+        file.read_to_string(&mut data).map_err(|e| e.to_string())?;
+        assert_eq!("Total\n5\n", data);
+
+        let server = MailServer {
+            from: "lmr@abc.com".to_string(),
+            to: "ana@abc.com".to_string(),
+            host: "localhost".to_string(),
+            port: 587,
+            user: "lmr".to_string(),
+            pass: "123".to_string(),
+            max_size: None,
+            upload: None,
+            recipients: vec![],
+            audit_bcc: None,
+            envelope_from: None,
+            dsn: vec![],
+        };
+        let message = message(&server, Some(&config), "Title", "ana@abc.com", &content).await?;
+        let body = String::from_utf8_lossy(&message.body);
+        assert!(body.contains("filename=\"sales.zip\""));
+        assert!(!body.contains("users.csv"));
+
+        let dir = std::env::temp_dir().join("test-lmr-zip");
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("report.txt");
+        to_file(&path.to_string_lossy(), &content, Some(&config)).await?;
+        assert!(dir.join("report-sales.zip").exists());
+        assert!(!dir.join("report-users.csv").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn archive() -> Result<(), String> {
+        let dir = std::env::temp_dir().join("test-lmr-archive");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("2000-01-01T000000")).unwrap();
+        fs::create_dir_all(dir.join("other")).unwrap();
+
+        let content = DataPresented {
+            is_html: true,
+            content: "<img src=\"cid:abc\">".to_string(),
+            images: vec![ImagePresented {
+                cid: "abc".to_string(),
+                mime: "image/png".to_string(),
+                data: vec![1, 2, 3],
+            }],
+            attachments: vec![Attachment {
+                name: "users.csv".to_string(),
+                mime: "text/csv".to_string(),
+                data: b"Quantity\n10\n".to_vec(),
+            }],
+        };
+
+        let query = Query {
+            title: "Users".to_string(),
+            ..Default::default()
+        };
+        let data = vec![
+            (
+                query.clone(),
+                Ok(vec![vec![Value {
+                    inner: Some(TypedValue::Integer(10)),
+                    field: Field {
+                        field: "qt".to_string(),
+                        kind: FieldType::Integer,
+                        ..Default::default()
+                    },
+                }]]),
+            ),
+            (query, Err("Query failed".to_string())),
+        ];
+
+        let config = Archive {
+            dir: dir.to_string_lossy().to_string(),
+            retention_days: Some(30),
+        };
+
+        to_archive(config, &content, &data).await?;
+
+        assert!(!dir.join("2000-01-01T000000").exists());
+        assert!(dir.join("other").exists());
+
+        let archived = fs::read_dir(&dir)
+            .unwrap()
+            .flatten()
+            .map(|e| e.path())
+            .find(|p| !p.ends_with("other"))
+            .unwrap();
+
+        assert_eq!(
+            "<img src=\"images/abc.png\">",
+            fs::read_to_string(archived.join("report.html")).unwrap()
+        );
+        assert_eq!(
+            vec![1, 2, 3],
+            fs::read(archived.join("images/abc.png")).unwrap()
+        );
+        assert_eq!(
+            "Quantity\n10\n",
+            fs::read_to_string(archived.join("users.csv")).unwrap()
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(archived.join("data.json")).unwrap()).unwrap();
+        assert_eq!(10, json[0]["rows"][0]["qt"]);
+        assert_eq!("Query failed", json[1]["error"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upload() -> Result<(), String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let requests = Arc::new(Mutex::new(vec![]));
+        let received = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 4096];
+                let n = stream.read(&mut buf).await.unwrap();
+                stream
+                    .write_all(
+                        b"HTTP/1.1 201 Created\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    )
+                    .await
+                    .unwrap();
+                received
+                    .lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&buf[..n]).to_string());
+            }
+        });
+
+        let attachment = |name: &str, size: usize| Attachment {
+            name: name.to_string(),
+            mime: "text/csv".to_string(),
+            data: vec![b'1'; size],
+        };
+        let content = DataPresented {
+            is_html: true,
+            content: "<img src=\"cid:abc\">".to_string(),
+            images: vec![ImagePresented {
+                cid: "abc".to_string(),
+                mime: "image/png".to_string(),
+                data: vec![1, 2, 3, 4],
+            }],
+            attachments: vec![attachment("small.csv", 3), attachment("big data.csv", 300)],
+        };
+        assert_eq!(431, message_size(&content));
+
+        let config = Upload {
+            url: format!("http://{}/reports/", addr),
+            user: Some("admin".to_string()),
+            pass: Some("123".to_string()),
+            public_url: Some("https://files.abc.com/reports".to_string()),
+        };
+
+        let uploaded = upload_files(&config, &content, 200).await?;
+        let run = uploaded
+            .content
+            .trim_start_matches("<img src=\"https://files.abc.com/reports/")
+            .split('-')
+            .next()
+            .unwrap()
+            .to_string();
+        assert_eq!(32, run.len());
+        assert!(uploaded.content.starts_with(&format!(
+            "<img src=\"https://files.abc.com/reports/{}-abc.png\"><p><a href=\"https://files.abc.com/reports/{}-big%20data%2Ecsv\">",
+            run, run
+        )));
+        assert!(uploaded
+            .content
+            .ends_with("-big%20data%2Ecsv\">big data.csv</a></p>"));
+        assert!(uploaded.images.is_empty());
+        assert_eq!(vec![attachment("small.csv", 3)], uploaded.attachments);
+        assert!(message_size(&uploaded) <= 200);
+
+        {
+            let requests = requests.lock().unwrap();
+            assert_eq!(2, requests.len());
+            assert!(requests[0].starts_with(&format!("PUT /reports/{}-abc.png HTTP/1.1", run)));
+            assert!(requests[0].contains("authorization: Basic YWRtaW46MTIz"));
+            assert!(
+                requests[1].starts_with(&format!("PUT /reports/{}-big%20data%2Ecsv HTTP/1.1", run))
+            );
+        }
+
+        let server = MailServer {
+            from: "lmr@abc.com".to_string(),
+            to: "ana@abc.com".to_string(),
+            host: "localhost".to_string(),
+            port: 587,
+            user: "lmr".to_string(),
+            pass: "123".to_string(),
+            max_size: Some(10),
+            upload: Some(config),
+            recipients: vec![],
+            audit_bcc: None,
+            envelope_from: None,
+            dsn: vec![],
+        };
+        let err = message(&server, None, "Title", "ana@abc.com", &content)
+            .await
+            .err()
+            .unwrap_or_default();
+        assert!(err.ends_with("even after the upload"), "{}", err);
+        assert_eq!(5, requests.lock().unwrap().len());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn envelope() -> Result<(), String> {
+        let config = MailServer {
+            from: "lmr@abc.com".to_string(),
+            to: "ana@abc.com".to_string(),
+            host: "localhost".to_string(),
+            port: 587,
+            user: "lmr".to_string(),
+            pass: "123".to_string(),
+            max_size: None,
+            upload: None,
+            recipients: vec![],
+            audit_bcc: Some("audit@abc.com".to_string()),
+            envelope_from: Some("bounces@abc.com".to_string()),
+            dsn: vec![DsnNotify::Failure, DsnNotify::Delay],
+        };
+
+        let content = DataPresented {
+            is_html: false,
+            content: "Report".to_string(),
+            images: vec![],
+            attachments: vec![Attachment {
+                name: "users.csv".to_string(),
+                mime: "text/csv".to_string(),
+                data: b"Quantity\n10\n".to_vec(),
+            }],
+        };
+
+        let mut message = message(&config, None, "Title", "ana@abc.com", &content).await?;
+        assert!(message.mail_from.parameters.to_string().is_empty());
+
+        let mut rcpts = message
+            .rcpt_to
+            .iter()
+            .map(|a| a.email.to_string())
+            .collect::<Vec<_>>();
+        rcpts.sort();
+        assert_eq!(vec!["ana@abc.com", "audit@abc.com"], rcpts);
+
+        let body = String::from_utf8_lossy(&message.body);
+        assert!(!body.contains("audit@abc.com"));
+        assert!(body.contains("From: \"lmr\" <lmr@abc.com>"));
+        assert!(body.contains("Content-Disposition: attachment; filename=\"users.csv\""));
+
+        assert_eq!("bounces@abc.com", message.mail_from.email);
+
+        add_dsn(&config, &mut message);
+        assert_eq!(" RET=HDRS", message.mail_from.parameters.to_string());
+        assert_eq!(
+            " NOTIFY=FAILURE,DELAY",
+            message.rcpt_to[0].parameters.to_string()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn mime_boundary() {
+        let content = DataPresented {
+            is_html: true,
+            content: "<img src=\"cid:abc\">".to_string(),
+            images: vec![ImagePresented {
+                cid: "abc".to_string(),
+                mime: "image/png".to_string(),
+                data: vec![1, 2, 3, 4],
+            }],
+            attachments: vec![Attachment {
+                name: "users.csv".to_string(),
+                mime: "text/csv".to_string(),
+                data: b"id\n1\n".to_vec(),
+            }],
+        };
+        let write = |deterministic: bool| {
+            let mut body = vec![];
+            mime_body(&content, deterministic)
+                .write_part(&mut body)
+                .unwrap();
+            String::from_utf8(body).unwrap()
+        };
+
+        let body = write(true);
+        assert_eq!(body, write(true));
+        assert!(body.contains("boundary=\"lmr-boundary\""));
+        assert!(body.contains("--lmr-boundary--"));
+        assert!(body.contains("Content-ID: <abc>"));
+        assert!(body.contains("filename=\"users.csv\""));
+
+        assert!(!write(false).contains("lmr-boundary"));
+    }
+}
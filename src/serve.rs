@@ -0,0 +1,252 @@
+//! Preview server, renders the report on each request
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use log::*;
+use sha2::{Digest, Sha256};
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::Mutex,
+    time::timeout,
+};
+
+const REFRESH_BUTTON: &str = "<form method='get' action='/' style='text-align: center'>\
+    <button type='submit'>Refresh</button></form>";
+
+/// Limit to receive the head of the request, against the idle clients
+const HEAD_TIMEOUT: Duration = Duration::from_secs(10);
+/// Limit of the whole request, with the querys of the report
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Listen on the address and render the report for each request, each
+/// connection on its own task. The renders are one at a time, since the
+/// clock, the language and the ids of the images are global
+pub async fn serve(config: String, bind: String, auth: Option<String>) -> Result<(), String> {
+    let listener = TcpListener::bind(&bind)
+        .await
+        .map_err(|e| format!("Bind on {} failed: {}", bind, e))?;
+
+    info!("Serving the report on http://{}", bind);
+
+    let rendering = Arc::new(Mutex::new(()));
+
+    loop {
+        let (mut stream, addr) = match listener.accept().await {
+            Ok(s) => s,
+            Err(e) => {
+                warn!("Accept failed: {}", e);
+                continue;
+            }
+        };
+
+        debug!("Request from {}", addr);
+
+        let config = config.clone();
+        let auth = auth.clone();
+        let rendering = rendering.clone();
+        tokio::spawn(async move {
+            let result = timeout(
+                REQUEST_TIMEOUT,
+                handle(&mut stream, &config, auth.as_deref(), &rendering),
+            )
+            .await
+            .unwrap_or_else(|_| Err("Request timed out".to_string()));
+
+            if let Err(e) = result {
+                warn!("Request from {} failed: {}", addr, e);
+            }
+        });
+    }
+}
+
+async fn handle(
+    stream: &mut TcpStream,
+    config: &str,
+    auth: Option<&str>,
+    rendering: &Mutex<()>,
+) -> Result<(), String> {
+    let head = timeout(HEAD_TIMEOUT, read_head(stream))
+        .await
+        .map_err(|_| "Request head timed out".to_string())??;
+    let request = Request::parse(&head)?;
+
+    if !request.authorized(auth) {
+        return respond(
+            stream,
+            "401 Unauthorized",
+            &[("WWW-Authenticate", "Basic realm=\"lmr\"")],
+            "text/plain",
+            "Unauthorized",
+        )
+        .await;
+    }
+
+    if request.method != "GET" || request.path != "/" {
+        return respond(stream, "404 Not Found", &[], "text/plain", "Not found").await;
+    }
+
+    let rendered = {
+        let _render = rendering.lock().await;
+        render(config).await
+    };
+
+    match rendered {
+        Ok((true, content)) => {
+            respond(stream, "200 OK", &[], "text/html; charset=utf-8", &content).await
+        }
+        Ok((false, content)) => {
+            respond(stream, "200 OK", &[], "text/plain; charset=utf-8", &content).await
+        }
+        Err(e) => {
+            warn!("Report failed: {}", e);
+            respond(
+                stream,
+                "500 Internal Server Error",
+                &[],
+                "text/plain; charset=utf-8",
+                &e,
+            )
+            .await
+        }
+    }
+}
+
+/// Report with the current state of the config file
+async fn render(config: &str) -> Result<(bool, String), String> {
+    let config = crate::load_config(config)?;
+    let content = crate::report(&config).await?.content;
+
+    let mut html = content.inline_content();
+    if content.is_html {
+        html = html.replacen("</body>", &format!("{}</body>", REFRESH_BUTTON), 1);
+    }
+
+    Ok((content.is_html, html))
+}
+
+async fn read_head(stream: &mut TcpStream) -> Result<String, String> {
+    let mut head = vec![];
+    let mut buf = [0u8; 1024];
+
+    while !head.ends_with(b"\r\n\r\n") {
+        let n = stream
+            .read(&mut buf)
+            .await
+            .map_err(|e| format!("Read failed: {}", e))?;
+        if n == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..n]);
+        if head.len() > 16 * 1024 {
+            return Err("Request head too large".to_string());
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&head).to_string())
+}
+
+async fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    headers: &[(&str, &str)],
+    content_type: &str,
+    body: &str,
+) -> Result<(), String> {
+    let mut r = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    for (k, v) in headers {
+        r.push_str(&format!("{}: {}\r\n", k, v));
+    }
+    r.push_str("\r\n");
+    r.push_str(body);
+
+    stream
+        .write_all(r.as_bytes())
+        .await
+        .map_err(|e| format!("Write failed: {}", e))
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Request {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+}
+
+impl Request {
+    fn parse(head: &str) -> Result<Self, String> {
+        let mut lines = head.lines();
+
+        let mut first = lines
+            .next()
+            .ok_or("Empty request".to_string())?
+            .split_whitespace();
+        let method = first.next().unwrap_or_default().to_string();
+        let path = first.next().ok_or("Invalid request line".to_string())?;
+        let path = path.split('?').next().unwrap_or_default().to_string();
+
+        let authorization = lines
+            .filter_map(|l| l.split_once(':'))
+            .find(|(k, _)| k.trim().eq_ignore_ascii_case("authorization"))
+            .map(|(_, v)| v.trim().to_string());
+
+        Ok(Self {
+            method,
+            path,
+            authorization,
+        })
+    }
+
+    /// Check the basic auth credentials, when required
+    fn authorized(&self, auth: Option<&str>) -> bool {
+        let Some(auth) = auth else {
+            return true;
+        };
+
+        let expected = format!("Basic {}", STANDARD.encode(auth));
+        let informed = self.authorization.as_deref().unwrap_or_default();
+
+        // The digests have the same size, compared on all the bytes to
+        // take the same time on any mismatch
+        let (expected, informed) = (
+            Sha256::digest(expected.as_bytes()),
+            Sha256::digest(informed.as_bytes()),
+        );
+        expected
+            .iter()
+            .zip(informed.iter())
+            .fold(0, |diff, (e, i)| diff | (e ^ i))
+            == 0
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::Request;
+
+    #[test]
+    fn parse_request() -> Result<(), String> {
+        let request = Request::parse(
+            "GET /?refresh=1 HTTP/1.1\r\nHost: localhost\r\nAuthorization: Basic YWRtaW46MTIz\r\n\r\n",
+        )?;
+
+        assert_eq!("GET", request.method);
+        assert_eq!("/", request.path);
+        assert!(request.authorized(Some("admin:123")));
+        assert!(!request.authorized(Some("admin:456")));
+        assert!(!request.authorized(Some("admin:12")));
+        assert!(request.authorized(None));
+
+        let request = Request::parse("GET / HTTP/1.1\r\n\r\n")?;
+        assert!(!request.authorized(Some("admin:123")));
+
+        assert!(Request::parse("").is_err());
+
+        Ok(())
+    }
+}
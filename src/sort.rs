@@ -0,0 +1,137 @@
+//! Sort of the fetched rows, before the presentation
+
+use crate::value::{TypedValue, Value};
+use rust_decimal::{prelude::FromPrimitive, Decimal};
+use serde::Deserialize;
+use std::cmp::Ordering;
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Rows sorted by the values of the fields, in the order of them. The
+/// nulls are the last ones on both orders
+pub fn sort_rows(
+    rows: Vec<Vec<Value>>,
+    by: &[String],
+    order: &SortOrder,
+) -> Result<Vec<Vec<Value>>, String> {
+    let Some(first) = rows.first() else {
+        return Ok(rows);
+    };
+
+    let mut columns = vec![];
+    for field in by {
+        let i = first
+            .iter()
+            .position(|v| v.field.field == *field)
+            .ok_or_else(|| format!("Field {} not found", field))?;
+        columns.push(i);
+    }
+
+    let mut rows = rows;
+    rows.sort_by(|a, b| {
+        for i in &columns {
+            let ord = match (&a[*i].inner, &b[*i].inner) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) if *order == SortOrder::Desc => compare(b, a),
+                (Some(a), Some(b)) => compare(a, b),
+            };
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        }
+        Ordering::Equal
+    });
+
+    Ok(rows)
+}
+
+/// Order of the values by their types, the numbers of any kind between them
+fn compare(a: &TypedValue, b: &TypedValue) -> Ordering {
+    match (a, b) {
+        (TypedValue::Integer(a), TypedValue::Integer(b)) => a.cmp(b),
+        (TypedValue::Date(a), TypedValue::Date(b)) => a.cmp(b),
+        (TypedValue::Time(a), TypedValue::Time(b)) => a.cmp(b),
+        (TypedValue::DateTime(a), TypedValue::DateTime(b)) => a.cmp(b),
+        (a, b) => match (number(a), number(b)) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            _ => a.to_string().cmp(&b.to_string()),
+        },
+    }
+}
+
+fn number(v: &TypedValue) -> Option<Decimal> {
+    match v {
+        TypedValue::Integer(v) => Some(Decimal::from(*v)),
+        TypedValue::Float(v) => Decimal::from_f64(*v),
+        TypedValue::Decimal(v) => Some(*v),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{sort_rows, SortOrder};
+    use crate::value::{Field, TypedValue, Value};
+
+    fn row(region: &str, total: Option<f64>) -> Vec<Value> {
+        vec![
+            Value {
+                inner: Some(TypedValue::String(region.to_string())),
+                field: Field {
+                    field: "region".to_string(),
+                    ..Default::default()
+                },
+            },
+            Value {
+                inner: total.map(TypedValue::Float),
+                field: Field {
+                    field: "total".to_string(),
+                    ..Default::default()
+                },
+            },
+        ]
+    }
+
+    fn values(rows: &[Vec<Value>]) -> Vec<String> {
+        rows.iter().map(|r| format!("{} {}", r[0], r[1])).collect()
+    }
+
+    #[test]
+    fn sort() -> Result<(), String> {
+        let rows = vec![
+            row("South", Some(9.5)),
+            row("North", None),
+            row("North", Some(100.0)),
+            row("East", Some(20.0)),
+        ];
+
+        let sorted = sort_rows(rows.clone(), &["total".to_string()], &SortOrder::Asc)?;
+        assert_eq!(
+            vec!["South 9.5", "East 20", "North 100", "North "],
+            values(&sorted)
+        );
+
+        let sorted = sort_rows(
+            rows.clone(),
+            &["region".to_string(), "total".to_string()],
+            &SortOrder::Desc,
+        )?;
+        assert_eq!(
+            vec!["South 9.5", "North 100", "North ", "East 20"],
+            values(&sorted)
+        );
+
+        assert!(sort_rows(rows, &["name".to_string()], &SortOrder::Asc).is_err());
+        assert!(sort_rows(vec![], &["name".to_string()], &SortOrder::Asc)?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,300 @@
+//! Google BigQuery driver implementation
+//!
+//! The `conn` is the path of the service account key file, the querys
+//! are run, as Standard SQL, on the project of the key
+
+use super::{google, json, Driver, Query};
+use crate::value::{apply_overrides, Field, FieldType, Value};
+use async_trait::async_trait;
+use chrono::DateTime;
+use serde_json::{json, Map, Value as JsonValue};
+
+const API: &str = "https://bigquery.googleapis.com/bigquery/v2";
+const SCOPE: &str = "https://www.googleapis.com/auth/bigquery.readonly";
+
+pub struct BigQueryDriver {
+    pub client: reqwest::Client,
+    pub project: String,
+    pub token: Option<String>,
+}
+
+impl BigQueryDriver {
+    pub fn init() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            project: String::new(),
+            token: None,
+        }
+    }
+}
+
+#[async_trait]
+impl Driver for BigQueryDriver {
+    async fn connect(&mut self, sconn: String) -> Result<(), String> {
+        let (project, token) = google::access_token(&self.client, &sconn, SCOPE).await?;
+
+        self.project = project;
+        self.token = Some(token);
+
+        Ok(())
+    }
+
+    fn paginate(&self, sql: &str, size: usize, offset: usize) -> Option<String> {
+        Some(super::limit_offset(sql, size, offset))
+    }
+
+    async fn fetch(&mut self, query: Query) -> Result<Vec<Vec<Value>>, String> {
+        let token = self
+            .token
+            .as_ref()
+            .ok_or("Connection not established".to_string())?;
+
+        let url = format!("{}/projects/{}/queries", API, self.project);
+        let body = json!({"query": query.sql, "useLegacySql": false, "timeoutMs": 60000});
+
+        let mut response = self
+            .request(
+                self.client
+                    .post(&url)
+                    .header("Content-Type", "application/json")
+                    .body(body.to_string()),
+                token,
+            )
+            .await?;
+
+        let mut pages = vec![];
+        loop {
+            let complete = response["jobComplete"].as_bool().unwrap_or(false);
+            let page = response["pageToken"].as_str().map(|p| p.to_string());
+
+            if complete {
+                pages.push(response.clone());
+            }
+            if complete && page.is_none() {
+                break;
+            }
+
+            let job = &response["jobReference"];
+            let url = format!(
+                "{}/projects/{}/queries/{}",
+                API,
+                self.project,
+                job["jobId"].as_str().unwrap_or_default()
+            );
+            let mut params = vec![("timeoutMs", "60000".to_string())];
+            if let Some(location) = job["location"].as_str() {
+                params.push(("location", location.to_string()));
+            }
+            if let Some(page) = page.filter(|_| complete) {
+                params.push(("pageToken", page));
+            }
+
+            response = self
+                .request(self.client.get(&url).query(&params), token)
+                .await?;
+        }
+
+        let (records, mut fields) = to_records(&pages)?;
+
+        let mut query = query;
+        if query.fields.is_empty() {
+            apply_overrides(&mut fields, &query.overrides);
+            query.fields = fields;
+        }
+
+        json::to_rows(&records, query)
+    }
+}
+
+impl BigQueryDriver {
+    async fn request(
+        &self,
+        request: reqwest::RequestBuilder,
+        token: &str,
+    ) -> Result<JsonValue, String> {
+        let response = request
+            .bearer_auth(token)
+            .send()
+            .await
+            .map_err(|e| format!("BigQuery request failed: {}", e))?;
+
+        let status = response.status();
+        let content = response
+            .text()
+            .await
+            .map_err(|e| format!("BigQuery response not read: {}", e))?;
+        let content: JsonValue = serde_json::from_str(&content)
+            .map_err(|e| format!("BigQuery response not parsed: {}", e))?;
+
+        if !status.is_success() {
+            return Err(format!(
+                "BigQuery query failed: {}",
+                content["error"]["message"]
+                    .as_str()
+                    .unwrap_or(status.as_str())
+            ));
+        }
+
+        Ok(content)
+    }
+}
+
+/// Records and fields of the schema and rows of the result pages
+fn to_records(pages: &[JsonValue]) -> Result<(Vec<JsonValue>, Vec<Field>), String> {
+    let schema = pages
+        .first()
+        .and_then(|p| p["schema"]["fields"].as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    let columns = schema
+        .iter()
+        .map(|f| {
+            (
+                f["name"].as_str().unwrap_or_default().to_string(),
+                f["type"].as_str().unwrap_or_default().to_string(),
+                f["mode"] == "REPEATED",
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let fields = columns
+        .iter()
+        .map(|(name, kind, repeated)| Field {
+            field: name.clone(),
+            title: name.clone(),
+            kind: match kind.as_str() {
+                _ if *repeated => FieldType::String,
+                "INTEGER" | "INT64" => FieldType::Integer,
+                "FLOAT" | "FLOAT64" | "NUMERIC" | "BIGNUMERIC" => FieldType::Float,
+                "DATE" => FieldType::Date,
+                "TIME" => FieldType::Time,
+                "TIMESTAMP" | "DATETIME" => FieldType::DateTime,
+                "BYTES" => FieldType::Image,
+                _ => FieldType::String,
+            },
+            ..Default::default()
+        })
+        .collect();
+
+    let mut records = vec![];
+    for row in pages
+        .iter()
+        .flat_map(|p| p["rows"].as_array().into_iter().flatten())
+    {
+        let mut record = Map::new();
+        for ((name, kind, repeated), cell) in columns
+            .iter()
+            .zip(row["f"].as_array().into_iter().flatten())
+        {
+            let v = &cell["v"];
+            let value = match (kind.as_str(), v) {
+                (_, JsonValue::Null) => JsonValue::Null,
+                _ if *repeated => JsonValue::from(v.to_string()),
+                ("TIMESTAMP", v) => {
+                    let secs = v
+                        .as_str()
+                        .and_then(|s| s.parse::<f64>().ok())
+                        .ok_or(format!("Invalid timestamp {}", v))?;
+                    let dt = DateTime::from_timestamp_micros((secs * 1_000_000.0).round() as i64)
+                        .ok_or(format!("Invalid timestamp {}", v))?;
+                    JsonValue::from(dt.to_rfc3339())
+                }
+                ("DATETIME", JsonValue::String(s)) => {
+                    JsonValue::from(format!("{}Z", s.split('.').next().unwrap_or_default()))
+                }
+                ("TIME", JsonValue::String(s)) => {
+                    JsonValue::from(s.split('.').next().unwrap_or_default())
+                }
+                ("RECORD" | "STRUCT", v) => JsonValue::from(v.to_string()),
+                (_, v) => v.clone(),
+            };
+            record.insert(name.clone(), value);
+        }
+        records.push(JsonValue::Object(record));
+    }
+
+    Ok((records, fields))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::to_records;
+    use crate::{
+        source::{json, Query},
+        value::{FieldType, TypedValue},
+    };
+    use chrono::{NaiveDate, NaiveTime};
+    use serde_json::json;
+
+    #[test]
+    fn pages() -> Result<(), String> {
+        let page = json!({
+            "jobComplete": true,
+            "schema": {"fields": [
+                {"name": "name", "type": "STRING", "mode": "NULLABLE"},
+                {"name": "qty", "type": "INTEGER", "mode": "NULLABLE"},
+                {"name": "total", "type": "NUMERIC", "mode": "NULLABLE"},
+                {"name": "day", "type": "DATE", "mode": "NULLABLE"},
+                {"name": "at", "type": "TIME", "mode": "NULLABLE"},
+                {"name": "created", "type": "TIMESTAMP", "mode": "NULLABLE"},
+                {"name": "tags", "type": "STRING", "mode": "REPEATED"}
+            ]},
+            "rows": [
+                {"f": [{"v": "Alice"}, {"v": "2"}, {"v": "10.5"}, {"v": "2024-06-10"},
+                       {"v": "10:30:00.123456"}, {"v": "1.718E9"}, {"v": [{"v": "a"}]}]},
+                {"f": [{"v": "Bob"}, {"v": null}, {"v": "1"}, {"v": null},
+                       {"v": null}, {"v": null}, {"v": []}]}
+            ]
+        });
+        let next = json!({"jobComplete": true, "rows": [
+            {"f": [{"v": "Carol"}, {"v": "3"}, {"v": null}, {"v": null},
+                   {"v": null}, {"v": null}, {"v": []}]}
+        ]});
+
+        let (records, fields) = to_records(&[page, next])?;
+        assert_eq!(3, records.len());
+        assert_eq!(
+            vec![
+                FieldType::String,
+                FieldType::Integer,
+                FieldType::Float,
+                FieldType::Date,
+                FieldType::Time,
+                FieldType::DateTime,
+                FieldType::String
+            ],
+            fields.iter().map(|f| f.kind.clone()).collect::<Vec<_>>()
+        );
+
+        let rows = json::to_rows(
+            &records,
+            Query {
+                fields,
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(Some(TypedValue::Integer(2)), rows[0][1].inner);
+        assert_eq!(Some(TypedValue::Float(10.5)), rows[0][2].inner);
+        assert_eq!(
+            Some(TypedValue::Date(
+                NaiveDate::from_ymd_opt(2024, 6, 10).unwrap()
+            )),
+            rows[0][3].inner
+        );
+        assert_eq!(
+            Some(TypedValue::Time(
+                NaiveTime::from_hms_opt(10, 30, 0).unwrap()
+            )),
+            rows[0][4].inner
+        );
+        assert_eq!(
+            "2024-06-10 06:13:20 +00:00",
+            rows[0][5].inner.as_ref().unwrap().to_string()
+        );
+        assert_eq!(None, rows[1][1].inner);
+        assert_eq!(Some(TypedValue::Integer(3)), rows[2][1].inner);
+
+        Ok(())
+    }
+}
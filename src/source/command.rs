@@ -0,0 +1,154 @@
+//! Shell command driver implementation
+//!
+//! The `sql` of the query is the command, run with `sh -c`, and its
+//! output is parsed as the format on `conn`: `csv`, `json` or, when
+//! empty, guessed by the first character
+
+use super::{csv, json, Driver, Query};
+use crate::value::Value;
+use async_trait::async_trait;
+use tokio::process;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Format {
+    Csv,
+    Json,
+    Guess,
+}
+
+pub struct CommandDriver {
+    pub format: Format,
+}
+
+impl CommandDriver {
+    pub fn init() -> Self {
+        Self {
+            format: Format::Guess,
+        }
+    }
+}
+
+#[async_trait]
+impl Driver for CommandDriver {
+    async fn connect(&mut self, sconn: String) -> Result<(), String> {
+        self.format = match sconn.trim().to_lowercase().as_str() {
+            "csv" => Format::Csv,
+            "json" | "jsonl" => Format::Json,
+            "" => Format::Guess,
+            other => return Err(format!("Unsupported command output format: {}", other)),
+        };
+
+        Ok(())
+    }
+
+    async fn fetch(&mut self, query: Query) -> Result<Vec<Vec<Value>>, String> {
+        let output = process::Command::new("sh")
+            .arg("-c")
+            .arg(&query.sql)
+            .output()
+            .await
+            .map_err(|e| format!("Command not started: {}", e))?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "Command failed with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        parse_output(&self.format, &stdout, query)
+    }
+}
+
+fn parse_output(format: &Format, stdout: &str, query: Query) -> Result<Vec<Vec<Value>>, String> {
+    let format = match format {
+        Format::Guess if stdout.trim_start().starts_with(['[', '{']) => &Format::Json,
+        Format::Guess => &Format::Csv,
+        f => f,
+    };
+
+    match format {
+        Format::Json => json::to_rows(&json::parse(stdout)?, query),
+        _ => {
+            let (headers, records) = csv::read(stdout.as_bytes())?;
+            csv::to_rows(&headers, &records.iter().collect::<Vec<_>>(), query)
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::{
+        source::{command::CommandDriver, Driver, Query},
+        value::TypedValue,
+    };
+
+    #[tokio::test]
+    async fn csv_output() -> Result<(), String> {
+        let mut driver = CommandDriver::init();
+        driver.connect("".to_string()).await?;
+
+        let result = driver
+            .fetch(Query {
+                title: "Test".to_string(),
+                sql: "printf 'disk,used\\n/,42\\n/home,13\\n'".to_string(),
+                ..Default::default()
+            })
+            .await?;
+
+        assert_eq!(2, result.len());
+        assert_eq!(
+            Some(TypedValue::String("/home".to_string())),
+            result[1][0].inner
+        );
+        assert_eq!(Some(TypedValue::Integer(42)), result[0][1].inner);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn json_output() -> Result<(), String> {
+        let mut driver = CommandDriver::init();
+        driver.connect("json".to_string()).await?;
+
+        let result = driver
+            .fetch(Query {
+                title: "Test".to_string(),
+                sql: "echo '{\"pod\": \"api\", \"restarts\": 3}'; echo '{\"pod\": \"db\", \"restarts\": 0}'"
+                    .to_string(),
+                ..Default::default()
+            })
+            .await?;
+
+        assert_eq!(2, result.len());
+        assert_eq!(Some(TypedValue::Integer(0)), result[1][1].inner);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failed() -> Result<(), String> {
+        let mut driver = CommandDriver::init();
+        driver.connect("csv".to_string()).await?;
+
+        let result = driver
+            .fetch(Query {
+                title: "Test".to_string(),
+                sql: "echo 'no such thing' >&2; exit 3".to_string(),
+                ..Default::default()
+            })
+            .await;
+
+        assert_eq!(
+            Some("Command failed with exit status: 3: no such thing".to_string()),
+            result.err()
+        );
+
+        assert!(driver.connect("xml".to_string()).await.is_err());
+
+        Ok(())
+    }
+}
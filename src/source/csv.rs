@@ -0,0 +1,348 @@
+//! CSV file driver implementation
+//!
+//! The `conn` is the path of the file, with a header line. The `sql`
+//! of the query is optional, or a simple filter like `age > 30 and city = 'Lisbon'`
+
+use super::{Driver, Query};
+use crate::value::{apply_overrides, Field, FieldType, TypedValue, Value};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, NaiveTime};
+use regex::Regex;
+use std::{cmp::Ordering, fs::File, io::Read};
+
+pub struct CsvDriver {
+    pub headers: Vec<String>,
+    pub records: Vec<Vec<String>>,
+}
+
+impl CsvDriver {
+    pub fn init() -> Self {
+        Self {
+            headers: vec![],
+            records: vec![],
+        }
+    }
+}
+
+#[async_trait]
+impl Driver for CsvDriver {
+    async fn connect(&mut self, sconn: String) -> Result<(), String> {
+        let file =
+            File::open(&sconn).map_err(|e| format!("CSV file {} not opened: {}", sconn, e))?;
+
+        (self.headers, self.records) = read(file)?;
+
+        Ok(())
+    }
+
+    async fn fetch(&mut self, query: Query) -> Result<Vec<Vec<Value>>, String> {
+        let filters = parse_filter(&query.sql)?;
+
+        let index = |field: &str| {
+            self.headers
+                .iter()
+                .position(|h| h == field)
+                .ok_or(format!("Column {} not found", field))
+        };
+
+        let mut conditions = vec![];
+        for f in filters {
+            conditions.push((index(&f.field)?, f));
+        }
+
+        let records = self
+            .records
+            .iter()
+            .filter(|r| {
+                conditions
+                    .iter()
+                    .all(|(idx, f)| f.matches(r.get(*idx).map(|v| v.as_str()).unwrap_or("")))
+            })
+            .collect::<Vec<_>>();
+
+        to_rows(&self.headers, &records, query)
+    }
+}
+
+/// Header and records of the CSV content
+pub(super) fn read(content: impl Read) -> Result<(Vec<String>, Vec<Vec<String>>), String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content);
+
+    let headers = reader
+        .headers()
+        .map_err(|e| format!("CSV header not read: {}", e))?
+        .iter()
+        .map(|h| h.trim().to_string())
+        .collect();
+
+    let mut records = vec![];
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("CSV record not read: {}", e))?;
+        records.push(record.iter().map(|v| v.to_string()).collect());
+    }
+
+    Ok((headers, records))
+}
+
+/// Rows with the values of the columns matched by the header
+pub(super) fn to_rows(
+    headers: &[String],
+    records: &[&Vec<String>],
+    query: Query,
+) -> Result<Vec<Vec<Value>>, String> {
+    let fields = if query.fields.is_empty() {
+        let mut fields = infer_fields(headers, records);
+        apply_overrides(&mut fields, &query.overrides);
+        fields
+    } else {
+        query.fields
+    };
+
+    let mut columns = vec![];
+    for col in fields {
+        let idx = headers
+            .iter()
+            .position(|h| *h == col.field)
+            .ok_or(format!("Column {} not found", col.field))?;
+        columns.push((col, idx));
+    }
+
+    let mut rows = vec![];
+
+    for (i, record) in records.iter().enumerate() {
+        let mut r = vec![];
+
+        for (col, idx) in &columns {
+            let raw = record.get(*idx).map(|v| v.as_str()).unwrap_or("");
+            let inner = to_typed(&col.kind, raw)
+                .map_err(|e| format!("Column {} row {} error: {}", col.field, i, e))?;
+
+            r.push(Value {
+                inner,
+                field: col.clone(),
+            });
+        }
+
+        rows.push(r);
+    }
+
+    Ok(rows)
+}
+
+/// Value of the raw text as the kind of the field, empty as null
+fn to_typed(kind: &FieldType, raw: &str) -> Result<Option<TypedValue>, String> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Ok(None);
+    }
+
+    let value = match kind {
+        FieldType::String => TypedValue::String(raw.to_string()),
+        FieldType::Integer => TypedValue::Integer(
+            raw.parse()
+                .map_err(|e| format!("Error on parse the {} to integer: {}", raw, e))?,
+        ),
+        FieldType::Float => TypedValue::Float(
+            raw.parse()
+                .map_err(|e| format!("Error on parse the {} to float: {}", raw, e))?,
+        ),
+        FieldType::Decimal => TypedValue::parse_decimal(raw)?,
+        FieldType::Date => TypedValue::Date(
+            NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+                .map_err(|e| format!("Error on parse the {} to date: {}", raw, e))?,
+        ),
+        FieldType::Time => TypedValue::Time(
+            NaiveTime::parse_from_str(raw, "%H:%M:%S")
+                .map_err(|e| format!("Error on parse the {} to time: {}", raw, e))?,
+        ),
+        FieldType::DateTime => TypedValue::DateTime(
+            DateTime::parse_from_rfc3339(raw)
+                .map_err(|e| format!("Error on parse the {} to datetime: {}", raw, e))?,
+        ),
+        FieldType::Image => TypedValue::Image(raw.as_bytes().to_vec()),
+        FieldType::Array | FieldType::Json => TypedValue::parse_json(kind, raw)?,
+    };
+
+    Ok(Some(value))
+}
+
+/// Fields by the values of the columns, string when mixed
+fn infer_fields(headers: &[String], records: &[&Vec<String>]) -> Vec<Field> {
+    headers
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let values = records
+                .iter()
+                .filter_map(|r| r.get(i))
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+                .collect::<Vec<_>>();
+
+            let kind = [
+                FieldType::Integer,
+                FieldType::Float,
+                FieldType::Date,
+                FieldType::DateTime,
+            ]
+            .into_iter()
+            .find(|k| !values.is_empty() && values.iter().all(|v| to_typed(k, v).is_ok()))
+            .unwrap_or(FieldType::String);
+
+            Field {
+                field: name.clone(),
+                title: name.clone(),
+                kind,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Filter {
+    field: String,
+    op: String,
+    value: String,
+}
+
+impl Filter {
+    /// Compare as numbers when both sides are numeric
+    fn matches(&self, raw: &str) -> bool {
+        let raw = raw.trim();
+        let ord = match (raw.parse::<f64>(), self.value.parse::<f64>()) {
+            (Ok(a), Ok(b)) => a.partial_cmp(&b),
+            _ => Some(raw.cmp(self.value.as_str())),
+        };
+
+        match (self.op.as_str(), ord) {
+            ("=", Some(o)) => o == Ordering::Equal,
+            ("!=" | "<>", Some(o)) => o != Ordering::Equal,
+            (">", Some(o)) => o == Ordering::Greater,
+            (">=", Some(o)) => o != Ordering::Less,
+            ("<", Some(o)) => o == Ordering::Less,
+            ("<=", Some(o)) => o != Ordering::Greater,
+            _ => false,
+        }
+    }
+}
+
+/// Conditions joined by `and`, with an optional leading `where`
+fn parse_filter(sql: &str) -> Result<Vec<Filter>, String> {
+    let sql = sql.trim().trim_end_matches(';');
+    if sql.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let where_re = Regex::new(r"(?i)^where\s+").map_err(|e| e.to_string())?;
+    let and_re = Regex::new(r"(?i)\s+and\s+").map_err(|e| e.to_string())?;
+    let cond_re =
+        Regex::new(r"^\s*(\w+)\s*(=|!=|<>|>=|<=|>|<)\s*(.+?)\s*$").map_err(|e| e.to_string())?;
+
+    let sql = where_re.replace(sql, "");
+
+    and_re
+        .split(&sql)
+        .map(|cond| {
+            let caps = cond_re
+                .captures(cond)
+                .ok_or(format!("Invalid CSV filter: {}", cond))?;
+            let value = caps[3].trim_matches(|c| c == '\'' || c == '"').to_string();
+
+            Ok(Filter {
+                field: caps[1].to_string(),
+                op: caps[2].to_string(),
+                value,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::parse_filter;
+    use crate::{
+        source::{csv::CsvDriver, Driver, Query},
+        value::{FieldOverride, FieldType, TypedValue},
+    };
+    use chrono::NaiveDate;
+
+    #[tokio::test]
+    async fn fetch() -> Result<(), String> {
+        std::fs::write(
+            "/tmp/test-lmr.csv",
+            "name,age,score,birth\nAlice,42,9.5,1982-03-01\nBob,69,,1955-07-21\nCarol,31,7.25,\n",
+        )
+        .unwrap();
+
+        let mut driver = CsvDriver::init();
+        driver.connect("/tmp/test-lmr.csv".to_string()).await?;
+
+        let query = Query {
+            title: "Test".to_string(),
+            overrides: vec![FieldOverride {
+                field: "age".to_string(),
+                title: Some("Age".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let result = driver.fetch(query).await?;
+        assert_eq!(3, result.len());
+
+        let kinds = result[0]
+            .iter()
+            .map(|v| v.field.kind.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                FieldType::String,
+                FieldType::Integer,
+                FieldType::Float,
+                FieldType::Date
+            ],
+            kinds
+        );
+        assert_eq!("Age", result[0][1].field.title);
+        assert_eq!(Some(TypedValue::Integer(42)), result[0][1].inner);
+        assert_eq!(
+            Some(TypedValue::Date(
+                NaiveDate::from_ymd_opt(1982, 3, 1).unwrap()
+            )),
+            result[0][3].inner
+        );
+        assert_eq!(None, result[1][2].inner);
+
+        let query = Query {
+            title: "Test".to_string(),
+            sql: "where age > 35 and name != 'Bob'".to_string(),
+            ..Default::default()
+        };
+
+        let result = driver.fetch(query).await?;
+        assert_eq!(1, result.len());
+        assert_eq!(
+            Some(TypedValue::String("Alice".to_string())),
+            result[0][0].inner
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn filter() {
+        let filters = parse_filter("city = 'Lisbon' AND age >= 30;").unwrap();
+        assert_eq!(2, filters.len());
+        assert_eq!("city", filters[0].field);
+        assert_eq!("Lisbon", filters[0].value);
+        assert!(filters[1].matches("30"));
+        assert!(filters[1].matches("100"));
+        assert!(!filters[1].matches("9"));
+
+        assert!(parse_filter("").unwrap().is_empty());
+        assert!(parse_filter("select * from x").is_err());
+    }
+}
@@ -0,0 +1,181 @@
+//! Elasticsearch/OpenSearch driver implementation
+//!
+//! The `conn` is the url of the index, like `http://localhost:9200/logs-*`,
+//! and the `sql` of the query is the search body. The rows are the buckets
+//! of the aggregations, nested ones flattened, or the `_source` of the hits
+
+use super::{http::HttpDriver, json, Driver, Query};
+use crate::value::{apply_overrides, Value};
+use async_trait::async_trait;
+use serde_json::{Map, Value as JsonValue};
+use std::collections::BTreeMap;
+
+pub struct ElasticDriver {
+    pub http: HttpDriver,
+}
+
+impl ElasticDriver {
+    pub fn init(headers: BTreeMap<String, String>, auth: Option<String>) -> Self {
+        Self {
+            http: HttpDriver::init(headers, auth),
+        }
+    }
+}
+
+#[async_trait]
+impl Driver for ElasticDriver {
+    async fn connect(&mut self, sconn: String) -> Result<(), String> {
+        self.http.connect(sconn).await
+    }
+
+    async fn fetch(&mut self, query: Query) -> Result<Vec<Vec<Value>>, String> {
+        let body = if query.sql.trim().is_empty() {
+            "{}".to_string()
+        } else {
+            query.sql.clone()
+        };
+
+        let content = self
+            .http
+            .post("/_search", &[], "application/json", body)
+            .await?;
+        let content: JsonValue = serde_json::from_str(&content)
+            .map_err(|e| format!("Search response not parsed: {}", e))?;
+
+        let records = to_records(&content);
+
+        let mut query = query;
+        if query.fields.is_empty() {
+            query.fields = json::infer_fields(&records);
+            apply_overrides(&mut query.fields, &query.overrides);
+        }
+
+        json::to_rows(&records, query)
+    }
+}
+
+/// Rows of the aggregations or, without them, of the hits
+fn to_records(content: &JsonValue) -> Vec<JsonValue> {
+    match content["aggregations"].as_object() {
+        Some(aggs) => flatten_buckets(aggs, &Map::new())
+            .into_iter()
+            .map(JsonValue::Object)
+            .collect(),
+        None => content["hits"]["hits"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|h| h["_source"].clone())
+            .collect(),
+    }
+}
+
+/// One row per bucket, with the metrics of the level and the keys of the parent buckets
+fn flatten_buckets(
+    aggs: &Map<String, JsonValue>,
+    parent: &Map<String, JsonValue>,
+) -> Vec<Map<String, JsonValue>> {
+    let mut base = parent.clone();
+    let mut nested = None;
+
+    for (name, agg) in aggs {
+        if agg.get("buckets").is_some() {
+            nested = nested.or(Some((name, &agg["buckets"])));
+        } else if let Some(value) = agg.get("value_as_string").or(agg.get("value")) {
+            base.insert(name.clone(), value.clone());
+        }
+    }
+
+    let Some((name, buckets)) = nested else {
+        return vec![base];
+    };
+
+    let buckets: Vec<(JsonValue, &JsonValue)> = match buckets {
+        JsonValue::Array(list) => list
+            .iter()
+            .map(|b| {
+                let key = b.get("key_as_string").or(b.get("key")).cloned();
+                (key.unwrap_or_default(), b)
+            })
+            .collect(),
+        JsonValue::Object(keyed) => keyed
+            .iter()
+            .map(|(k, b)| (JsonValue::from(k.clone()), b))
+            .collect(),
+        _ => vec![],
+    };
+
+    let mut rows = vec![];
+    for (key, bucket) in buckets {
+        let mut record = base.clone();
+        record.insert(name.clone(), key);
+        record.insert("doc_count".to_string(), bucket["doc_count"].clone());
+
+        let subs = bucket
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter(|(_, v)| v.is_object())
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect::<Map<_, _>>();
+
+        rows.extend(flatten_buckets(&subs, &record));
+    }
+
+    rows
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::to_records;
+    use serde_json::json;
+
+    #[test]
+    fn hits() {
+        let content = json!({
+            "hits": {"total": {"value": 2}, "hits": [
+                {"_id": "1", "_source": {"level": "error", "msg": "a"}},
+                {"_id": "2", "_source": {"level": "warn", "msg": "b"}}
+            ]}
+        });
+
+        assert_eq!(
+            vec![
+                json!({"level": "error", "msg": "a"}),
+                json!({"level": "warn", "msg": "b"})
+            ],
+            to_records(&content)
+        );
+    }
+
+    #[test]
+    fn aggregations() {
+        let content = json!({
+            "hits": {"hits": []},
+            "aggregations": {
+                "service": {
+                    "doc_count_error_upper_bound": 0,
+                    "buckets": [
+                        {"key": "api", "doc_count": 10, "level": {"buckets": [
+                            {"key": "error", "doc_count": 3, "latency": {"value": 1.5}},
+                            {"key": "warn", "doc_count": 7, "latency": {"value": 0.5}}
+                        ]}},
+                        {"key": "db", "doc_count": 2, "level": {"buckets": [
+                            {"key": "error", "doc_count": 2, "latency": {"value": null}}
+                        ]}}
+                    ]
+                },
+                "total_latency": {"value": 20.0}
+            }
+        });
+
+        assert_eq!(
+            vec![
+                json!({"total_latency": 20.0, "service": "api", "doc_count": 3, "level": "error", "latency": 1.5}),
+                json!({"total_latency": 20.0, "service": "api", "doc_count": 7, "level": "warn", "latency": 0.5}),
+                json!({"total_latency": 20.0, "service": "db", "doc_count": 2, "level": "error", "latency": null}),
+            ],
+            to_records(&content)
+        );
+    }
+}
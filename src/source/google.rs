@@ -0,0 +1,72 @@
+//! Google service account authentication, shared by the Google sources
+
+use chrono::Utc;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::fs;
+
+#[derive(Deserialize)]
+struct ServiceAccount {
+    client_email: String,
+    private_key: String,
+    token_uri: String,
+    project_id: String,
+}
+
+#[derive(Serialize)]
+struct Claims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: i64,
+    exp: i64,
+}
+
+/// Access token of the scope, with the project, for the service account key file
+pub async fn access_token(
+    client: &reqwest::Client,
+    key_path: &str,
+    scope: &str,
+) -> Result<(String, String), String> {
+    let key = fs::read_to_string(key_path)
+        .map_err(|e| format!("Service account key {} not loaded: {}", key_path, e))?;
+    let key: ServiceAccount =
+        serde_json::from_str(&key).map_err(|e| format!("Service account key not parsed: {}", e))?;
+
+    let now = Utc::now().timestamp();
+    let claims = Claims {
+        iss: &key.client_email,
+        scope,
+        aud: &key.token_uri,
+        iat: now,
+        exp: now + 3600,
+    };
+    let pem = EncodingKey::from_rsa_pem(key.private_key.as_bytes())
+        .map_err(|e| format!("Invalid service account private key: {}", e))?;
+    let assertion = jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &pem)
+        .map_err(|e| format!("Service account token not signed: {}", e))?;
+
+    let response = client
+        .post(&key.token_uri)
+        .form(&[
+            ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+            ("assertion", &assertion),
+        ])
+        .send()
+        .await
+        .map_err(|e| format!("Google authentication failed: {}", e))?
+        .error_for_status()
+        .map_err(|e| format!("Google authentication failed: {}", e))?
+        .text()
+        .await
+        .map_err(|e| format!("Google authentication failed: {}", e))?;
+    let response: JsonValue = serde_json::from_str(&response)
+        .map_err(|e| format!("Google authentication failed: {}", e))?;
+
+    let token = response["access_token"]
+        .as_str()
+        .ok_or("Google authentication failed: no access token".to_string())?;
+
+    Ok((key.project_id, token.to_string()))
+}
@@ -0,0 +1,151 @@
+//! Google Sheets driver implementation
+//!
+//! The `conn` is the path of the service account key file, shared with
+//! the sheet, and the `sql` of the query is the spreadsheet id followed by
+//! the range, like `1BxiMVs0XRA5nFMd Sheet1!A:D`. The first row of the
+//! range is the header, matched by the `field` of the fields
+
+use super::{google, json, Driver, Query};
+use crate::value::{apply_overrides, Value};
+use async_trait::async_trait;
+use serde_json::{Map, Value as JsonValue};
+
+const API: &str = "https://sheets.googleapis.com/v4/spreadsheets";
+const SCOPE: &str = "https://www.googleapis.com/auth/spreadsheets.readonly";
+
+pub struct GSheetsDriver {
+    pub client: reqwest::Client,
+    pub token: Option<String>,
+}
+
+impl GSheetsDriver {
+    pub fn init() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            token: None,
+        }
+    }
+}
+
+#[async_trait]
+impl Driver for GSheetsDriver {
+    async fn connect(&mut self, sconn: String) -> Result<(), String> {
+        let (_, token) = google::access_token(&self.client, &sconn, SCOPE).await?;
+
+        self.token = Some(token);
+
+        Ok(())
+    }
+
+    async fn fetch(&mut self, query: Query) -> Result<Vec<Vec<Value>>, String> {
+        let token = self
+            .token
+            .as_ref()
+            .ok_or("Connection not established".to_string())?;
+
+        let (spreadsheet, range) = query
+            .sql
+            .trim()
+            .split_once(char::is_whitespace)
+            .ok_or("Expected the spreadsheet id followed by the range".to_string())?;
+
+        let mut url = reqwest::Url::parse(API).map_err(|e| e.to_string())?;
+        url.path_segments_mut()
+            .map_err(|_| "Invalid url".to_string())?
+            .extend([spreadsheet, "values", range.trim()]);
+
+        let response = self
+            .client
+            .get(url)
+            .query(&[
+                ("valueRenderOption", "UNFORMATTED_VALUE"),
+                ("dateTimeRenderOption", "FORMATTED_STRING"),
+            ])
+            .bearer_auth(token)
+            .send()
+            .await
+            .map_err(|e| format!("Sheet request failed: {}", e))?
+            .error_for_status()
+            .map_err(|e| format!("Sheet request failed: {}", e))?
+            .text()
+            .await
+            .map_err(|e| format!("Sheet response not read: {}", e))?;
+        let content: JsonValue = serde_json::from_str(&response)
+            .map_err(|e| format!("Sheet response not parsed: {}", e))?;
+
+        let records = to_records(&content);
+
+        let mut query = query;
+        if query.fields.is_empty() {
+            query.fields = json::infer_fields(&records);
+            apply_overrides(&mut query.fields, &query.overrides);
+        }
+
+        json::to_rows(&records, query)
+    }
+}
+
+/// Records of the rows keyed by the header, the first row
+fn to_records(content: &JsonValue) -> Vec<JsonValue> {
+    let mut rows = content["values"].as_array().into_iter().flatten();
+
+    let header = rows
+        .next()
+        .and_then(|h| h.as_array())
+        .map(|h| {
+            h.iter()
+                .map(|c| match c {
+                    JsonValue::String(s) => s.trim().to_string(),
+                    c => c.to_string(),
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    rows.map(|row| {
+        let cells = row.as_array().cloned().unwrap_or_default();
+        let record = header
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| !name.is_empty())
+            .map(|(i, name)| {
+                let cell = match cells.get(i) {
+                    Some(JsonValue::String(s)) if s.is_empty() => JsonValue::Null,
+                    Some(c) => c.clone(),
+                    None => JsonValue::Null,
+                };
+                (name.clone(), cell)
+            })
+            .collect::<Map<_, _>>();
+
+        JsonValue::Object(record)
+    })
+    .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::to_records;
+    use serde_json::json;
+
+    #[test]
+    fn rows() {
+        let content = json!({
+            "range": "Sheet1!A1:C3",
+            "values": [
+                ["Region", "Sales", ""],
+                ["South", 120.5, "x"],
+                ["North", ""]
+            ]
+        });
+
+        assert_eq!(
+            vec![
+                json!({"Region": "South", "Sales": 120.5}),
+                json!({"Region": "North", "Sales": null}),
+            ],
+            to_records(&content)
+        );
+        assert!(to_records(&json!({})).is_empty());
+    }
+}
@@ -0,0 +1,197 @@
+//! HTTP REST API driver implementation
+//!
+//! The `conn` is the base url and the `sql` of the query is the path
+//! requested on it, optionally followed by the path of the records on
+//! the JSON response, like `/v1/orders?status=open $.data`. The fields
+//! are paths on the records, like on the JSON source
+
+use super::{json, Driver, Query};
+use crate::value::Value;
+use async_trait::async_trait;
+use reqwest::RequestBuilder;
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+
+pub struct HttpDriver {
+    pub client: reqwest::Client,
+    pub base: String,
+    pub headers: BTreeMap<String, String>,
+    pub auth: Option<String>,
+}
+
+impl HttpDriver {
+    pub fn init(headers: BTreeMap<String, String>, auth: Option<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base: String::new(),
+            headers,
+            auth,
+        }
+    }
+
+    /// Request the path, relative to the base url, and parse the JSON response
+    pub async fn get(&self, path: &str, params: &[(&str, String)]) -> Result<JsonValue, String> {
+        let url = self.url(path);
+        let body = self.send(&url, self.client.get(&url).query(params)).await?;
+
+        serde_json::from_str(&body).map_err(|e| format!("Response of {} not parsed: {}", url, e))
+    }
+
+    /// Post the body to the path, relative to the base url, and read the response
+    pub async fn post(
+        &self,
+        path: &str,
+        params: &[(&str, String)],
+        content_type: &str,
+        body: String,
+    ) -> Result<String, String> {
+        let url = self.url(path);
+        let request = self
+            .client
+            .post(&url)
+            .query(params)
+            .header("Content-Type", content_type)
+            .body(body);
+
+        self.send(&url, request).await
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base.trim_end_matches('/'), path)
+    }
+
+    /// Send the request with the headers and credentials of the source
+    async fn send(&self, url: &str, request: RequestBuilder) -> Result<String, String> {
+        let mut request = request;
+        for (k, v) in &self.headers {
+            request = request.header(k, v);
+        }
+        if let Some(auth) = &self.auth {
+            let (user, pass) = auth.split_once(':').unwrap_or((auth, ""));
+            request = request.basic_auth(user, Some(pass));
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("Request to {} failed: {}", url, e))?
+            .error_for_status()
+            .map_err(|e| format!("Request to {} failed: {}", url, e))?;
+
+        response
+            .text()
+            .await
+            .map_err(|e| format!("Response of {} not read: {}", url, e))
+    }
+}
+
+#[async_trait]
+impl Driver for HttpDriver {
+    async fn connect(&mut self, sconn: String) -> Result<(), String> {
+        reqwest::Url::parse(&sconn).map_err(|e| format!("Invalid url {}: {}", sconn, e))?;
+
+        self.base = sconn;
+
+        Ok(())
+    }
+
+    async fn fetch(&mut self, query: Query) -> Result<Vec<Vec<Value>>, String> {
+        let (path, records) = split_request(&query.sql);
+
+        let content = self.get(path, &[]).await?;
+
+        let records = match records {
+            Some(p) => json::resolve(&content, p)
+                .ok_or(format!("Records not found on {} of the response", p))?,
+            None => &content,
+        };
+
+        match records {
+            JsonValue::Array(records) => json::to_rows(records, query),
+            record => json::to_rows(std::slice::from_ref(record), query),
+        }
+    }
+}
+
+/// Requested path and the path of the records on the response
+fn split_request(sql: &str) -> (&str, Option<&str>) {
+    let mut parts = sql.split_whitespace();
+    let path = parts.next().unwrap_or_default();
+
+    (path, parts.next())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::split_request;
+    use crate::{
+        source::{http::HttpDriver, Driver, Query},
+        value::TypedValue,
+    };
+    use std::collections::BTreeMap;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    #[test]
+    fn request() {
+        assert_eq!(
+            ("/v1/orders?status=open", Some("$.data")),
+            split_request(" /v1/orders?status=open  $.data")
+        );
+        assert_eq!(("", None), split_request(""));
+    }
+
+    #[tokio::test]
+    async fn fetch() -> Result<(), String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let n = stream.read(&mut buf).await.unwrap();
+            let body = r#"{"data": [{"name": "Alice", "age": 42}, {"name": "Bob", "age": 69}]}"#;
+            stream
+                .write_all(
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                    .as_bytes(),
+                )
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&buf[..n]).to_string()
+        });
+
+        let mut headers = BTreeMap::new();
+        headers.insert("X-Api-Key".to_string(), "secret".to_string());
+
+        let mut driver = HttpDriver::init(headers, None);
+        driver.connect(format!("http://{}/api/", addr)).await?;
+
+        let result = driver
+            .fetch(Query {
+                title: "Test".to_string(),
+                sql: "/users?active=1 $.data".to_string(),
+                ..Default::default()
+            })
+            .await?;
+
+        assert_eq!(2, result.len());
+        assert_eq!(
+            Some(TypedValue::String("Alice".to_string())),
+            result[0][0].inner
+        );
+        assert_eq!(Some(TypedValue::Integer(69)), result[1][1].inner);
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("GET /api/users?active=1 HTTP/1.1"));
+        assert!(request.to_lowercase().contains("x-api-key: secret"));
+
+        Ok(())
+    }
+}
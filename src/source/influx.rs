@@ -0,0 +1,287 @@
+//! InfluxDB driver implementation
+//!
+//! The `conn` is the server url with the database (InfluxQL) or the
+//! organization (Flux) as params, like `http://localhost:8086?db=sensors`
+//! or `http://localhost:8086?org=acme`. The `sql` is a Flux query when it
+//! has pipes (`|>`), otherwise an InfluxQL one
+
+use super::{http::HttpDriver, json, Driver, Query};
+use crate::value::{apply_overrides, Field, FieldType, Value};
+use async_trait::async_trait;
+use serde_json::{json, Map, Value as JsonValue};
+use std::collections::BTreeMap;
+
+pub struct InfluxDriver {
+    pub http: HttpDriver,
+    pub params: Vec<(String, String)>,
+}
+
+impl InfluxDriver {
+    pub fn init(headers: BTreeMap<String, String>, auth: Option<String>) -> Self {
+        Self {
+            http: HttpDriver::init(headers, auth),
+            params: vec![],
+        }
+    }
+}
+
+#[async_trait]
+impl Driver for InfluxDriver {
+    async fn connect(&mut self, sconn: String) -> Result<(), String> {
+        let mut url =
+            reqwest::Url::parse(&sconn).map_err(|e| format!("Invalid url {}: {}", sconn, e))?;
+
+        self.params = url.query_pairs().into_owned().collect();
+        url.set_query(None);
+
+        self.http.connect(url.to_string()).await
+    }
+
+    async fn fetch(&mut self, query: Query) -> Result<Vec<Vec<Value>>, String> {
+        let mut params = self
+            .params
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.clone()))
+            .collect::<Vec<_>>();
+
+        let (records, mut fields) = if query.sql.contains("|>") {
+            let body = json!({
+                "query": query.sql,
+                "type": "flux",
+                "dialect": {"annotations": ["datatype"]},
+            });
+            let content = self
+                .http
+                .post(
+                    "/api/v2/query",
+                    &params,
+                    "application/json",
+                    body.to_string(),
+                )
+                .await?;
+
+            flux_records(&content)?
+        } else {
+            params.push(("q", query.sql.clone()));
+            let content = self.http.get("/query", &params).await?;
+
+            influxql_records(&content)?
+        };
+
+        let mut query = query;
+        if query.fields.is_empty() {
+            apply_overrides(&mut fields, &query.overrides);
+            query.fields = fields;
+        }
+
+        json::to_rows(&records, query)
+    }
+}
+
+/// Records and fields of the series of the InfluxQL results
+fn influxql_records(content: &JsonValue) -> Result<(Vec<JsonValue>, Vec<Field>), String> {
+    if let Some(e) = content["error"].as_str() {
+        return Err(format!("InfluxQL query failed: {}", e));
+    }
+
+    let mut records = vec![];
+    for result in content["results"].as_array().into_iter().flatten() {
+        if let Some(e) = result["error"].as_str() {
+            return Err(format!("InfluxQL query failed: {}", e));
+        }
+
+        for serie in result["series"].as_array().into_iter().flatten() {
+            let columns = serie["columns"]
+                .as_array()
+                .ok_or("Invalid serie, without columns".to_string())?;
+
+            for values in serie["values"].as_array().into_iter().flatten() {
+                let mut record = serie["tags"].as_object().cloned().unwrap_or_default();
+                for (c, v) in columns.iter().zip(values.as_array().into_iter().flatten()) {
+                    record.insert(c.as_str().unwrap_or_default().to_string(), v.clone());
+                }
+                records.push(JsonValue::Object(record));
+            }
+        }
+    }
+
+    let mut fields = json::infer_fields(&records);
+    for f in fields.iter_mut().filter(|f| f.field == "time") {
+        f.kind = FieldType::DateTime;
+    }
+
+    Ok((records, fields))
+}
+
+/// Records and fields of the tables of the Flux annotated CSV
+fn flux_records(content: &str) -> Result<(Vec<JsonValue>, Vec<Field>), String> {
+    let content = content.replace("\r\n", "\n");
+
+    let mut records = vec![];
+    let mut fields: Vec<Field> = vec![];
+
+    for table in content.split("\n\n").filter(|t| !t.trim().is_empty()) {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(table.as_bytes());
+
+        let mut datatypes = vec![];
+        let mut header: Option<Vec<String>> = None;
+
+        for row in reader.records() {
+            let row = row.map_err(|e| format!("Flux response not parsed: {}", e))?;
+            let row = row.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+
+            if row.first().is_some_and(|c| c.starts_with('#')) {
+                if row[0] == "#datatype" {
+                    datatypes = row;
+                }
+                continue;
+            }
+
+            let Some(columns) = &header else {
+                for (i, name) in row.iter().enumerate() {
+                    if ["", "result", "table"].contains(&name.as_str())
+                        || fields.iter().any(|f| &f.field == name)
+                    {
+                        continue;
+                    }
+
+                    fields.push(Field {
+                        field: name.clone(),
+                        title: name.clone(),
+                        kind: flux_kind(datatypes.get(i).map(|d| d.as_str())),
+                        ..Default::default()
+                    });
+                }
+                header = Some(row);
+                continue;
+            };
+
+            let mut record = Map::new();
+            for (i, (name, raw)) in columns.iter().zip(row.iter()).enumerate() {
+                let value = match flux_kind(datatypes.get(i).map(|d| d.as_str())) {
+                    _ if raw.is_empty() => JsonValue::Null,
+                    FieldType::Integer => {
+                        raw.parse::<i64>().map(JsonValue::from).unwrap_or_default()
+                    }
+                    FieldType::Float => raw.parse::<f64>().map(JsonValue::from).unwrap_or_default(),
+                    _ => JsonValue::from(raw.clone()),
+                };
+                record.insert(name.clone(), value);
+            }
+            records.push(JsonValue::Object(record));
+        }
+    }
+
+    Ok((records, fields))
+}
+
+/// Field type of the Flux datatype annotation
+fn flux_kind(datatype: Option<&str>) -> FieldType {
+    match datatype {
+        Some("long") | Some("unsignedLong") => FieldType::Integer,
+        Some("double") => FieldType::Float,
+        Some(d) if d.starts_with("dateTime") => FieldType::DateTime,
+        _ => FieldType::String,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{flux_records, influxql_records};
+    use crate::{
+        source::{json, Query},
+        value::{FieldType, TypedValue},
+    };
+    use serde_json::json;
+
+    #[test]
+    fn influxql() -> Result<(), String> {
+        let content = json!({
+            "results": [{
+                "statement_id": 0,
+                "series": [{
+                    "name": "temperature",
+                    "tags": {"room": "lab"},
+                    "columns": ["time", "mean"],
+                    "values": [["2024-06-10T00:00:00Z", 21.5], ["2024-06-11T00:00:00Z", 22]]
+                }]
+            }]
+        });
+
+        let (records, fields) = influxql_records(&content)?;
+        assert_eq!(
+            vec![
+                ("room".to_string(), FieldType::String),
+                ("time".to_string(), FieldType::DateTime),
+                ("mean".to_string(), FieldType::Float),
+            ],
+            fields
+                .iter()
+                .map(|f| (f.field.clone(), f.kind.clone()))
+                .collect::<Vec<_>>()
+        );
+
+        let rows = json::to_rows(
+            &records,
+            Query {
+                fields,
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(2, rows.len());
+        assert_eq!(Some(TypedValue::Float(22.0)), rows[1][2].inner);
+
+        let error = json!({"results": [{"statement_id": 0, "error": "database not found: x"}]});
+        assert_eq!(
+            Some("InfluxQL query failed: database not found: x".to_string()),
+            influxql_records(&error).err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn flux() -> Result<(), String> {
+        let content = "#datatype,string,long,dateTime:RFC3339,double,string\r\n\
+            ,result,table,_time,_value,room\r\n\
+            ,_result,0,2024-06-10T00:00:00Z,21.5,lab\r\n\
+            ,_result,0,2024-06-11T00:00:00Z,,lab\r\n\
+            \r\n\
+            #datatype,string,long,dateTime:RFC3339,double,string\r\n\
+            ,result,table,_time,_value,room\r\n\
+            ,_result,1,2024-06-10T00:00:00Z,18,office\r\n";
+
+        let (records, fields) = flux_records(content)?;
+        assert_eq!(3, records.len());
+        assert_eq!(
+            vec![
+                ("_time".to_string(), FieldType::DateTime),
+                ("_value".to_string(), FieldType::Float),
+                ("room".to_string(), FieldType::String),
+            ],
+            fields
+                .iter()
+                .map(|f| (f.field.clone(), f.kind.clone()))
+                .collect::<Vec<_>>()
+        );
+
+        let rows = json::to_rows(
+            &records,
+            Query {
+                fields,
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(Some(TypedValue::Float(21.5)), rows[0][1].inner);
+        assert_eq!(None, rows[1][1].inner);
+        assert_eq!(
+            Some(TypedValue::String("office".to_string())),
+            rows[2][2].inner
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,287 @@
+//! JSON file driver implementation
+//!
+//! The `conn` is the path of a file with an array of objects, or with
+//! one object per line (JSONL). The `field` of the fields is a path
+//! on the objects, like `$.customer.name` or `items[0].price`
+
+use super::{Driver, Query};
+use crate::value::{apply_overrides, Field, FieldType, TypedValue, Value};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, NaiveTime};
+use serde_json::Value as JsonValue;
+use std::fs;
+
+pub struct JsonDriver {
+    pub records: Vec<JsonValue>,
+}
+
+impl JsonDriver {
+    pub fn init() -> Self {
+        Self { records: vec![] }
+    }
+}
+
+#[async_trait]
+impl Driver for JsonDriver {
+    async fn connect(&mut self, sconn: String) -> Result<(), String> {
+        let content = fs::read_to_string(&sconn)
+            .map_err(|e| format!("JSON file {} not opened: {}", sconn, e))?;
+
+        self.records = parse(&content)?;
+
+        Ok(())
+    }
+
+    async fn fetch(&mut self, query: Query) -> Result<Vec<Vec<Value>>, String> {
+        to_rows(&self.records, query)
+    }
+}
+
+/// Rows with the values on the paths of the fields
+pub(super) fn to_rows(records: &[JsonValue], query: Query) -> Result<Vec<Vec<Value>>, String> {
+    let fields = if query.fields.is_empty() {
+        let mut fields = infer_fields(records);
+        apply_overrides(&mut fields, &query.overrides);
+        fields
+    } else {
+        query.fields
+    };
+
+    let mut rows = vec![];
+
+    for (i, record) in records.iter().enumerate() {
+        let mut r = vec![];
+
+        for col in &fields {
+            let inner = resolve(record, &col.field)
+                .map(|v| to_typed(&col.kind, v))
+                .transpose()
+                .map_err(|e| format!("Column {} row {} error: {}", col.field, i, e))?
+                .flatten();
+
+            r.push(Value {
+                inner,
+                field: col.clone(),
+            });
+        }
+
+        rows.push(r);
+    }
+
+    Ok(rows)
+}
+
+/// Records of a JSON array or of JSON lines
+pub(super) fn parse(content: &str) -> Result<Vec<JsonValue>, String> {
+    if content.trim_start().starts_with('[') {
+        return serde_json::from_str(content).map_err(|e| format!("JSON not parsed: {}", e));
+    }
+
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+        .map(|(i, l)| {
+            serde_json::from_str(l).map_err(|e| format!("JSON line {} not parsed: {}", i + 1, e))
+        })
+        .collect()
+}
+
+/// Value on the path, like `$.a.b[0].c`
+pub(super) fn resolve<'a>(record: &'a JsonValue, path: &str) -> Option<&'a JsonValue> {
+    let path = path.trim_start_matches('$').trim_start_matches('.');
+
+    let mut current = record;
+    for part in path.split('.').filter(|p| !p.is_empty()) {
+        let (key, indexes) = match part.find('[') {
+            Some(i) => (&part[..i], &part[i..]),
+            None => (part, ""),
+        };
+
+        if !key.is_empty() {
+            current = current.get(key)?;
+        }
+
+        for idx in indexes.split(['[', ']']).filter(|i| !i.is_empty()) {
+            current = current.get(idx.parse::<usize>().ok()?)?;
+        }
+    }
+
+    Some(current)
+}
+
+/// Value of the JSON as the kind of the field
+fn to_typed(kind: &FieldType, value: &JsonValue) -> Result<Option<TypedValue>, String> {
+    let text = match value {
+        JsonValue::Null => return Ok(None),
+        JsonValue::String(s) => s.clone(),
+        v => v.to_string(),
+    };
+
+    let invalid = |k: &str| format!("Error on parse the {} to {}", text, k);
+
+    let value = match kind {
+        FieldType::String => TypedValue::String(text.clone()),
+        FieldType::Integer => match value {
+            JsonValue::Bool(b) => TypedValue::Integer(i64::from(*b)),
+            _ => TypedValue::Integer(text.parse().map_err(|_| invalid("integer"))?),
+        },
+        FieldType::Float => TypedValue::Float(text.parse().map_err(|_| invalid("float"))?),
+        FieldType::Decimal => TypedValue::parse_decimal(&text)?,
+        FieldType::Date => TypedValue::Date(
+            NaiveDate::parse_from_str(&text, "%Y-%m-%d").map_err(|_| invalid("date"))?,
+        ),
+        FieldType::Time => TypedValue::Time(
+            NaiveTime::parse_from_str(&text, "%H:%M:%S").map_err(|_| invalid("time"))?,
+        ),
+        FieldType::DateTime => TypedValue::DateTime(
+            DateTime::parse_from_rfc3339(&text).map_err(|_| invalid("datetime"))?,
+        ),
+        FieldType::Image => TypedValue::Image(text.into_bytes()),
+        FieldType::Array | FieldType::Json => TypedValue::parse_json(kind, &text)?,
+    };
+
+    Ok(Some(value))
+}
+
+/// Fields by the scalar values of the records, with the
+/// nested objects as dotted paths
+pub(super) fn infer_fields(records: &[JsonValue]) -> Vec<Field> {
+    let mut fields: Vec<Field> = vec![];
+
+    for record in records {
+        let mut paths = vec![];
+        scalar_paths(record, "", &mut paths);
+
+        for (path, value) in paths {
+            let kind = match value {
+                JsonValue::Number(n) if n.is_i64() || n.is_u64() => FieldType::Integer,
+                JsonValue::Number(_) => FieldType::Float,
+                _ => FieldType::String,
+            };
+
+            match fields.iter_mut().find(|f| f.field == path) {
+                Some(f) if f.kind == FieldType::Integer && kind == FieldType::Float => {
+                    f.kind = kind
+                }
+                Some(_) => {}
+                None if value.is_null() => {}
+                None => fields.push(Field {
+                    field: path.clone(),
+                    title: path,
+                    kind,
+                    ..Default::default()
+                }),
+            }
+        }
+    }
+
+    fields
+}
+
+fn scalar_paths<'a>(value: &'a JsonValue, prefix: &str, paths: &mut Vec<(String, &'a JsonValue)>) {
+    match value {
+        JsonValue::Object(map) => {
+            for (k, v) in map {
+                let path = if prefix.is_empty() {
+                    k.clone()
+                } else {
+                    format!("{}.{}", prefix, k)
+                };
+                scalar_paths(v, &path, paths);
+            }
+        }
+        JsonValue::Array(_) => {}
+        v => paths.push((prefix.to_string(), v)),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{parse, resolve};
+    use crate::{
+        source::{json::JsonDriver, Driver, Query},
+        value::{Field, FieldType, TypedValue},
+    };
+    use serde_json::json;
+
+    #[test]
+    fn paths() {
+        let record = json!({"a": {"b": [{"c": 1}, {"c": 2}]}, "d": "x"});
+
+        assert_eq!(Some(&json!(2)), resolve(&record, "$.a.b[1].c"));
+        assert_eq!(Some(&json!("x")), resolve(&record, "d"));
+        assert_eq!(None, resolve(&record, "a.e"));
+        assert_eq!(None, resolve(&record, "a.b[5]"));
+    }
+
+    #[test]
+    fn lines() -> Result<(), String> {
+        let records = parse("{\"a\": 1}\n\n{\"a\": 2}\n")?;
+        assert_eq!(2, records.len());
+
+        let records = parse("[{\"a\": 1}]")?;
+        assert_eq!(1, records.len());
+
+        assert!(parse("{\"a\": 1}\n{a}").is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fetch() -> Result<(), String> {
+        std::fs::write(
+            "/tmp/test-lmr.json",
+            r#"[
+                {"id": 1, "customer": {"name": "Alice"}, "total": 10, "items": [{"sku": "A1"}]},
+                {"id": 2, "customer": {"name": "Bob"}, "total": 12.5, "items": []}
+            ]"#,
+        )
+        .unwrap();
+
+        let mut driver = JsonDriver::init();
+        driver.connect("/tmp/test-lmr.json".to_string()).await?;
+
+        let result = driver
+            .fetch(Query {
+                title: "Test".to_string(),
+                ..Default::default()
+            })
+            .await?;
+
+        let kinds = result[0]
+            .iter()
+            .map(|v| (v.field.field.clone(), v.field.kind.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                ("id".to_string(), FieldType::Integer),
+                ("customer.name".to_string(), FieldType::String),
+                ("total".to_string(), FieldType::Float),
+            ],
+            kinds
+        );
+
+        let result = driver
+            .fetch(Query {
+                title: "Test".to_string(),
+                fields: vec![Field {
+                    title: "First item".to_string(),
+                    field: "$.items[0].sku".to_string(),
+                    kind: FieldType::String,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            })
+            .await?;
+
+        assert_eq!(2, result.len());
+        assert_eq!(
+            Some(TypedValue::String("A1".to_string())),
+            result[0][0].inner
+        );
+        assert_eq!(None, result[1][0].inner);
+
+        Ok(())
+    }
+}
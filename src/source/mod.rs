@@ -1,88 +1,918 @@
 //! Data sources drivers
 
-use crate::value::{Field, Value};
+use crate::{
+    expect::Severity,
+    presentation::attachment::CsvWriter,
+    value::{Field, FieldOverride, Value},
+};
 use async_trait::async_trait;
 use log::*;
 use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
+#[cfg(feature = "bigquery")]
+pub mod bigquery;
+pub mod command;
+pub mod csv;
+pub mod elastic;
+#[cfg(any(feature = "bigquery", feature = "gsheets"))]
+pub mod google;
+#[cfg(feature = "gsheets")]
+pub mod gsheets;
+pub mod http;
+pub mod influx;
+pub mod json;
+#[cfg(feature = "mongo")]
+pub mod mongo;
+#[cfg(feature = "mssql")]
+pub mod mssql;
+#[cfg(feature = "parquet")]
+pub mod parquet;
 #[cfg(feature = "postgres")]
 pub mod postgres;
+pub mod prometheus;
+#[cfg(feature = "redis")]
+pub mod redis;
+pub mod s3;
+pub mod snowflake;
 pub mod sqlite;
+#[cfg(feature = "xlsx")]
+pub mod xlsx;
 
-#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
 pub enum SourceType {
+    #[default]
     Sqlite,
     Postgres,
+    Mssql,
+    Csv,
+    Json,
+    Http,
+    Prometheus,
+    Influx,
+    Elastic,
+    Mongo,
+    BigQuery,
+    Snowflake,
+    Command,
+    GSheets,
+    Xlsx,
+    Parquet,
+    Redis,
 }
 
 #[derive(Clone, Debug, PartialEq, Deserialize)]
 pub struct Source {
     pub kind: SourceType,
     pub conn: String,
+    /// Headers of the requests, on the http based sources
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Basic auth credentials, as user:pass, on the http based sources
+    #[serde(default)]
+    pub auth: Option<String>,
+    /// Bearer token, on the Snowflake source
+    #[serde(default)]
+    pub token: Option<String>,
+    /// Attempts after a failed connection
+    #[serde(default)]
+    pub retries: u32,
+    /// Seconds before the first retry, doubled on each of the next ones. Default 1
+    #[serde(default)]
+    pub retry_delay: Option<u64>,
+    /// Retry the failed querys too
+    #[serde(default)]
+    pub retry_fetch: bool,
+    /// TLS of the connection, on the Postgres source
+    #[serde(default, flatten)]
+    pub ssl: Ssl,
+    /// Run the querys without changes on the data, on the Sqlite,
+    /// Postgres and Mssql sources. Default true
+    #[serde(default = "read_only")]
+    pub read_only: bool,
+    /// Other databases attached on the connection, by their alias, on the Sqlite source
+    #[serde(default)]
+    pub attach: BTreeMap<String, String>,
+    /// Settings of the session, like the `search_path`, on the Postgres source
+    #[serde(default, deserialize_with = "crate::config::params_as_text")]
+    pub session: BTreeMap<String, String>,
+}
+
+fn read_only() -> bool {
+    true
+}
+
+impl Default for Source {
+    fn default() -> Self {
+        Self {
+            kind: SourceType::default(),
+            conn: String::new(),
+            headers: BTreeMap::new(),
+            auth: None,
+            token: None,
+            retries: 0,
+            retry_delay: None,
+            retry_fetch: false,
+            ssl: Ssl::default(),
+            read_only: read_only(),
+            attach: BTreeMap::new(),
+            session: BTreeMap::new(),
+        }
+    }
+}
+
+/// TLS options, named like the libpq ones
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct Ssl {
+    /// The `sslmode` of the conn, without the certificate checks, when omitted
+    #[serde(default)]
+    pub sslmode: Option<SslMode>,
+    /// PEM file of the trusted CAs, the Mozilla ones by default
+    #[serde(default)]
+    pub sslrootcert: Option<String>,
+    /// PEM files of the client certificate and of its key
+    #[serde(default)]
+    pub sslcert: Option<String>,
+    #[serde(default)]
+    pub sslkey: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SslMode {
+    Disable,
+    /// TLS when supported by the server
+    Prefer,
+    Require,
+    /// Require, checking the certificate chain
+    VerifyCa,
+    /// Require, checking the certificate chain and the host name
+    VerifyFull,
+}
+
+impl Source {
+    /// Wait before the retry of the attempt
+    fn backoff(&self, attempt: u32) -> Duration {
+        Duration::from_secs(self.retry_delay.unwrap_or(1)) * 2u32.pow(attempt.min(10))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
 pub struct Query {
     pub sql: String,
     pub title: String,
+    /// Text beneath the heading of the query
+    #[serde(default)]
+    pub description: Option<String>,
+    pub fields: Vec<Field>,
+    /// Changes on the inferred fields, when `fields` is empty
+    #[serde(default)]
+    pub overrides: Vec<FieldOverride>,
+    /// Fetch the rows in pages of this size, the sql must have an ORDER BY
+    #[serde(default)]
+    pub batch_size: Option<usize>,
+    /// Maximum of rows, the fetch stops on it. With the `attach_csv`,
+    /// of the rows kept for the report, the CSV gets all of them
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Values of the `:name` parameters of the sql, bound on the
+    /// prepared statements of the Sqlite, Postgres and Mssql sources
+    #[serde(default)]
+    pub params: BTreeMap<String, String>,
+    /// Source of this query, in place of the config one
+    #[serde(default)]
+    pub source: Option<Source>,
+    /// Querys materialized as tables of a in memory SQLite, where
+    /// the `sql` of this query runs
+    #[serde(default)]
+    pub joins: Vec<Join>,
+    /// Text presented in place of a result, nothing is fetched
+    #[serde(default)]
+    pub text: Option<String>,
+    /// Of the failures, listed at the top of the report
+    #[serde(default)]
+    pub severity: Severity,
+    /// Result attached to the report as a CSV file
+    #[serde(default)]
+    pub attach_csv: bool,
+    /// CSV of the `attach_csv`, written on the fetch chunk by chunk
+    #[serde(skip)]
+    pub csv: Option<Arc<Vec<u8>>>,
+}
+
+/// Query of a source materialized as a table
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct Join {
+    pub table: String,
+    #[serde(default)]
+    pub sql: String,
+    #[serde(default)]
+    pub source: Option<Source>,
+    /// Columns of the table, needed when the result can be empty
+    #[serde(default)]
     pub fields: Vec<Field>,
 }
 
+/// Query with the fetched rows or the error
+pub type QueryResult = (Query, Result<Vec<Vec<Value>>, String>);
+
+/// Receiver of the fetched rows, chunk by chunk. Returns false to stop the fetch
+pub type OnChunk<'a> = dyn FnMut(Vec<Vec<Value>>) -> Result<bool, String> + Send + 'a;
+
+/// Changes on the fetched rows, like the transforms, applied chunk by chunk
+pub type Prepare<'a> = dyn Fn(Vec<Vec<Value>>) -> Vec<Vec<Value>> + Sync + 'a;
+
+/// Rows kept of the fetch and the CSV written with all of them
+type Fetched = (Vec<Vec<Value>>, Option<Vec<u8>>);
+
+/// Rows of each chunk of the streamed fetchs
+const CHUNK_SIZE: usize = 1000;
+
+/// Time a connection can stay idle on the pool
+const POOL_IDLE: Duration = Duration::from_secs(300);
+
+/// Idle connections of the reusable drivers, with the source and when they were released
+type Pooled = (Source, Instant, Box<dyn Driver + Send>);
+static POOL: Mutex<Vec<Pooled>> = Mutex::new(Vec::new());
+
 /// Data source driver definitions
 #[async_trait]
 pub trait Driver {
     // Establish the connection and prepare for fetch
     async fn connect(&mut self, conn: String) -> Result<(), String>;
 
+    // If the connection can be kept on the pool, for the next fetchs
+    // of the same source. The file based drivers read the data on connect
+    fn reusable(&self) -> bool {
+        false
+    }
+
     // Query and fetch the data
     async fn fetch(&mut self, query: Query) -> Result<Vec<Vec<Value>>, String>;
+
+    // Sql of a page of the query, with the `size` rows after the `offset`.
+    // None for the sources that can't paginate, like the file based ones
+    fn paginate(&self, _sql: &str, _size: usize, _offset: usize) -> Option<String> {
+        None
+    }
+
+    // Query and stream the data, in chunks of the size, until the receiver
+    // stops it. By default all the rows are fetched as a single chunk
+    async fn fetch_chunks(
+        &mut self,
+        query: Query,
+        _size: usize,
+        on_chunk: &mut OnChunk,
+    ) -> Result<(), String> {
+        let rows = self.fetch(query).await?;
+        on_chunk(rows).map(|_| ())
+    }
+}
+
+impl Query {
+    /// Sql with the `:name` parameters replaced by the placeholders of their
+    /// positions, and the values of each position. The strings and comments
+    /// are kept as they are. Untouched without params
+    pub fn bind_params(
+        &self,
+        placeholder: impl Fn(usize) -> String,
+    ) -> Result<(String, Vec<String>), String> {
+        if self.params.is_empty() {
+            return Ok((self.sql.clone(), vec![]));
+        }
+
+        let mut sql = String::new();
+        let mut names: Vec<String> = vec![];
+        let mut quote = None;
+        let mut chars = self.sql.chars().peekable();
+        let mut prev = ' ';
+
+        while let Some(c) = chars.next() {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '\'' | '"') => quote = Some(c),
+                (None, '-') if chars.peek() == Some(&'-') => {
+                    sql.push(c);
+                    for c in chars.by_ref() {
+                        sql.push(c);
+                        if c == '\n' {
+                            break;
+                        }
+                    }
+                    prev = ' ';
+                    continue;
+                }
+                (None, '/') if chars.peek() == Some(&'*') => {
+                    sql.push(c);
+                    let mut last = ' ';
+                    for c in chars.by_ref() {
+                        sql.push(c);
+                        if last == '*' && c == '/' {
+                            break;
+                        }
+                        last = c;
+                    }
+                    prev = ' ';
+                    continue;
+                }
+                (None, ':')
+                    if prev != ':'
+                        && chars
+                            .peek()
+                            .is_some_and(|n| n.is_ascii_alphabetic() || *n == '_') =>
+                {
+                    let mut name = String::new();
+                    while let Some(n) = chars.next_if(|n| n.is_ascii_alphanumeric() || *n == '_') {
+                        name.push(n);
+                    }
+
+                    let pos = match names.iter().position(|n| *n == name) {
+                        Some(pos) => pos,
+                        None => {
+                            names.push(name.clone());
+                            names.len() - 1
+                        }
+                    };
+
+                    sql.push_str(&placeholder(pos + 1));
+                    prev = name.chars().last().unwrap_or(c);
+                    continue;
+                }
+                _ => {}
+            }
+
+            sql.push(c);
+            prev = c;
+        }
+
+        let values = names
+            .iter()
+            .map(|n| {
+                self.params
+                    .get(n)
+                    .cloned()
+                    .ok_or(format!("Parameter {} not informed", n))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((sql, values))
+    }
+
+    /// If the main statement of the sql has an ORDER BY, out of the
+    /// subquerys and the window functions
+    pub fn is_ordered(&self) -> bool {
+        let mut depth = 0;
+        let mut words = vec![];
+        let mut word = String::new();
+
+        for c in self.code().chars() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                c if depth == 0 && (c.is_alphanumeric() || c == '_') => {
+                    word.push(c.to_ascii_lowercase());
+                    continue;
+                }
+                _ => {}
+            }
+
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+        }
+        words.push(word);
+
+        words.windows(2).any(|w| w == ["order", "by"])
+    }
+
+    /// Sql without the strings and the comments
+    fn code(&self) -> String {
+        let mut code = String::new();
+        let mut quote = None;
+        let mut chars = self.sql.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '\'' | '"') => quote = Some(c),
+                (None, '[') => quote = Some(']'),
+                (None, '-') if chars.peek() == Some(&'-') => {
+                    chars.by_ref().find(|c| *c == '\n');
+                    code.push(' ');
+                }
+                (None, '/') if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    let mut last = ' ';
+                    chars.by_ref().find(|c| {
+                        let end = last == '*' && *c == '/';
+                        last = *c;
+                        end
+                    });
+                    code.push(' ');
+                }
+                (None, c) => code.push(c),
+            }
+        }
+
+        code
+    }
+
+    /// If the sql is a single select, without the words of the statements
+    /// that change the data, like the `SELECT ... INTO`. The comments are
+    /// ignored and the `WITH` must be followed by a select
+    pub fn is_select(&self) -> bool {
+        const CHANGES: [&str; 12] = [
+            "insert", "update", "delete", "merge", "drop", "alter", "create", "truncate", "exec",
+            "execute", "grant", "into",
+        ];
+
+        let code = self.code();
+        let code = code.trim().trim_end_matches(';');
+        if code.contains(';') {
+            return false;
+        }
+
+        let words = code
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .filter(|w| !w.is_empty())
+            .map(|w| w.to_lowercase())
+            .collect::<Vec<_>>();
+
+        let statement = match words.first().map(|w| w.as_str()) {
+            Some("with") => main_statement(code),
+            first => first.map(|w| w.to_string()),
+        };
+
+        statement.as_deref() == Some("select")
+            && !words.iter().any(|w| CHANGES.contains(&w.as_str()))
+    }
 }
 
 /// Setup the driver of specified kind
 #[allow(unreachable_patterns)]
-fn get_driver(kind: SourceType) -> Result<Box<dyn Driver + Send>, String> {
-    debug!("Preparing the driver for {:?}", kind);
+fn get_driver(source: &Source) -> Result<Box<dyn Driver + Send>, String> {
+    debug!("Preparing the driver for {:?}", source.kind);
 
-    match kind {
-        SourceType::Sqlite => Ok(Box::new(sqlite::SqliteDriver::init())),
+    match source.kind {
+        SourceType::Sqlite => {
+            let mut driver = sqlite::SqliteDriver::init();
+            driver.read_only = source.read_only;
+            driver.attach = source.attach.clone();
+            Ok(Box::new(driver))
+        }
         #[cfg(feature = "postgres")]
-        SourceType::Postgres => Ok(Box::new(postgres::PostgresDriver::init())),
+        SourceType::Postgres => {
+            let mut driver = postgres::PostgresDriver::init(source.ssl.clone());
+            driver.read_only = source.read_only;
+            driver.session = source.session.clone();
+            Ok(Box::new(driver))
+        }
+        #[cfg(feature = "mssql")]
+        SourceType::Mssql => {
+            let mut driver = mssql::MssqlDriver::init();
+            driver.read_only = source.read_only;
+            Ok(Box::new(driver))
+        }
+        SourceType::Csv => Ok(Box::new(csv::CsvDriver::init())),
+        SourceType::Json => Ok(Box::new(json::JsonDriver::init())),
+        SourceType::Http => Ok(Box::new(http::HttpDriver::init(
+            source.headers.clone(),
+            source.auth.clone(),
+        ))),
+        SourceType::Prometheus => Ok(Box::new(prometheus::PrometheusDriver::init(
+            source.headers.clone(),
+            source.auth.clone(),
+        ))),
+        SourceType::Influx => Ok(Box::new(influx::InfluxDriver::init(
+            source.headers.clone(),
+            source.auth.clone(),
+        ))),
+        SourceType::Elastic => Ok(Box::new(elastic::ElasticDriver::init(
+            source.headers.clone(),
+            source.auth.clone(),
+        ))),
+        #[cfg(feature = "mongo")]
+        SourceType::Mongo => Ok(Box::new(mongo::MongoDriver::init())),
+        #[cfg(feature = "bigquery")]
+        SourceType::BigQuery => Ok(Box::new(bigquery::BigQueryDriver::init())),
+        SourceType::Snowflake => Ok(Box::new(snowflake::SnowflakeDriver::init(
+            source.headers.clone(),
+            source.token.clone(),
+        ))),
+        SourceType::Command => Ok(Box::new(command::CommandDriver::init())),
+        #[cfg(feature = "gsheets")]
+        SourceType::GSheets => Ok(Box::new(gsheets::GSheetsDriver::init())),
+        #[cfg(feature = "xlsx")]
+        SourceType::Xlsx => Ok(Box::new(xlsx::XlsxDriver::init())),
+        #[cfg(feature = "parquet")]
+        SourceType::Parquet => Ok(Box::new(parquet::ParquetDriver::init())),
+        #[cfg(feature = "redis")]
+        SourceType::Redis => Ok(Box::new(redis::RedisDriver::init())),
         _ => Err("Not supported kind".to_string()),
     }
 }
 
-/// Query and fetch the data from the database
-pub async fn fetch(
+/// First word of the statement after the common table expressions of a
+/// `WITH`, the one after a closing parenthesis that isn't followed by another
+/// expression
+fn main_statement(code: &str) -> Option<String> {
+    let mut depth = 0;
+    let mut closed = false;
+    let mut rest = code.char_indices();
+
+    while let Some((i, c)) = rest.next() {
+        match c {
+            '(' => {
+                depth += 1;
+                closed = false;
+            }
+            ')' => {
+                depth -= 1;
+                closed = depth == 0;
+            }
+            ',' if depth == 0 => closed = false,
+            c if depth == 0 && closed && (c.is_alphanumeric() || c == '_') => {
+                let word = code[i..]
+                    .split(|c: char| !c.is_alphanumeric() && c != '_')
+                    .next()
+                    .unwrap_or_default()
+                    .to_lowercase();
+                if word != "as" {
+                    return Some(word);
+                }
+                closed = false;
+                rest.by_ref().take(word.chars().count() - 1).for_each(drop);
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Query and fetch the data from the databases, with one
+/// connection per source. The results keep the querys order and
+/// the querys of a source not connected are failed
+pub async fn fetch(source: Source, querys: Vec<Query>) -> Result<Vec<QueryResult>, String> {
+    fetch_with(source, querys, &|rows| rows).await
+}
+
+/// Fetch the querys, with the changes applied on the rows as they arrive
+pub async fn fetch_with(
     source: Source,
     querys: Vec<Query>,
-) -> Result<Vec<(Query, Result<Vec<Vec<Value>>, String>)>, String> {
-    let mut driver = get_driver(source.kind)?;
+    prepare: &Prepare<'_>,
+) -> Result<Vec<QueryResult>, String> {
+    let mut groups: Vec<(Source, Vec<(usize, Query)>)> = vec![];
+    let mut r = vec![];
 
-    info!("Connecting on database");
+    for (i, mut query) in querys.into_iter().enumerate() {
+        if query.text.is_some() {
+            r.push((i, (query, Ok(vec![]))));
+            continue;
+        }
 
-    driver.connect(source.conn).await?;
+        if !query.joins.is_empty() {
+            info!("Fetching '{}' joined query", query.title);
 
-    debug!("Database connected");
+            let result = fetch_joined(&source, query.clone()).await.map(prepare);
+            if query.fields.is_empty() {
+                if let Some(row) = result.as_ref().ok().and_then(|rows| rows.first()) {
+                    query.fields = row.iter().map(|v| v.field.clone()).collect();
+                }
+            }
 
-    let mut r = vec![];
+            r.push((i, (query, result)));
+            continue;
+        }
+
+        let qsource = query.source.clone().unwrap_or(source.clone());
+
+        match groups.iter_mut().find(|(s, _)| *s == qsource) {
+            Some((_, gquerys)) => gquerys.push((i, query)),
+            None => groups.push((qsource, vec![(i, query)])),
+        }
+    }
+
+    for (source, gquerys) in groups {
+        // Only the querys of the source fail, the others are still fetched
+        let (mut driver, mut pooled) = match connect(&source).await {
+            Ok(connected) => connected,
+            Err(e) => {
+                warn!("Connection on {:?} database failed: {}", source.kind, e);
+                for (i, query) in gquerys {
+                    r.push((i, (query, Err(format!("Connection failed: {}", e)))));
+                }
+                continue;
+            }
+        };
+        let mut failed = false;
 
-    for query in querys {
-        info!("Fetching '{}' query", query.title);
+        for (i, mut query) in gquerys {
+            info!("Fetching '{}' query", query.title);
 
-        let result = driver.fetch(query.clone()).await;
+            let mut result = fetch_retrying(&mut driver, &source, &query, prepare).await;
 
-        r.push((query, result));
+            // The pooled connection can be closed by the server while idle,
+            // so its first failure is retried once on a new one
+            if pooled {
+                pooled = false;
+                if let Err(e) = &result {
+                    warn!(
+                        "Query '{}' failed on a pooled connection, reconnecting: {}",
+                        query.title, e
+                    );
+                    if let Ok(fresh) = open(&source).await {
+                        driver = fresh;
+                        result = fetch_retrying(&mut driver, &source, &query, prepare).await;
+                    }
+                }
+            }
+
+            let result = result.map(|(rows, csv)| {
+                query.csv = csv.map(Arc::new);
+                rows
+            });
+
+            if query.fields.is_empty() {
+                if let Some(row) = result.as_ref().ok().and_then(|rows| rows.first()) {
+                    query.fields = row.iter().map(|v| v.field.clone()).collect();
+                }
+            }
+
+            failed |= result.is_err();
+            r.push((i, (query, result)));
+        }
+
+        // A failed query can leave the connection on an unknown state
+        if !failed {
+            checkin(source, driver);
+        }
     }
 
-    Ok(r)
+    r.sort_by_key(|(i, _)| *i);
+
+    Ok(r.into_iter().map(|(_, r)| r).collect())
+}
+
+/// Fetch the query, retrying the failures when the source allows
+async fn fetch_retrying(
+    driver: &mut Box<dyn Driver + Send>,
+    source: &Source,
+    query: &Query,
+    prepare: &Prepare<'_>,
+) -> Result<Fetched, String> {
+    let mut attempt = 0;
+
+    loop {
+        let result = match query.batch_size {
+            Some(size) => fetch_batched(driver, query, size, prepare).await,
+            None => fetch_streamed(driver, query, prepare).await,
+        };
+
+        match result {
+            Err(e) if source.retry_fetch && attempt < source.retries => {
+                let delay = source.backoff(attempt);
+                warn!(
+                    "Query '{}' failed, retrying in {}s: {}",
+                    query.title,
+                    delay.as_secs(),
+                    e
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Driver connected on the source, and if it was on the pool
+async fn connect(source: &Source) -> Result<(Box<dyn Driver + Send>, bool), String> {
+    if let Some(driver) = checkout(source) {
+        debug!("Reusing a {:?} connection of the pool", source.kind);
+        return Ok((driver, true));
+    }
+
+    open(source).await.map(|driver| (driver, false))
+}
+
+/// New connection on the source, retrying the failed ones
+async fn open(source: &Source) -> Result<Box<dyn Driver + Send>, String> {
+    let mut attempt = 0;
+
+    loop {
+        let mut driver = get_driver(source)?;
+
+        info!("Connecting on {:?} database", source.kind);
+
+        match driver.connect(source.conn.clone()).await {
+            Ok(()) => {
+                debug!("Database connected");
+                return Ok(driver);
+            }
+            Err(e) if attempt < source.retries => {
+                let delay = source.backoff(attempt);
+                warn!("Connection failed, retrying in {}s: {}", delay.as_secs(), e);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Idle connection of the source, when on the pool
+fn checkout(source: &Source) -> Option<Box<dyn Driver + Send>> {
+    let mut pool = POOL.lock().ok()?;
+    pool.retain(|(_, released, driver)| released.elapsed() < POOL_IDLE && driver.reusable());
+
+    let pos = pool.iter().position(|(s, _, _)| s == source)?;
+    Some(pool.swap_remove(pos).2)
+}
+
+/// Keep the connection on the pool, when reusable
+fn checkin(source: Source, driver: Box<dyn Driver + Send>) {
+    if !driver.reusable() {
+        return;
+    }
+
+    if let Ok(mut pool) = POOL.lock() {
+        pool.push((source, Instant::now(), driver));
+    }
+}
+
+/// Materialize the joined querys on a in memory SQLite and run the query over them
+async fn fetch_joined(source: &Source, query: Query) -> Result<Vec<Vec<Value>>, String> {
+    let jquerys = query
+        .joins
+        .iter()
+        .map(|j| Query {
+            title: format!("{} ({})", query.title, j.table),
+            sql: j.sql.clone(),
+            source: j.source.clone(),
+            fields: j.fields.clone(),
+            params: query.params.clone(),
+            ..Default::default()
+        })
+        .collect();
+
+    let results = Box::pin(fetch(source.clone(), jquerys)).await?;
+
+    let mut driver = sqlite::SqliteDriver::init();
+    driver.connect(":memory:".to_string()).await?;
+    let conn = driver
+        .conn
+        .as_ref()
+        .ok_or("Connection not established".to_string())?;
+
+    for (join, (jquery, rows)) in query.joins.iter().zip(results) {
+        let rows = rows.map_err(|e| format!("Join {} failed: {}", join.table, e))?;
+
+        debug!("Materializing {} rows on {}", rows.len(), join.table);
+
+        sqlite::materialize(conn, &join.table, &jquery.fields, &rows)?;
+    }
+
+    driver
+        .fetch(Query {
+            joins: vec![],
+            ..query
+        })
+        .await
+}
+
+/// Fetch the query in chunks, stopping the stream on the limit. The
+/// chunks are written on the CSV of the `attach_csv`, without the limit
+async fn fetch_streamed(
+    driver: &mut Box<dyn Driver + Send>,
+    query: &Query,
+    prepare: &Prepare<'_>,
+) -> Result<Fetched, String> {
+    let max = query.limit.unwrap_or(usize::MAX);
+    let mut csv = query.attach_csv.then(|| CsvWriter::new(query));
+    let mut rows = vec![];
+
+    let size = match csv {
+        Some(_) => CHUNK_SIZE,
+        None => CHUNK_SIZE.min(max.max(1)),
+    };
+
+    driver
+        .fetch_chunks(query.clone(), size, &mut |chunk| {
+            let chunk = prepare(chunk);
+            if let Some(csv) = &mut csv {
+                csv.write(&chunk)?;
+            }
+
+            let remaining = max - rows.len();
+            rows.extend(chunk.into_iter().take(remaining));
+            Ok(rows.len() < max || csv.is_some())
+        })
+        .await?;
+
+    Ok((rows, csv.map(|csv| csv.finish()).transpose()?))
+}
+
+/// Page of the sql with LIMIT/OFFSET, of the most databases
+fn limit_offset(sql: &str, size: usize, offset: usize) -> String {
+    format!(
+        "select * from ({}) lmr_batch limit {} offset {}",
+        sql, size, offset
+    )
+}
+
+/// Fetch the query in pages of the driver, the sql must be ordered to keep
+/// them stable. The batches are written on the CSV of the `attach_csv`,
+/// without the limit
+async fn fetch_batched(
+    driver: &mut Box<dyn Driver + Send>,
+    query: &Query,
+    size: usize,
+    prepare: &Prepare<'_>,
+) -> Result<Fetched, String> {
+    if size == 0 {
+        return Err("Batch size must be greater than zero".to_string());
+    }
+    if !query.is_ordered() {
+        return Err("Batches need an ORDER BY on the sql, to keep the pages stable".to_string());
+    }
+
+    let sql = query.sql.trim().trim_end_matches(';');
+
+    let max = query.limit.unwrap_or(usize::MAX);
+    let mut csv = query.attach_csv.then(|| CsvWriter::new(query));
+    let mut rows = vec![];
+    let mut offset = 0;
+
+    loop {
+        let size = match csv {
+            Some(_) => size,
+            None => size.min(max - offset),
+        };
+
+        debug!("Fetching '{}' batch at offset {}", query.title, offset);
+
+        let mut bquery = query.clone();
+        bquery.sql = driver
+            .paginate(sql, size, offset)
+            .ok_or("Batches not supported by the source, remove the batch_size".to_string())?;
+
+        let batch = prepare(driver.fetch(bquery).await?);
+        let len = batch.len();
+        offset += len;
+
+        if let Some(csv) = &mut csv {
+            csv.write(&batch)?;
+        }
+
+        let remaining = max - rows.len();
+        rows.extend(batch.into_iter().take(remaining));
+
+        if len < size || (rows.len() >= max && csv.is_none()) {
+            break;
+        }
+    }
+
+    Ok((rows, csv.map(|csv| csv.finish()).transpose()?))
 }
 
 #[cfg(test)]
 pub mod tests {
     use crate::{
-        source::{Query, Source, SourceType},
-        value::{Field, FieldType, TypedValue},
+        source::{checkin, checkout, Driver, Join, Query, Source, SourceType, CHUNK_SIZE},
+        value::{Field, FieldType, TypedValue, Value},
     };
+    use async_trait::async_trait;
+    use std::collections::BTreeMap;
+
+    struct PooledDriver {
+        reusable: bool,
+        closed: bool,
+    }
+
+    #[async_trait]
+    impl Driver for PooledDriver {
+        async fn connect(&mut self, _conn: String) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn reusable(&self) -> bool {
+            self.reusable
+        }
+
+        async fn fetch(&mut self, _query: Query) -> Result<Vec<Vec<Value>>, String> {
+            match self.closed {
+                true => Err("Connection closed".to_string()),
+                false => Ok(vec![]),
+            }
+        }
+    }
 
     #[tokio::test]
     async fn fetch() -> Result<(), String> {
@@ -103,6 +933,7 @@ pub mod tests {
         let source = Source {
             conn: "/tmp/test-lmr.db".to_string(),
             kind: SourceType::Sqlite,
+            ..Default::default()
         };
 
         let query = Query {
@@ -113,13 +944,16 @@ pub mod tests {
                     title: "User name".to_string(),
                     field: "name".to_string(),
                     kind: FieldType::String,
+                    ..Default::default()
                 },
                 Field {
                     title: "Age".to_string(),
                     field: "age".to_string(),
                     kind: FieldType::Integer,
+                    ..Default::default()
                 },
             ],
+            ..Default::default()
         };
 
         let results = super::fetch(source, vec![query.clone()]).await?;
@@ -165,6 +999,7 @@ pub mod tests {
         let source = Source {
             conn: "/tmp/test-lmr2.db".to_string(),
             kind: SourceType::Sqlite,
+            ..Default::default()
         };
 
         let query1 = Query {
@@ -174,7 +1009,9 @@ pub mod tests {
                 title: "User name".to_string(),
                 field: "name".to_string(),
                 kind: FieldType::String,
+                ..Default::default()
             }],
+            ..Default::default()
         };
 
         let query2 = Query {
@@ -184,7 +1021,9 @@ pub mod tests {
                 title: "User name".to_string(),
                 field: "name".to_string(),
                 kind: FieldType::String,
+                ..Default::default()
             }],
+            ..Default::default()
         };
 
         let querys = vec![query1.clone(), query2.clone(), query1.clone()];
@@ -209,4 +1048,569 @@ pub mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn fetch_batched() -> Result<(), String> {
+        let query = "
+            drop table if exists users;
+            CREATE TABLE users (name TEXT, age INTEGER);
+            INSERT INTO users VALUES ('Alice', 42);
+            INSERT INTO users VALUES ('Bob', 69);
+            INSERT INTO users VALUES ('Carol', 31);
+            INSERT INTO users VALUES ('Dave', 25);
+            INSERT INTO users VALUES ('Eve', 50);
+        ";
+        sqlite::Connection::open_with_flags(
+            "/tmp/test-lmr3.db",
+            sqlite::OpenFlags::new().with_create().with_read_write(),
+        )
+        .unwrap()
+        .execute(query)
+        .unwrap();
+
+        let source = Source {
+            conn: "/tmp/test-lmr3.db".to_string(),
+            kind: SourceType::Sqlite,
+            ..Default::default()
+        };
+
+        let query = Query {
+            title: "Test".to_string(),
+            sql: "select * from users order by age;".to_string(),
+            fields: vec![Field {
+                title: "Age".to_string(),
+                field: "age".to_string(),
+                kind: FieldType::Integer,
+                ..Default::default()
+            }],
+            batch_size: Some(2),
+            ..Default::default()
+        };
+
+        let results = super::fetch(source.clone(), vec![query.clone()]).await?;
+        let result = results[0].1.clone()?;
+
+        assert_eq!(5, result.len());
+        assert_eq!(Some(TypedValue::Integer(25)), result[0][0].inner);
+        assert_eq!(Some(TypedValue::Integer(69)), result[4][0].inner);
+
+        let query = Query {
+            limit: Some(3),
+            ..query
+        };
+
+        let results = super::fetch(source.clone(), vec![query.clone()]).await?;
+        assert_eq!(3, results[0].1.clone()?.len());
+
+        let query = Query {
+            batch_size: None,
+            ..query
+        };
+
+        let results = super::fetch(source.clone(), vec![query]).await?;
+        assert_eq!(3, results[0].1.clone()?.len());
+
+        // Without a limit, all the rows of the batches and of the chunks
+        let total = CHUNK_SIZE * 2 + 10;
+        let query = Query {
+            title: "Test".to_string(),
+            sql: format!(
+                "with recursive seq(n) as (select 1 union all select n + 1 from seq limit {})
+                select n from seq order by n",
+                total
+            ),
+            batch_size: Some(CHUNK_SIZE),
+            ..Default::default()
+        };
+
+        let results = super::fetch(source.clone(), vec![query.clone()]).await?;
+        let rows = results[0].1.clone()?;
+        assert_eq!(total, rows.len());
+        assert_eq!(
+            Some(TypedValue::Integer(total as i64)),
+            rows[total - 1][0].inner
+        );
+
+        let query = Query {
+            limit: Some(total - 5),
+            ..query
+        };
+        let results = super::fetch(source.clone(), vec![query.clone()]).await?;
+        assert_eq!(total - 5, results[0].1.clone()?.len());
+
+        let query = Query {
+            limit: None,
+            batch_size: None,
+            ..query
+        };
+        let results = super::fetch(source.clone(), vec![query.clone()]).await?;
+        assert_eq!(total, results[0].1.clone()?.len());
+
+        // The pages need the order
+        let query = Query {
+            sql: "select * from users".to_string(),
+            batch_size: Some(2),
+            ..query
+        };
+        let results = super::fetch(source, vec![query.clone()]).await?;
+        assert_eq!(
+            Some("Batches need an ORDER BY on the sql, to keep the pages stable".to_string()),
+            results[0].1.clone().err()
+        );
+
+        // Only on the sources with pages
+        std::fs::write("/tmp/test-lmr-batched.csv", "name,age\nAlice,42\n").unwrap();
+        let source = Source {
+            conn: "/tmp/test-lmr-batched.csv".to_string(),
+            kind: SourceType::Csv,
+            ..Default::default()
+        };
+        let query = Query {
+            sql: "select * from users order by age".to_string(),
+            ..query
+        };
+        let results = super::fetch(source, vec![query]).await?;
+        assert_eq!(
+            Some("Batches not supported by the source, remove the batch_size".to_string()),
+            results[0].1.clone().err()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fetch_csv() -> Result<(), String> {
+        let source = Source {
+            conn: ":memory:".to_string(),
+            kind: SourceType::Sqlite,
+            ..Default::default()
+        };
+
+        let total = CHUNK_SIZE + 10;
+        let query = Query {
+            title: "Test".to_string(),
+            sql: format!(
+                "with recursive seq(n) as (select 1 union all select n + 1 from seq limit {})
+                select n from seq order by n",
+                total
+            ),
+            limit: Some(3),
+            attach_csv: true,
+            ..Default::default()
+        };
+
+        // Only the limit on the rows, the CSV with all of them prepared
+        let prepare = |rows: Vec<Vec<Value>>| {
+            rows.into_iter()
+                .map(|row| {
+                    row.into_iter()
+                        .map(|mut v| {
+                            if let Some(TypedValue::Integer(n)) = v.inner {
+                                v.inner = Some(TypedValue::Integer(n * 10));
+                            }
+                            v
+                        })
+                        .collect()
+                })
+                .collect()
+        };
+
+        for batch_size in [None, Some(400)] {
+            let query = Query {
+                batch_size,
+                ..query.clone()
+            };
+            let results = super::fetch_with(source.clone(), vec![query], &prepare).await?;
+            let (rquery, rows) = &results[0];
+            let rows = rows.clone()?;
+
+            assert_eq!(3, rows.len());
+            assert_eq!(Some(TypedValue::Integer(30)), rows[2][0].inner);
+
+            let csv = String::from_utf8(rquery.csv.as_deref().cloned().unwrap_or_default())
+                .map_err(|e| e.to_string())?;
+            let lines = csv.lines().collect::<Vec<_>>();
+            assert_eq!(total + 1, lines.len());
+            assert_eq!(vec!["n", "10", "20"], lines[..3]);
+            assert_eq!(format!("{}", total * 10), lines[total]);
+        }
+
+        let query = Query {
+            attach_csv: false,
+            ..query
+        };
+        let results = super::fetch(source, vec![query]).await?;
+        assert_eq!(None, results[0].0.csv);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fetch_failed_source() -> Result<(), String> {
+        sqlite::Connection::open_with_flags(
+            "/tmp/test-lmr-failed-source.db",
+            sqlite::OpenFlags::new().with_create().with_read_write(),
+        )
+        .unwrap()
+        .execute(
+            "drop table if exists users;
+            CREATE TABLE users (name TEXT);
+            INSERT INTO users VALUES ('Alice');",
+        )
+        .unwrap();
+
+        let source = Source {
+            conn: "/tmp/test-lmr-failed-source.db".to_string(),
+            kind: SourceType::Sqlite,
+            ..Default::default()
+        };
+
+        let query = Query {
+            title: "Test".to_string(),
+            sql: "select name from users".to_string(),
+            ..Default::default()
+        };
+        let bad = Query {
+            source: Some(Source {
+                conn: "/tmp/lmr-missing-dir/test.db".to_string(),
+                ..source.clone()
+            }),
+            ..query.clone()
+        };
+
+        let results = super::fetch(source, vec![bad.clone(), query, bad]).await?;
+        assert_eq!(3, results.len());
+
+        let err = results[0].1.clone().err().unwrap_or_default();
+        assert!(err.starts_with("Connection failed: "), "{}", err);
+        assert_eq!(results[0].1, results[2].1);
+
+        let rows = results[1].1.clone()?;
+        assert_eq!(
+            Some(TypedValue::String("Alice".to_string())),
+            rows[0][0].inner
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fetch_multiple_sources() -> Result<(), String> {
+        for (path, name) in [("/tmp/test-lmr4.db", "Alice"), ("/tmp/test-lmr5.db", "Bob")] {
+            sqlite::Connection::open_with_flags(
+                path,
+                sqlite::OpenFlags::new().with_create().with_read_write(),
+            )
+            .unwrap()
+            .execute(format!(
+                "drop table if exists users;
+                CREATE TABLE users (name TEXT);
+                INSERT INTO users VALUES ('{}');",
+                name
+            ))
+            .unwrap();
+        }
+
+        let source = Source {
+            conn: "/tmp/test-lmr4.db".to_string(),
+            kind: SourceType::Sqlite,
+            ..Default::default()
+        };
+
+        let query = Query {
+            title: "Test".to_string(),
+            sql: "select name from users".to_string(),
+            ..Default::default()
+        };
+        let other = Query {
+            source: Some(Source {
+                conn: "/tmp/test-lmr5.db".to_string(),
+                ..source.clone()
+            }),
+            ..query.clone()
+        };
+
+        let results = super::fetch(source, vec![other.clone(), query, other]).await?;
+        let names = results
+            .iter()
+            .map(|(_, r)| r.clone().map(|rows| rows[0][0].inner.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        assert_eq!(
+            vec![
+                Some(TypedValue::String("Bob".to_string())),
+                Some(TypedValue::String("Alice".to_string())),
+                Some(TypedValue::String("Bob".to_string())),
+            ],
+            names
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fetch_joined() -> Result<(), String> {
+        sqlite::Connection::open_with_flags(
+            "/tmp/test-lmr6.db",
+            sqlite::OpenFlags::new().with_create().with_read_write(),
+        )
+        .unwrap()
+        .execute(
+            "drop table if exists users;
+            CREATE TABLE users (id INTEGER, name TEXT);
+            INSERT INTO users VALUES (1, 'Alice');
+            INSERT INTO users VALUES (2, 'Bob');",
+        )
+        .unwrap();
+        std::fs::write("/tmp/test-lmr-orders.csv", "user,total\n1,10.5\n2,3\n1,4\n").unwrap();
+
+        let query = Query {
+            title: "Test".to_string(),
+            sql: "select u.name, sum(o.total) as total
+                    from users u join orders o on o.user = u.id
+                  group by 1 order by 1"
+                .to_string(),
+            joins: vec![
+                Join {
+                    table: "users".to_string(),
+                    sql: "select * from users".to_string(),
+                    ..Default::default()
+                },
+                Join {
+                    table: "orders".to_string(),
+                    source: Some(Source {
+                        kind: SourceType::Csv,
+                        conn: "/tmp/test-lmr-orders.csv".to_string(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let source = Source {
+            conn: "/tmp/test-lmr6.db".to_string(),
+            ..Default::default()
+        };
+
+        let results = super::fetch(source, vec![query]).await?;
+        let (rquery, result) = &results[0];
+        let result = result.clone()?;
+
+        assert_eq!(2, rquery.fields.len());
+        assert_eq!(2, result.len());
+        assert_eq!(
+            Some(TypedValue::String("Alice".to_string())),
+            result[0][0].inner
+        );
+        assert_eq!(Some(TypedValue::Float(14.5)), result[0][1].inner);
+        assert_eq!(Some(TypedValue::Float(3.0)), result[1][1].inner);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fetch_with_retries() -> Result<(), String> {
+        let _ = std::fs::remove_dir_all("/tmp/test-lmr-retry");
+
+        let source = Source {
+            conn: "/tmp/test-lmr-retry/test.db".to_string(),
+            retries: 2,
+            retry_delay: Some(1),
+            // Creating the database, once the dir exists
+            read_only: false,
+            ..Default::default()
+        };
+        let query = Query {
+            title: "Test".to_string(),
+            sql: "select 1 as one".to_string(),
+            ..Default::default()
+        };
+
+        let results = super::fetch(
+            Source {
+                retries: 0,
+                ..source.clone()
+            },
+            vec![query.clone()],
+        )
+        .await?;
+        assert!(results[0].1.is_err());
+
+        // Available before the first retry
+        tokio::spawn(async {
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            std::fs::create_dir_all("/tmp/test-lmr-retry").unwrap();
+        });
+
+        let results = super::fetch(source, vec![query]).await?;
+        assert_eq!(
+            Some(TypedValue::Integer(1)),
+            results[0].1.clone()?[0][0].inner
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn bind_params() -> Result<(), String> {
+        let query = Query {
+            sql: "select * from orders where customer = :customer and status <> ':open'
+                    and created::date >= :since and (:customer > 0 or :since_2 is null)"
+                .to_string(),
+            params: BTreeMap::from([
+                ("customer".to_string(), "42".to_string()),
+                ("since".to_string(), "2024-01-01".to_string()),
+                ("since_2".to_string(), "x".to_string()),
+            ]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            (
+                "select * from orders where customer = $1 and status <> ':open'
+                    and created::date >= $2 and ($1 > 0 or $3 is null)"
+                    .to_string(),
+                vec!["42".to_string(), "2024-01-01".to_string(), "x".to_string()]
+            ),
+            query.bind_params(|i| format!("${}", i))?
+        );
+
+        let query = Query {
+            sql: "select :customer -- filtered by :customer, not :other\n
+                    /* :since is ignored\n too, :other */ from orders"
+                .to_string(),
+            ..query
+        };
+        assert_eq!(
+            (
+                "select $1 -- filtered by :customer, not :other\n
+                    /* :since is ignored\n too, :other */ from orders"
+                    .to_string(),
+                vec!["42".to_string()]
+            ),
+            query.bind_params(|i| format!("${}", i))?
+        );
+
+        let query = Query {
+            sql: "select :other".to_string(),
+            ..query
+        };
+        assert_eq!(
+            Some("Parameter other not informed".to_string()),
+            query.bind_params(|i| format!("${}", i)).err()
+        );
+
+        let query = Query {
+            params: BTreeMap::new(),
+            ..query
+        };
+        assert_eq!(
+            ("select :other".to_string(), vec![]),
+            query.bind_params(|i| format!("${}", i))?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn is_select() {
+        let query = |sql: &str| Query {
+            sql: sql.to_string(),
+            ..Default::default()
+        };
+
+        assert!(query("select * from orders;").is_select());
+        assert!(query("WITH t AS (select 1) SELECT * FROM t").is_select());
+        assert!(query("select 'delete; drop' as [update] from orders").is_select());
+        assert!(!query("delete from orders").is_select());
+        assert!(!query("select 1; drop table orders").is_select());
+        assert!(!query("select * into backup from orders").is_select());
+        assert!(!query("with t as (select 1) delete from orders").is_select());
+        assert!(!query("with t as (select 1) table t").is_select());
+        assert!(query("with t (id) as (select 1), u as (select 2) select * from t, u").is_select());
+        assert!(query("-- the orders; drop\nselect * from orders").is_select());
+        assert!(query("select * /* delete */ from orders -- into").is_select());
+        assert!(!query("select 1 /* comment */; drop table orders").is_select());
+        assert!(!query("/* select */ delete from orders").is_select());
+    }
+
+    #[test]
+    fn is_ordered() {
+        let query = |sql: &str| Query {
+            sql: sql.to_string(),
+            ..Default::default()
+        };
+
+        assert!(query("select * from orders ORDER BY id;").is_ordered());
+        assert!(query("with t as (select 1 as id) select * from t order\n by id").is_ordered());
+        assert!(!query("select * from orders").is_ordered());
+        assert!(!query("select * from (select * from orders order by id) t").is_ordered());
+        assert!(!query("select rank() over (order by total) from orders").is_ordered());
+        assert!(!query("select 'order by' from orders -- order by id").is_ordered());
+        assert!(!query("select [order] by_id from orders").is_ordered());
+    }
+
+    #[test]
+    fn pool() {
+        let source = Source {
+            kind: SourceType::Postgres,
+            conn: "postgresql://test-lmr-pool".to_string(),
+            ..Default::default()
+        };
+        let other = Source {
+            conn: "postgresql://test-lmr-pool-other".to_string(),
+            ..source.clone()
+        };
+
+        checkin(
+            source.clone(),
+            Box::new(PooledDriver {
+                reusable: true,
+                closed: false,
+            }),
+        );
+        checkin(
+            other.clone(),
+            Box::new(PooledDriver {
+                reusable: false,
+                closed: false,
+            }),
+        );
+
+        assert!(checkout(&other).is_none());
+        assert!(checkout(&source).is_some());
+        assert!(checkout(&source).is_none());
+    }
+
+    #[tokio::test]
+    async fn pool_reconnect() -> Result<(), String> {
+        let source = Source {
+            kind: SourceType::Sqlite,
+            conn: ":memory:".to_string(),
+            attach: BTreeMap::from([("pool".to_string(), ":memory:".to_string())]),
+            ..Default::default()
+        };
+        let query = Query {
+            title: "Test".to_string(),
+            sql: "select 1 as n".to_string(),
+            ..Default::default()
+        };
+
+        // Closed while idle, the query runs on a new connection
+        checkin(
+            source.clone(),
+            Box::new(PooledDriver {
+                reusable: true,
+                closed: true,
+            }),
+        );
+        let results = super::fetch(source, vec![query.clone(), query]).await?;
+
+        assert_eq!(1, results[0].1.clone()?.len());
+        assert_eq!(1, results[1].1.clone()?.len());
+
+        Ok(())
+    }
 }
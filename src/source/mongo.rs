@@ -0,0 +1,325 @@
+//! MongoDB driver implementation
+//!
+//! The `conn` is the connection string, with the database, like
+//! `mongodb://localhost:27017/shop`. The `sql` of the query is the
+//! aggregation pipeline, like `{"collection": "orders", "pipeline": [...]}`,
+//! and the fields are paths on the resulting documents
+
+use super::{json, Driver, Query};
+use crate::value::{apply_overrides, Field, FieldType, Value};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use mongodb::{
+    bson::{Bson, Document},
+    Client, Database,
+};
+use serde_json::{Map, Value as JsonValue};
+
+pub struct MongoDriver {
+    pub db: Option<Database>,
+}
+
+impl MongoDriver {
+    pub fn init() -> Self {
+        Self { db: None }
+    }
+}
+
+#[async_trait]
+impl Driver for MongoDriver {
+    async fn connect(&mut self, sconn: String) -> Result<(), String> {
+        let client = Client::with_uri_str(&sconn)
+            .await
+            .map_err(|e| format!("MongoDB connection failed: {}", e))?;
+
+        let db = client
+            .default_database()
+            .ok_or("Database not informed on the MongoDB conn".to_string())?;
+
+        self.db = Some(db);
+
+        Ok(())
+    }
+
+    fn reusable(&self) -> bool {
+        self.db.is_some()
+    }
+
+    async fn fetch(&mut self, query: Query) -> Result<Vec<Vec<Value>>, String> {
+        let db = self
+            .db
+            .as_ref()
+            .ok_or("Connection not established".to_string())?;
+
+        let (collection, pipeline) = parse_pipeline(&query.sql)?;
+
+        let mut cursor = db
+            .collection::<Document>(&collection)
+            .aggregate(pipeline, None)
+            .await
+            .map_err(|e| format!("Aggregation failed: {}", e))?;
+
+        let mut docs = vec![];
+        while cursor
+            .advance()
+            .await
+            .map_err(|e| format!("Aggregation failed: {}", e))?
+        {
+            docs.push(
+                cursor
+                    .deserialize_current()
+                    .map_err(|e| format!("Document not read: {}", e))?,
+            );
+        }
+
+        let mut query = query;
+        if query.fields.is_empty() {
+            query.fields = infer_fields(&docs);
+            apply_overrides(&mut query.fields, &query.overrides);
+        }
+
+        let records = docs
+            .into_iter()
+            .map(|d| to_json(&Bson::Document(d)))
+            .collect::<Vec<_>>();
+
+        json::to_rows(&records, query)
+    }
+}
+
+/// Collection and stages of the pipeline, in extended JSON
+fn parse_pipeline(sql: &str) -> Result<(String, Vec<Document>), String> {
+    let content: JsonValue =
+        serde_json::from_str(sql).map_err(|e| format!("Pipeline not parsed: {}", e))?;
+
+    let collection = content["collection"]
+        .as_str()
+        .ok_or("Collection not informed on the pipeline".to_string())?
+        .to_string();
+
+    let mut stages = vec![];
+    for stage in content["pipeline"].as_array().into_iter().flatten() {
+        match Bson::try_from(stage.clone()) {
+            Ok(Bson::Document(d)) => stages.push(d),
+            Ok(_) => return Err(format!("Invalid pipeline stage: {}", stage)),
+            Err(e) => return Err(format!("Invalid pipeline stage {}: {}", stage, e)),
+        }
+    }
+
+    Ok((collection, stages))
+}
+
+/// JSON of the document, with the dates as RFC3339 and the ids as hex
+fn to_json(value: &Bson) -> JsonValue {
+    match value {
+        Bson::Null | Bson::Undefined => JsonValue::Null,
+        Bson::Boolean(v) => JsonValue::from(*v),
+        Bson::Int32(v) => JsonValue::from(*v),
+        Bson::Int64(v) => JsonValue::from(*v),
+        Bson::Double(v) => JsonValue::from(*v),
+        Bson::Decimal128(v) => JsonValue::from(v.to_string()),
+        Bson::String(v) => JsonValue::from(v.clone()),
+        Bson::ObjectId(v) => JsonValue::from(v.to_hex()),
+        Bson::DateTime(v) => v
+            .try_to_rfc3339_string()
+            .map(JsonValue::from)
+            .unwrap_or_default(),
+        Bson::Binary(v) => JsonValue::from(STANDARD.encode(&v.bytes)),
+        Bson::Array(v) => JsonValue::Array(v.iter().map(to_json).collect()),
+        Bson::Document(v) => JsonValue::Object(
+            v.iter()
+                .map(|(k, v)| (k.clone(), to_json(v)))
+                .collect::<Map<_, _>>(),
+        ),
+        v => JsonValue::from(v.to_string()),
+    }
+}
+
+/// Fields by the types of the documents values, with the
+/// embedded documents as dotted paths
+fn infer_fields(docs: &[Document]) -> Vec<Field> {
+    let mut fields: Vec<Field> = vec![];
+
+    for doc in docs {
+        let mut paths = vec![];
+        scalar_paths(doc, "", &mut paths);
+
+        for (path, value) in paths {
+            let kind = match value {
+                Bson::Int32(_) | Bson::Int64(_) => FieldType::Integer,
+                Bson::Double(_) | Bson::Decimal128(_) => FieldType::Float,
+                Bson::DateTime(_) => FieldType::DateTime,
+                Bson::Binary(_) => FieldType::Image,
+                _ => FieldType::String,
+            };
+
+            match fields.iter_mut().find(|f| f.field == path) {
+                Some(f) if f.kind == FieldType::Integer && kind == FieldType::Float => {
+                    f.kind = kind
+                }
+                Some(_) => {}
+                None if matches!(value, Bson::Null) => {}
+                None => fields.push(Field {
+                    field: path.clone(),
+                    title: path,
+                    kind,
+                    ..Default::default()
+                }),
+            }
+        }
+    }
+
+    fields
+}
+
+fn scalar_paths<'a>(doc: &'a Document, prefix: &str, paths: &mut Vec<(String, &'a Bson)>) {
+    for (k, v) in doc {
+        let path = if prefix.is_empty() {
+            k.clone()
+        } else {
+            format!("{}.{}", prefix, k)
+        };
+
+        match v {
+            Bson::Document(d) => scalar_paths(d, &path, paths),
+            Bson::Array(_) => {}
+            v => paths.push((path, v)),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{infer_fields, parse_pipeline, to_json};
+    use crate::{
+        source::{json, mongo::MongoDriver, Driver, Query},
+        value::{FieldType, TypedValue},
+    };
+    use mongodb::bson::{doc, oid::ObjectId, Bson, DateTime};
+
+    #[test]
+    fn pipeline() -> Result<(), String> {
+        let (collection, stages) = parse_pipeline(
+            r#"{"collection": "orders", "pipeline": [
+                {"$match": {"created": {"$gte": {"$date": "2024-01-01T00:00:00Z"}}}},
+                {"$group": {"_id": "$status", "total": {"$sum": "$amount"}}}
+            ]}"#,
+        )?;
+
+        assert_eq!("orders", collection);
+        assert_eq!(2, stages.len());
+        assert!(matches!(
+            stages[0]
+                .get_document("$match")
+                .unwrap()
+                .get_document("created")
+                .unwrap()
+                .get("$gte"),
+            Some(Bson::DateTime(_))
+        ));
+
+        assert!(parse_pipeline(r#"{"pipeline": []}"#).is_err());
+        assert!(parse_pipeline(r#"{"collection": "orders", "pipeline": [1]}"#).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn documents() -> Result<(), String> {
+        let docs = vec![
+            doc! {
+                "_id": ObjectId::parse_str("65f000000000000000000001").unwrap(),
+                "customer": {"name": "Alice"},
+                "qty": 2,
+                "amount": 10,
+                "created": DateTime::from_millis(1718000000000),
+            },
+            doc! { "_id": ObjectId::new(), "qty": 1, "amount": 12.5, "created": Bson::Null },
+        ];
+
+        let fields = infer_fields(&docs);
+        assert_eq!(
+            vec![
+                ("_id".to_string(), FieldType::String),
+                ("customer.name".to_string(), FieldType::String),
+                ("qty".to_string(), FieldType::Integer),
+                ("amount".to_string(), FieldType::Float),
+                ("created".to_string(), FieldType::DateTime),
+            ],
+            fields
+                .iter()
+                .map(|f| (f.field.clone(), f.kind.clone()))
+                .collect::<Vec<_>>()
+        );
+
+        let records = docs
+            .into_iter()
+            .map(|d| to_json(&Bson::Document(d)))
+            .collect::<Vec<_>>();
+        let rows = json::to_rows(
+            &records,
+            Query {
+                fields,
+                ..Default::default()
+            },
+        )?;
+
+        assert_eq!(
+            Some(TypedValue::String("65f000000000000000000001".to_string())),
+            rows[0][0].inner
+        );
+        assert_eq!(Some(TypedValue::Float(10.0)), rows[0][3].inner);
+        assert_eq!(
+            "2024-06-10 06:13:20 +00:00",
+            rows[0][4].inner.as_ref().unwrap().to_string()
+        );
+        assert_eq!(None, rows[1][1].inner);
+        assert_eq!(None, rows[1][4].inner);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fetch() -> Result<(), String> {
+        let mut driver = MongoDriver::init();
+        driver
+            .connect("mongodb://localhost:27017/lmr_tests".to_string())
+            .await?;
+
+        let db = driver.db.as_ref().unwrap();
+        let orders = db.collection("orders");
+        orders.drop(None).await.unwrap();
+        orders
+            .insert_many(
+                vec![
+                    doc! {"status": "open", "amount": 10},
+                    doc! {"status": "open", "amount": 5},
+                    doc! {"status": "paid", "amount": 7},
+                ],
+                None,
+            )
+            .await
+            .unwrap();
+
+        let result = driver
+            .fetch(Query {
+                title: "Test".to_string(),
+                sql: r#"{"collection": "orders", "pipeline": [
+                    {"$group": {"_id": "$status", "total": {"$sum": "$amount"}}},
+                    {"$sort": {"_id": 1}}
+                ]}"#
+                .to_string(),
+                ..Default::default()
+            })
+            .await?;
+
+        assert_eq!(2, result.len());
+        assert_eq!(
+            Some(TypedValue::String("open".to_string())),
+            result[0][0].inner
+        );
+        assert_eq!(Some(TypedValue::Integer(15)), result[0][1].inner);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,408 @@
+//! Microsoft SQL Server driver implementation
+
+use super::{Driver, OnChunk, Query};
+use crate::value::{apply_overrides, Field, FieldType, TypedValue, Value};
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+use futures_util::TryStreamExt;
+use rust_decimal::{prelude::FromPrimitive, Decimal};
+use tiberius::{Client, Column, ColumnData, ColumnType, Config, FromSql, QueryItem};
+use tokio::net::TcpStream;
+use tokio_util::compat::{Compat, TokioAsyncWriteCompatExt};
+
+pub struct MssqlDriver {
+    pub conn: Option<Client<Compat<TcpStream>>>,
+    /// Only the select querys accepted
+    pub read_only: bool,
+}
+
+impl MssqlDriver {
+    pub fn init() -> Self {
+        Self {
+            conn: None,
+            read_only: false,
+        }
+    }
+}
+
+#[async_trait]
+impl Driver for MssqlDriver {
+    /// Connect with an ADO.NET connection string, like
+    /// `server=tcp:localhost,1433;user=sa;password=...;TrustServerCertificate=true`
+    async fn connect(&mut self, sconn: String) -> Result<(), String> {
+        let config =
+            Config::from_ado_string(&sconn).map_err(|e| format!("Invalid MSSQL conn: {}", e))?;
+
+        let tcp = TcpStream::connect(config.get_addr())
+            .await
+            .map_err(|e| format!("MSSQL connection failed: {}", e))?;
+        tcp.set_nodelay(true)
+            .map_err(|e| format!("MSSQL connection failed: {}", e))?;
+
+        let client = Client::connect(config, tcp.compat_write())
+            .await
+            .map_err(|e| format!("MSSQL connection failed: {}", e))?;
+
+        self.conn = Some(client);
+
+        Ok(())
+    }
+
+    fn reusable(&self) -> bool {
+        self.conn.is_some()
+    }
+
+    /// Page by the OFFSET/FETCH, after the ORDER BY of the sql
+    fn paginate(&self, sql: &str, size: usize, offset: usize) -> Option<String> {
+        Some(format!(
+            "{} OFFSET {} ROWS FETCH NEXT {} ROWS ONLY",
+            sql, offset, size
+        ))
+    }
+
+    async fn fetch(&mut self, query: Query) -> Result<Vec<Vec<Value>>, String> {
+        let mut values = vec![];
+
+        self.fetch_chunks(query, usize::MAX, &mut |rows| {
+            values.extend(rows);
+            Ok(true)
+        })
+        .await?;
+
+        Ok(values)
+    }
+
+    /// Rows of the first result, read from the stream. The rows left
+    /// behind by a stop are discarded on the next query of the connection
+    async fn fetch_chunks(
+        &mut self,
+        query: Query,
+        size: usize,
+        on_chunk: &mut OnChunk,
+    ) -> Result<(), String> {
+        let conn = self
+            .conn
+            .as_mut()
+            .ok_or("Connection not established".to_string())?;
+
+        if self.read_only && !query.is_select() {
+            return Err("Only select querys are allowed on read only sources".to_string());
+        }
+
+        let (sql, params) = query.bind_params(|i| format!("@P{}", i))?;
+
+        let mut select = tiberius::Query::new(sql);
+        for p in params {
+            select.bind(p);
+        }
+
+        let mut stream = select
+            .query(conn)
+            .await
+            .map_err(|e| format!("Query failed: {}", e))?;
+
+        let rcolumns = stream
+            .columns()
+            .await
+            .map_err(|e| format!("Query failed: {}", e))?
+            .map(|c| c.to_vec())
+            .unwrap_or_default();
+
+        let fields = if query.fields.is_empty() {
+            let mut fields = infer_fields(&rcolumns);
+            apply_overrides(&mut fields, &query.overrides);
+            fields
+        } else {
+            query.fields
+        };
+
+        let mut columns = vec![];
+        for col in fields {
+            let idx = rcolumns
+                .iter()
+                .position(|c| c.name() == col.field)
+                .ok_or(format!("Column {} not found", col.field))?;
+
+            columns.push((col, idx));
+        }
+
+        let mut rows = vec![];
+
+        while let Some(item) = stream
+            .try_next()
+            .await
+            .map_err(|e| format!("Fetch row failed: {}", e))?
+        {
+            let row = match item {
+                QueryItem::Row(row) if row.result_index() == 0 => row,
+                QueryItem::Row(_) => break,
+                QueryItem::Metadata(_) => continue,
+            };
+
+            let data = row.into_iter().collect::<Vec<_>>();
+            let mut r = vec![];
+
+            for (col, idx) in &columns {
+                let inner = to_typed(&col.kind, &data[*idx])
+                    .map_err(|e| format!("Column {} row {} error: {}", col.field, r.len(), e))?;
+
+                r.push(Value {
+                    inner,
+                    field: col.clone(),
+                });
+            }
+
+            rows.push(r);
+
+            if rows.len() >= size && !on_chunk(std::mem::take(&mut rows))? {
+                return Ok(());
+            }
+        }
+
+        if !rows.is_empty() {
+            on_chunk(rows)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Value of the column as the kind of the field
+fn to_typed(kind: &FieldType, data: &ColumnData<'static>) -> Result<Option<TypedValue>, String> {
+    let invalid = || format!("Invalid {:?} type {:?}", kind, data);
+
+    let value = match kind {
+        FieldType::Integer => match data {
+            ColumnData::U8(v) => v.map(i64::from),
+            ColumnData::I16(v) => v.map(i64::from),
+            ColumnData::I32(v) => v.map(i64::from),
+            ColumnData::I64(v) => *v,
+            ColumnData::Bit(v) => v.map(i64::from),
+            _ => return Err(invalid()),
+        }
+        .map(TypedValue::Integer),
+        FieldType::Float => match data {
+            ColumnData::F32(v) => v.map(f64::from),
+            ColumnData::F64(v) => *v,
+            ColumnData::Numeric(v) => v.map(f64::from),
+            ColumnData::U8(v) => v.map(f64::from),
+            ColumnData::I16(v) => v.map(f64::from),
+            ColumnData::I32(v) => v.map(f64::from),
+            ColumnData::I64(v) => v.map(|v| v as f64),
+            _ => return Err(invalid()),
+        }
+        .map(TypedValue::Float),
+        FieldType::String => match data {
+            ColumnData::String(v) => v.as_ref().map(|v| v.to_string()),
+            ColumnData::Guid(v) => v.map(|v| v.to_string()),
+            ColumnData::Xml(v) => v.as_ref().map(|v| v.to_string()),
+            _ => return Err(invalid()),
+        }
+        .map(TypedValue::String),
+        FieldType::Decimal => match data {
+            ColumnData::Numeric(_) => Decimal::from_sql(data).map_err(|e| e.to_string())?,
+            ColumnData::U8(v) => v.map(Decimal::from),
+            ColumnData::I16(v) => v.map(Decimal::from),
+            ColumnData::I32(v) => v.map(Decimal::from),
+            ColumnData::I64(v) => v.map(Decimal::from),
+            ColumnData::F32(v) => v.and_then(Decimal::from_f32),
+            ColumnData::F64(v) => v.and_then(Decimal::from_f64),
+            _ => return Err(invalid()),
+        }
+        .map(TypedValue::Decimal),
+        FieldType::Array | FieldType::Json => match data {
+            ColumnData::String(v) => v
+                .as_ref()
+                .map(|v| TypedValue::parse_json(kind, v))
+                .transpose()?,
+            _ => return Err(invalid()),
+        },
+        FieldType::Image => match data {
+            ColumnData::Binary(v) => v.as_ref().map(|v| v.to_vec()),
+            ColumnData::String(v) => v.as_ref().map(|v| v.as_bytes().to_vec()),
+            _ => return Err(invalid()),
+        }
+        .map(TypedValue::Image),
+        FieldType::Date => NaiveDate::from_sql(data)
+            .map_err(|e| e.to_string())?
+            .map(TypedValue::Date),
+        FieldType::Time => NaiveTime::from_sql(data)
+            .map_err(|e| e.to_string())?
+            .map(TypedValue::Time),
+        FieldType::DateTime => match data {
+            ColumnData::DateTimeOffset(_) => {
+                DateTime::<FixedOffset>::from_sql(data).map_err(|e| e.to_string())?
+            }
+            _ => {
+                let utc = FixedOffset::east_opt(0).ok_or("Invalid timezone".to_string())?;
+                NaiveDateTime::from_sql(data)
+                    .map_err(|e| e.to_string())?
+                    .map(|v| DateTime::from_naive_utc_and_offset(v, utc))
+            }
+        }
+        .map(TypedValue::DateTime),
+    };
+
+    Ok(value)
+}
+
+/// Fields by the type of the result columns
+fn infer_fields(columns: &[Column]) -> Vec<Field> {
+    columns
+        .iter()
+        .map(|c| {
+            let kind = match c.column_type() {
+                ColumnType::Bit
+                | ColumnType::Bitn
+                | ColumnType::Int1
+                | ColumnType::Int2
+                | ColumnType::Int4
+                | ColumnType::Int8
+                | ColumnType::Intn => FieldType::Integer,
+                ColumnType::Float4
+                | ColumnType::Float8
+                | ColumnType::Floatn
+                | ColumnType::Money
+                | ColumnType::Money4
+                | ColumnType::Decimaln
+                | ColumnType::Numericn => FieldType::Float,
+                ColumnType::Daten => FieldType::Date,
+                ColumnType::Timen => FieldType::Time,
+                ColumnType::Datetime
+                | ColumnType::Datetime4
+                | ColumnType::Datetimen
+                | ColumnType::Datetime2
+                | ColumnType::DatetimeOffsetn => FieldType::DateTime,
+                ColumnType::BigVarBin | ColumnType::BigBinary | ColumnType::Image => {
+                    FieldType::Image
+                }
+                _ => FieldType::String,
+            };
+
+            Field {
+                field: c.name().to_string(),
+                title: c.name().to_string(),
+                kind,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{infer_fields, to_typed};
+    use crate::{
+        source::{mssql::MssqlDriver, Driver, Query},
+        value::{FieldType, TypedValue},
+    };
+    use chrono::{DateTime, NaiveDate};
+    use std::borrow::Cow;
+    use tiberius::{numeric::Numeric, Column, ColumnData, ColumnType};
+
+    #[test]
+    fn column_data() -> Result<(), String> {
+        assert_eq!(
+            Some(TypedValue::Integer(2024)),
+            to_typed(&FieldType::Integer, &ColumnData::I32(Some(2024)))?
+        );
+        assert_eq!(
+            Some(TypedValue::Float(123.45)),
+            to_typed(
+                &FieldType::Float,
+                &ColumnData::Numeric(Some(Numeric::new_with_scale(12345, 2)))
+            )?
+        );
+        assert_eq!(
+            Some(TypedValue::String("Olá mundo".to_string())),
+            to_typed(
+                &FieldType::String,
+                &ColumnData::String(Some(Cow::from("Olá mundo")))
+            )?
+        );
+        assert_eq!(
+            None,
+            to_typed(&FieldType::String, &ColumnData::String(None))?
+        );
+        assert!(to_typed(&FieldType::Integer, &ColumnData::String(None)).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn paginate() {
+        assert_eq!(
+            Some(
+                "select * from orders order by id OFFSET 20 ROWS FETCH NEXT 10 ROWS ONLY"
+                    .to_string()
+            ),
+            MssqlDriver::init().paginate("select * from orders order by id", 10, 20)
+        );
+    }
+
+    #[test]
+    fn infer() {
+        let fields = infer_fields(&[
+            Column::new("a".to_string(), ColumnType::BigVarChar),
+            Column::new("b".to_string(), ColumnType::Intn),
+            Column::new("c".to_string(), ColumnType::Decimaln),
+            Column::new("d".to_string(), ColumnType::DatetimeOffsetn),
+        ]);
+
+        assert_eq!(
+            vec![
+                FieldType::String,
+                FieldType::Integer,
+                FieldType::Float,
+                FieldType::DateTime
+            ],
+            fields.into_iter().map(|f| f.kind).collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn supported_types() -> Result<(), String> {
+        let mut driver = MssqlDriver::init();
+
+        driver
+            .connect(
+                "server=tcp:localhost,1433;user=sa;password=Lmr@12345;TrustServerCertificate=true"
+                    .to_string(),
+            )
+            .await?;
+
+        let query = Query {
+            title: "Test".to_string(),
+            sql: "select cast('Olá mundo' as nvarchar(50)) as a, cast(2024 as bigint) as b,
+                    cast(123.45 as decimal(10, 2)) as c, cast('2024-05-15' as date) as e,
+                    cast('1996-12-19T16:39:57-08:00' as datetimeoffset) as f"
+                .to_string(),
+            ..Default::default()
+        };
+
+        let result = driver.fetch(query).await?;
+        assert_eq!(1, result.len());
+
+        let row = &result[0];
+        assert_eq!(
+            Some(TypedValue::String("Olá mundo".to_string())),
+            row[0].inner
+        );
+        assert_eq!(Some(TypedValue::Integer(2024)), row[1].inner);
+        assert_eq!(Some(TypedValue::Float(123.45)), row[2].inner);
+        assert_eq!(
+            Some(TypedValue::Date(
+                NaiveDate::from_ymd_opt(2024, 5, 15).unwrap()
+            )),
+            row[3].inner
+        );
+        assert_eq!(
+            Some(TypedValue::DateTime(
+                DateTime::parse_from_rfc3339("1996-12-19T16:39:57-08:00").unwrap()
+            )),
+            row[4].inner
+        );
+
+        Ok(())
+    }
+}
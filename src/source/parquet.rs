@@ -0,0 +1,294 @@
+//! Parquet file driver implementation
+//!
+//! The `conn` is the path of the file, or a glob like `/data/sales/*.parquet`
+//! to read all the matched files, on the same schema. The `sql` of the
+//! query is not used, the fields select the columns
+
+use super::{Driver, Query};
+use crate::value::{apply_overrides, Field, FieldType, TypedValue, Value};
+use arrow_array::{
+    cast::AsArray,
+    types::{Date32Type, Float64Type, Int64Type, Time64MicrosecondType, TimestampMicrosecondType},
+    Array, RecordBatch,
+};
+use arrow_schema::{DataType, Schema, TimeUnit};
+use async_trait::async_trait;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use std::fs::File;
+
+pub struct ParquetDriver {
+    pub batches: Vec<RecordBatch>,
+}
+
+impl ParquetDriver {
+    pub fn init() -> Self {
+        Self { batches: vec![] }
+    }
+}
+
+#[async_trait]
+impl Driver for ParquetDriver {
+    async fn connect(&mut self, sconn: String) -> Result<(), String> {
+        let mut paths = glob::glob(&sconn)
+            .map_err(|e| format!("Invalid path {}: {}", sconn, e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Path not read: {}", e))?;
+        paths.sort();
+
+        if paths.is_empty() {
+            return Err(format!("Parquet file {} not found", sconn));
+        }
+
+        let mut batches = vec![];
+        for path in paths {
+            let file = File::open(&path)
+                .map_err(|e| format!("Parquet file {} not opened: {}", path.display(), e))?;
+
+            let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+                .and_then(|b| b.build())
+                .map_err(|e| format!("Parquet file {} not read: {}", path.display(), e))?;
+
+            for batch in reader {
+                batches.push(
+                    batch
+                        .map_err(|e| format!("Parquet file {} not read: {}", path.display(), e))?,
+                );
+            }
+        }
+
+        self.batches = batches;
+
+        Ok(())
+    }
+
+    async fn fetch(&mut self, query: Query) -> Result<Vec<Vec<Value>>, String> {
+        let fields = if query.fields.is_empty() {
+            let mut fields = self
+                .batches
+                .first()
+                .map(|b| infer_fields(&b.schema()))
+                .unwrap_or_default();
+            apply_overrides(&mut fields, &query.overrides);
+            fields
+        } else {
+            query.fields
+        };
+
+        let mut rows = vec![];
+
+        for batch in &self.batches {
+            let mut columns = vec![];
+            for col in &fields {
+                let array = batch
+                    .column_by_name(&col.field)
+                    .ok_or(format!("Column {} not found", col.field))?;
+                let array = arrow_cast::cast(array, &arrow_type(&col.kind))
+                    .map_err(|e| format!("Column {} error: {}", col.field, e))?;
+                columns.push((col, array));
+            }
+
+            for i in 0..batch.num_rows() {
+                let mut r = vec![];
+
+                for (col, array) in &columns {
+                    r.push(Value {
+                        inner: to_typed(&col.kind, array, i),
+                        field: (*col).clone(),
+                    });
+                }
+
+                rows.push(r);
+            }
+        }
+
+        Ok(rows)
+    }
+}
+
+/// Fields by the Arrow types of the schema
+fn infer_fields(schema: &Schema) -> Vec<Field> {
+    schema
+        .fields()
+        .iter()
+        .map(|f| {
+            let kind = match f.data_type() {
+                t if t.is_integer() => FieldType::Integer,
+                t if t.is_floating() => FieldType::Float,
+                DataType::Decimal128(..) | DataType::Decimal256(..) => FieldType::Decimal,
+                DataType::Date32 | DataType::Date64 => FieldType::Date,
+                DataType::Time32(_) | DataType::Time64(_) => FieldType::Time,
+                DataType::Timestamp(..) => FieldType::DateTime,
+                DataType::Binary | DataType::LargeBinary => FieldType::Image,
+                _ => FieldType::String,
+            };
+
+            Field {
+                field: f.name().clone(),
+                title: f.name().clone(),
+                kind,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Arrow type to cast the columns of the kind of field
+fn arrow_type(kind: &FieldType) -> DataType {
+    match kind {
+        // The decimals by the exact text
+        FieldType::String | FieldType::Decimal | FieldType::Array | FieldType::Json => {
+            DataType::Utf8
+        }
+        FieldType::Integer => DataType::Int64,
+        FieldType::Float => DataType::Float64,
+        FieldType::Date => DataType::Date32,
+        FieldType::Time => DataType::Time64(TimeUnit::Microsecond),
+        FieldType::DateTime => DataType::Timestamp(TimeUnit::Microsecond, Some("+00:00".into())),
+        FieldType::Image => DataType::Binary,
+    }
+}
+
+/// Value of the row of the column, already cast to the kind of the field
+fn to_typed(kind: &FieldType, array: &dyn Array, i: usize) -> Option<TypedValue> {
+    if array.is_null(i) {
+        return None;
+    }
+
+    let value = match kind {
+        FieldType::String => TypedValue::String(array.as_string::<i32>().value(i).to_string()),
+        FieldType::Integer => TypedValue::Integer(array.as_primitive::<Int64Type>().value(i)),
+        FieldType::Float => TypedValue::Float(array.as_primitive::<Float64Type>().value(i)),
+        FieldType::Decimal => TypedValue::parse_decimal(array.as_string::<i32>().value(i)).ok()?,
+        FieldType::Date => TypedValue::Date(array.as_primitive::<Date32Type>().value_as_date(i)?),
+        FieldType::Time => TypedValue::Time(
+            array
+                .as_primitive::<Time64MicrosecondType>()
+                .value_as_time(i)?,
+        ),
+        FieldType::DateTime => TypedValue::DateTime(
+            array
+                .as_primitive::<TimestampMicrosecondType>()
+                .value_as_datetime(i)?
+                .and_utc()
+                .fixed_offset(),
+        ),
+        FieldType::Image => TypedValue::Image(array.as_binary::<i32>().value(i).to_vec()),
+        FieldType::Array | FieldType::Json => {
+            TypedValue::parse_json(kind, array.as_string::<i32>().value(i)).ok()?
+        }
+    };
+
+    Some(value)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::{
+        source::{parquet::ParquetDriver, Driver, Query},
+        value::{Field, FieldType, TypedValue},
+    };
+    use arrow_array::{
+        ArrayRef, Date32Array, Float64Array, Int32Array, RecordBatch, StringArray,
+        TimestampMillisecondArray,
+    };
+    use chrono::NaiveDate;
+    use parquet::arrow::ArrowWriter;
+    use std::{fs::File, sync::Arc};
+
+    fn write(path: &str, names: Vec<&str>, qty: Vec<Option<i32>>) {
+        let len = names.len();
+        let batch = RecordBatch::try_from_iter(vec![
+            ("name", Arc::new(StringArray::from(names)) as ArrayRef),
+            ("qty", Arc::new(Int32Array::from(qty)) as ArrayRef),
+            (
+                "price",
+                Arc::new(Float64Array::from(vec![9.5; len])) as ArrayRef,
+            ),
+            (
+                "day",
+                Arc::new(Date32Array::from(vec![19884; len])) as ArrayRef,
+            ),
+            (
+                "created",
+                Arc::new(TimestampMillisecondArray::from(vec![1718000000000; len])) as ArrayRef,
+            ),
+        ])
+        .unwrap();
+
+        let mut writer =
+            ArrowWriter::try_new(File::create(path).unwrap(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+    }
+
+    #[tokio::test]
+    async fn fetch() -> Result<(), String> {
+        std::fs::create_dir_all("/tmp/test-lmr-parquet").unwrap();
+        write(
+            "/tmp/test-lmr-parquet/a.parquet",
+            vec!["Alice", "Bob"],
+            vec![Some(2), None],
+        );
+        write(
+            "/tmp/test-lmr-parquet/b.parquet",
+            vec!["Carol"],
+            vec![Some(3)],
+        );
+
+        let mut driver = ParquetDriver::init();
+        driver
+            .connect("/tmp/test-lmr-parquet/*.parquet".to_string())
+            .await?;
+
+        let result = driver.fetch(Query::default()).await?;
+        assert_eq!(3, result.len());
+        assert_eq!(
+            vec![
+                FieldType::String,
+                FieldType::Integer,
+                FieldType::Float,
+                FieldType::Date,
+                FieldType::DateTime
+            ],
+            result[0]
+                .iter()
+                .map(|v| v.field.kind.clone())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some(TypedValue::Integer(2)), result[0][1].inner);
+        assert_eq!(None, result[1][1].inner);
+        assert_eq!(
+            Some(TypedValue::String("Carol".to_string())),
+            result[2][0].inner
+        );
+        assert_eq!(
+            Some(TypedValue::Date(
+                NaiveDate::from_ymd_opt(2024, 6, 10).unwrap()
+            )),
+            result[0][3].inner
+        );
+        assert_eq!(
+            "2024-06-10 06:13:20 +00:00",
+            result[0][4].inner.as_ref().unwrap().to_string()
+        );
+
+        let result = driver
+            .fetch(Query {
+                fields: vec![Field {
+                    field: "qty".to_string(),
+                    kind: FieldType::Float,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            })
+            .await?;
+        assert_eq!(Some(TypedValue::Float(3.0)), result[2][0].inner);
+
+        assert!(driver
+            .connect("/tmp/test-lmr-parquet/*.csv".to_string())
+            .await
+            .is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,275 @@
+//! Prometheus driver implementation
+//!
+//! The `conn` is the server url and the `sql` of the query is the PromQL
+//! expression. Prefixed by a `[range:step]`, like `[7d:1h] up`, it's a
+//! range query until the run date, otherwise an instant query.
+//!
+//! Each sample is a row with the `timestamp`, `value` and label fields
+
+use super::{http::HttpDriver, json, Driver, Query};
+use crate::{
+    clock,
+    value::{apply_overrides, Field, FieldType, Value},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration};
+use serde_json::{Map, Value as JsonValue};
+use std::collections::BTreeMap;
+
+pub struct PrometheusDriver {
+    pub http: HttpDriver,
+}
+
+impl PrometheusDriver {
+    pub fn init(headers: BTreeMap<String, String>, auth: Option<String>) -> Self {
+        Self {
+            http: HttpDriver::init(headers, auth),
+        }
+    }
+}
+
+#[async_trait]
+impl Driver for PrometheusDriver {
+    async fn connect(&mut self, sconn: String) -> Result<(), String> {
+        self.http.connect(sconn).await
+    }
+
+    async fn fetch(&mut self, query: Query) -> Result<Vec<Vec<Value>>, String> {
+        let (range, promql) = split_range(&query.sql)?;
+        let now = clock::current().now();
+
+        let content = match range {
+            Some((range, step)) => {
+                let params = [
+                    ("query", promql.to_string()),
+                    ("start", (now - range).timestamp().to_string()),
+                    ("end", now.timestamp().to_string()),
+                    ("step", step.num_seconds().to_string()),
+                ];
+                self.http.get("/api/v1/query_range", &params).await?
+            }
+            None => {
+                let params = [
+                    ("query", promql.to_string()),
+                    ("time", now.timestamp().to_string()),
+                ];
+                self.http.get("/api/v1/query", &params).await?
+            }
+        };
+
+        let records = to_records(&content)?;
+
+        let mut query = query;
+        if query.fields.is_empty() {
+            query.fields = infer_fields(&records);
+            apply_overrides(&mut query.fields, &query.overrides);
+        }
+
+        json::to_rows(&records, query)
+    }
+}
+
+/// Range and step of a range query
+type Range = (Duration, Duration);
+
+/// The `[range:step]` prefix and the PromQL expression
+fn split_range(sql: &str) -> Result<(Option<Range>, &str), String> {
+    let sql = sql.trim();
+
+    let Some(rest) = sql.strip_prefix('[') else {
+        return Ok((None, sql));
+    };
+
+    let (prefix, promql) = rest
+        .split_once(']')
+        .ok_or("Invalid range, expected [range:step]".to_string())?;
+    let (range, step) = prefix
+        .split_once(':')
+        .ok_or("Invalid range, expected [range:step]".to_string())?;
+
+    Ok((
+        Some((parse_duration(range)?, parse_duration(step)?)),
+        promql.trim(),
+    ))
+}
+
+/// Durations like `30s`, `5m`, `1h`, `7d` or `2w`
+fn parse_duration(raw: &str) -> Result<Duration, String> {
+    let raw = raw.trim();
+    let invalid = || format!("Invalid duration: {}", raw);
+
+    let unit = raw.chars().last().ok_or_else(invalid)?;
+    let n = raw[..raw.len() - unit.len_utf8()]
+        .parse::<i64>()
+        .map_err(|_| invalid())?;
+
+    match unit {
+        's' => Ok(Duration::seconds(n)),
+        'm' => Ok(Duration::minutes(n)),
+        'h' => Ok(Duration::hours(n)),
+        'd' => Ok(Duration::days(n)),
+        'w' => Ok(Duration::weeks(n)),
+        _ => Err(invalid()),
+    }
+}
+
+/// One record per sample of the vector, matrix or scalar result
+fn to_records(content: &JsonValue) -> Result<Vec<JsonValue>, String> {
+    if content["status"] != "success" {
+        return Err(format!(
+            "Prometheus query failed: {}",
+            content["error"].as_str().unwrap_or("unknown error")
+        ));
+    }
+
+    let data = &content["data"];
+    let result = &data["result"];
+
+    let mut records = vec![];
+    match data["resultType"].as_str() {
+        Some("vector") => {
+            for serie in result.as_array().into_iter().flatten() {
+                records.push(sample(&serie["metric"], &serie["value"])?);
+            }
+        }
+        Some("matrix") => {
+            for serie in result.as_array().into_iter().flatten() {
+                for value in serie["values"].as_array().into_iter().flatten() {
+                    records.push(sample(&serie["metric"], value)?);
+                }
+            }
+        }
+        Some("scalar") | Some("string") => records.push(sample(&JsonValue::Null, result)?),
+        other => return Err(format!("Unsupported result type {:?}", other)),
+    }
+
+    Ok(records)
+}
+
+/// Record of the `[timestamp, "value"]` pair with the labels
+fn sample(metric: &JsonValue, value: &JsonValue) -> Result<JsonValue, String> {
+    let ts = value[0]
+        .as_f64()
+        .ok_or(format!("Invalid sample {}", value))?;
+    let timestamp = DateTime::from_timestamp_millis((ts * 1000.0) as i64)
+        .ok_or(format!("Invalid timestamp {}", ts))?;
+
+    let mut record = Map::new();
+    record.insert("timestamp".to_string(), timestamp.to_rfc3339().into());
+    record.insert(
+        "value".to_string(),
+        value[1]
+            .as_str()
+            .and_then(|v| v.parse::<f64>().ok())
+            .map(JsonValue::from)
+            .unwrap_or(JsonValue::Null),
+    );
+    if let Some(labels) = metric.as_object() {
+        for (k, v) in labels {
+            record.insert(k.clone(), v.clone());
+        }
+    }
+
+    Ok(JsonValue::Object(record))
+}
+
+/// The timestamp and value fields followed by the labels of all series
+fn infer_fields(records: &[JsonValue]) -> Vec<Field> {
+    let field = |name: &str, kind| Field {
+        field: name.to_string(),
+        title: name.to_string(),
+        kind,
+        ..Default::default()
+    };
+
+    let mut fields = vec![
+        field("timestamp", FieldType::DateTime),
+        field("value", FieldType::Float),
+    ];
+
+    let mut labels = records
+        .iter()
+        .filter_map(|r| r.as_object())
+        .flat_map(|r| r.keys())
+        .filter(|k| *k != "timestamp" && *k != "value")
+        .collect::<Vec<_>>();
+    labels.sort();
+    labels.dedup();
+
+    fields.extend(labels.into_iter().map(|l| field(l, FieldType::String)));
+
+    fields
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{split_range, to_records};
+    use crate::{
+        source::{json, prometheus::infer_fields, Query},
+        value::TypedValue,
+    };
+    use chrono::Duration;
+    use serde_json::json;
+
+    #[test]
+    fn range() -> Result<(), String> {
+        assert_eq!((None, "up"), split_range(" up ")?);
+        assert_eq!(
+            (
+                Some((Duration::days(7), Duration::hours(1))),
+                "sum(rate(x[5m]))"
+            ),
+            split_range("[7d:1h] sum(rate(x[5m]))")?
+        );
+        assert!(split_range("[7d] up").is_err());
+        assert!(split_range("[7y:1h] up").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn samples() -> Result<(), String> {
+        let content = json!({
+            "status": "success",
+            "data": {
+                "resultType": "matrix",
+                "result": [
+                    {"metric": {"job": "api"}, "values": [[1718000000, "1"], [1718003600, "0.5"]]},
+                    {"metric": {"job": "db", "instance": "a"}, "values": [[1718000000, "1"]]}
+                ]
+            }
+        });
+
+        let records = to_records(&content)?;
+        assert_eq!(3, records.len());
+
+        let fields = infer_fields(&records);
+        assert_eq!(
+            vec!["timestamp", "value", "instance", "job"],
+            fields.iter().map(|f| f.field.as_str()).collect::<Vec<_>>()
+        );
+
+        let rows = json::to_rows(
+            &records,
+            Query {
+                fields,
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(Some(TypedValue::Float(0.5)), rows[1][1].inner);
+        assert_eq!(None, rows[1][2].inner);
+        assert_eq!(Some(TypedValue::String("db".to_string())), rows[2][3].inner);
+        assert_eq!(
+            "2024-06-10 06:13:20 +00:00",
+            rows[0][0].inner.as_ref().unwrap().to_string()
+        );
+
+        let error = json!({"status": "error", "error": "parse error"});
+        assert_eq!(
+            Some("Prometheus query failed: parse error".to_string()),
+            to_records(&error).err()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+//! Redis driver implementation
+//!
+//! The `conn` is the url of the server, like `redis://localhost:6379/0`.
+//! The `sql` of the query is one of the read commands:
+//!
+//! - `SCAN <pattern>`: one row per matched hash, with the `key` and the `HGETALL` of it
+//! - `LRANGE <key> <start> <stop>`: one row per element, JSON objects or as `value`
+//! - `EVAL <script>`: Lua script, returning a list of flat key/value lists or of values
+//!
+//! The numeric texts are read as numbers
+
+use super::{json, Driver, Query};
+use crate::value::{apply_overrides, Value};
+use async_trait::async_trait;
+use redis::{aio::MultiplexedConnection, Value as RedisValue};
+use serde_json::{Map, Value as JsonValue};
+
+pub struct RedisDriver {
+    pub conn: Option<MultiplexedConnection>,
+}
+
+impl RedisDriver {
+    pub fn init() -> Self {
+        Self { conn: None }
+    }
+}
+
+#[async_trait]
+impl Driver for RedisDriver {
+    async fn connect(&mut self, sconn: String) -> Result<(), String> {
+        let client = redis::Client::open(sconn.as_str())
+            .map_err(|e| format!("Invalid url {}: {}", sconn, e))?;
+
+        let conn = client
+            .get_multiplexed_async_connection()
+            .await
+            .map_err(|e| format!("Redis connection failed: {}", e))?;
+
+        self.conn = Some(conn);
+
+        Ok(())
+    }
+
+    fn reusable(&self) -> bool {
+        self.conn.is_some()
+    }
+
+    async fn fetch(&mut self, query: Query) -> Result<Vec<Vec<Value>>, String> {
+        let conn = self
+            .conn
+            .as_mut()
+            .ok_or("Connection not established".to_string())?;
+
+        let sql = query.sql.trim();
+        let (command, args) = sql.split_once(char::is_whitespace).unwrap_or((sql, ""));
+        let args = args.trim();
+        let failed = |e: redis::RedisError| format!("Redis command failed: {}", e);
+
+        let records = match command.to_uppercase().as_str() {
+            "SCAN" => {
+                let mut keys = vec![];
+                let mut cursor = 0;
+                loop {
+                    let (next, page): (u64, Vec<String>) = redis::cmd("SCAN")
+                        .arg(cursor)
+                        .arg("MATCH")
+                        .arg(if args.is_empty() { "*" } else { args })
+                        .arg("COUNT")
+                        .arg(100)
+                        .query_async(conn)
+                        .await
+                        .map_err(failed)?;
+
+                    keys.extend(page);
+                    cursor = next;
+                    if cursor == 0 {
+                        break;
+                    }
+                }
+                keys.sort();
+
+                let mut records = vec![];
+                for key in keys {
+                    let hash: RedisValue = redis::cmd("HGETALL")
+                        .arg(&key)
+                        .query_async(conn)
+                        .await
+                        .map_err(failed)?;
+
+                    let mut record = Map::new();
+                    record.insert("key".to_string(), key.into());
+                    if let JsonValue::Object(fields) = to_record(&hash) {
+                        record.extend(fields);
+                    }
+                    records.push(JsonValue::Object(record));
+                }
+
+                records
+            }
+            "LRANGE" => {
+                let parts = args.split_whitespace().collect::<Vec<_>>();
+                let [key, start, stop] = parts[..] else {
+                    return Err("Expected the LRANGE key, start and stop".to_string());
+                };
+
+                let list: RedisValue = redis::cmd("LRANGE")
+                    .arg(key)
+                    .arg(start)
+                    .arg(stop)
+                    .query_async(conn)
+                    .await
+                    .map_err(failed)?;
+
+                to_records(&list)
+            }
+            "EVAL" => {
+                let result: RedisValue = redis::cmd("EVAL")
+                    .arg(args)
+                    .arg(0)
+                    .query_async(conn)
+                    .await
+                    .map_err(failed)?;
+
+                to_records(&result)
+            }
+            c => return Err(format!("Redis command {} not supported", c)),
+        };
+
+        let mut query = query;
+        if query.fields.is_empty() {
+            query.fields = json::infer_fields(&records);
+            apply_overrides(&mut query.fields, &query.overrides);
+        }
+
+        json::to_rows(&records, query)
+    }
+}
+
+/// Records of the list, one per element
+fn to_records(value: &RedisValue) -> Vec<JsonValue> {
+    match value {
+        RedisValue::Nil => vec![],
+        RedisValue::Array(items) | RedisValue::Set(items) => items.iter().map(to_record).collect(),
+        v => vec![to_record(v)],
+    }
+}
+
+/// Record of the flat key/value list, of the map, of the JSON object
+/// text or, for anything else, with the value as `value`
+fn to_record(value: &RedisValue) -> JsonValue {
+    let pairs = match value {
+        RedisValue::Map(pairs) => pairs.iter().map(|(k, v)| (k, v)).collect::<Vec<_>>(),
+        RedisValue::Array(items) if items.len() % 2 == 0 && !items.is_empty() => {
+            items.chunks(2).map(|p| (&p[0], &p[1])).collect::<Vec<_>>()
+        }
+        _ => vec![],
+    };
+
+    if !pairs.is_empty() {
+        let record = pairs
+            .into_iter()
+            .map(|(k, v)| match to_json(k) {
+                JsonValue::String(k) => (k, to_json(v)),
+                k => (k.to_string(), to_json(v)),
+            })
+            .collect::<Map<_, _>>();
+        return JsonValue::Object(record);
+    }
+
+    match to_json(value) {
+        JsonValue::String(s) if s.trim_start().starts_with('{') => {
+            match serde_json::from_str::<JsonValue>(&s) {
+                Ok(object @ JsonValue::Object(_)) => object,
+                _ => serde_json::json!({ "value": s }),
+            }
+        }
+        v => serde_json::json!({ "value": v }),
+    }
+}
+
+/// JSON of the value, with the numeric texts as numbers
+fn to_json(value: &RedisValue) -> JsonValue {
+    let text = match value {
+        RedisValue::Nil => return JsonValue::Null,
+        RedisValue::Int(v) => return JsonValue::from(*v),
+        RedisValue::Double(v) => return JsonValue::from(*v),
+        RedisValue::Boolean(v) => return JsonValue::from(*v),
+        RedisValue::Okay => "OK".to_string(),
+        RedisValue::BulkString(v) => String::from_utf8_lossy(v).to_string(),
+        RedisValue::SimpleString(v) => v.clone(),
+        RedisValue::VerbatimString { text, .. } => text.clone(),
+        RedisValue::Array(items) | RedisValue::Set(items) => {
+            return JsonValue::Array(items.iter().map(to_json).collect())
+        }
+        v => format!("{:?}", v),
+    };
+
+    if let Ok(v) = text.parse::<i64>() {
+        return JsonValue::from(v);
+    }
+    match text.parse::<f64>() {
+        Ok(v) if v.is_finite() => JsonValue::from(v),
+        _ => JsonValue::from(text),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::to_records;
+    use redis::Value as RedisValue;
+    use serde_json::json;
+
+    fn bulk(v: &str) -> RedisValue {
+        RedisValue::BulkString(v.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn lists() {
+        let list = RedisValue::Array(vec![
+            bulk(r#"{"job": "mail", "tries": 2}"#),
+            bulk("plain"),
+            bulk("3.5"),
+        ]);
+        assert_eq!(
+            vec![
+                json!({"job": "mail", "tries": 2}),
+                json!({"value": "plain"}),
+                json!({"value": 3.5}),
+            ],
+            to_records(&list)
+        );
+
+        let script = RedisValue::Array(vec![
+            RedisValue::Array(vec![
+                bulk("queue"),
+                bulk("mail"),
+                bulk("len"),
+                RedisValue::Int(12),
+            ]),
+            RedisValue::Array(vec![
+                bulk("queue"),
+                bulk("sms"),
+                bulk("len"),
+                RedisValue::Int(0),
+            ]),
+        ]);
+        assert_eq!(
+            vec![
+                json!({"queue": "mail", "len": 12}),
+                json!({"queue": "sms", "len": 0}),
+            ],
+            to_records(&script)
+        );
+
+        assert_eq!(vec![json!({"value": 7})], to_records(&RedisValue::Int(7)));
+        assert!(to_records(&RedisValue::Nil).is_empty());
+    }
+}
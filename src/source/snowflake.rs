@@ -0,0 +1,268 @@
+//! Snowflake driver implementation, over the SQL REST API
+//!
+//! The `conn` is the account url with the context as params, like
+//! `https://acme.snowflakecomputing.com?warehouse=WH&database=SALES&schema=PUBLIC`,
+//! and the `token` of the source is sent as bearer, an OAuth one by default
+
+use super::{http::HttpDriver, json, Driver, Query};
+use crate::value::{apply_overrides, Field, FieldType, Value};
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime, TimeDelta};
+use serde_json::{Map, Value as JsonValue};
+use std::{collections::BTreeMap, time::Duration};
+
+const TOKEN_TYPE: &str = "X-Snowflake-Authorization-Token-Type";
+
+pub struct SnowflakeDriver {
+    pub http: HttpDriver,
+    pub context: Vec<(String, String)>,
+}
+
+impl SnowflakeDriver {
+    pub fn init(headers: BTreeMap<String, String>, token: Option<String>) -> Self {
+        let mut headers = headers;
+        headers.insert("Accept".to_string(), "application/json".to_string());
+        if let Some(token) = token {
+            headers.insert("Authorization".to_string(), format!("Bearer {}", token));
+            headers
+                .entry(TOKEN_TYPE.to_string())
+                .or_insert("OAUTH".to_string());
+        }
+
+        Self {
+            http: HttpDriver::init(headers, None),
+            context: vec![],
+        }
+    }
+}
+
+#[async_trait]
+impl Driver for SnowflakeDriver {
+    async fn connect(&mut self, sconn: String) -> Result<(), String> {
+        let mut url =
+            reqwest::Url::parse(&sconn).map_err(|e| format!("Invalid url {}: {}", sconn, e))?;
+
+        self.context = url.query_pairs().into_owned().collect();
+        url.set_query(None);
+
+        self.http.connect(url.to_string()).await
+    }
+
+    fn paginate(&self, sql: &str, size: usize, offset: usize) -> Option<String> {
+        Some(super::limit_offset(sql, size, offset))
+    }
+
+    async fn fetch(&mut self, query: Query) -> Result<Vec<Vec<Value>>, String> {
+        let mut body = Map::new();
+        body.insert("statement".to_string(), query.sql.clone().into());
+        body.insert("timeout".to_string(), 300.into());
+        for (k, v) in &self.context {
+            body.insert(k.to_lowercase(), v.clone().into());
+        }
+
+        let content = self
+            .http
+            .post(
+                "/api/v2/statements",
+                &[],
+                "application/json",
+                JsonValue::Object(body).to_string(),
+            )
+            .await?;
+        let mut content: JsonValue = serde_json::from_str(&content)
+            .map_err(|e| format!("Snowflake response not parsed: {}", e))?;
+
+        let handle = content["statementHandle"]
+            .as_str()
+            .ok_or(format!(
+                "Snowflake statement failed: {}",
+                content["message"]
+            ))?
+            .to_string();
+        let path = format!("/api/v2/statements/{}", handle);
+
+        // Asynchronous execution, while the statement is running
+        while content.get("resultSetMetaData").is_none() {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            content = self.http.get(&path, &[]).await?;
+        }
+
+        let partitions = content["resultSetMetaData"]["partitionInfo"]
+            .as_array()
+            .map(|p| p.len())
+            .unwrap_or(1);
+
+        let mut pages = vec![content];
+        for partition in 1..partitions {
+            pages.push(
+                self.http
+                    .get(&path, &[("partition", partition.to_string())])
+                    .await?,
+            );
+        }
+
+        let (records, mut fields) = to_records(&pages)?;
+
+        let mut query = query;
+        if query.fields.is_empty() {
+            apply_overrides(&mut fields, &query.overrides);
+            query.fields = fields;
+        }
+
+        json::to_rows(&records, query)
+    }
+}
+
+/// Records and fields of the row type and data of the partitions
+fn to_records(pages: &[JsonValue]) -> Result<(Vec<JsonValue>, Vec<Field>), String> {
+    let columns = pages
+        .first()
+        .and_then(|p| p["resultSetMetaData"]["rowType"].as_array())
+        .cloned()
+        .unwrap_or_default()
+        .iter()
+        .map(|c| {
+            (
+                c["name"].as_str().unwrap_or_default().to_string(),
+                c["type"].as_str().unwrap_or_default().to_lowercase(),
+                c["scale"].as_i64().unwrap_or_default(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let fields = columns
+        .iter()
+        .map(|(name, kind, scale)| Field {
+            field: name.clone(),
+            title: name.clone(),
+            kind: match kind.as_str() {
+                "fixed" if *scale == 0 => FieldType::Integer,
+                "fixed" | "real" => FieldType::Float,
+                "date" => FieldType::Date,
+                "time" => FieldType::Time,
+                k if k.starts_with("timestamp") => FieldType::DateTime,
+                _ => FieldType::String,
+            },
+            ..Default::default()
+        })
+        .collect();
+
+    let mut records = vec![];
+    for row in pages
+        .iter()
+        .flat_map(|p| p["data"].as_array().into_iter().flatten())
+    {
+        let mut record = Map::new();
+        for ((name, kind, scale), cell) in columns.iter().zip(row.as_array().into_iter().flatten())
+        {
+            let value = match cell.as_str() {
+                Some(raw) => convert(kind, *scale, raw)
+                    .ok_or(format!("Invalid {} value {} on {}", kind, raw, name))?,
+                None => JsonValue::Null,
+            };
+            record.insert(name.clone(), value);
+        }
+        records.push(JsonValue::Object(record));
+    }
+
+    Ok((records, fields))
+}
+
+/// JSON of the raw value, with the dates and times on the ISO formats
+fn convert(kind: &str, scale: i64, raw: &str) -> Option<JsonValue> {
+    let epoch = |secs: f64| DateTime::from_timestamp_micros((secs * 1_000_000.0).round() as i64);
+
+    let value = match kind {
+        "fixed" if scale == 0 => raw.parse::<i64>().ok()?.into(),
+        "fixed" | "real" => raw.parse::<f64>().ok()?.into(),
+        "date" => {
+            let days = raw.parse::<i64>().ok()?;
+            let date = NaiveDate::from_ymd_opt(1970, 1, 1)? + TimeDelta::try_days(days)?;
+            date.format("%Y-%m-%d").to_string().into()
+        }
+        "time" => {
+            let secs = raw.parse::<f64>().ok()?;
+            NaiveTime::from_num_seconds_from_midnight_opt(secs as u32, 0)?
+                .format("%H:%M:%S")
+                .to_string()
+                .into()
+        }
+        "timestamp_tz" => {
+            let (secs, offset) = raw.split_once(' ')?;
+            let offset = FixedOffset::east_opt((offset.parse::<i32>().ok()? - 1440) * 60)?;
+            epoch(secs.parse().ok()?)?
+                .with_timezone(&offset)
+                .to_rfc3339()
+                .into()
+        }
+        k if k.starts_with("timestamp") => epoch(raw.parse().ok()?)?.to_rfc3339().into(),
+        _ => raw.into(),
+    };
+
+    Some(value)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{convert, to_records};
+    use crate::value::FieldType;
+    use serde_json::json;
+
+    #[test]
+    fn values() {
+        assert_eq!(Some(json!(42)), convert("fixed", 0, "42"));
+        assert_eq!(Some(json!(4.25)), convert("fixed", 2, "4.25"));
+        assert_eq!(Some(json!("2024-06-10")), convert("date", 0, "19884"));
+        assert_eq!(
+            Some(json!("10:30:00")),
+            convert("time", 9, "37800.000000000")
+        );
+        assert_eq!(
+            Some(json!("2024-06-10T06:13:20+00:00")),
+            convert("timestamp_ntz", 9, "1718000000.000000000")
+        );
+        assert_eq!(
+            Some(json!("2024-06-10T03:13:20-03:00")),
+            convert("timestamp_tz", 9, "1718000000.000000000 1260")
+        );
+        assert_eq!(None, convert("fixed", 0, "abc"));
+    }
+
+    #[test]
+    fn partitions() -> Result<(), String> {
+        let first = json!({
+            "statementHandle": "01b2",
+            "resultSetMetaData": {
+                "partitionInfo": [{"rowCount": 1}, {"rowCount": 1}],
+                "rowType": [
+                    {"name": "REGION", "type": "text", "scale": null},
+                    {"name": "ORDERS", "type": "fixed", "scale": 0},
+                    {"name": "DAY", "type": "date", "scale": null}
+                ]
+            },
+            "data": [["South", "10", "19884"]]
+        });
+        let second = json!({"data": [["North", null, null]]});
+
+        let (records, fields) = to_records(&[first, second])?;
+        assert_eq!(
+            vec![FieldType::String, FieldType::Integer, FieldType::Date],
+            fields.iter().map(|f| f.kind.clone()).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![
+                json!({"REGION": "South", "ORDERS": 10, "DAY": "2024-06-10"}),
+                json!({"REGION": "North", "ORDERS": null, "DAY": null}),
+            ],
+            records
+        );
+
+        assert!(to_records(&[json!({
+            "resultSetMetaData": {"rowType": [{"name": "A", "type": "fixed", "scale": 0}]},
+            "data": [["x"]]
+        })])
+        .is_err());
+
+        Ok(())
+    }
+}
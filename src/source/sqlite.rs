@@ -1,75 +1,160 @@
 //! Sqlite driver implementation
-use super::{Driver, Query};
-use crate::value::{FieldType, TypedValue, Value};
+//!
+//! The `conn` is the path of the database or a remote one, on a http(s)
+//! url or a `s3://bucket/key` uri, downloaded to a temporary file
+use super::{s3, Driver, OnChunk, Query};
+use crate::value::{apply_overrides, Field, FieldType, TypedValue, Value};
 use async_trait::async_trait;
 use chrono::{DateTime, NaiveDate, NaiveTime};
-use sqlite::{self, Connection, Error, State};
+use sqlite::{self, Connection, Error, OpenFlags, State, Statement, Type, Value as SqlValue};
+use std::{collections::BTreeMap, ffi::CStr, fs, path::PathBuf};
 
 pub struct SqliteDriver {
     pub conn: Option<Connection>,
+    pub temp: Option<PathBuf>,
+    /// Database, and the attached ones, opened read only
+    pub read_only: bool,
+    /// Other databases, by their alias, attached on the connection
+    pub attach: BTreeMap<String, String>,
 }
 
 impl SqliteDriver {
     pub fn init() -> Self {
-        Self { conn: None }
+        Self {
+            conn: None,
+            temp: None,
+            read_only: false,
+            attach: BTreeMap::new(),
+        }
+    }
+}
+
+impl Drop for SqliteDriver {
+    fn drop(&mut self) {
+        self.conn = None;
+        if let Some(temp) = self.temp.take() {
+            let _ = fs::remove_file(temp);
+        }
     }
 }
 
 #[async_trait]
 impl Driver for SqliteDriver {
     async fn connect(&mut self, sconn: String) -> Result<(), String> {
-        let conn = sqlite::open(sconn)
-            .map_err(|e| format!("Sqlite connection failed: {}", e.to_string()))?;
+        let sconn = if is_remote(&sconn) {
+            let content = download(&sconn).await?;
+
+            let temp = std::env::temp_dir().join(format!("lmr-{}.sqlite", uuid::Uuid::new_v4()));
+            fs::write(&temp, content)
+                .map_err(|e| format!("Database {} not saved: {}", temp.display(), e))?;
+            self.temp = Some(temp.clone());
+
+            temp.to_string_lossy().to_string()
+        } else {
+            sconn
+        };
+
+        // The attached databases are opened with the same flags
+        let flags = if self.read_only {
+            OpenFlags::new().with_read_only()
+        } else {
+            OpenFlags::new().with_create().with_read_write()
+        };
+
+        let conn = Connection::open_with_flags(sconn, flags)
+            .map_err(|e| format!("Sqlite connection failed: {}", e))?;
+
+        for (alias, path) in &self.attach {
+            let mut statement = conn
+                .prepare(format!(
+                    "ATTACH DATABASE ?1 AS \"{}\"",
+                    alias.replace('"', "\"\"")
+                ))
+                .and_then(|mut s| s.bind((1, path.as_str())).map(|_| s))
+                .map_err(|e| format!("Database {} not attached: {}", alias, e))?;
+            statement
+                .next()
+                .map_err(|e| format!("Database {} not attached: {}", alias, e))?;
+        }
 
         self.conn = Some(conn);
 
         Ok(())
     }
 
+    fn paginate(&self, sql: &str, size: usize, offset: usize) -> Option<String> {
+        Some(super::limit_offset(sql, size, offset))
+    }
+
     async fn fetch(&mut self, query: Query) -> Result<Vec<Vec<Value>>, String> {
+        let mut values = vec![];
+
+        self.fetch_chunks(query, usize::MAX, &mut |rows| {
+            values.extend(rows);
+            Ok(true)
+        })
+        .await?;
+
+        Ok(values)
+    }
+
+    async fn fetch_chunks(
+        &mut self,
+        query: Query,
+        size: usize,
+        on_chunk: &mut OnChunk,
+    ) -> Result<(), String> {
         let conn = self
             .conn
             .as_ref()
             .ok_or("Connection not established".to_string())?;
 
+        let (sql, params) = query.bind_params(|i| format!("?{}", i))?;
+
         let mut statement = conn
-            .prepare(query.sql)
-            .map_err(|e| format!("Prepare statement failed: {}", e.to_string()))?;
+            .prepare(sql)
+            .map_err(|e| format!("Prepare statement failed: {}", e))?;
+        statement
+            .bind(&params.iter().map(|p| p.as_str()).collect::<Vec<_>>()[..])
+            .map_err(|e| format!("Bind parameters failed: {}", e))?;
 
         let mut values = vec![];
+        let mut fields = query.fields.clone();
 
-        while let Ok(State::Row) = statement.next() {
+        while let State::Row = statement
+            .next()
+            .map_err(|e| format!("Fetch row failed: {}", e))?
+        {
             let mut row = vec![];
 
-            for col in &query.fields {
-                let efmt = |e: Error| {
-                    format!(
-                        "Read column {} row {} failed: {}",
-                        col.field,
-                        row.len(),
-                        e.to_string()
-                    )
-                };
+            if fields.is_empty() {
+                fields = infer_fields(&statement);
+                apply_overrides(&mut fields, &query.overrides);
+            }
+
+            for col in &fields {
+                let efmt =
+                    |e: Error| format!("Read column {} row {} failed: {}", col.field, row.len(), e);
                 let inner = match &col.kind {
                     FieldType::Integer => statement
                         .read::<Option<i64>, _>(col.field.as_str())
                         .map_err(efmt)?
-                        .map(|v| TypedValue::Integer(v)),
+                        .map(TypedValue::Integer),
                     FieldType::String => statement
                         .read::<Option<String>, _>(col.field.as_str())
                         .map_err(efmt)?
-                        .map(|v| TypedValue::String(v)),
+                        .map(TypedValue::String),
                     FieldType::Float => statement
                         .read::<Option<f64>, _>(col.field.as_str())
                         .map_err(efmt)?
-                        .map(|v| TypedValue::Float(v)),
+                        .map(TypedValue::Float),
                     FieldType::Time => {
                         let raw = statement
                             .read::<Option<String>, _>(col.field.as_str())
                             .map_err(efmt)?;
                         if let Some(raw) = raw {
                             let dt = NaiveTime::parse_from_str(&raw, "%H:%M:%S").map_err(|e| {
-                                format!("Error on parse the {} to time: {}", raw, e.to_string())
+                                format!("Error on parse the {} to time: {}", raw, e)
                             })?;
 
                             Some(TypedValue::Time(dt))
@@ -83,7 +168,7 @@ impl Driver for SqliteDriver {
                             .map_err(efmt)?;
                         if let Some(raw) = raw {
                             let dt = NaiveDate::parse_from_str(&raw, "%Y-%m-%d").map_err(|e| {
-                                format!("Error on parse the {} to date: {}", raw, e.to_string())
+                                format!("Error on parse the {} to date: {}", raw, e)
                             })?;
 
                             Some(TypedValue::Date(dt))
@@ -91,13 +176,27 @@ impl Driver for SqliteDriver {
                             None
                         }
                     }
+                    FieldType::Image => statement
+                        .read::<Option<Vec<u8>>, _>(col.field.as_str())
+                        .map_err(efmt)?
+                        .map(TypedValue::Image),
+                    FieldType::Decimal => statement
+                        .read::<Option<String>, _>(col.field.as_str())
+                        .map_err(efmt)?
+                        .map(|raw| TypedValue::parse_decimal(&raw))
+                        .transpose()?,
+                    FieldType::Array | FieldType::Json => statement
+                        .read::<Option<String>, _>(col.field.as_str())
+                        .map_err(efmt)?
+                        .map(|raw| TypedValue::parse_json(&col.kind, &raw))
+                        .transpose()?,
                     FieldType::DateTime => {
                         let raw = statement
                             .read::<Option<String>, _>(col.field.as_str())
                             .map_err(efmt)?;
                         if let Some(raw) = raw {
                             let dt = DateTime::parse_from_rfc3339(&raw).map_err(|e| {
-                                format!("Error on parse the {} to datetime: {}", raw, e.to_string())
+                                format!("Error on parse the {} to datetime: {}", raw, e)
                             })?;
 
                             Some(TypedValue::DateTime(dt))
@@ -114,9 +213,172 @@ impl Driver for SqliteDriver {
             }
 
             values.push(row);
+
+            if values.len() >= size && !on_chunk(std::mem::take(&mut values))? {
+                return Ok(());
+            }
         }
 
-        Ok(values)
+        if !values.is_empty() {
+            on_chunk(values)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn is_remote(sconn: &str) -> bool {
+    ["http://", "https://", "s3://"]
+        .iter()
+        .any(|p| sconn.starts_with(p))
+}
+
+/// Content of the remote database
+async fn download(sconn: &str) -> Result<Vec<u8>, String> {
+    let client = reqwest::Client::new();
+
+    if sconn.starts_with("s3://") {
+        return s3::get(&client, sconn).await;
+    }
+
+    let response = client
+        .get(sconn)
+        .send()
+        .await
+        .map_err(|e| format!("Database download failed: {}", e))?
+        .error_for_status()
+        .map_err(|e| format!("Database download failed: {}", e))?;
+
+    let content = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Database download failed: {}", e))?;
+
+    Ok(content.to_vec())
+}
+
+/// Create the table with the rows, the columns typed by the fields
+pub fn materialize(
+    conn: &Connection,
+    table: &str,
+    fields: &[Field],
+    rows: &[Vec<Value>],
+) -> Result<(), String> {
+    if fields.is_empty() {
+        return Err(format!("Table {} without columns", table));
+    }
+
+    let quote = |name: &str| format!("\"{}\"", name.replace('"', "\"\""));
+
+    let columns = fields
+        .iter()
+        .map(|f| {
+            let kind = match f.kind {
+                FieldType::Integer => "INTEGER",
+                FieldType::Float => "REAL",
+                FieldType::Image => "BLOB",
+                _ => "TEXT",
+            };
+            format!("{} {}", quote(&f.field), kind)
+        })
+        .collect::<Vec<_>>();
+
+    conn.execute(format!(
+        "CREATE TABLE {} ({})",
+        quote(table),
+        columns.join(", ")
+    ))
+    .map_err(|e| format!("Table {} not created: {}", table, e))?;
+
+    let mut statement = conn
+        .prepare(format!(
+            "INSERT INTO {} VALUES ({})",
+            quote(table),
+            vec!["?"; fields.len()].join(", ")
+        ))
+        .map_err(|e| format!("Prepare statement failed: {}", e))?;
+
+    for row in rows {
+        let values = row
+            .iter()
+            .map(|v| match &v.inner {
+                None => SqlValue::Null,
+                Some(TypedValue::Integer(i)) => SqlValue::Integer(*i),
+                Some(TypedValue::Float(f)) => SqlValue::Float(*f),
+                Some(TypedValue::Image(b)) => SqlValue::Binary(b.clone()),
+                Some(TypedValue::DateTime(dt)) => SqlValue::String(dt.to_rfc3339()),
+                Some(v @ (TypedValue::Array(_) | TypedValue::Json(_))) => {
+                    SqlValue::String(v.to_json().to_string())
+                }
+                Some(v) => SqlValue::String(v.to_string()),
+            })
+            .collect::<Vec<_>>();
+
+        statement
+            .bind(&values[..])
+            .and_then(|_| statement.next())
+            .and_then(|_| statement.reset())
+            .map_err(|e| format!("Insert on {} failed: {}", table, e))?;
+    }
+
+    Ok(())
+}
+
+/// Fields by the declared type of the columns or, for expressions,
+/// by the type of the value on the current row
+fn infer_fields(statement: &Statement) -> Vec<Field> {
+    let mut fields = vec![];
+
+    for (i, name) in statement.column_names().iter().enumerate() {
+        let kind = match declared_type(statement, i) {
+            Some(decl) if decl.contains("INT") => FieldType::Integer,
+            Some(decl)
+                if decl.contains("REAL")
+                    || decl.contains("FLOA")
+                    || decl.contains("DOUB")
+                    || decl.contains("NUMERIC")
+                    || decl.contains("DECIMAL") =>
+            {
+                FieldType::Float
+            }
+            Some(decl) if decl.contains("BLOB") => FieldType::Image,
+            Some(_) => FieldType::String,
+            None => match statement.column_type(i) {
+                Ok(Type::Integer) => FieldType::Integer,
+                Ok(Type::Float) => FieldType::Float,
+                _ => FieldType::String,
+            },
+        };
+
+        fields.push(Field {
+            field: name.clone(),
+            title: name.clone(),
+            kind,
+            ..Default::default()
+        });
+    }
+
+    fields
+}
+
+/// Type declared on the table of the column, uppercased. None for the
+/// expressions, the crate doesn't expose it so read from the ffi
+fn declared_type(statement: &Statement, column: usize) -> Option<String> {
+    if column >= statement.column_count() {
+        return None;
+    }
+
+    // SAFETY: `as_raw` is the statement prepared and owned by `statement`,
+    // alive for the whole borrow, and the column is inside of its bounds.
+    // Sqlite returns null or a nul terminated text owned by the statement,
+    // valid until it is finalized or reprepared, so it's copied right away
+    unsafe {
+        let raw = sqlite::ffi::sqlite3_column_decltype(statement.as_raw(), column as i32);
+        if raw.is_null() {
+            None
+        } else {
+            Some(CStr::from_ptr(raw).to_string_lossy().to_uppercase())
+        }
     }
 }
 
@@ -124,10 +386,11 @@ impl Driver for SqliteDriver {
 #[allow(deprecated)]
 pub mod tests {
     use chrono::{FixedOffset, NaiveDate, NaiveTime, TimeZone};
+    use std::collections::BTreeMap;
 
     use crate::{
         source::{sqlite::SqliteDriver, Driver, Query},
-        value::{Field, FieldType, TypedValue},
+        value::{Field, FieldOverride, FieldType, TypedValue},
     };
 
     #[tokio::test]
@@ -150,33 +413,40 @@ pub mod tests {
                     title: "a".to_string(),
                     field: "a".to_string(),
                     kind: FieldType::String,
+                    ..Default::default()
                 },
                 Field {
                     title: "b".to_string(),
                     field: "b".to_string(),
                     kind: FieldType::Integer,
+                    ..Default::default()
                 },
                 Field {
                     title: "c".to_string(),
                     field: "c".to_string(),
                     kind: FieldType::Float,
+                    ..Default::default()
                 },
                 Field {
                     title: "d".to_string(),
                     field: "d".to_string(),
                     kind: FieldType::Time,
+                    ..Default::default()
                 },
                 Field {
                     title: "e".to_string(),
                     field: "e".to_string(),
                     kind: FieldType::Date,
+                    ..Default::default()
                 },
                 Field {
                     title: "f".to_string(),
                     field: "f".to_string(),
                     kind: FieldType::DateTime,
+                    ..Default::default()
                 },
             ],
+            ..Default::default()
         };
 
         let result = driver.fetch(query.clone()).await?;
@@ -202,7 +472,7 @@ pub mod tests {
             row[3].inner
         );
         assert_eq!(
-            Some(TypedValue::Date(NaiveDate::from_ymd(2024, 05, 15))),
+            Some(TypedValue::Date(NaiveDate::from_ymd(2024, 5, 15))),
             row[4].inner
         );
         assert_eq!(
@@ -237,13 +507,16 @@ pub mod tests {
                     title: "a".to_string(),
                     field: "a".to_string(),
                     kind: FieldType::String,
+                    ..Default::default()
                 },
                 Field {
                     title: "g".to_string(),
                     field: "g".to_string(),
                     kind: FieldType::Integer,
+                    ..Default::default()
                 },
             ],
+            ..Default::default()
         };
 
         let result = driver.fetch(query.clone()).await;
@@ -254,4 +527,187 @@ pub mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn infer_fields() -> Result<(), String> {
+        let mut driver = SqliteDriver::init();
+        driver.connect(":memory:".to_string()).await?;
+
+        let query = "
+                CREATE TABLE test (a TEXT, b INTEGER, c REAL, d VARCHAR(10));
+                INSERT INTO test VALUES ('Olá mundo', 2024, 123.45, 'abc');
+            ";
+        driver.conn.as_ref().unwrap().execute(query).unwrap();
+
+        let query = Query {
+            title: "Test".to_string(),
+            sql: "select a, b, c, d, count(1) as qt, avg(c) as e from test".to_string(),
+            overrides: vec![FieldOverride {
+                field: "qt".to_string(),
+                title: Some("Quantity".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let result = driver.fetch(query).await?;
+        assert_eq!(1, result.len());
+
+        let row = &result[0];
+        let kinds = row
+            .iter()
+            .map(|v| (v.field.field.clone(), v.field.kind.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                ("a".to_string(), FieldType::String),
+                ("b".to_string(), FieldType::Integer),
+                ("c".to_string(), FieldType::Float),
+                ("d".to_string(), FieldType::String),
+                ("qt".to_string(), FieldType::Integer),
+                ("e".to_string(), FieldType::Float),
+            ],
+            kinds
+        );
+        assert_eq!(Some(TypedValue::Integer(2024)), row[1].inner);
+        assert_eq!(Some(TypedValue::Integer(1)), row[4].inner);
+        assert_eq!("Quantity", row[4].field.title);
+        assert_eq!("e", row[5].field.title);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn chunks() -> Result<(), String> {
+        let mut driver = SqliteDriver::init();
+        driver.connect(":memory:".to_string()).await?;
+
+        let query = Query {
+            title: "Test".to_string(),
+            sql: "with recursive n(i) as (select 1 union all select i + 1 from n where i < 5)
+                  select i from n"
+                .to_string(),
+            ..Default::default()
+        };
+
+        let mut sizes = vec![];
+        driver
+            .fetch_chunks(query.clone(), 2, &mut |rows| {
+                sizes.push(rows.len());
+                Ok(true)
+            })
+            .await?;
+        assert_eq!(vec![2, 2, 1], sizes);
+
+        let mut sizes = vec![];
+        driver
+            .fetch_chunks(query.clone(), 2, &mut |rows| {
+                sizes.push(rows.len());
+                Ok(sizes.len() < 2)
+            })
+            .await?;
+        assert_eq!(vec![2, 2], sizes);
+
+        let query = Query {
+            sql: "with recursive n(i) as (select 1 union all select i + 1 from n where i < 5)
+                  select abs(case when i = 4 then -9223372036854775807 - 1 else i end) as i from n"
+                .to_string(),
+            ..query
+        };
+        let mut sizes = vec![];
+        let result = driver
+            .fetch_chunks(query, 2, &mut |rows| {
+                sizes.push(rows.len());
+                Ok(true)
+            })
+            .await;
+        assert!(result.is_err_and(|e| e.starts_with("Fetch row failed")));
+        assert_eq!(vec![2], sizes);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn params() -> Result<(), String> {
+        let mut driver = SqliteDriver::init();
+        driver.connect(":memory:".to_string()).await?;
+
+        let query = "
+                    CREATE TABLE users (id INTEGER, name TEXT);
+                    INSERT INTO users VALUES (1, 'Alice');
+                    INSERT INTO users VALUES (2, 'Bob');
+                ";
+        driver.conn.as_ref().unwrap().execute(query).unwrap();
+
+        let result = driver
+            .fetch(Query {
+                title: "Test".to_string(),
+                sql: "select name from users where id = :id or name = ':id'".to_string(),
+                params: BTreeMap::from([("id".to_string(), "2".to_string())]),
+                ..Default::default()
+            })
+            .await?;
+
+        assert_eq!(1, result.len());
+        assert_eq!(
+            Some(TypedValue::String("Bob".to_string())),
+            result[0][0].inner
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn read_only() -> Result<(), String> {
+        let path = std::env::temp_dir().join("test-lmr-read-only.sqlite");
+        let archive = std::env::temp_dir().join("test-lmr-read-only-archive.sqlite");
+        for (path, sql) in [
+            (
+                &path,
+                "CREATE TABLE users (id INTEGER); INSERT INTO users VALUES (1);",
+            ),
+            (
+                &archive,
+                "CREATE TABLE users (id INTEGER); INSERT INTO users VALUES (2);",
+            ),
+        ] {
+            let _ = std::fs::remove_file(path);
+            sqlite::open(path)
+                .and_then(|c| c.execute(sql))
+                .map_err(|e| e.to_string())?;
+        }
+
+        let mut driver = SqliteDriver::init();
+        driver.read_only = true;
+        driver.attach =
+            BTreeMap::from([("archive".to_string(), archive.to_string_lossy().to_string())]);
+        driver.connect(path.to_string_lossy().to_string()).await?;
+
+        let query = |sql: &str| Query {
+            title: "Test".to_string(),
+            sql: sql.to_string(),
+            ..Default::default()
+        };
+
+        let _ = driver.fetch(query("delete from users")).await;
+        let _ = driver.fetch(query("delete from archive.users")).await;
+        assert_eq!(
+            2,
+            driver
+                .fetch(query(
+                    "select id from main.users union all select id from archive.users"
+                ))
+                .await?
+                .len()
+        );
+
+        let mut driver = SqliteDriver::init();
+        driver.read_only = true;
+        assert!(driver
+            .connect("/tmp/test-lmr-missing.sqlite".to_string())
+            .await
+            .is_err());
+
+        Ok(())
+    }
 }
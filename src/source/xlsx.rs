@@ -0,0 +1,108 @@
+//! Excel file driver implementation
+//!
+//! The `conn` is the path of the workbook, `.xlsx` or any other format
+//! read by calamine. The `sql` of the query is the name of the worksheet,
+//! the first one when empty, with a header on the first row
+
+use super::{csv, Driver, Query};
+use crate::value::Value;
+use async_trait::async_trait;
+use calamine::{open_workbook_auto, Data, Reader, Sheets};
+use chrono::NaiveTime;
+use std::{fs::File, io::BufReader};
+
+pub struct XlsxDriver {
+    pub workbook: Option<Sheets<BufReader<File>>>,
+}
+
+impl XlsxDriver {
+    pub fn init() -> Self {
+        Self { workbook: None }
+    }
+}
+
+#[async_trait]
+impl Driver for XlsxDriver {
+    async fn connect(&mut self, sconn: String) -> Result<(), String> {
+        let workbook = open_workbook_auto(&sconn)
+            .map_err(|e| format!("Workbook {} not opened: {}", sconn, e))?;
+
+        self.workbook = Some(workbook);
+
+        Ok(())
+    }
+
+    async fn fetch(&mut self, query: Query) -> Result<Vec<Vec<Value>>, String> {
+        let workbook = self
+            .workbook
+            .as_mut()
+            .ok_or("Connection not established".to_string())?;
+
+        let sheet = match query.sql.trim() {
+            "" => workbook
+                .sheet_names()
+                .first()
+                .cloned()
+                .ok_or("Workbook without worksheets".to_string())?,
+            name => name.to_string(),
+        };
+
+        let range = workbook
+            .worksheet_range(&sheet)
+            .map_err(|e| format!("Worksheet {} not read: {}", sheet, e))?;
+
+        let mut rows = range
+            .rows()
+            .map(|r| r.iter().map(to_text).collect::<Vec<_>>());
+
+        let headers = rows
+            .next()
+            .unwrap_or_default()
+            .iter()
+            .map(|h| h.trim().to_string())
+            .collect::<Vec<_>>();
+        let records = rows.collect::<Vec<_>>();
+
+        csv::to_rows(&headers, &records.iter().collect::<Vec<_>>(), query)
+    }
+}
+
+/// Text of the cell, with the dates and times on the ISO formats
+fn to_text(cell: &Data) -> String {
+    match cell {
+        Data::Empty | Data::Error(_) => String::new(),
+        Data::Float(v) if v.fract() == 0.0 && v.abs() < 1e15 => (*v as i64).to_string(),
+        Data::DateTime(dt) => match dt.as_datetime() {
+            Some(_) if dt.is_duration() || dt.as_f64() < 1.0 => {
+                let secs = (dt.as_f64().fract() * 86400.0).round() as u32;
+                NaiveTime::from_num_seconds_from_midnight_opt(secs % 86400, 0)
+                    .map(|t| t.format("%H:%M:%S").to_string())
+                    .unwrap_or_default()
+            }
+            Some(v) if v.time() == NaiveTime::MIN => v.format("%Y-%m-%d").to_string(),
+            Some(v) => v.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            None => dt.as_f64().to_string(),
+        },
+        v => v.to_string(),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::to_text;
+    use calamine::{Data, ExcelDateTime, ExcelDateTimeType};
+
+    #[test]
+    fn cells() {
+        let date = |v| Data::DateTime(ExcelDateTime::new(v, ExcelDateTimeType::DateTime, false));
+
+        assert_eq!("Alice", to_text(&Data::String("Alice".to_string())));
+        assert_eq!("42", to_text(&Data::Float(42.0)));
+        assert_eq!("9.5", to_text(&Data::Float(9.5)));
+        assert_eq!("true", to_text(&Data::Bool(true)));
+        assert_eq!("", to_text(&Data::Empty));
+        assert_eq!("2024-06-10", to_text(&date(45453.0)));
+        assert_eq!("2024-06-10T12:00:00Z", to_text(&date(45453.5)));
+        assert_eq!("10:30:00", to_text(&date(0.4375)));
+    }
+}
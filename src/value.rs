@@ -1,7 +1,14 @@
 //! Field/Value api
 
+use crate::clock;
 use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime};
+use rust_decimal::{
+    prelude::{FromPrimitive, ToPrimitive},
+    Decimal, RoundingStrategy,
+};
 use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use std::{collections::HashMap, fmt};
 
 /// Raw value from an data source row
 #[derive(Clone, Debug, PartialEq)]
@@ -10,55 +17,563 @@ pub struct Value {
     pub field: Field,
 }
 
+impl Value {
+    /// Numeric value, rounded to the field precision. Nulls are zero
+    pub fn to_float(&self) -> Result<f32, String> {
+        let v = match &self.inner {
+            Some(v) => v.to_float()?,
+            None => 0.0,
+        };
+
+        Ok(match self.field.precision {
+            Some(p) => {
+                let m = 10f32.powi(p as i32);
+                (v * m).round() / m
+            }
+            None => v,
+        })
+    }
+
+    /// Change against the last run with the arrow of it, like `▲ +12`
+    pub fn trend(&self) -> Option<String> {
+        self.field.trend.as_ref()?;
+        let v = self.inner.as_ref()?.to_float().ok()?;
+
+        Some(if v > 0.0 {
+            format!("▲ +{}", self)
+        } else if v < 0.0 {
+            format!("▼ {}", self)
+        } else {
+            format!("= {}", self)
+        })
+    }
+
+    /// Whether the change is to the better direction, none without change
+    pub fn better(&self) -> Option<bool> {
+        let v = self.inner.as_ref()?.to_float().ok()?;
+        match self.field.trend.as_ref()? {
+            _ if v == 0.0 => None,
+            Trend::Higher => Some(v > 0.0),
+            Trend::Lower => Some(v < 0.0),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let (Some(Unit::Bytes), Some(v)) = (&self.field.unit, &self.inner) {
+            if let Ok(v) = v.to_float() {
+                return write!(
+                    f,
+                    "{}",
+                    Unit::Bytes.humanize(v as f64, self.field.precision)
+                );
+            }
+        }
+
+        if let (Some(format), Some(v)) = (&self.field.format, &self.inner) {
+            let number = match v {
+                TypedValue::Integer(v) => Some(Decimal::from(*v)),
+                TypedValue::Float(v) => Decimal::from_f64(*v),
+                TypedValue::Decimal(v) => Some(*v),
+                _ => None,
+            };
+            if let Some(number) = number {
+                return write!(f, "{}", format.apply(number, self.field.precision));
+            }
+        }
+
+        match (&self.inner, self.field.precision) {
+            (Some(TypedValue::Float(v)), Some(p)) => write!(f, "{:.*}", p, v),
+            (Some(TypedValue::Decimal(v)), Some(p)) => write!(
+                f,
+                "{:.*}",
+                p,
+                v.round_dp_with_strategy(p as u32, RoundingStrategy::MidpointAwayFromZero)
+            ),
+            (Some(v), _) => write!(f, "{}", v),
+            (None, _) => Ok(()),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum TypedValue {
     String(String),
     Integer(i64),
     Float(f64),
+    /// Exact numbers, like the monetary ones
+    Decimal(Decimal),
     Time(NaiveTime),
     Date(NaiveDate),
     DateTime(DateTime<FixedOffset>),
+    Image(Vec<u8>),
+    /// Elements of the list, without the nulls
+    Array(Vec<TypedValue>),
+    Json(JsonValue),
 }
 
 impl TypedValue {
+    /// Decimal value of the text, also on the scientific notation
+    pub fn parse_decimal(raw: &str) -> Result<TypedValue, String> {
+        raw.parse::<Decimal>()
+            .or_else(|_| Decimal::from_scientific(raw))
+            .map(TypedValue::Decimal)
+            .map_err(|e| format!("Error on parse the {} to decimal: {}", raw, e))
+    }
+
+    /// Array or Json value of the JSON text, by the kind of the field
+    pub fn parse_json(kind: &FieldType, raw: &str) -> Result<TypedValue, String> {
+        let json = serde_json::from_str::<JsonValue>(raw)
+            .map_err(|e| format!("Error on parse the {} to json: {}", raw, e))?;
+
+        Ok(match (kind, json) {
+            (FieldType::Array, JsonValue::Array(items)) => TypedValue::Array(
+                items
+                    .into_iter()
+                    .filter(|i| !i.is_null())
+                    .map(TypedValue::from_json)
+                    .collect(),
+            ),
+            (FieldType::Array, v) => TypedValue::Array(vec![TypedValue::from_json(v)]),
+            (_, v) => TypedValue::Json(v),
+        })
+    }
+
+    /// JSON of the value, the numbers, lists and objects kept as such
+    pub fn to_json(&self) -> JsonValue {
+        match self {
+            TypedValue::Integer(v) => JsonValue::from(*v),
+            TypedValue::Float(v) => JsonValue::from(*v),
+            TypedValue::Array(v) => JsonValue::Array(v.iter().map(|i| i.to_json()).collect()),
+            TypedValue::Json(v) => v.clone(),
+            v => JsonValue::from(v.to_string()),
+        }
+    }
+
+    /// Scalar value of the JSON, the objects and lists kept as Json
+    pub fn from_json(value: JsonValue) -> TypedValue {
+        match value {
+            JsonValue::Number(n) if n.is_i64() => {
+                TypedValue::Integer(n.as_i64().unwrap_or_default())
+            }
+            JsonValue::Number(n) => TypedValue::Float(n.as_f64().unwrap_or_default()),
+            JsonValue::String(s) => TypedValue::String(s),
+            v => TypedValue::Json(v),
+        }
+    }
+
     pub fn to_float(&self) -> Result<f32, String> {
         match self {
             TypedValue::Float(v) => Ok(*v as f32),
+            TypedValue::Decimal(v) => Ok(v.to_f32().unwrap_or_default()),
             TypedValue::Integer(v) => Ok(*v as f32),
-            _ => Err(format!("Value {} is not a float", self.to_string())),
+            _ => Err(format!("Value {} is not a float", self)),
         }
     }
 }
 
-impl ToString for TypedValue {
-    fn to_string(&self) -> String {
+impl fmt::Display for TypedValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            TypedValue::String(v) => v.clone(),
-            TypedValue::Integer(v) => v.to_string(),
-            TypedValue::Float(v) => v.to_string(),
-            TypedValue::Time(v) => v.to_string(),
-            TypedValue::Date(v) => v.to_string(),
-            TypedValue::DateTime(v) => v.to_string(),
+            TypedValue::String(v) => write!(f, "{}", v),
+            TypedValue::Integer(v) => write!(f, "{}", v),
+            TypedValue::Float(v) => write!(f, "{}", v),
+            TypedValue::Decimal(v) => write!(f, "{}", v),
+            TypedValue::Time(v) => write!(f, "{}", v),
+            TypedValue::Date(v) => write!(f, "{}", v),
+            TypedValue::DateTime(v) => write!(f, "{}", clock::current().to_local(v)),
+            TypedValue::Image(v) => match std::str::from_utf8(v) {
+                Ok(url) if url.starts_with("http://") || url.starts_with("https://") => {
+                    write!(f, "{}", url)
+                }
+                _ => write!(f, "[image]"),
+            },
+            TypedValue::Array(v) => write!(
+                f,
+                "{}",
+                v.iter()
+                    .map(|i| i.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            TypedValue::Json(v) => write!(f, "{}", v),
         }
     }
 }
 
 /// Field definition
-#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
 pub struct Field {
     pub field: String,
+    /// Column name/alias when omitted
+    #[serde(default)]
     pub title: String,
     pub kind: FieldType,
+    /// Present the value as a link to itself
+    #[serde(default)]
+    pub link: bool,
+    /// Present the value as a link to the url, with {{field}} placeholders
+    #[serde(default)]
+    pub link_template: Option<String>,
+    /// Decimal places of the float values
+    #[serde(default)]
+    pub precision: Option<usize>,
+    /// Bar proportional to the value inside the HTML cell
+    #[serde(default)]
+    pub data_bar: bool,
+    /// Icons/emojis presented in place of the values, like `ok: ✅`
+    #[serde(default)]
+    pub icons: HashMap<String, String>,
+    /// Change on the fetched values, like `upper` or `round:2`
+    #[serde(default)]
+    pub transform: Option<Transform>,
+    /// Presentation of the numbers, like `1.234.567,89 €`
+    #[serde(default)]
+    pub format: Option<NumberFormat>,
+    /// Unit of the numbers, presented on a readable scale
+    #[serde(default)]
+    pub unit: Option<Unit>,
+    /// Alignment of the values on the tables
+    #[serde(default)]
+    pub align: Option<Align>,
+    /// Maximum of characters of the values on the tables, truncated with `…`
+    #[serde(default)]
+    pub max_width: Option<usize>,
+    /// Trend line of the list values inside the HTML cell
+    #[serde(default)]
+    pub sparkline: bool,
+    /// Change against the last run, of the delta columns
+    #[serde(skip)]
+    pub trend: Option<Trend>,
+}
+
+/// Direction of the better changes
+#[derive(Clone, Debug, PartialEq)]
+pub enum Trend {
+    Higher,
+    Lower,
 }
 
+/// Horizontal alignment of the table cells
 #[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+/// Unit of the numeric values
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+    /// Sizes, presented as KB, MB, GB...
+    Bytes,
+}
+
+impl Unit {
+    /// Divisor and name of the scale that fits the value
+    pub fn scale(&self, v: f64) -> (f64, &'static str) {
+        match self {
+            Unit::Bytes => {
+                let mut scale = (1.0, "B");
+                for name in ["KB", "MB", "GB", "TB", "PB"] {
+                    if v.abs() < scale.0 * 1024.0 {
+                        break;
+                    }
+                    scale = (scale.0 * 1024.0, name);
+                }
+                scale
+            }
+        }
+    }
+
+    /// Text of the value on its scale, with one decimal place by default.
+    /// The values not scaled keep their integer form, without a precision
+    pub fn humanize(&self, v: f64, precision: Option<usize>) -> String {
+        match (self.scale(v), precision) {
+            ((1.0, name), None) if v.fract() == 0.0 => format!("{} {}", v, name),
+            ((d, name), precision) => format!("{:.*} {}", precision.unwrap_or(1), v / d, name),
+        }
+    }
+}
+
+impl Field {
+    /// Text of the number by the format, or rounded to the precision
+    pub fn format_number(&self, v: f64) -> String {
+        match (&self.format, Decimal::from_f64(v)) {
+            (Some(format), Some(number)) => format.apply(number, self.precision),
+            _ => match self.precision {
+                Some(p) => format!("{:.*}", p, v),
+                None => v.to_string(),
+            },
+        }
+    }
+}
+
+/// Presentation of the numeric values: separators, percent and currency
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct NumberFormat {
+    /// Decimal places, the field precision when omitted
+    #[serde(default)]
+    pub decimals: Option<usize>,
+    /// Separator of the thousands groups, like `.` or `,`
+    #[serde(default)]
+    pub thousands: Option<String>,
+    /// Separator of the decimal places, `.` when omitted
+    #[serde(default)]
+    pub decimal: Option<String>,
+    /// Value multiplied by 100, followed by `%`
+    #[serde(default)]
+    pub percent: bool,
+    /// Symbol after the value, like `€`
+    #[serde(default)]
+    pub currency: Option<String>,
+    /// Currency symbol before the value, like `$ 10.00`
+    #[serde(default)]
+    pub currency_before: bool,
+    /// Value on the scale of the SI prefixes, like `1.2M`
+    #[serde(default)]
+    pub si: bool,
+}
+
+impl NumberFormat {
+    /// Text of the number, rounded to the decimals or to the precision
+    pub fn apply(&self, number: Decimal, precision: Option<usize>) -> String {
+        let mut number = number;
+        if self.percent {
+            number *= Decimal::ONE_HUNDRED;
+        }
+
+        let mut prefix = "";
+        if self.si {
+            let thousand = Decimal::ONE_THOUSAND;
+            for name in ["k", "M", "G", "T", "P"] {
+                if number.abs() < thousand {
+                    break;
+                }
+                number /= thousand;
+                prefix = name;
+            }
+        }
+
+        let round = |p: usize| {
+            number.round_dp_with_strategy(p as u32, RoundingStrategy::MidpointAwayFromZero)
+        };
+        let text = match self.decimals.or(precision) {
+            Some(p) => format!("{:.*}", p, round(p)),
+            // One decimal place of the scaled ones, like `1.2M`
+            None if self.si => round(1).normalize().to_string(),
+            None => number.normalize().to_string(),
+        };
+
+        let (sign, text) = match text.strip_prefix('-') {
+            Some(t) => ("-", t),
+            None => ("", text.as_str()),
+        };
+        let (int, frac) = text.split_once('.').unwrap_or((text, ""));
+
+        let mut grouped = String::new();
+        for (i, c) in int.chars().enumerate() {
+            if i > 0 && (int.len() - i) % 3 == 0 {
+                grouped.push_str(self.thousands.as_deref().unwrap_or_default());
+            }
+            grouped.push(c);
+        }
+
+        let mut r = format!("{}{}", sign, grouped);
+        if !frac.is_empty() {
+            r.push_str(self.decimal.as_deref().unwrap_or("."));
+            r.push_str(frac);
+        }
+        r.push_str(prefix);
+        if self.percent {
+            r.push('%');
+        }
+
+        match &self.currency {
+            Some(c) if self.currency_before => format!("{} {}", c, r),
+            Some(c) => format!("{} {}", r, c),
+            None => r,
+        }
+    }
+}
+
+/// Change on the values of a field, applied after the fetch
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Transform {
+    Upper,
+    Lower,
+    Trim,
+    Abs,
+    /// Decimal places of the floats and decimals
+    Round(u32),
+}
+
+impl TryFrom<String> for Transform {
+    type Error = String;
+
+    fn try_from(raw: String) -> Result<Self, Self::Error> {
+        let (name, arg) = raw.split_once(':').unwrap_or((&raw, ""));
+
+        match (name.trim(), arg.trim()) {
+            ("upper", "") => Ok(Transform::Upper),
+            ("lower", "") => Ok(Transform::Lower),
+            ("trim", "") => Ok(Transform::Trim),
+            ("abs", "") => Ok(Transform::Abs),
+            ("round", "") => Ok(Transform::Round(0)),
+            ("round", places) => places
+                .parse()
+                .map(Transform::Round)
+                .map_err(|e| format!("Invalid transform {}: {}", raw, e)),
+            _ => Err(format!(
+                "Invalid transform {}, expected upper, lower, trim, abs or round:<places>",
+                raw
+            )),
+        }
+    }
+}
+
+impl Transform {
+    /// Value changed, the not supported kinds are kept
+    pub fn apply(&self, value: TypedValue) -> TypedValue {
+        match (self, value) {
+            (Transform::Upper, TypedValue::String(s)) => TypedValue::String(s.to_uppercase()),
+            (Transform::Lower, TypedValue::String(s)) => TypedValue::String(s.to_lowercase()),
+            (Transform::Trim, TypedValue::String(s)) => TypedValue::String(s.trim().to_string()),
+            (Transform::Abs, TypedValue::Integer(v)) => TypedValue::Integer(v.abs()),
+            (Transform::Abs, TypedValue::Float(v)) => TypedValue::Float(v.abs()),
+            (Transform::Abs, TypedValue::Decimal(v)) => TypedValue::Decimal(v.abs()),
+            (Transform::Round(p), TypedValue::Float(v)) => {
+                let m = 10f64.powi(*p as i32);
+                TypedValue::Float((v * m).round() / m)
+            }
+            (Transform::Round(p), TypedValue::Decimal(v)) => TypedValue::Decimal(
+                v.round_dp_with_strategy(*p, RoundingStrategy::MidpointAwayFromZero),
+            ),
+            (_, v) => v,
+        }
+    }
+}
+
+/// Apply the transforms of the fields on the values of the rows
+pub fn apply_transforms(rows: Vec<Vec<Value>>) -> Vec<Vec<Value>> {
+    rows.into_iter()
+        .map(|row| {
+            row.into_iter()
+                .map(|mut value| {
+                    if let Some(transform) = &value.field.transform {
+                        value.inner = value.inner.take().map(|v| transform.apply(v));
+                    }
+                    value
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Changes on a inferred field, only the informed attributes are replaced
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct FieldOverride {
+    pub field: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub kind: Option<FieldType>,
+    #[serde(default)]
+    pub link: Option<bool>,
+    #[serde(default)]
+    pub link_template: Option<String>,
+    #[serde(default)]
+    pub precision: Option<usize>,
+    #[serde(default)]
+    pub data_bar: Option<bool>,
+    #[serde(default)]
+    pub icons: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub transform: Option<Transform>,
+    #[serde(default)]
+    pub format: Option<NumberFormat>,
+    #[serde(default)]
+    pub unit: Option<Unit>,
+    #[serde(default)]
+    pub align: Option<Align>,
+    #[serde(default)]
+    pub max_width: Option<usize>,
+    #[serde(default)]
+    pub sparkline: Option<bool>,
+}
+
+impl FieldOverride {
+    fn apply(&self, field: &mut Field) {
+        if let Some(title) = &self.title {
+            field.title = title.clone();
+        }
+        if let Some(kind) = &self.kind {
+            field.kind = kind.clone();
+        }
+        if let Some(link) = self.link {
+            field.link = link;
+        }
+        if self.link_template.is_some() {
+            field.link_template = self.link_template.clone();
+        }
+        if self.precision.is_some() {
+            field.precision = self.precision;
+        }
+        if let Some(data_bar) = self.data_bar {
+            field.data_bar = data_bar;
+        }
+        if let Some(icons) = &self.icons {
+            field.icons = icons.clone();
+        }
+        if self.transform.is_some() {
+            field.transform = self.transform.clone();
+        }
+        if self.format.is_some() {
+            field.format = self.format.clone();
+        }
+        if self.unit.is_some() {
+            field.unit = self.unit.clone();
+        }
+        if self.align.is_some() {
+            field.align = self.align.clone();
+        }
+        if self.max_width.is_some() {
+            field.max_width = self.max_width;
+        }
+        if let Some(sparkline) = self.sparkline {
+            field.sparkline = sparkline;
+        }
+    }
+}
+
+/// Apply the overrides on the fields with the same name
+pub fn apply_overrides(fields: &mut [Field], overrides: &[FieldOverride]) {
+    for o in overrides {
+        for field in fields.iter_mut().filter(|f| f.field == o.field) {
+            o.apply(field);
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
 pub enum FieldType {
+    #[default]
     String,
     Integer,
     Float,
+    Decimal,
     Time,
     Date,
     DateTime,
+    /// Urls, base64 or the raw bytes, like the blob columns, as inline images
+    #[serde(alias = "Blob")]
+    Image,
+    Array,
+    Json,
 }
 
 #[cfg(test)]
@@ -66,7 +581,11 @@ pub enum FieldType {
 pub mod tests {
     use chrono::{DateTime, NaiveDate, NaiveTime};
 
-    use crate::value::TypedValue;
+    use crate::value::{
+        apply_overrides, apply_transforms, Field, FieldOverride, FieldType, NumberFormat,
+        Transform, TypedValue, Unit, Value,
+    };
+    use serde_json::json;
 
     #[test]
     fn typed_value_to_string() -> Result<(), String> {
@@ -85,7 +604,7 @@ pub mod tests {
         );
         assert_eq!(
             "2025-05-12".to_string(),
-            TypedValue::Date(NaiveDate::from_ymd(2025, 05, 12)).to_string()
+            TypedValue::Date(NaiveDate::from_ymd(2025, 5, 12)).to_string()
         );
         assert_eq!(
             "2015-05-15 00:00:00 +00:00".to_string(),
@@ -96,6 +615,275 @@ pub mod tests {
             )
             .to_string()
         );
+        assert_eq!(
+            "https://abc.com/a.png".to_string(),
+            TypedValue::Image(b"https://abc.com/a.png".to_vec()).to_string()
+        );
+        assert_eq!(
+            "[image]".to_string(),
+            TypedValue::Image(vec![0x89, b'P', b'N', b'G']).to_string()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn overrides() {
+        let mut fields = vec![
+            Field {
+                field: "name".to_string(),
+                title: "name".to_string(),
+                kind: FieldType::String,
+                ..Default::default()
+            },
+            Field {
+                field: "dt".to_string(),
+                title: "dt".to_string(),
+                kind: FieldType::String,
+                ..Default::default()
+            },
+        ];
+
+        apply_overrides(
+            &mut fields,
+            &[FieldOverride {
+                field: "dt".to_string(),
+                title: Some("Created at".to_string()),
+                kind: Some(FieldType::Date),
+                ..Default::default()
+            }],
+        );
+
+        assert_eq!("name", fields[0].title);
+        assert_eq!(FieldType::String, fields[0].kind);
+        assert_eq!("Created at", fields[1].title);
+        assert_eq!(FieldType::Date, fields[1].kind);
+        assert!(!fields[1].link);
+    }
+
+    #[test]
+    fn precision() -> Result<(), String> {
+        let value = Value {
+            inner: Some(TypedValue::Float(123.44999694824219)),
+            field: Field {
+                field: "v".to_string(),
+                precision: Some(2),
+                ..Default::default()
+            },
+        };
+
+        assert_eq!("123.45", value.to_string());
+        assert_eq!(123.45, value.to_float()?);
+
+        let value = Value {
+            inner: Some(TypedValue::Integer(10)),
+            ..value
+        };
+        assert_eq!("10", value.to_string());
+
+        let value = Value {
+            inner: None,
+            ..value
+        };
+        assert_eq!("", value.to_string());
+
+        Ok(())
+    }
+
+    #[test]
+    fn json_values() -> Result<(), String> {
+        let tags = TypedValue::parse_json(&FieldType::Array, r#"["a", null, 2, 2.5]"#)?;
+        assert_eq!(
+            TypedValue::Array(vec![
+                TypedValue::String("a".to_string()),
+                TypedValue::Integer(2),
+                TypedValue::Float(2.5)
+            ]),
+            tags
+        );
+        assert_eq!("a, 2, 2.5", tags.to_string());
+        assert_eq!(json!(["a", 2, 2.5]), tags.to_json());
+
+        let meta = TypedValue::parse_json(&FieldType::Json, r#"{"plan": "pro", "seats": 3}"#)?;
+        assert_eq!(TypedValue::Json(json!({"plan": "pro", "seats": 3})), meta);
+        assert_eq!(r#"{"plan":"pro","seats":3}"#, meta.to_string());
+
+        assert!(TypedValue::parse_json(&FieldType::Json, "{plan").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn decimals() -> Result<(), String> {
+        let total = TypedValue::parse_decimal("1234567.895")?;
+        assert_eq!("1234567.895", total.to_string());
+        assert_eq!(1234567.9, total.to_float()?);
+
+        let value = Value {
+            inner: Some(total),
+            field: Field {
+                kind: FieldType::Decimal,
+                precision: Some(2),
+                ..Default::default()
+            },
+        };
+        assert_eq!("1234567.90", value.to_string());
+
+        let value = Value {
+            inner: Some(TypedValue::parse_decimal("0.885")?),
+            ..value
+        };
+        assert_eq!("0.89", value.to_string());
+
+        assert_eq!(
+            TypedValue::parse_decimal("125")?,
+            TypedValue::parse_decimal("1.25e2")?
+        );
+        assert!(TypedValue::parse_decimal("abc").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn blob_kind() {
+        assert_eq!(
+            Ok(FieldType::Image),
+            serde_yaml::from_str::<FieldType>("Blob").map_err(|e| e.to_string())
+        );
+    }
+
+    #[test]
+    fn transforms() -> Result<(), String> {
+        let transform =
+            |raw: &str| serde_yaml::from_str::<Transform>(raw).map_err(|e| e.to_string());
+
+        assert_eq!(Transform::Round(2), transform("round:2")?);
+        assert_eq!(Transform::Upper, transform("upper")?);
+        assert!(transform("round:x").is_err());
+        assert!(transform("reverse").is_err());
+
+        let value = |inner: TypedValue, transform: Option<Transform>| Value {
+            inner: Some(inner),
+            field: Field {
+                transform,
+                ..Default::default()
+            },
+        };
+        let rows = apply_transforms(vec![vec![
+            value(
+                TypedValue::String(" Lisbon ".to_string()),
+                Some(Transform::Trim),
+            ),
+            value(
+                TypedValue::String("lisbon".to_string()),
+                Some(Transform::Upper),
+            ),
+            value(TypedValue::Float(-12.346), Some(Transform::Round(2))),
+            value(TypedValue::Integer(-7), Some(Transform::Abs)),
+            value(TypedValue::Integer(10), Some(Transform::Upper)),
+            value(TypedValue::String(" kept ".to_string()), None),
+        ]]);
+
+        assert_eq!(
+            vec![
+                Some(TypedValue::String("Lisbon".to_string())),
+                Some(TypedValue::String("LISBON".to_string())),
+                Some(TypedValue::Float(-12.35)),
+                Some(TypedValue::Integer(7)),
+                Some(TypedValue::Integer(10)),
+                Some(TypedValue::String(" kept ".to_string())),
+            ],
+            rows[0].iter().map(|v| v.inner.clone()).collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn number_format() -> Result<(), String> {
+        let format: NumberFormat =
+            serde_yaml::from_str("{decimals: 2, thousands: '.', decimal: ',', currency: '€'}")
+                .map_err(|e| e.to_string())?;
+
+        let value = |inner: TypedValue| Value {
+            inner: Some(inner),
+            field: Field {
+                format: Some(format.clone()),
+                ..Default::default()
+            },
+        };
+
+        assert_eq!(
+            "1.234.567,89 €",
+            value(TypedValue::Float(1234567.891)).to_string()
+        );
+        assert_eq!("-1.000,00 €", value(TypedValue::Integer(-1000)).to_string());
+        assert_eq!(
+            "text",
+            value(TypedValue::String("text".to_string())).to_string()
+        );
+
+        let format = NumberFormat {
+            thousands: Some(",".to_string()),
+            currency: Some("$".to_string()),
+            currency_before: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            "$ 123,456.5",
+            format.apply("123456.50".parse().unwrap(), None)
+        );
+        assert_eq!("$ 999", format.apply(999.into(), None));
+        assert_eq!("$ 1,000.00", format.apply(1000.into(), Some(2)));
+
+        let format = NumberFormat {
+            percent: true,
+            decimals: Some(1),
+            ..Default::default()
+        };
+        assert_eq!("12.3%", format.apply("0.1234".parse().unwrap(), None));
+
+        let format = NumberFormat {
+            si: true,
+            currency: Some("$".to_string()),
+            currency_before: true,
+            ..Default::default()
+        };
+        assert_eq!("$ 1.2M", format.apply(1234567.into(), None));
+        assert_eq!("$ -45k", format.apply((-45000).into(), None));
+        assert_eq!("$ 999", format.apply(999.into(), None));
+        assert_eq!("$ 1.50k", format.apply(1500.into(), Some(2)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn bytes() -> Result<(), String> {
+        let unit: Unit = serde_yaml::from_str("bytes").map_err(|e| e.to_string())?;
+        assert_eq!(Unit::Bytes, unit);
+
+        let value = |inner: TypedValue| Value {
+            inner: Some(inner),
+            field: Field {
+                unit: Some(Unit::Bytes),
+                ..Default::default()
+            },
+        };
+
+        assert_eq!("512 B", value(TypedValue::Integer(512)).to_string());
+        assert_eq!("1.5 KB", value(TypedValue::Integer(1536)).to_string());
+        assert_eq!("2.0 GB", value(TypedValue::Integer(2147483648)).to_string());
+        assert_eq!(
+            "4.3 MB",
+            value(TypedValue::Decimal(4509715.into())).to_string()
+        );
+        assert_eq!((1048576.0, "MB"), Unit::Bytes.scale(5e6));
+        assert_eq!(
+            "100.1 B",
+            value(TypedValue::Float(100.1f32 as f64)).to_string()
+        );
+        assert_eq!("100.100 B", Unit::Bytes.humanize(100.1f32 as f64, Some(3)));
+        assert_eq!("512.00 B", Unit::Bytes.humanize(512.0, Some(2)));
 
         Ok(())
     }